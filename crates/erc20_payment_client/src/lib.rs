@@ -0,0 +1,438 @@
+//! Typed client of the payment server http api, types are shared with the server
+//! through [`erc20_payment_lib::server::api`].
+
+use awc::http::{Method, StatusCode};
+use awc::Client;
+use erc20_payment_lib::server::auth::{
+    sign_request, HMAC_KEY_ID_HEADER, HMAC_SIGNATURE_HEADER, HMAC_TIMESTAMP_HEADER,
+};
+use erc20_rpc_pool::Web3RpcEndpointChange;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tokio::task;
+
+pub use erc20_payment_lib::server::api::*;
+
+const PAYLOAD_LIMIT: usize = 256 * 1024 * 1024;
+
+#[derive(Debug, Clone)]
+pub enum ClientAuth {
+    Anonymous,
+    /// Sent as `Authorization: Bearer` header
+    Token(String),
+    /// Every request is signed with the shared secret
+    Hmac {
+        key_id: String,
+        secret: String,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct PaymentClient {
+    url_base: String,
+    auth: ClientAuth,
+}
+
+/// Error responses come either with error status or with status 200 and [`ApiError`] body
+fn parse_response<T: DeserializeOwned>(
+    status: StatusCode,
+    path: &str,
+    body: &[u8],
+) -> Result<T, anyhow::Error> {
+    let body = String::from_utf8_lossy(body);
+    if !status.is_success() {
+        return Err(anyhow::anyhow!(
+            "Request {} failed: {}: {}",
+            path,
+            status,
+            body
+        ));
+    }
+    let value: serde_json::Value = serde_json::from_str(&body)
+        .map_err(|e| anyhow::anyhow!("Error parsing json from {}: {}: {}", path, e, body))?;
+    if let Some(error) = value
+        .as_object()
+        .filter(|o| o.len() == 1)
+        .and_then(|o| o.get("error"))
+    {
+        return Err(anyhow::anyhow!(
+            "Request {} failed: {}",
+            path,
+            error.as_str().unwrap_or(&error.to_string())
+        ));
+    }
+    serde_json::from_value(value)
+        .map_err(|e| anyhow::anyhow!("Unexpected response from {}: {}: {}", path, e, body))
+}
+
+impl PaymentClient {
+    /// `url_base` is the address of the server without `/api`, i.e. `http://127.0.0.1:8080`
+    pub fn new(url_base: &str) -> Self {
+        Self {
+            url_base: url_base.trim_end_matches('/').to_string(),
+            auth: ClientAuth::Anonymous,
+        }
+    }
+
+    pub fn with_auth(mut self, auth: ClientAuth) -> Self {
+        self.auth = auth;
+        self
+    }
+
+    async fn request<T: DeserializeOwned, Q: Serialize>(
+        &self,
+        method: Method,
+        path: &str,
+        query: Option<&Q>,
+        body: Option<Vec<u8>>,
+    ) -> Result<T, anyhow::Error> {
+        let url = format!("{}/api{}", self.url_base, path);
+        let auth = self.auth.clone();
+        let body = body.unwrap_or_default();
+
+        let local = task::LocalSet::new();
+        let (status, resp_body) = local
+            .run_until(async move {
+                let client = Client::default();
+                let mut req = client
+                    .request(method.clone(), &url)
+                    .insert_header(("Content-Type", "application/json"));
+                if let Some(query) = query {
+                    req = req
+                        .query(query)
+                        .map_err(|e| anyhow::anyhow!("Error encoding query for {}: {}", url, e))?;
+                }
+                match auth {
+                    ClientAuth::Anonymous => {}
+                    ClientAuth::Token(token) => {
+                        req = req.insert_header(("Authorization", format!("Bearer {}", token)));
+                    }
+                    ClientAuth::Hmac { key_id, secret } => {
+                        let timestamp = chrono::Utc::now().timestamp();
+                        let path_and_query = req
+                            .get_uri()
+                            .path_and_query()
+                            .map(|p| p.as_str().to_string())
+                            .unwrap_or_default();
+                        let signature = sign_request(
+                            &secret,
+                            timestamp,
+                            method.as_str(),
+                            &path_and_query,
+                            &body,
+                        );
+                        req = req
+                            .insert_header((HMAC_KEY_ID_HEADER, key_id))
+                            .insert_header((HMAC_TIMESTAMP_HEADER, timestamp.to_string()))
+                            .insert_header((HMAC_SIGNATURE_HEADER, signature));
+                    }
+                }
+                let mut res = req
+                    .send_body(body)
+                    .await
+                    .map_err(|e| anyhow::anyhow!("Error sending request to {}: {}", url, e))?;
+                let resp_body =
+                    res.body().limit(PAYLOAD_LIMIT).await.map_err(|e| {
+                        anyhow::anyhow!("Error reading response from {}: {}", url, e)
+                    })?;
+                Ok::<_, anyhow::Error>((res.status(), resp_body))
+            })
+            .await?;
+        parse_response(status, path, &resp_body)
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, anyhow::Error> {
+        self.request::<T, ()>(Method::GET, path, None, None).await
+    }
+
+    async fn send<T: DeserializeOwned, B: Serialize>(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<&B>,
+    ) -> Result<T, anyhow::Error> {
+        let body = body.map(serde_json::to_vec).transpose()?;
+        self.request::<T, ()>(method, path, None, body).await
+    }
+
+    pub async fn version(&self) -> Result<VersionResponse, anyhow::Error> {
+        self.get("/version").await
+    }
+
+    pub async fn openapi(&self) -> Result<serde_json::Value, anyhow::Error> {
+        self.get("/openapi.json").await
+    }
+
+    pub async fn config(&self) -> Result<ConfigResponse, anyhow::Error> {
+        self.get("/config").await
+    }
+
+    pub async fn debug(&self) -> Result<DebugResponse, anyhow::Error> {
+        self.get("/debug").await
+    }
+
+    pub async fn accounts(&self) -> Result<AccountsResponse, anyhow::Error> {
+        self.get("/accounts").await
+    }
+
+    pub async fn account_details(
+        &self,
+        account: &str,
+    ) -> Result<AccountDetailsResponse, anyhow::Error> {
+        self.get(&format!("/account/{}", account)).await
+    }
+
+    pub async fn account_transfers_in(
+        &self,
+        account: &str,
+        query: &TransferInListQuery,
+    ) -> Result<AccountTransfersInResponse, anyhow::Error> {
+        self.request(
+            Method::GET,
+            &format!("/account/{}/in", account),
+            Some(query),
+            None,
+        )
+        .await
+    }
+
+    pub async fn account_balance(
+        &self,
+        account: &str,
+        chain_id: i64,
+    ) -> Result<AccountBalanceResponse, anyhow::Error> {
+        self.get(&format!("/balance/{}/{}", account, chain_id))
+            .await
+    }
+
+    pub async fn allowances(&self) -> Result<AllowancesResponse, anyhow::Error> {
+        self.get("/allowances").await
+    }
+
+    pub async fn attestation(
+        &self,
+        chain: &str,
+        uid: &str,
+    ) -> Result<AttestationCheckResult, anyhow::Error> {
+        self.get(&format!("/attestation/{}/{}", chain, uid)).await
+    }
+
+    pub async fn rpc_pool(&self) -> Result<RpcPoolResponse, anyhow::Error> {
+        self.get("/rpc_pool").await
+    }
+
+    pub async fn change_rpc_endpoints(
+        &self,
+        chain_id: i64,
+        change: &Web3RpcEndpointChange,
+    ) -> Result<RpcPoolEndpointsResponse, anyhow::Error> {
+        self.send(
+            Method::POST,
+            &format!("/rpc_pool/{}/endpoints", chain_id),
+            Some(change),
+        )
+        .await
+    }
+
+    pub async fn stats_transfers(
+        &self,
+        request: &StatsTransferRequest,
+    ) -> Result<StatsTransferResult, anyhow::Error> {
+        self.request(Method::GET, "/stats/transfers", Some(request), None)
+            .await
+    }
+
+    /// One page of transactions, use `next_cursor` of response as `cursor` of next query
+    pub async fn transactions(&self, query: &TxListQuery) -> Result<TxsResponse, anyhow::Error> {
+        self.request(Method::GET, "/transactions", Some(query), None)
+            .await
+    }
+
+    pub async fn transactions_count(&self) -> Result<TransactionsCountResponse, anyhow::Error> {
+        self.get("/transactions/count").await
+    }
+
+    pub async fn transactions_next(
+        &self,
+        count: i64,
+        query: &TxFeedQuery,
+    ) -> Result<NextTxsResponse, anyhow::Error> {
+        self.request(
+            Method::GET,
+            &format!("/transactions/next/{}", count),
+            Some(query),
+            None,
+        )
+        .await
+    }
+
+    pub async fn transactions_current(
+        &self,
+        query: &TxFeedQuery,
+    ) -> Result<TxsResponse, anyhow::Error> {
+        self.request(Method::GET, "/transactions/current", Some(query), None)
+            .await
+    }
+
+    pub async fn transactions_last(
+        &self,
+        count: i64,
+        query: &TxFeedQuery,
+    ) -> Result<TxsResponse, anyhow::Error> {
+        self.request(
+            Method::GET,
+            &format!("/transactions/last/{}", count),
+            Some(query),
+            None,
+        )
+        .await
+    }
+
+    pub async fn transactions_feed(
+        &self,
+        prev: i64,
+        next: i64,
+        query: &TxFeedQuery,
+    ) -> Result<TxsFeedResponse, anyhow::Error> {
+        self.request(
+            Method::GET,
+            &format!("/transactions/feed/{}/{}", prev, next),
+            Some(query),
+            None,
+        )
+        .await
+    }
+
+    pub async fn tx(&self, tx_id: i64) -> Result<TxResponse, anyhow::Error> {
+        self.get(&format!("/tx/{}", tx_id)).await
+    }
+
+    pub async fn skip_tx(&self, tx_id: i64) -> Result<SkipTxResponse, anyhow::Error> {
+        self.send::<_, ()>(Method::POST, &format!("/tx/skip/{}", tx_id), None)
+            .await
+    }
+
+    /// One page of transfers, use `next_cursor` of response as `cursor` of next query
+    pub async fn transfers(
+        &self,
+        query: &TransferListQuery,
+    ) -> Result<TransfersResponse, anyhow::Error> {
+        self.request(Method::GET, "/transfers", Some(query), None)
+            .await
+    }
+
+    pub async fn transfers_by_tx(&self, tx_id: i64) -> Result<TransfersResponse, anyhow::Error> {
+        self.get(&format!("/transfers/{}", tx_id)).await
+    }
+
+    pub async fn new_transfer(
+        &self,
+        request: &TransactionRequest,
+    ) -> Result<NewTransferResponse, anyhow::Error> {
+        self.send(Method::POST, "/transfers/create", Some(request))
+            .await
+    }
+
+    pub async fn estimate(
+        &self,
+        requests: &[TransactionRequest],
+    ) -> Result<EstimateResponse, anyhow::Error> {
+        self.send(Method::POST, "/estimate", Some(&requests)).await
+    }
+
+    pub async fn bulk_transfer(
+        &self,
+        requests: &[TransactionRequest],
+    ) -> Result<BulkTransferResponse, anyhow::Error> {
+        self.send(Method::POST, "/transfers/bulk", Some(&requests))
+            .await
+    }
+
+    pub async fn transfer(&self, id: i64) -> Result<TransferResponse, anyhow::Error> {
+        self.get(&format!("/transfers/id/{}", id)).await
+    }
+
+    pub async fn cancel_transfer(&self, id: i64) -> Result<TransferResponse, anyhow::Error> {
+        self.send::<_, ()>(Method::DELETE, &format!("/transfers/id/{}", id), None)
+            .await
+    }
+
+    pub async fn edit_transfer(
+        &self,
+        id: i64,
+        change: &TransferChangeRequest,
+    ) -> Result<TransferResponse, anyhow::Error> {
+        self.send(
+            Method::PATCH,
+            &format!("/transfers/id/{}", id),
+            Some(change),
+        )
+        .await
+    }
+
+    pub async fn approve_transfer(&self, id: i64) -> Result<TransferResponse, anyhow::Error> {
+        self.send::<_, ()>(Method::POST, &format!("/transfers/id/{}/approve", id), None)
+            .await
+    }
+
+    /// One page of webhook delivery log, see [`PaymentClient::transactions`]
+    pub async fn webhook_deliveries(
+        &self,
+        query: &WebhookDeliveryListQuery,
+    ) -> Result<WebhookDeliveriesResponse, anyhow::Error> {
+        self.request(Method::GET, "/webhooks/deliveries", Some(query), None)
+            .await
+    }
+
+    pub async fn retry_webhook_delivery(
+        &self,
+        id: i64,
+    ) -> Result<WebhookDeliveryResponse, anyhow::Error> {
+        self.send::<_, ()>(
+            Method::POST,
+            &format!("/webhooks/deliveries/{}/retry", id),
+            None,
+        )
+        .await
+    }
+
+    pub async fn faucet_status(&self) -> Result<FaucetStatusResponse, anyhow::Error> {
+        self.get("/faucet").await
+    }
+
+    pub async fn faucet(&self, chain_id: i64, addr: &str) -> Result<FaucetResponse, anyhow::Error> {
+        self.get(&format!("/faucet/{}/{}", chain_id, addr)).await
+    }
+
+    pub async fn faucet_history(
+        &self,
+        query: &FaucetHistoryQuery,
+    ) -> Result<FaucetHistoryResponse, anyhow::Error> {
+        self.request(Method::GET, "/faucet/history", Some(query), None)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_response() {
+        let ok: SkipTxResponse =
+            parse_response(StatusCode::OK, "/tx/skip/1", br#"{"success":"true"}"#).unwrap();
+        assert_eq!(ok.success, "true");
+
+        let err = parse_response::<SkipTxResponse>(
+            StatusCode::OK,
+            "/tx/skip/1",
+            br#"{"error":"Tx not found"}"#,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("Tx not found"));
+
+        let err =
+            parse_response::<SkipTxResponse>(StatusCode::FORBIDDEN, "/tx/skip/1", b"").unwrap_err();
+        assert!(err.to_string().contains("403"));
+    }
+}
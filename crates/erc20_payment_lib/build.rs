@@ -0,0 +1,12 @@
+fn main() {
+    #[cfg(feature = "grpc")]
+    {
+        // vendored protoc, so building with grpc does not need protobuf compiler installed
+        std::env::set_var(
+            "PROTOC",
+            protoc_bin_vendored::protoc_bin_path().expect("Vendored protoc not found"),
+        );
+        tonic_build::compile_protos("proto/payment.proto")
+            .expect("Failed to compile payment.proto");
+    }
+}
@@ -24,6 +24,7 @@ pub struct BalanceOptions2 {
     pub debug_loop: Option<u64>,
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct BalanceResult2 {
@@ -0,0 +1,500 @@
+use crate::config::ArchiveSettings;
+use crate::error::{ErrorBag, PaymentError};
+use crate::{err_custom_create, err_from};
+use chrono::{DateTime, Utc};
+use erc20_payment_lib_common::create_sqlite_connection;
+use erc20_payment_lib_common::model::{
+    ChainTransferDbObj, TokenTransferDbObj, TransferArchiveStatsDbObj, TxDbObj,
+    ARCHIVE_SOURCE_CHAIN_TRANSFER, ARCHIVE_SOURCE_TOKEN_TRANSFER,
+};
+use erc20_payment_lib_common::ops::{
+    get_chain_tx_hash, insert_archived_token_transfer, insert_chain_transfer, insert_chain_tx,
+    insert_tx,
+};
+use erc20_payment_lib_common::store::{PaymentStore, PaymentStoreOps, PaymentStoreTransaction};
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use web3::types::U256;
+
+/// Number of transactions moved to archive in one database transaction
+const ARCHIVE_BATCH_SIZE: i64 = 500;
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveResult {
+    pub tx_count: u64,
+    pub token_transfer_count: u64,
+    pub chain_tx_count: u64,
+    pub chain_transfer_count: u64,
+}
+
+/// Open archive file, creating it with the same schema as the main database if needed.
+/// Archive is always SQLite file, the archived store can be SQLite or PostgreSQL
+pub async fn open_archive(path: &Path) -> Result<SqlitePool, PaymentError> {
+    create_sqlite_connection(Some(path), None, false, true).await
+}
+
+/// (chain_id, from_addr, receiver_addr, token_addr)
+type StatsKey = (i64, String, Option<String>, Option<String>);
+
+/// Totals of rows moved to archive in one batch, merged into transfer_archive_stats
+struct ArchiveStats {
+    source: &'static str,
+    rows: BTreeMap<StatsKey, (TransferArchiveStatsDbObj, HashSet<i64>)>,
+}
+
+fn add_amount(total: &str, amount: &str) -> Result<String, PaymentError> {
+    let total = U256::from_dec_str(total).map_err(err_from!())?;
+    let amount = U256::from_dec_str(amount).map_err(err_from!())?;
+    Ok((total + amount).to_string())
+}
+
+fn min_date(a: Option<DateTime<Utc>>, b: Option<DateTime<Utc>>) -> Option<DateTime<Utc>> {
+    a.into_iter().chain(b).min()
+}
+
+fn max_date(a: Option<DateTime<Utc>>, b: Option<DateTime<Utc>>) -> Option<DateTime<Utc>> {
+    a.into_iter().chain(b).max()
+}
+
+struct StatsEntry<'a> {
+    chain_id: i64,
+    from_addr: &'a str,
+    receiver_addr: &'a str,
+    token_addr: &'a Option<String>,
+    token_amount: &'a str,
+    tx_id: Option<i64>,
+    /// Set for transfers that are done, only these are counted into amount
+    fee_paid: Option<&'a str>,
+    transfer_date: Option<DateTime<Utc>>,
+    paid_date: Option<DateTime<Utc>>,
+}
+
+impl ArchiveStats {
+    fn new(source: &'static str) -> Self {
+        Self {
+            source,
+            rows: BTreeMap::new(),
+        }
+    }
+
+    fn add_token_transfer(&mut self, tt: &TokenTransferDbObj) -> Result<(), PaymentError> {
+        self.add(StatsEntry {
+            chain_id: tt.chain_id,
+            from_addr: &tt.from_addr,
+            receiver_addr: &tt.receiver_addr,
+            token_addr: &tt.token_addr,
+            token_amount: &tt.token_amount,
+            tx_id: tt.tx_id,
+            fee_paid: tt.fee_paid.as_deref().filter(|_| tt.tx_id.is_some()),
+            transfer_date: Some(tt.create_date),
+            paid_date: tt.paid_date,
+        })
+    }
+
+    fn add_chain_transfer(&mut self, ct: &ChainTransferDbObj) -> Result<(), PaymentError> {
+        // same as in get_transfer_stats_from_blockchain, every chain transfer is done
+        self.add(StatsEntry {
+            chain_id: ct.chain_id,
+            from_addr: &ct.from_addr,
+            receiver_addr: &ct.receiver_addr,
+            token_addr: &ct.token_addr,
+            token_amount: &ct.token_amount,
+            tx_id: Some(ct.chain_tx_id),
+            fee_paid: Some(ct.fee_paid.as_deref().unwrap_or("0")),
+            transfer_date: None,
+            paid_date: ct.blockchain_date,
+        })
+    }
+
+    fn add(&mut self, entry: StatsEntry) -> Result<(), PaymentError> {
+        for receiver_addr in [Some(entry.receiver_addr.to_string()), None] {
+            let key = (
+                entry.chain_id,
+                entry.from_addr.to_string(),
+                receiver_addr.clone(),
+                entry.token_addr.clone(),
+            );
+            let (row, tx_ids) = self.rows.entry(key).or_insert_with(|| {
+                (
+                    TransferArchiveStatsDbObj {
+                        id: 0,
+                        source: self.source.to_string(),
+                        chain_id: entry.chain_id,
+                        from_addr: entry.from_addr.to_string(),
+                        receiver_addr,
+                        token_addr: entry.token_addr.clone(),
+                        token_amount: "0".to_string(),
+                        fee_paid: "0".to_string(),
+                        ..Default::default()
+                    },
+                    HashSet::new(),
+                )
+            });
+            row.transfer_count += 1;
+            if let Some(tx_id) = entry.tx_id {
+                tx_ids.insert(tx_id);
+            }
+            row.first_transfer_date = min_date(row.first_transfer_date, entry.transfer_date);
+            row.last_transfer_date = max_date(row.last_transfer_date, entry.transfer_date);
+            if let Some(fee_paid) = entry.fee_paid {
+                row.done_count += 1;
+                row.fee_paid = add_amount(&row.fee_paid, fee_paid)?;
+                row.token_amount = add_amount(&row.token_amount, entry.token_amount)?;
+            }
+            if let Some(paid_date) = entry.paid_date {
+                row.first_paid_date = min_date(row.first_paid_date, Some(paid_date));
+                row.last_paid_date = max_date(row.last_paid_date, Some(paid_date));
+                if let Some(transfer_date) = entry.transfer_date {
+                    let delay = (paid_date - transfer_date).num_milliseconds();
+                    row.max_payment_delay = Some(row.max_payment_delay.unwrap_or(delay).max(delay));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Add totals to rows already stored in database
+    async fn save<S: PaymentStoreOps>(self, conn: &S) -> Result<(), PaymentError> {
+        let mut existing_by_chain = HashMap::<i64, Vec<TransferArchiveStatsDbObj>>::new();
+        for ((chain_id, ..), (row, tx_ids)) in self.rows {
+            if let std::collections::hash_map::Entry::Vacant(e) = existing_by_chain.entry(chain_id)
+            {
+                e.insert(
+                    conn.get_transfer_archive_stats(chain_id, self.source)
+                        .await
+                        .map_err(err_from!())?,
+                );
+            }
+            let tx_count = tx_ids.len() as i64;
+            let existing = existing_by_chain[&chain_id].iter().find(|e| {
+                e.from_addr == row.from_addr
+                    && e.receiver_addr == row.receiver_addr
+                    && e.token_addr == row.token_addr
+            });
+            match existing {
+                Some(existing) => {
+                    let merged = TransferArchiveStatsDbObj {
+                        transfer_count: existing.transfer_count + row.transfer_count,
+                        done_count: existing.done_count + row.done_count,
+                        tx_count: existing.tx_count + tx_count,
+                        token_amount: add_amount(&existing.token_amount, &row.token_amount)?,
+                        fee_paid: add_amount(&existing.fee_paid, &row.fee_paid)?,
+                        first_transfer_date: min_date(
+                            existing.first_transfer_date,
+                            row.first_transfer_date,
+                        ),
+                        last_transfer_date: max_date(
+                            existing.last_transfer_date,
+                            row.last_transfer_date,
+                        ),
+                        first_paid_date: min_date(existing.first_paid_date, row.first_paid_date),
+                        last_paid_date: max_date(existing.last_paid_date, row.last_paid_date),
+                        max_payment_delay: existing
+                            .max_payment_delay
+                            .into_iter()
+                            .chain(row.max_payment_delay)
+                            .max(),
+                        ..existing.clone()
+                    };
+                    conn.update_transfer_archive_stats(&merged)
+                        .await
+                        .map_err(err_from!())?;
+                }
+                None => {
+                    conn.insert_transfer_archive_stats(&TransferArchiveStatsDbObj {
+                        tx_count,
+                        ..row
+                    })
+                    .await
+                    .map_err(err_from!())?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Move finished transactions with their transfers and chain transactions with their transfers
+/// older than `before` to archive database. Totals of moved transfers are kept in main database,
+/// so transfer stats stay the same.
+///
+/// Every batch is committed to archive first and then removed from main database. If the second
+/// commit fails, rows of that batch are archived again on next run.
+pub async fn archive_before<S: PaymentStore>(
+    conn: &S,
+    archive_conn: &SqlitePool,
+    before: DateTime<Utc>,
+) -> Result<ArchiveResult, PaymentError> {
+    let mut result = ArchiveResult::default();
+    // replacement transactions point to transactions archived in previous batches
+    let mut archived_tx_ids = HashMap::<i64, i64>::new();
+
+    loop {
+        let db_transaction = conn.begin().await.map_err(err_from!())?;
+        let txs: Vec<TxDbObj> = db_transaction
+            .get_archivable_transactions(before, ARCHIVE_BATCH_SIZE)
+            .await
+            .map_err(err_from!())?;
+        if txs.is_empty() {
+            break;
+        }
+        let mut archive_transaction = archive_conn.begin().await.map_err(err_from!())?;
+        let mut stats = ArchiveStats::new(ARCHIVE_SOURCE_TOKEN_TRANSFER);
+        for tx in &txs {
+            let transfers = db_transaction
+                .get_token_transfers_by_tx(tx.id)
+                .await
+                .map_err(err_from!())?;
+            let archived_tx = insert_tx(
+                &mut *archive_transaction,
+                &TxDbObj {
+                    orig_tx_id: tx
+                        .orig_tx_id
+                        .and_then(|id| archived_tx_ids.get(&id).copied()),
+                    ..tx.clone()
+                },
+            )
+            .await
+            .map_err(err_from!())?;
+            archived_tx_ids.insert(tx.id, archived_tx.id);
+            for tt in &transfers {
+                insert_archived_token_transfer(
+                    &mut *archive_transaction,
+                    &TokenTransferDbObj {
+                        tx_id: Some(archived_tx.id),
+                        ..tt.clone()
+                    },
+                )
+                .await
+                .map_err(err_from!())?;
+                stats.add_token_transfer(tt)?;
+            }
+            db_transaction
+                .delete_token_transfers_by_tx(tx.id)
+                .await
+                .map_err(err_from!())?;
+            db_transaction.delete_tx(tx.id).await.map_err(err_from!())?;
+            result.tx_count += 1;
+            result.token_transfer_count += transfers.len() as u64;
+        }
+        stats.save(&db_transaction).await?;
+        archive_transaction.commit().await.map_err(err_from!())?;
+        db_transaction.commit().await.map_err(err_from!())?;
+    }
+
+    loop {
+        let db_transaction = conn.begin().await.map_err(err_from!())?;
+        let chain_txs = db_transaction
+            .get_chain_txs_before(before, ARCHIVE_BATCH_SIZE)
+            .await
+            .map_err(err_from!())?;
+        if chain_txs.is_empty() {
+            break;
+        }
+        let mut archive_transaction = archive_conn.begin().await.map_err(err_from!())?;
+        let mut stats = ArchiveStats::new(ARCHIVE_SOURCE_CHAIN_TRANSFER);
+        for chain_tx in &chain_txs {
+            let transfers = db_transaction
+                .get_chain_transfers_by_chain_tx(chain_tx.id)
+                .await
+                .map_err(err_from!())?;
+            // tx_hash is unique, so chain transaction is archived only once
+            if get_chain_tx_hash(&mut *archive_transaction, chain_tx.tx_hash.clone())
+                .await
+                .map_err(err_from!())?
+                .is_none()
+            {
+                let archived_chain_tx = insert_chain_tx(&mut *archive_transaction, chain_tx)
+                    .await
+                    .map_err(err_from!())?;
+                for ct in &transfers {
+                    insert_chain_transfer(
+                        &mut *archive_transaction,
+                        &ChainTransferDbObj {
+                            chain_tx_id: archived_chain_tx.id,
+                            ..ct.clone()
+                        },
+                    )
+                    .await
+                    .map_err(err_from!())?;
+                }
+            }
+            for ct in &transfers {
+                stats.add_chain_transfer(ct)?;
+            }
+            db_transaction
+                .delete_chain_transfers_by_chain_tx(chain_tx.id)
+                .await
+                .map_err(err_from!())?;
+            db_transaction
+                .delete_chain_tx(chain_tx.id)
+                .await
+                .map_err(err_from!())?;
+            result.chain_tx_count += 1;
+            result.chain_transfer_count += transfers.len() as u64;
+        }
+        stats.save(&db_transaction).await?;
+        archive_transaction.commit().await.map_err(err_from!())?;
+        db_transaction.commit().await.map_err(err_from!())?;
+    }
+
+    Ok(result)
+}
+
+/// Periodically archive rows older than configured retention
+pub fn start_archive_job<S: PaymentStore>(
+    conn: S,
+    settings: ArchiveSettings,
+) -> Result<JoinHandle<()>, PaymentError> {
+    if settings.retention_days == 0 {
+        return Err(err_custom_create!(
+            "Archive retention-days has to be positive"
+        ));
+    }
+    let interval = Duration::from_secs(settings.interval_secs.unwrap_or(3600));
+    Ok(tokio::spawn(async move {
+        let archive_conn = match open_archive(&settings.archive_file).await {
+            Ok(archive_conn) => archive_conn,
+            Err(err) => {
+                log::error!(
+                    "Failed to open archive {}, archiving disabled: {}",
+                    settings.archive_file.display(),
+                    err
+                );
+                return;
+            }
+        };
+        loop {
+            let before = Utc::now() - chrono::Duration::days(settings.retention_days as i64);
+            match archive_before(&conn, &archive_conn, before).await {
+                Ok(res) => {
+                    if res.tx_count > 0 || res.chain_tx_count > 0 {
+                        log::info!(
+                            "Archived {} transactions and {} chain transactions older than {}",
+                            res.tx_count,
+                            res.chain_tx_count,
+                            before
+                        );
+                    }
+                }
+                Err(err) => {
+                    log::error!("Failed to archive old transactions: {}", err);
+                }
+            }
+            tokio::time::sleep(interval).await;
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use erc20_payment_lib_common::ops::get_transfer_stats;
+    use erc20_payment_lib_common::store::MemoryStore;
+    use web3::types::Address;
+
+    #[tokio::test]
+    async fn test_archive_keeps_stats() {
+        let store = MemoryStore::new();
+        let archive_conn = create_sqlite_connection(None, None, false, true)
+            .await
+            .unwrap();
+        let created = Utc::now() - chrono::Duration::days(10);
+        let tx = store
+            .insert_tx(&TxDbObj {
+                from_addr: "0x0000000000000000000000000000000000000001".to_string(),
+                chain_id: 987789,
+                processing: 0,
+                created_date: created,
+                fee_paid: Some("100".to_string()),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        for receiver in [
+            "0x0000000000000000000000000000000000000002",
+            "0x0000000000000000000000000000000000000003",
+        ] {
+            store
+                .insert_token_transfer(&TokenTransferDbObj {
+                    id: 0,
+                    payment_id: None,
+                    from_addr: tx.from_addr.clone(),
+                    receiver_addr: receiver.to_string(),
+                    chain_id: 987789,
+                    token_addr: None,
+                    token_amount: "7".to_string(),
+                    deposit_id: None,
+                    deposit_finish: 0,
+                    create_date: created,
+                    tx_id: Some(tx.id),
+                    paid_date: Some(created),
+                    fee_paid: Some("50".to_string()),
+                    error: None,
+                    priority: "normal".to_string(),
+                    approved_date: None,
+                    deadline: None,
+                })
+                .await
+                .unwrap();
+        }
+
+        let res = archive_before(
+            &store,
+            &archive_conn,
+            Utc::now() - chrono::Duration::days(1),
+        )
+        .await
+        .unwrap();
+        assert_eq!(res.tx_count, 1);
+        assert_eq!(res.token_transfer_count, 2);
+        assert!(store
+            .get_token_transfers_by_tx(tx.id)
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            get_transfer_stats(&archive_conn, 987789, None)
+                .await
+                .unwrap()
+                .per_sender
+                .len(),
+            1
+        );
+
+        let archived = store
+            .get_transfer_archive_stats(987789, ARCHIVE_SOURCE_TOKEN_TRANSFER)
+            .await
+            .unwrap();
+        // one row per receiver and one with sender totals
+        assert_eq!(archived.len(), 3);
+        let totals = archived.iter().find(|a| a.receiver_addr.is_none()).unwrap();
+        assert_eq!(totals.transfer_count, 2);
+        assert_eq!(totals.done_count, 2);
+        assert_eq!(totals.tx_count, 1);
+        assert_eq!(totals.token_amount, "14");
+        assert_eq!(totals.fee_paid, "100");
+
+        // nothing left to archive
+        let res = archive_before(&store, &archive_conn, Utc::now())
+            .await
+            .unwrap();
+        assert_eq!(res.tx_count, 0);
+        let sender = Address::from_low_u64_be(1);
+        assert_eq!(
+            store
+                .get_transfer_archive_stats(987789, ARCHIVE_SOURCE_TOKEN_TRANSFER)
+                .await
+                .unwrap()
+                .iter()
+                .filter(|a| a.from_addr == format!("{:#x}", sender))
+                .count(),
+            3
+        );
+    }
+}
@@ -0,0 +1,278 @@
+use crate::config::BackupSettings;
+use crate::error::{ErrorBag, PaymentError};
+use crate::{err_custom_create, err_from};
+use chrono::{DateTime, Utc};
+use erc20_payment_lib_common::check_sqlite_migrations;
+use sqlx::sqlite::{SqliteConnectOptions, SqliteLockingMode};
+use sqlx::{ConnectOptions, Connection, Executor, SqlitePool};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// Running instance refreshes its marker file this often
+const INSTANCE_HEARTBEAT: Duration = Duration::from_secs(10);
+
+fn backup_file_name(prefix: &str, date: DateTime<Utc>) -> String {
+    format!("{}-{}.sqlite", prefix, date.format("%Y%m%d-%H%M%S"))
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+/// Write consistent snapshot of the database using VACUUM INTO.
+/// Safe to call while payments are processed, the snapshot contains only committed data.
+pub async fn backup_sqlite(
+    conn: &SqlitePool,
+    backup_dir: &Path,
+    prefix: &str,
+) -> Result<PathBuf, PaymentError> {
+    tokio::fs::create_dir_all(backup_dir)
+        .await
+        .map_err(err_from!())?;
+    let path = backup_dir.join(backup_file_name(prefix, Utc::now()));
+    if path.exists() {
+        return Err(err_custom_create!(
+            "Backup file {} already exists",
+            path.display()
+        ));
+    }
+    let path_str = path
+        .to_str()
+        .ok_or_else(|| err_custom_create!("path not convertible to string: {path:?}"))?;
+    sqlx::query("VACUUM INTO $1")
+        .bind(path_str)
+        .execute(conn)
+        .await
+        .map_err(err_from!())?;
+    Ok(path)
+}
+
+/// Backups with given prefix, oldest first
+pub fn list_backups(backup_dir: &Path, prefix: &str) -> Result<Vec<PathBuf>, PaymentError> {
+    let mut backups = Vec::new();
+    for entry in std::fs::read_dir(backup_dir).map_err(err_from!())? {
+        let path = entry.map_err(err_from!())?.path();
+        let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        let Some(date_part) = name
+            .strip_prefix(prefix)
+            .and_then(|n| n.strip_prefix('-'))
+            .and_then(|n| n.strip_suffix(".sqlite"))
+        else {
+            continue;
+        };
+        if chrono::NaiveDateTime::parse_from_str(date_part, "%Y%m%d-%H%M%S").is_ok() {
+            backups.push(path);
+        }
+    }
+    // names contain timestamp, so they are sorted by creation date
+    backups.sort();
+    Ok(backups)
+}
+
+/// Remove oldest backups so that only `keep` newest are left, returns removed files
+pub fn rotate_backups(
+    backup_dir: &Path,
+    prefix: &str,
+    keep: usize,
+) -> Result<Vec<PathBuf>, PaymentError> {
+    let backups = list_backups(backup_dir, prefix)?;
+    let remove_count = backups.len().saturating_sub(keep);
+    let removed = backups.into_iter().take(remove_count).collect::<Vec<_>>();
+    for path in &removed {
+        std::fs::remove_file(path).map_err(err_from!())?;
+    }
+    Ok(removed)
+}
+
+/// Check that file is a complete payment database that can be used by this version
+pub async fn validate_backup(path: &Path) -> Result<(), PaymentError> {
+    if !path.exists() {
+        return Err(err_custom_create!(
+            "Backup file {} not found",
+            path.display()
+        ));
+    }
+    let conn = sqlx::sqlite::SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect_with(SqliteConnectOptions::new().filename(path).read_only(true))
+        .await
+        .map_err(err_from!())?;
+    let integrity: String = sqlx::query_scalar("PRAGMA integrity_check")
+        .fetch_one(&conn)
+        .await
+        .map_err(err_from!())?;
+    if integrity != "ok" {
+        conn.close().await;
+        return Err(err_custom_create!(
+            "Backup {} is corrupted: {}",
+            path.display(),
+            integrity
+        ));
+    }
+    let res = check_sqlite_migrations(&conn).await;
+    conn.close().await;
+    res
+}
+
+/// Marks database as used by running instance, see [`is_instance_running`].
+/// Marker file is removed when dropped.
+pub struct InstanceLock {
+    path: PathBuf,
+    heartbeat: JoinHandle<()>,
+}
+
+impl InstanceLock {
+    pub async fn new(db_path: &Path) -> Result<Self, PaymentError> {
+        let path = with_suffix(db_path, ".instance");
+        let pid = std::process::id().to_string();
+        tokio::fs::write(&path, &pid).await.map_err(err_from!())?;
+        let path_ = path.clone();
+        let heartbeat = tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(INSTANCE_HEARTBEAT).await;
+                if let Err(err) = tokio::fs::write(&path_, &pid).await {
+                    log::warn!(
+                        "Failed to refresh instance file {}: {}",
+                        path_.display(),
+                        err
+                    );
+                }
+            }
+        });
+        Ok(Self { path, heartbeat })
+    }
+}
+
+impl Drop for InstanceLock {
+    fn drop(&mut self) {
+        self.heartbeat.abort();
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Database is considered in use when it is locked (connections in WAL mode hold a lock
+/// as long as they are open) or when its instance file was refreshed recently
+pub async fn is_instance_running(db_path: &Path) -> Result<bool, PaymentError> {
+    let instance_file = with_suffix(db_path, ".instance");
+    if let Ok(modified) = std::fs::metadata(&instance_file).and_then(|m| m.modified()) {
+        if modified.elapsed().unwrap_or_default() < INSTANCE_HEARTBEAT * 3 {
+            return Ok(true);
+        }
+    }
+    if !db_path.exists() {
+        return Ok(false);
+    }
+    let mut conn = SqliteConnectOptions::new()
+        .filename(db_path)
+        .locking_mode(SqliteLockingMode::Exclusive)
+        .busy_timeout(Duration::ZERO)
+        .connect()
+        .await
+        .map_err(err_from!())?;
+    let locked = conn.execute("BEGIN EXCLUSIVE").await.is_err();
+    if !locked {
+        conn.execute("ROLLBACK").await.map_err(err_from!())?;
+    }
+    conn.close().await.map_err(err_from!())?;
+    Ok(locked)
+}
+
+/// Replace database file with validated backup.
+/// Refuses to overwrite database used by running instance.
+pub async fn restore_sqlite(backup_path: &Path, db_path: &Path) -> Result<(), PaymentError> {
+    validate_backup(backup_path).await?;
+    if is_instance_running(db_path).await? {
+        return Err(err_custom_create!(
+            "Database {} is used by running instance, stop it before restoring",
+            db_path.display()
+        ));
+    }
+    let tmp_path = with_suffix(db_path, ".restore");
+    tokio::fs::copy(backup_path, &tmp_path)
+        .await
+        .map_err(err_from!())?;
+    // leftover journal of the old database would be applied to restored one
+    for suffix in ["-wal", "-shm", "-journal"] {
+        let journal = with_suffix(db_path, suffix);
+        if journal.exists() {
+            tokio::fs::remove_file(&journal)
+                .await
+                .map_err(err_from!())?;
+        }
+    }
+    tokio::fs::rename(&tmp_path, db_path)
+        .await
+        .map_err(err_from!())?;
+    Ok(())
+}
+
+/// Periodically write backup and remove old ones
+pub fn start_backup_job(conn: SqlitePool, settings: BackupSettings) -> JoinHandle<()> {
+    let interval = Duration::from_secs(settings.interval_secs.unwrap_or(24 * 3600));
+    let prefix = settings.prefix.unwrap_or("erc20lib".to_string());
+    tokio::spawn(async move {
+        loop {
+            match backup_sqlite(&conn, &settings.backup_dir, &prefix).await {
+                Ok(path) => log::info!("Database backup written to {}", path.display()),
+                Err(err) => log::error!("Failed to backup database: {}", err),
+            }
+            if let Some(keep) = settings.keep {
+                if let Err(err) = rotate_backups(&settings.backup_dir, &prefix, keep) {
+                    log::error!("Failed to remove old backups: {}", err);
+                }
+            }
+            tokio::time::sleep(interval).await;
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use erc20_payment_lib_common::create_sqlite_connection;
+    use std::env;
+
+    #[tokio::test]
+    async fn test_backup_rotate_restore() {
+        let dir = env::temp_dir().join(format!("erc20_backup_test_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let db_path = dir.join("db.sqlite");
+
+        let conn = create_sqlite_connection(Some(&db_path), None, false, true)
+            .await
+            .unwrap();
+        let backup = backup_sqlite(&conn, &dir.join("backups"), "db")
+            .await
+            .unwrap();
+        validate_backup(&backup).await.unwrap();
+
+        // pool keeps the database open, but only WAL mode connections hold the lock
+        let instance = InstanceLock::new(&db_path).await.unwrap();
+        assert!(restore_sqlite(&backup, &db_path).await.is_err());
+        drop(instance);
+        conn.close().await;
+        restore_sqlite(&backup, &db_path).await.unwrap();
+
+        for date in ["20240101-000000", "20240102-000000"] {
+            std::fs::write(dir.join("backups").join(format!("db-{date}.sqlite")), "").unwrap();
+        }
+        std::fs::write(dir.join("backups").join("other.sqlite"), "").unwrap();
+        let removed = rotate_backups(&dir.join("backups"), "db", 1).unwrap();
+        assert_eq!(removed.len(), 2);
+        assert_eq!(
+            list_backups(&dir.join("backups"), "db").unwrap(),
+            vec![backup]
+        );
+
+        assert!(validate_backup(&dir.join("backups").join("other.sqlite"))
+            .await
+            .is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1,12 +1,17 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::collections::btree_map::BTreeMap as Map;
+use std::fmt;
+use std::net::IpAddr;
 
 use rust_decimal::Decimal;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use crate::err_custom_create;
 use crate::error::*;
-use erc20_payment_lib_common::err_create;
+use crate::server::auth::ApiRole;
+use erc20_payment_lib_common::model::TransferPriority;
+use erc20_payment_lib_common::{err_create, DnsResolverSettings};
+use erc20_rpc_pool::{parse_source_signer, Web3EndpointParams, Web3RpcHeader, Web3RpcSecret};
 use tokio::fs;
 use web3::types::Address;
 
@@ -67,6 +72,230 @@ pub struct Engine {
 pub struct Config {
     pub chain: Map<String, Chain>,
     pub engine: Engine,
+    /// DNS resolver used for dns-source endpoints and faucet discovery
+    pub dns: Option<DnsResolverSettings>,
+    /// Periodically move old finished transactions to archive file
+    pub archive: Option<ArchiveSettings>,
+    /// Periodically write snapshot of SQLite database
+    pub backup: Option<BackupSettings>,
+    /// Rules holding back transfers of given priority until conditions are met
+    pub scheduling: Option<Vec<SchedulingRule>>,
+    /// Minimum payouts, transfers to receiver accumulate until threshold is reached
+    pub payout: Option<Vec<PayoutThreshold>>,
+    /// Spending limits, violating transfers are not sent until approved
+    pub policy: Option<Vec<SpendingPolicy>>,
+    /// Authentication of http api, all endpoints except management ones are public when not set
+    pub api_auth: Option<ApiAuthSettings>,
+    /// Http endpoints receiving driver events
+    pub webhook: Option<Vec<WebhookSettings>>,
+    /// Persistent log of driver events, used to resume event streams
+    pub event_log: Option<EventLogSettings>,
+    /// Limits of the faucet endpoint, only used when the faucet is enabled
+    pub faucet: Option<FaucetSettings>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct SchedulingRule {
+    /// Transfers of this priority are held back until all conditions of the rule are met,
+    /// urgent transfers are never held back
+    pub priority: TransferPriority,
+    /// Sender the rule applies to, all senders when not set
+    pub account: Option<Address>,
+    /// Chain the rule applies to, all chains when not set
+    pub chain_id: Option<i64>,
+    /// Base fee of the latest block has to be below this value (gwei)
+    pub max_base_fee: Option<Decimal>,
+    /// Time ranges in UTC, i.e. "02:00-06:00", current time has to be in one of them
+    pub time_windows: Option<Vec<String>>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct PayoutThreshold {
+    /// Sender the threshold applies to, all senders when not set
+    pub account: Option<Address>,
+    /// Receiver the threshold applies to, all receivers when not set
+    pub receiver: Option<Address>,
+    /// Chain the threshold applies to, all chains when not set
+    pub chain_id: Option<i64>,
+    /// "native", "erc20" or token address, native and all token transfers when not set
+    pub token: Option<String>,
+    /// Pending transfers to receiver are paid when their sum reaches this amount
+    /// (in token units, or ETH for native transfers)
+    pub min_amount: Decimal,
+    /// Pending transfers are paid regardless of amount after oldest one waits this long
+    pub max_age_secs: Option<u64>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct SpendingPolicy {
+    /// Sender the policy applies to, all senders when not set
+    pub account: Option<Address>,
+    /// Chain the policy applies to, all chains when not set
+    pub chain_id: Option<i64>,
+    /// Token the policy applies to, all tokens and native currency when not set
+    pub token_addr: Option<Address>,
+    /// Maximum amount of single transfer (in token units, or ETH for native transfers)
+    pub max_transfer_amount: Option<Decimal>,
+    /// Maximum amount sent in last 24 hours, counted separately for every token
+    pub max_daily_amount: Option<Decimal>,
+    /// Only these receivers can be paid when set
+    pub allowed_receivers: Option<Vec<Address>>,
+    /// These receivers are never paid
+    pub denied_receivers: Option<Vec<Address>>,
+    /// Transfers to receiver are held back until first transfer to it is this old
+    pub new_receiver_cooldown_secs: Option<u64>,
+}
+
+/// Secret of api credentials, webhook signature or captcha, never printed in plain text.
+/// Serialized as is, so configuration can be written back.
+#[derive(Clone, PartialEq, Eq)]
+pub struct ApiSecret(String);
+
+impl ApiSecret {
+    pub fn new(secret: String) -> Self {
+        Self(secret)
+    }
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for ApiSecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "***")
+    }
+}
+
+impl Serialize for ApiSecret {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for ApiSecret {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Self(String::deserialize(deserializer)?))
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct ApiAuthSettings {
+    /// Roles of requests without credentials, read-only when not set
+    pub anonymous_roles: Option<Vec<ApiRole>>,
+    /// Static tokens sent in Authorization: Bearer header
+    pub tokens: Option<Vec<ApiTokenSettings>>,
+    /// Keys for requests signed with HMAC-SHA256
+    pub hmac_keys: Option<Vec<ApiHmacKeySettings>>,
+    /// Allowed difference between signature timestamp and server time, defaults to 300
+    pub hmac_max_skew_secs: Option<u64>,
+    /// Header with subject of client certificate verified by TLS terminating proxy,
+    /// proxy has to remove this header from incoming requests
+    pub client_cert_header: Option<String>,
+    /// Addresses of TLS terminating proxies, client certificate header sent from
+    /// any other address is rejected
+    pub trusted_proxies: Option<Vec<IpAddr>>,
+    pub client_certs: Option<Vec<ApiClientCertSettings>>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct ApiTokenSettings {
+    pub name: String,
+    pub token: ApiSecret,
+    pub roles: Vec<ApiRole>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct ApiHmacKeySettings {
+    pub key_id: String,
+    pub secret: ApiSecret,
+    pub roles: Vec<ApiRole>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct ApiClientCertSettings {
+    pub subject: String,
+    pub roles: Vec<ApiRole>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct EventLogSettings {
+    /// Events older than this are removed from the log, defaults to 30
+    pub retention_days: Option<u64>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(rename_all = "kebab-case")]
+pub struct FaucetSettings {
+    /// Minimum time between requests for the same address, defaults to 120
+    pub address_interval_secs: Option<u64>,
+    /// Minimum time between requests from the same client IP, no limit when not set
+    pub ip_interval_secs: Option<u64>,
+    /// Requests for the same address in the last 24 hours
+    pub max_requests_per_address: Option<u64>,
+    /// Requests from the same client IP in the last 24 hours
+    pub max_requests_per_ip: Option<u64>,
+    /// Take client IP from `Forwarded` or `X-Forwarded-For` header, enable only behind proxy
+    #[serde(default)]
+    pub trust_forwarded_for: bool,
+    /// Require captcha solved by the client, sent in `X-Captcha-Token` header
+    pub captcha: Option<FaucetCaptchaSettings>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct FaucetCaptchaSettings {
+    /// Siteverify endpoint, i.e. https://hcaptcha.com/siteverify or
+    /// https://challenges.cloudflare.com/turnstile/v0/siteverify
+    pub verify_url: String,
+    pub secret: ApiSecret,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct WebhookSettings {
+    /// Unique name of the webhook, shown in delivery log
+    pub name: String,
+    pub url: String,
+    /// Event types to send, i.e. "transferFinished", all except "alive" when not set
+    pub events: Option<Vec<String>>,
+    /// Payloads are signed with HMAC-SHA256 using this secret when set
+    pub secret: Option<ApiSecret>,
+    /// Delivery is abandoned after this many failed attempts, defaults to 10
+    pub max_attempts: Option<u32>,
+    /// Defaults to 10
+    pub timeout_secs: Option<u64>,
+    /// Maximum number of requests sent to the webhook at once, defaults to 4
+    pub concurrency: Option<usize>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct BackupSettings {
+    pub backup_dir: PathBuf,
+    /// Defaults to one day
+    pub interval_secs: Option<u64>,
+    /// Number of newest backups to keep, all are kept when not set
+    pub keep: Option<usize>,
+    /// Backup file name prefix, defaults to erc20lib
+    pub prefix: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct ArchiveSettings {
+    pub archive_file: PathBuf,
+    /// Rows older than this are moved to archive
+    pub retention_days: u64,
+    /// Defaults to one hour
+    pub interval_secs: Option<u64>,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -123,6 +352,64 @@ pub struct FaucetClientSettings {
     pub faucet_lookup_domain: String,
 }
 
+/// Http header attached to every request sent to the rpc endpoint.
+/// Value is taken from (in order) value-env, value-file or value.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct RpcHeaderSettings {
+    pub name: String,
+    /// Plain value, prefer value-env or value-file for secrets
+    pub value: Option<Web3RpcSecret>,
+    /// Name of environment variable containing value
+    pub value_env: Option<String>,
+    /// Path to the file containing value (trailing whitespace is trimmed)
+    pub value_file: Option<String>,
+    /// Prepended to the value, i.e. "Bearer "
+    pub value_prefix: Option<String>,
+}
+
+impl RpcHeaderSettings {
+    pub fn resolve(&self) -> Result<Web3RpcHeader, PaymentError> {
+        let value = if let Some(env_name) = &self.value_env {
+            std::env::var(env_name).map_err(|e| {
+                err_custom_create!(
+                    "Failed to read env {} for header {}: {}",
+                    env_name,
+                    self.name,
+                    e
+                )
+            })?
+        } else if let Some(file) = &self.value_file {
+            std::fs::read_to_string(file)
+                .map_err(|e| {
+                    err_custom_create!(
+                        "Failed to read file {} for header {}: {}",
+                        file,
+                        self.name,
+                        e
+                    )
+                })?
+                .trim_end()
+                .to_string()
+        } else if let Some(value) = &self.value {
+            value.expose().to_string()
+        } else {
+            return Err(err_custom_create!(
+                "Header {} has to have value, value-env or value-file",
+                self.name
+            ));
+        };
+        Ok(Web3RpcHeader {
+            name: self.name.clone(),
+            value: Web3RpcSecret::new(format!(
+                "{}{}",
+                self.value_prefix.clone().unwrap_or_default(),
+                value
+            )),
+        })
+    }
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(rename_all = "kebab-case")]
 pub struct RpcSettings {
@@ -137,6 +424,61 @@ pub struct RpcSettings {
     pub max_timeout_ms: Option<u64>,
     pub allowed_head_behind_secs: Option<i64>,
     pub max_consecutive_errors: Option<u64>,
+    /// Maximum number of requests sent in a burst (token bucket capacity)
+    pub rate_limit_burst: Option<u64>,
+    /// Number of requests per second allowed on average (token bucket refill rate)
+    pub rate_limit_per_sec: Option<f64>,
+    /// Maximum number of requests per day (UTC), endpoint is not used when exhausted
+    pub daily_request_budget: Option<u64>,
+    /// Maximum number of requests per calendar month (UTC)
+    pub monthly_request_budget: Option<u64>,
+    pub headers: Option<Vec<RpcHeaderSettings>>,
+    /// Address (or public key) that has to sign lists from dns-source/json-source
+    pub source_signer: Option<String>,
+    /// Set for endpoints overriding config by env variable, these are kept on config reload
+    #[serde(skip)]
+    pub from_env: bool,
+}
+
+impl RpcSettings {
+    pub fn source_signer(&self) -> Result<Option<Address>, PaymentError> {
+        self.source_signer
+            .as_deref()
+            .map(|signer| parse_source_signer(signer).map_err(|e| err_custom_create!("{}", e)))
+            .transpose()
+    }
+
+    pub fn resolve_headers(&self) -> Result<Vec<Web3RpcHeader>, PaymentError> {
+        self.headers
+            .iter()
+            .flatten()
+            .map(|header| header.resolve())
+            .collect()
+    }
+
+    /// Endpoint parameters shared by all endpoints defined by this entry (including dns and json sources)
+    pub fn endpoint_params(&self) -> Result<Web3EndpointParams, PaymentError> {
+        let max_head_behind_secs = self.allowed_head_behind_secs.unwrap_or(120);
+        let max_head_behind_secs = if max_head_behind_secs < 0 {
+            None
+        } else {
+            Some(max_head_behind_secs as u64)
+        };
+        Ok(Web3EndpointParams {
+            backup_level: self.backup_level.unwrap_or(0),
+            skip_validation: self.skip_validation.unwrap_or(false),
+            verify_interval_secs: self.verify_interval_secs.unwrap_or(120),
+            max_response_time_ms: self.max_timeout_ms.unwrap_or(10000),
+            max_head_behind_secs,
+            max_number_of_consecutive_errors: self.max_consecutive_errors.unwrap_or(5),
+            min_interval_requests_ms: self.min_interval_ms,
+            rate_limit_burst: self.rate_limit_burst,
+            rate_limit_per_sec: self.rate_limit_per_sec,
+            daily_request_budget: self.daily_request_budget,
+            monthly_request_budget: self.monthly_request_budget,
+            headers: self.resolve_headers()?,
+        })
+    }
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -161,9 +503,26 @@ pub struct Chain {
     pub confirmation_blocks: u64,
     pub faucet_eth_amount: Option<Decimal>,
     pub faucet_glm_amount: Option<Decimal>,
+    /// Faucet requests allowed on this chain in the last 24 hours
+    pub faucet_daily_limit: Option<u64>,
     pub block_explorer_url: Option<String>,
     pub replacement_timeout: Option<f64>,
     pub external_source_check_interval: Option<u64>,
+    /// Wait with sending batches until it is worth paying the fee
+    pub batching: Option<BatchingSettings>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct BatchingSettings {
+    /// Minimum number of transfers in batch
+    pub min_batch_size: Option<usize>,
+    /// Maximum estimated fee as percentage of the batch value
+    pub max_fee_percent: Option<Decimal>,
+    /// Price of the token in gas currency, needed to apply max-fee-percent to token batches
+    pub token_price: Option<Decimal>,
+    /// Batch is sent regardless of other limits when its oldest transfer waits that long
+    pub max_wait_secs: Option<u64>,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
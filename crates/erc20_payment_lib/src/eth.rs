@@ -270,7 +270,7 @@ pub async fn validate_deposit_eth(
     })
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct AttestationSchema {
     pub uid: H256,
     pub resolver: Address,
@@ -324,7 +324,7 @@ pub async fn get_schema_details(
     Ok(schema)
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Attestation {
     pub uid: H256,
     pub schema: H256,
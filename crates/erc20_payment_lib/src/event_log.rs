@@ -0,0 +1,338 @@
+//! Persistent log of driver events. Events are stored before they are broadcast, so consumers
+//! of event streams can resume from the id of the last event they have seen without gaps.
+
+use crate::config::EventLogSettings;
+use chrono::Utc;
+use erc20_payment_lib_common::model::DriverEventDbObj;
+use erc20_payment_lib_common::store::PaymentStore;
+use erc20_payment_lib_common::{DriverEvent, DriverEventContent, DRIVER_EVENT_TYPES};
+use futures::{Stream, StreamExt};
+use std::collections::{HashSet, VecDeque};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+
+const DEFAULT_RETENTION_DAYS: u64 = 30;
+const PRUNE_INTERVAL: Duration = Duration::from_secs(3600);
+/// Number of stored events loaded at once when replaying
+const REPLAY_BATCH_SIZE: i64 = 500;
+
+pub struct EventLog<S: PaymentStore> {
+    conn: S,
+    retention: chrono::Duration,
+    last_prune: Option<Instant>,
+}
+
+impl<S: PaymentStore> EventLog<S> {
+    pub fn new(conn: S, settings: Option<&EventLogSettings>) -> Self {
+        let retention_days = settings
+            .and_then(|s| s.retention_days)
+            .unwrap_or(DEFAULT_RETENTION_DAYS);
+        Self {
+            conn,
+            retention: chrono::Duration::days(retention_days as i64),
+            last_prune: None,
+        }
+    }
+
+    /// Store event and set its id. Alive events are not stored, event is passed on
+    /// without id when it cannot be stored.
+    pub async fn append(&mut self, mut event: DriverEvent) -> DriverEvent {
+        if matches!(event.content, DriverEventContent::Alive) {
+            return event;
+        }
+        event.id = None;
+        let payload = match serde_json::to_string(&event) {
+            Ok(payload) => payload,
+            Err(err) => {
+                log::error!("Failed to serialize driver event: {}", err);
+                return event;
+            }
+        };
+        match self
+            .conn
+            .insert_driver_event(&DriverEventDbObj {
+                id: 0,
+                event_type: event.content.event_type().to_string(),
+                create_date: event.create_date,
+                payload,
+            })
+            .await
+        {
+            Ok(row) => event.id = Some(row.id),
+            Err(err) => log::error!("Failed to store driver event: {}", err),
+        }
+        self.prune_if_needed().await;
+        event
+    }
+
+    async fn prune_if_needed(&mut self) {
+        if self
+            .last_prune
+            .is_some_and(|last| last.elapsed() < PRUNE_INTERVAL)
+        {
+            return;
+        }
+        self.last_prune = Some(Instant::now());
+        if let Err(err) = self
+            .conn
+            .delete_driver_events_before(Utc::now() - self.retention)
+            .await
+        {
+            log::error!("Failed to remove old driver events: {}", err);
+        }
+    }
+}
+
+/// Serialized event sent to stream consumers
+#[derive(Debug, Clone)]
+pub struct EventLogEntry {
+    /// None for events that are not stored
+    pub id: Option<i64>,
+    pub event_type: String,
+    pub json: String,
+}
+
+impl EventLogEntry {
+    fn from_event(event: &DriverEvent) -> Self {
+        Self {
+            id: event.id,
+            event_type: event.content.event_type().to_string(),
+            json: serde_json::to_string(event).expect("Failed to serialize DriverEvent"),
+        }
+    }
+
+    pub(crate) fn from_row(row: DriverEventDbObj) -> Self {
+        let json = match serde_json::from_str::<serde_json::Value>(&row.payload) {
+            Ok(serde_json::Value::Object(mut payload)) => {
+                payload.insert("id".to_string(), row.id.into());
+                serde_json::Value::Object(payload).to_string()
+            }
+            _ => row.payload,
+        };
+        Self {
+            id: Some(row.id),
+            event_type: row.event_type,
+            json,
+        }
+    }
+}
+
+/// Parse comma separated list of event types, see [`DriverEventContent::event_type`]
+pub fn parse_event_types(list: &str) -> Result<HashSet<String>, String> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .map(|t| {
+            if DRIVER_EVENT_TYPES.contains(&t) {
+                Ok(t.to_string())
+            } else {
+                Err(format!(
+                    "Unknown event type {}, expected one of: {}",
+                    t,
+                    DRIVER_EVENT_TYPES.join(", ")
+                ))
+            }
+        })
+        .collect()
+}
+
+struct EventStreamState<S> {
+    conn: S,
+    rx: broadcast::Receiver<DriverEvent>,
+    last_id: Option<i64>,
+    backlog: VecDeque<EventLogEntry>,
+    catching_up: bool,
+}
+
+/// Stored events with id greater than `since` followed by live events from `rx`.
+/// `rx` has to be subscribed before calling, so no event is lost between replay and live part.
+/// When receiver lags behind, skipped events are loaded from the log.
+pub fn event_stream<S: PaymentStore>(
+    conn: S,
+    rx: broadcast::Receiver<DriverEvent>,
+    since: Option<i64>,
+) -> impl Stream<Item = EventLogEntry> {
+    let state = EventStreamState {
+        conn,
+        rx,
+        last_id: since,
+        backlog: VecDeque::new(),
+        catching_up: since.is_some(),
+    };
+    futures::stream::unfold(state, |mut state| async move {
+        loop {
+            if let Some(entry) = state.backlog.pop_front() {
+                state.last_id = entry.id.or(state.last_id);
+                return Some((entry, state));
+            }
+            if state.catching_up {
+                match state
+                    .conn
+                    .get_driver_events_after(state.last_id.unwrap_or(0), REPLAY_BATCH_SIZE)
+                    .await
+                {
+                    Ok(rows) => {
+                        state.catching_up = rows.len() as i64 >= REPLAY_BATCH_SIZE;
+                        state
+                            .backlog
+                            .extend(rows.into_iter().map(EventLogEntry::from_row));
+                    }
+                    Err(err) => {
+                        log::error!("Failed to load driver events: {}", err);
+                        state.catching_up = false;
+                    }
+                }
+                continue;
+            }
+            match state.rx.recv().await {
+                Ok(event) => {
+                    if let (Some(id), Some(last_id)) = (event.id, state.last_id) {
+                        if id <= last_id {
+                            // already sent from the log
+                            continue;
+                        }
+                    }
+                    let entry = EventLogEntry::from_event(&event);
+                    state.last_id = entry.id.or(state.last_id);
+                    return Some((entry, state));
+                }
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    if state.last_id.is_some() {
+                        log::debug!("Event stream skipped {} events, loading them from log", n);
+                        state.catching_up = true;
+                    } else {
+                        log::warn!("Event stream skipped {} events", n);
+                    }
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    })
+}
+
+/// [`event_stream`] limited to given event types, all events are passed when None
+pub fn filtered_event_stream<S: PaymentStore>(
+    conn: S,
+    rx: broadcast::Receiver<DriverEvent>,
+    since: Option<i64>,
+    event_types: Option<HashSet<String>>,
+) -> impl Stream<Item = EventLogEntry> {
+    event_stream(conn, rx, since).filter(move |entry| {
+        futures::future::ready(
+            event_types
+                .as_ref()
+                .is_none_or(|types| types.contains(&entry.event_type)),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use erc20_payment_lib_common::store::MemoryStore;
+    use erc20_payment_lib_common::TransactionFailedReason;
+
+    async fn next_id(stream: &mut (impl Stream<Item = EventLogEntry> + Unpin)) -> i64 {
+        stream.next().await.unwrap().id.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_event_stream_resume() {
+        let conn = MemoryStore::new();
+        let mut log = EventLog::new(conn.clone(), None);
+        let (tx, _) = broadcast::channel(2);
+        let status_event = || DriverEvent::now(DriverEventContent::StatusChanged(vec![]));
+
+        for _ in 0..3 {
+            let event = log.append(status_event()).await;
+            assert!(event.id.is_some());
+        }
+        assert_eq!(
+            log.append(DriverEvent::now(DriverEventContent::Alive))
+                .await
+                .id,
+            None
+        );
+
+        let stream = event_stream(conn.clone(), tx.subscribe(), Some(1));
+        futures::pin_mut!(stream);
+        // replayed from log
+        assert_eq!(next_id(&mut stream).await, 2);
+        assert_eq!(next_id(&mut stream).await, 3);
+
+        // receiver lags behind, keeping only events 6 and 7
+        for _ in 0..4 {
+            let _ = tx.send(log.append(status_event()).await);
+        }
+        for id in 4..=7 {
+            assert_eq!(next_id(&mut stream).await, id);
+        }
+        // 6 and 7 left in receiver are not sent again
+        let _ = tx.send(log.append(status_event()).await);
+        assert_eq!(next_id(&mut stream).await, 8);
+    }
+
+    #[test]
+    fn test_parse_event_types() {
+        assert_eq!(
+            parse_event_types(" transferFinished, statusChanged ,").unwrap(),
+            HashSet::from(["transferFinished".to_string(), "statusChanged".to_string()])
+        );
+        assert!(parse_event_types("").unwrap().is_empty());
+        let err = parse_event_types("transferFinished,transferfinished").unwrap_err();
+        assert!(err.contains("Unknown event type transferfinished"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_filtered_event_stream() {
+        let conn = MemoryStore::new();
+        let mut log = EventLog::new(conn.clone(), None);
+        let (tx, _) = broadcast::channel(10);
+        let status_event = || DriverEvent::now(DriverEventContent::StatusChanged(vec![]));
+        let failed_event = || {
+            DriverEvent::now(DriverEventContent::TransactionFailed(
+                TransactionFailedReason::Unknown,
+            ))
+        };
+
+        log.append(status_event()).await;
+        log.append(failed_event()).await;
+        let stream = filtered_event_stream(
+            conn.clone(),
+            tx.subscribe(),
+            Some(0),
+            Some(parse_event_types("transactionFailed,alive").unwrap()),
+        );
+        futures::pin_mut!(stream);
+        // replayed part is filtered
+        let entry = stream.next().await.unwrap();
+        assert_eq!(
+            (entry.id, entry.event_type.as_str()),
+            (Some(2), "transactionFailed")
+        );
+
+        // live part is filtered, alive events are not stored but passed
+        let _ = tx.send(log.append(status_event()).await);
+        let _ = tx.send(
+            log.append(DriverEvent::now(DriverEventContent::Alive))
+                .await,
+        );
+        let _ = tx.send(log.append(failed_event()).await);
+        let entry = stream.next().await.unwrap();
+        assert_eq!((entry.id, entry.event_type.as_str()), (None, "alive"));
+        let entry = stream.next().await.unwrap();
+        assert_eq!(
+            (entry.id, entry.event_type.as_str()),
+            (Some(4), "transactionFailed")
+        );
+
+        // no filter passes all events
+        let stream = filtered_event_stream(conn.clone(), tx.subscribe(), Some(2), None);
+        futures::pin_mut!(stream);
+        let entry = stream.next().await.unwrap();
+        assert_eq!(
+            (entry.id, entry.event_type.as_str()),
+            (Some(3), "statusChanged")
+        );
+    }
+}
@@ -0,0 +1,293 @@
+//! Abuse protection of the faucet endpoint. Served requests are stored in the database,
+//! so limits survive restarts and are checked over exact time windows.
+
+use crate::config::FaucetSettings;
+use crate::err_custom_create;
+use crate::error::PaymentError;
+use chrono::{DateTime, Utc};
+use erc20_payment_lib_common::err_from;
+use erc20_payment_lib_common::error::*;
+use erc20_payment_lib_common::store::{FaucetRequestListFilter, PaymentStoreOps};
+use serde::Deserialize;
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+
+/// Captcha solved by the client, see `captcha` faucet setting
+pub const CAPTCHA_TOKEN_HEADER: &str = "X-Captcha-Token";
+pub const DEFAULT_ADDRESS_INTERVAL_SECS: u64 = 120;
+const CAPTCHA_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Reason of refusing faucet request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FaucetDenied {
+    /// Limit reached, request can be repeated after given number of seconds
+    Limited {
+        reason: String,
+        retry_after_secs: u64,
+    },
+    /// Captcha token missing or not accepted
+    Captcha(String),
+}
+
+impl Display for FaucetDenied {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FaucetDenied::Limited {
+                reason,
+                retry_after_secs,
+            } => write!(f, "{}. Try again in {} seconds", reason, retry_after_secs),
+            FaucetDenied::Captcha(reason) => write!(f, "Captcha verification failed: {}", reason),
+        }
+    }
+}
+
+/// Response of siteverify endpoint, the same for hCaptcha, reCAPTCHA and Turnstile
+#[derive(Deserialize)]
+struct CaptchaVerifyResponse {
+    success: bool,
+    #[serde(default, rename = "error-codes")]
+    error_codes: Vec<String>,
+}
+
+pub struct FaucetLimits {
+    settings: FaucetSettings,
+    client: reqwest::Client,
+}
+
+impl FaucetLimits {
+    pub fn new(settings: Option<&FaucetSettings>) -> Result<Self, PaymentError> {
+        let client = reqwest::Client::builder()
+            .timeout(CAPTCHA_TIMEOUT)
+            .build()
+            .map_err(|e| err_custom_create!("Failed to build captcha client: {}", e))?;
+        Ok(Self {
+            settings: settings.cloned().unwrap_or_default(),
+            client,
+        })
+    }
+
+    pub fn trust_forwarded_for(&self) -> bool {
+        self.settings.trust_forwarded_for
+    }
+
+    /// Check captcha token with configured siteverify endpoint, passes when captcha is not configured
+    pub async fn verify_captcha(
+        &self,
+        token: Option<&str>,
+        client_ip: Option<&str>,
+    ) -> Result<(), FaucetDenied> {
+        let Some(captcha) = &self.settings.captcha else {
+            return Ok(());
+        };
+        let token = token
+            .filter(|t| !t.is_empty())
+            .ok_or_else(|| FaucetDenied::Captcha("missing captcha token".to_string()))?;
+        let mut form = vec![("secret", captcha.secret.expose()), ("response", token)];
+        if let Some(client_ip) = client_ip {
+            form.push(("remoteip", client_ip));
+        }
+        let response = self
+            .client
+            .post(&captcha.verify_url)
+            .form(&form)
+            .send()
+            .await
+            .map_err(|e| {
+                log::warn!("Captcha verification request failed: {}", e.without_url());
+                FaucetDenied::Captcha("verification service unavailable".to_string())
+            })?;
+        let body = response.bytes().await.map_err(|e| {
+            log::warn!("Captcha verification request failed: {}", e.without_url());
+            FaucetDenied::Captcha("verification service unavailable".to_string())
+        })?;
+        let result = serde_json::from_slice::<CaptchaVerifyResponse>(&body).map_err(|e| {
+            log::warn!("Invalid captcha verification response: {}", e);
+            FaucetDenied::Captcha("verification service unavailable".to_string())
+        })?;
+        if result.success {
+            Ok(())
+        } else {
+            Err(FaucetDenied::Captcha(if result.error_codes.is_empty() {
+                "token not accepted".to_string()
+            } else {
+                result.error_codes.join(", ")
+            }))
+        }
+    }
+
+    /// Check request against per-address, per-IP and per-chain limits.
+    /// Has to be called with the same connection lock held until the request is stored.
+    pub async fn check<T: PaymentStoreOps + ?Sized>(
+        &self,
+        ops: &T,
+        chain_id: i64,
+        chain_daily_limit: Option<u64>,
+        receiver: &str,
+        client_ip: Option<&str>,
+        now: DateTime<Utc>,
+    ) -> Result<Option<FaucetDenied>, PaymentError> {
+        let day = chrono::Duration::days(1);
+        let by_receiver = FaucetRequestListFilter {
+            chain_id: Some(chain_id),
+            receiver: Some(receiver.to_string()),
+            ..Default::default()
+        };
+        let address_interval = self
+            .settings
+            .address_interval_secs
+            .unwrap_or(DEFAULT_ADDRESS_INTERVAL_SECS);
+        let mut checks = vec![
+            (
+                by_receiver.clone(),
+                chrono::Duration::seconds(address_interval as i64),
+                Some(1),
+                "Already sent to this address recently",
+            ),
+            (
+                by_receiver,
+                day,
+                self.settings.max_requests_per_address,
+                "Daily limit of requests for this address reached",
+            ),
+            (
+                FaucetRequestListFilter {
+                    chain_id: Some(chain_id),
+                    ..Default::default()
+                },
+                day,
+                chain_daily_limit,
+                "Daily faucet limit of this chain reached",
+            ),
+        ];
+        if let Some(client_ip) = client_ip {
+            let by_ip = FaucetRequestListFilter {
+                chain_id: Some(chain_id),
+                client_ip: Some(client_ip.to_string()),
+                ..Default::default()
+            };
+            checks.push((
+                by_ip.clone(),
+                chrono::Duration::seconds(self.settings.ip_interval_secs.unwrap_or(0) as i64),
+                Some(1),
+                "Already requested from this IP recently",
+            ));
+            checks.push((
+                by_ip,
+                day,
+                self.settings.max_requests_per_ip,
+                "Daily limit of requests from this IP reached",
+            ));
+        }
+
+        for (filter, window, max_requests, reason) in checks {
+            let Some(max_requests) = max_requests else {
+                continue;
+            };
+            if window <= chrono::Duration::zero() {
+                continue;
+            }
+            let requests = ops
+                .list_faucet_requests(&FaucetRequestListFilter {
+                    created_from: Some(now - window),
+                    limit: Some(max_requests as i64),
+                    ..filter
+                })
+                .await
+                .map_err(err_from!())?;
+            if requests.len() as u64 >= max_requests {
+                // the oldest request in window has to expire first
+                let expires = requests
+                    .last()
+                    .map(|r| r.create_date + window)
+                    .unwrap_or(now);
+                return Ok(Some(FaucetDenied::Limited {
+                    reason: reason.to_string(),
+                    retry_after_secs: (expires - now).num_seconds().max(1) as u64,
+                }));
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use erc20_payment_lib_common::model::FaucetRequestDbObj;
+    use erc20_payment_lib_common::store::MemoryStore;
+
+    #[tokio::test]
+    async fn test_faucet_limits() {
+        let conn = MemoryStore::new();
+        let limits = FaucetLimits::new(Some(&FaucetSettings {
+            address_interval_secs: Some(60),
+            max_requests_per_ip: Some(2),
+            ..Default::default()
+        }))
+        .unwrap();
+        let start = DateTime::parse_from_rfc3339("2024-07-10T23:59:30Z")
+            .unwrap()
+            .to_utc();
+        let store_request = |receiver: &str, create_date: DateTime<Utc>| FaucetRequestDbObj {
+            id: 0,
+            chain_id: 17000,
+            receiver_addr: receiver.to_string(),
+            client_ip: Some("10.0.0.1".to_string()),
+            gas_transfer_id: None,
+            gas_amount: "1".to_string(),
+            glm_transfer_id: None,
+            glm_amount: "1".to_string(),
+            create_date,
+        };
+        let check = |receiver: &'static str, ip: &'static str, now: DateTime<Utc>| {
+            let (conn, limits) = (&conn, &limits);
+            async move {
+                limits
+                    .check(conn, 17000, Some(3), receiver, Some(ip), now)
+                    .await
+                    .unwrap()
+            }
+        };
+
+        conn.insert_faucet_request(&store_request("0xa", start))
+            .await
+            .unwrap();
+        // interval is checked across midnight
+        let now = start + chrono::Duration::seconds(40);
+        assert_eq!(
+            check("0xa", "10.0.0.2", now).await,
+            Some(FaucetDenied::Limited {
+                reason: "Already sent to this address recently".to_string(),
+                retry_after_secs: 20,
+            })
+        );
+        assert_eq!(
+            check("0xa", "10.0.0.2", start + chrono::Duration::seconds(61)).await,
+            None
+        );
+
+        conn.insert_faucet_request(&store_request("0xb", now))
+            .await
+            .unwrap();
+        assert!(matches!(
+            check("0xc", "10.0.0.1", now).await,
+            Some(FaucetDenied::Limited { reason, .. }) if reason.contains("this IP")
+        ));
+
+        conn.insert_faucet_request(&FaucetRequestDbObj {
+            client_ip: None,
+            ..store_request("0xc", now)
+        })
+        .await
+        .unwrap();
+        assert!(matches!(
+            check("0xd", "10.0.0.3", now).await,
+            Some(FaucetDenied::Limited { reason, .. }) if reason.contains("chain")
+        ));
+        // window of the first request expired
+        assert_eq!(
+            check("0xd", "10.0.0.3", start + chrono::Duration::seconds(86401)).await,
+            None
+        );
+    }
+}
@@ -1,10 +1,9 @@
 use crate::err_custom_create;
 use crate::error::PaymentError;
+use erc20_payment_lib_common::create_dns_resolver;
 use lazy_static::lazy_static;
 use regex::Regex;
 use std::io::{Error as IoError, ErrorKind as IoErrorKind};
-use trust_dns_resolver::config::{ResolverConfig, ResolverOpts};
-use trust_dns_resolver::TokioAsyncResolver;
 use url::Url;
 use web3::types::H160;
 
@@ -41,8 +40,7 @@ async fn resolve_faucet_url(
 
 pub async fn resolve_srv_record(record: &str) -> std::io::Result<String> {
     log::debug!("resolve_srv_record: {}", record);
-    let resolver: TokioAsyncResolver =
-        TokioAsyncResolver::tokio(ResolverConfig::google(), ResolverOpts::default());
+    let resolver = create_dns_resolver();
     let lookup = resolver.srv_lookup(record).await?;
     let srv = lookup
         .iter()
@@ -72,7 +70,7 @@ pub async fn resolve_dns_record(request_url: &str) -> Result<String, PaymentErro
 }
 
 pub async fn resolve_dns_record_host(host: &str) -> Result<String, PaymentError> {
-    let resolver = TokioAsyncResolver::tokio(ResolverConfig::google(), ResolverOpts::default());
+    let resolver = create_dns_resolver();
 
     let response = resolver
         .lookup_ip(host)
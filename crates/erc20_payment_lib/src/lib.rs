@@ -1,7 +1,11 @@
 mod account_balance;
+pub mod archive;
+pub mod backup;
 pub mod config;
 mod contracts;
 pub mod eth;
+pub mod event_log;
+pub mod faucet;
 pub mod faucet_client;
 pub mod misc;
 mod multi;
@@ -12,6 +16,7 @@ pub mod service;
 pub mod setup;
 pub mod signer;
 pub mod transaction;
+pub mod webhook;
 
 pub use contracts::DUMMY_RPC_PROVIDER;
 use erc20_payment_lib_common::*;
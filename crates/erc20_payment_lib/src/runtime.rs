@@ -3,26 +3,27 @@ use crate::transaction::{
     create_create_deposit, create_distribute_transaction, create_faucet_mint,
     create_terminate_deposit, create_token_transfer, find_receipt_extended, FindReceiptParseResult,
 };
-use crate::{err_custom_create, err_from};
-use erc20_payment_lib_common::create_sqlite_connection;
-use erc20_payment_lib_common::ops::{
-    cleanup_allowance_tx, cleanup_token_transfer_tx, delete_tx, get_last_unsent_tx,
-    get_token_transfers_by_deposit_id, get_transaction_chain, get_transactions,
-    get_unpaid_token_transfers, insert_token_transfer, insert_token_transfer_with_deposit_check,
-    insert_tx, update_token_transfer,
-};
+use crate::{err_create, err_custom_create, err_from};
+use erc20_payment_lib_common::{create_db_connection, redact_db_url, PaymentDbPool};
 use std::collections::BTreeMap;
 use std::ops::DerefMut;
 use std::path::PathBuf;
 use std::str::FromStr;
 
-use crate::error::{ErrorBag, PaymentError};
+use crate::error::{ErrorBag, PaymentError, TransferNotPending};
 
-use crate::setup::{ChainSetup, ExtraOptionsForTesting, PaymentSetup};
+use crate::setup::{reload_rpc_endpoints, ChainSetup, ExtraOptionsForTesting, PaymentSetup};
 
-use crate::config::{self, Config};
+use crate::archive::start_archive_job;
+use crate::backup::start_backup_job;
+use crate::config::{self, ArchiveSettings, BackupSettings, Config, WebhookSettings};
+use crate::event_log::EventLog;
+use crate::webhook::start_webhook_job;
+use erc20_payment_lib_common::store::{
+    BulkInsertError, PaymentDbStore, PaymentStore, PaymentStoreOps, PaymentStoreTransaction,
+    TransactionFilter, TransactionOrder, TransferInsertResult,
+};
 use secp256k1::SecretKey;
-use sqlx::SqlitePool;
 
 use crate::account_balance::{test_balance_loop, BalanceOptions2};
 use crate::config::AdditionalOptions;
@@ -31,21 +32,25 @@ use crate::eth::{
     get_eth_addr_from_secret, get_latest_block_info, DepositDetails, GetBalanceArgs,
     GetBalanceResult,
 };
+use crate::sender::batch_policy::BatchEstimate;
+use crate::sender::estimate::{estimate_fees, ChainFeeEstimate};
 use crate::sender::service_loop;
 use crate::utils::{DecimalConvExt, StringConvExt, U256ConvExt};
 use chrono::{DateTime, Utc};
-use erc20_payment_lib_common::model::{DepositId, TokenTransferDbObj};
+use erc20_payment_lib_common::model::{DepositId, TokenTransferDbObj, TransferPriority};
 use erc20_payment_lib_common::{
-    DriverEvent, DriverEventContent, FaucetData, SharedInfoTx, StatusProperty,
-    TransactionStuckReason, Web3RpcPoolContent,
+    DriverEvent, DriverEventContent, SharedInfoTx, StatusProperty, TransactionStuckReason,
+    Web3RpcPoolContent,
+};
+use erc20_rpc_pool::{
+    Web3ExternalSources, Web3FullNodeData, Web3PoolType, Web3RpcEndpointChange, Web3RpcPool,
 };
-use erc20_rpc_pool::{Web3ExternalSources, Web3FullNodeData, Web3PoolType, Web3RpcPool};
 use rust_decimal::prelude::FromPrimitive;
 use rust_decimal::Decimal;
 use serde::Serialize;
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::sync::{broadcast, mpsc, Mutex, Notify};
+use tokio::sync::{broadcast, mpsc, Mutex, Notify, OwnedMutexGuard};
 use tokio::task::{JoinError, JoinHandle};
 use web3::types::{Address, H256, U256};
 
@@ -56,11 +61,12 @@ pub struct SharedState {
     #[serde(skip)]
     pub web3_pool_ref: Arc<std::sync::Mutex<BTreeMap<i64, Web3PoolType>>>,
 
-    pub faucet: Option<FaucetData>,
     pub inserted: usize,
     pub idling: bool,
 
     pub accounts: Vec<SignerAccount>,
+    /// Batches evaluated in last gathering round of every account, by [`BatchEstimate::key`]
+    pub pending_batches: BTreeMap<String, BatchEstimate>,
 }
 
 impl SharedState {
@@ -217,10 +223,11 @@ impl StatusTracker {
         status_props.len() != old_len
     }
 
-    fn new(
+    fn new<S: PaymentStore>(
         mut broadcast_sender: Option<broadcast::Sender<DriverEvent>>,
         mut mpsc_sender: Option<mpsc::Sender<DriverEvent>>,
         mut status_rx: mpsc::Receiver<DriverEvent>,
+        mut event_log: EventLog<S>,
     ) -> Self {
         let status = Arc::new(Mutex::new(Vec::new()));
         let status_ = Arc::clone(&status);
@@ -306,30 +313,22 @@ impl StatusTracker {
                 };
 
                 if pass_raw_message {
-                    if let Some(sender) = &mut mpsc_sender {
-                        if let Err(err) = sender.send(ev.clone()).await {
-                            log::warn!("Error resending driver event: {}", err);
-                        }
-                        if emit_changed {
-                            if let Err(err) = sender
-                                .send(DriverEvent::now(DriverEventContent::StatusChanged(
-                                    status.lock().await.clone(),
-                                )))
-                                .await
-                            {
-                                log::warn!("Error resending driver status changed event: {}", err);
-                            }
-                        }
+                    let mut events = vec![ev];
+                    if emit_changed {
+                        events.push(DriverEvent::now(DriverEventContent::StatusChanged(
+                            status.lock().await.clone(),
+                        )));
                     }
-
-                    if let Some(sender) = &mut broadcast_sender {
-                        if let Err(_err) = sender.send(ev) {
-                            //channel closed - it's normal
+                    for ev in events {
+                        // stored before sending, so stream consumers can resume from the log
+                        let ev = event_log.append(ev).await;
+                        if let Some(sender) = &mut mpsc_sender {
+                            if let Err(err) = sender.send(ev.clone()).await {
+                                log::warn!("Error resending driver event: {}", err);
+                            }
                         }
-                        if emit_changed {
-                            if let Err(_err) = sender.send(DriverEvent::now(
-                                DriverEventContent::StatusChanged(status.lock().await.clone()),
-                            )) {
+                        if let Some(sender) = &mut broadcast_sender {
+                            if let Err(_err) = sender.send(ev) {
                                 //channel closed - it's normal
                             }
                         }
@@ -353,7 +352,7 @@ pub enum TransferType {
     Gas,
 }
 
-pub struct PaymentRuntime {
+pub struct PaymentRuntime<S: PaymentStore = PaymentDbStore> {
     //pub runtime_handles: Arc<std::sync::Mutex<Vec<JoinHandle<()>>>>,
     pub setup: PaymentSetup,
     pub shared_state: Arc<std::sync::Mutex<SharedState>>,
@@ -361,16 +360,18 @@ pub struct PaymentRuntime {
     pub driver_broadcast_sender: Option<broadcast::Sender<DriverEvent>>,
     pub driver_mpsc_sender: Option<mpsc::Sender<DriverEvent>>,
     pub raw_event_sender: mpsc::Sender<DriverEvent>,
-    conn: SqlitePool,
+    conn: S,
     status_tracker: StatusTracker,
     config: Config,
 }
 
 pub struct PaymentRuntimeArgs {
     pub secret_keys: Vec<SecretKey>,
+    /// SQLite file or database url (postgres://...), see [`create_db_connection`]
     pub db_filename: PathBuf,
     pub config: config::Config,
-    pub conn: Option<SqlitePool>,
+    /// Used only by [`PaymentRuntime::new`], overrides `db_filename`
+    pub conn: Option<PaymentDbPool>,
     pub options: Option<AdditionalOptions>,
     pub broadcast_sender: Option<broadcast::Sender<DriverEvent>>,
     pub mspc_sender: Option<mpsc::Sender<DriverEvent>>,
@@ -387,9 +388,57 @@ pub struct TransferArgs {
     pub payment_id: String,
     pub deadline: Option<DateTime<Utc>>,
     pub deposit_id: Option<DepositId>,
+    pub priority: TransferPriority,
+}
+
+/// Changes of pending transfer, fields left as None are not changed
+#[derive(Debug, Clone, Default)]
+pub struct TransferChange {
+    pub receiver: Option<Address>,
+    pub amount: Option<U256>,
+    /// Gather transfer not later than this date, stored with the transfer
+    pub deadline: Option<DateTime<Utc>>,
+    pub priority: Option<TransferPriority>,
+}
+
+impl PaymentRuntime<PaymentDbStore> {
+    /// Create runtime over SQLite or PostgreSQL database, selected by `db_filename` url
+    /// unless `conn` is given
+    pub async fn new(
+        payment_runtime_args: PaymentRuntimeArgs,
+        signer: Arc<Box<dyn Signer + Send + Sync + 'static>>,
+    ) -> Result<PaymentRuntime, PaymentError> {
+        let conn = if let Some(conn) = payment_runtime_args.conn.clone() {
+            conn
+        } else {
+            let db_url = payment_runtime_args.db_filename.to_str().ok_or_else(|| {
+                err_custom_create!(
+                    "Database path not convertible to string: {:?}",
+                    payment_runtime_args.db_filename
+                )
+            })?;
+            log::info!("connecting to db: {}", redact_db_url(db_url));
+            create_db_connection(db_url, false, true).await?
+        };
+        Self::new_with_store(payment_runtime_args, PaymentDbStore::from(conn), signer).await
+    }
+
+    /// Start background job writing database backups, see [`crate::backup::backup_sqlite`].
+    /// Backups are written only for SQLite, PostgreSQL has to be backed up with its own tools.
+    pub fn start_backup_job(
+        &self,
+        settings: BackupSettings,
+    ) -> Result<JoinHandle<()>, PaymentError> {
+        let pool = self.conn.sqlite_pool().ok_or_else(|| {
+            err_custom_create!(
+                "Backup job is supported only for SQLite database, use pg_dump for PostgreSQL"
+            )
+        })?;
+        Ok(start_backup_job(pool.clone(), settings))
+    }
 }
 
-impl PaymentRuntime {
+impl<S: PaymentStore> PaymentRuntime<S> {
     fn start_service_loop(
         &self,
         signer_address: Address,
@@ -454,10 +503,12 @@ impl PaymentRuntime {
         jh
     }
 
-    pub async fn new(
+    /// Create runtime using given store, `db_filename` and `conn` from arguments are ignored
+    pub async fn new_with_store(
         payment_runtime_args: PaymentRuntimeArgs,
+        conn: S,
         signer: Arc<Box<dyn Signer + Send + Sync + 'static>>,
-    ) -> Result<PaymentRuntime, PaymentError> {
+    ) -> Result<PaymentRuntime<S>, PaymentError> {
         let options = payment_runtime_args.options.unwrap_or_default();
 
         let web3_rpc_pool_info =
@@ -479,17 +530,6 @@ impl PaymentRuntime {
         payment_setup.contract_use_unpacked_method = options.contract_use_unpacked_method;
         log::debug!("Starting payment engine: {:#?}", payment_setup);
 
-        let conn = if let Some(conn) = payment_runtime_args.conn {
-            conn
-        } else {
-            log::info!(
-                "connecting to sqlite file db: {}",
-                payment_runtime_args.db_filename.display()
-            );
-            create_sqlite_connection(Some(&payment_runtime_args.db_filename), None, false, true)
-                .await?
-        };
-
         let driver_broadcast_sender = payment_runtime_args.broadcast_sender.clone();
         let driver_mpsc_sender = payment_runtime_args.mspc_sender.clone();
 
@@ -497,6 +537,7 @@ impl PaymentRuntime {
             payment_runtime_args.broadcast_sender,
             payment_runtime_args.mspc_sender,
             status_rx,
+            EventLog::new(conn.clone(), payment_runtime_args.config.event_log.as_ref()),
         );
 
         let accounts = payment_runtime_args
@@ -510,8 +551,8 @@ impl PaymentRuntime {
             inserted: 0,
             idling: false,
             current_tx_info: BTreeMap::new(),
-            faucet: None,
             web3_pool_ref: web3_rpc_pool_info.clone(),
+            pending_batches: BTreeMap::new(),
         }));
 
         let notify = Arc::new(Notify::new());
@@ -834,6 +875,78 @@ impl PaymentRuntime {
         Ok(res)
     }
 
+    /// Add, remove, disable or reprioritize rpc endpoint of the chain at runtime
+    pub fn change_rpc_endpoint(
+        &self,
+        chain_id: i64,
+        change: Web3RpcEndpointChange,
+    ) -> Result<(), PaymentError> {
+        self.setup
+            .get_provider(chain_id)?
+            .apply_endpoint_change(change)
+    }
+
+    /// Start background job moving old rows to archive, see [`crate::archive::archive_before`]
+    pub fn start_archive_job(
+        &self,
+        settings: ArchiveSettings,
+    ) -> Result<JoinHandle<()>, PaymentError> {
+        start_archive_job(self.conn.clone(), settings)
+    }
+
+    /// Start background job delivering driver events to webhooks, see [`crate::webhook`].
+    /// Requires runtime created with broadcast sender.
+    pub fn start_webhook_job(
+        &self,
+        settings: Vec<WebhookSettings>,
+    ) -> Result<JoinHandle<()>, PaymentError> {
+        let events = self
+            .driver_broadcast_sender
+            .as_ref()
+            .ok_or(err_custom_create!(
+                "Webhooks require runtime with driver broadcast sender"
+            ))?
+            .subscribe();
+        start_webhook_job(self.conn.clone(), settings, events)
+    }
+
+    /// Watch config file and reload rpc endpoints when it changes.
+    /// Only endpoints defined directly in config are reloaded, dns and json sources require restart.
+    pub fn start_config_watcher(&self, path: PathBuf, check_interval: Duration) -> JoinHandle<()> {
+        let setup = self.setup.clone();
+        tokio::spawn(async move {
+            let modified_time = |path: PathBuf| async move {
+                tokio::fs::metadata(&path)
+                    .await
+                    .and_then(|m| m.modified())
+                    .ok()
+            };
+            let mut last_modified = modified_time(path.clone()).await;
+            loop {
+                tokio::time::sleep(check_interval).await;
+                let modified = modified_time(path.clone()).await;
+                if modified.is_none() || modified == last_modified {
+                    continue;
+                }
+                last_modified = modified;
+                log::info!(
+                    "Config file {} changed, reloading rpc endpoints",
+                    path.display()
+                );
+                let config = match Config::load(&path).await {
+                    Ok(config) => config,
+                    Err(err) => {
+                        log::error!("Failed to load changed config, keeping old one: {}", err);
+                        continue;
+                    }
+                };
+                if let Err(err) = reload_rpc_endpoints(&setup, &config) {
+                    log::error!("Failed to reload rpc endpoints: {}", err);
+                }
+            }
+        })
+    }
+
     pub fn get_rpc_endpoints(
         &self,
         network: Option<String>,
@@ -878,7 +991,7 @@ impl PaymentRuntime {
     pub async fn transfer_guess_account(
         &self,
         transfer_args: TransferArgs,
-    ) -> Result<(), PaymentError> {
+    ) -> Result<TransferInsertResult, PaymentError> {
         let account = {
             self.shared_state
                 .lock()
@@ -898,11 +1011,10 @@ impl PaymentRuntime {
         }
     }
 
-    pub async fn transfer_with_account(
+    fn token_transfer_from_args(
         &self,
-        account: &SignerAccount,
-        transfer_args: TransferArgs,
-    ) -> Result<(), PaymentError> {
+        transfer_args: &TransferArgs,
+    ) -> Result<TokenTransferDbObj, PaymentError> {
         let chain_cfg = self
             .config
             .chain
@@ -920,33 +1032,185 @@ impl PaymentRuntime {
             TransferType::Gas => None,
         };
 
-        let token_transfer = create_token_transfer(
-            transfer_args.from,
-            transfer_args.receiver,
-            chain_cfg.chain_id,
-            Some(&transfer_args.payment_id),
-            token_addr,
-            transfer_args.amount,
-            transfer_args.deposit_id,
-        );
+        Ok(TokenTransferDbObj {
+            priority: transfer_args.priority.to_string(),
+            deadline: transfer_args.deadline,
+            ..create_token_transfer(
+                transfer_args.from,
+                transfer_args.receiver,
+                chain_cfg.chain_id,
+                Some(&transfer_args.payment_id),
+                token_addr,
+                transfer_args.amount,
+                transfer_args.deposit_id,
+            )
+        })
+    }
 
-        insert_token_transfer_with_deposit_check(&self.conn, &token_transfer).await?;
+    /// Transfer with payment id already used by the account is not created again,
+    /// see [`PaymentStore::insert_token_transfer_idempotent`]
+    pub async fn transfer_with_account(
+        &self,
+        account: &SignerAccount,
+        transfer_args: TransferArgs,
+    ) -> Result<TransferInsertResult, PaymentError> {
+        let token_transfer = self.token_transfer_from_args(&transfer_args)?;
+
+        let res = self
+            .conn
+            .insert_token_transfer_idempotent(&token_transfer)
+            .await?;
+        if let TransferInsertResult::Existing(existing) = &res {
+            log::info!(
+                "Transfer with payment id {} already exists (id: {})",
+                transfer_args.payment_id,
+                existing.id
+            );
+            return Ok(res);
+        }
 
-        if !self.setup.ignore_deadlines {
-            if let Some(deadline) = transfer_args.deadline {
-                let mut ext_gath_time_guard = account.external_gather_time.lock().unwrap();
-                let new_time = ext_gath_time_guard
-                    .map(|t| t.min(deadline))
-                    .unwrap_or(deadline);
+        self.schedule_created_transfer(account, &transfer_args);
+        Ok(res)
+    }
 
-                if Some(new_time) != *ext_gath_time_guard {
-                    *ext_gath_time_guard = Some(new_time);
-                    self.wake.notify_one();
-                }
+    /// Create all transfers or none of them, see
+    /// [`PaymentStore::insert_token_transfers_idempotent`]
+    pub async fn transfer_bulk(
+        &self,
+        transfers: &[(SignerAccount, TransferArgs)],
+    ) -> Result<Vec<TransferInsertResult>, BulkInsertError> {
+        let token_transfers = transfers
+            .iter()
+            .enumerate()
+            .map(|(idx, (_, transfer_args))| {
+                self.token_transfer_from_args(transfer_args)
+                    .map_err(|error| BulkInsertError {
+                        index: Some(idx),
+                        error,
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let results = self
+            .conn
+            .insert_token_transfers_idempotent(&token_transfers)
+            .await?;
+        for ((account, transfer_args), res) in transfers.iter().zip(&results) {
+            if let TransferInsertResult::Created(_) = res {
+                self.schedule_created_transfer(account, transfer_args);
             }
         }
+        Ok(results)
+    }
 
-        Ok(())
+    /// Quote fees of sending given transfers now, nothing is stored
+    pub async fn estimate_transfers(
+        &self,
+        transfers: &[TransferArgs],
+    ) -> Result<Vec<ChainFeeEstimate>, PaymentError> {
+        let token_transfers = transfers
+            .iter()
+            .map(|transfer_args| self.token_transfer_from_args(transfer_args))
+            .collect::<Result<Vec<_>, _>>()?;
+        estimate_fees(&self.setup, &token_transfers).await
+    }
+
+    fn schedule_created_transfer(&self, account: &SignerAccount, transfer_args: &TransferArgs) {
+        if transfer_args.priority == TransferPriority::Urgent {
+            self.set_gather_deadline(account, Utc::now());
+        } else if let Some(deadline) = transfer_args.deadline {
+            self.set_gather_deadline(account, deadline);
+        }
+    }
+
+    fn set_gather_deadline(&self, account: &SignerAccount, deadline: DateTime<Utc>) {
+        if self.setup.ignore_deadlines {
+            return;
+        }
+        let mut ext_gath_time_guard = account.external_gather_time.lock().unwrap();
+        let new_time = ext_gath_time_guard
+            .map(|t| t.min(deadline))
+            .unwrap_or(deadline);
+
+        if Some(new_time) != *ext_gath_time_guard {
+            *ext_gath_time_guard = Some(new_time);
+            self.wake.notify_one();
+        }
+    }
+
+    /// Find active sender of the transfer and block gathering of its payments,
+    /// so that transfer is not picked up while it is changed
+    async fn lock_transfer_sender(
+        &self,
+        id: i64,
+    ) -> Result<(Option<SignerAccount>, Option<OwnedMutexGuard<()>>), PaymentError> {
+        let token_transfer = get_pending_token_transfer(&self.conn, id).await?;
+        let account = self
+            .shared_state
+            .lock()
+            .unwrap()
+            .accounts
+            .iter()
+            .find(|a| format!("{:#x}", a.address) == token_transfer.from_addr)
+            .cloned();
+        let guard = match &account {
+            Some(account) => Some(account.gather_lock.clone().lock_owned().await),
+            None => None,
+        };
+        Ok((account, guard))
+    }
+
+    /// Approve transfer violating spending policy, it is sent in next gathering round
+    pub async fn approve_transfer(&self, id: i64) -> Result<TokenTransferDbObj, PaymentError> {
+        let (account, _guard) = self.lock_transfer_sender(id).await?;
+        let token_transfer = approve_token_transfer(&self.conn, id).await?;
+        if let Some(account) = account {
+            self.set_gather_deadline(&account, Utc::now());
+        }
+        Ok(token_transfer)
+    }
+
+    /// Remove transfer that is not yet attached to transaction
+    pub async fn cancel_transfer(&self, id: i64) -> Result<TokenTransferDbObj, PaymentError> {
+        let (_account, _guard) = self.lock_transfer_sender(id).await?;
+        cancel_token_transfer(&self.conn, id).await
+    }
+
+    /// Change transfer that is not yet attached to transaction.
+    /// Deadline is stored with the transfer, so it is honoured by gathering also
+    /// after restart or when the sender becomes active later.
+    pub async fn edit_transfer(
+        &self,
+        id: i64,
+        change: TransferChange,
+    ) -> Result<TokenTransferDbObj, PaymentError> {
+        let (account, _guard) = self.lock_transfer_sender(id).await?;
+        let token_transfer = if change.receiver.is_some()
+            || change.amount.is_some()
+            || change.priority.is_some()
+            || change.deadline.is_some()
+        {
+            edit_token_transfer(
+                &self.conn,
+                id,
+                change.receiver,
+                change.amount,
+                change.priority,
+                change.deadline,
+            )
+            .await?
+        } else {
+            get_pending_token_transfer(&self.conn, id).await?
+        };
+        let deadline = if change.priority == Some(TransferPriority::Urgent) {
+            Some(Utc::now())
+        } else {
+            change.deadline
+        };
+        if let (Some(account), Some(deadline)) = (account, deadline) {
+            self.set_gather_deadline(&account, deadline);
+        }
+        Ok(token_transfer)
     }
 
     pub async fn distribute_gas(
@@ -1093,9 +1357,9 @@ impl VerifyTransactionResult {
 }
 
 #[allow(clippy::too_many_arguments)]
-pub async fn distribute_gas(
+pub async fn distribute_gas<S: PaymentStore>(
     web3: Arc<Web3RpcPool>,
-    conn: &SqlitePool,
+    conn: &S,
     chain_id: u64,
     from: Address,
     distribute_contract_address: Option<Address>,
@@ -1156,7 +1420,7 @@ pub async fn distribute_gas(
         recipients,
         &amounts_u256,
     )?;
-    let distribute_tx = insert_tx(conn, &distribute_tx).await.map_err(err_from!())?;
+    let distribute_tx = conn.insert_tx(&distribute_tx).await.map_err(err_from!())?;
 
     log::info!(
         "Distribute transaction added to queue: {}",
@@ -1166,9 +1430,9 @@ pub async fn distribute_gas(
 }
 
 #[allow(clippy::too_many_arguments)]
-pub async fn mint_golem_token(
+pub async fn mint_golem_token<S: PaymentStore>(
     web3: Arc<Web3RpcPool>,
-    conn: &SqlitePool,
+    conn: &S,
     chain_id: u64,
     from: Address,
     glm_address: Address,
@@ -1223,19 +1487,18 @@ pub async fn mint_golem_token(
         };
     }
 
-    let mut db_transaction = conn.begin().await.map_err(err_from!())?;
-    let filter = "method=\"FAUCET.create\" AND fee_paid is NULL";
-
-    let tx_existing = get_transactions(
-        &mut *db_transaction,
-        Some(from),
-        Some(filter),
-        None,
-        None,
-        Some(chain_id as i64),
-    )
-    .await
-    .map_err(err_from!())?;
+    let db_transaction = conn.begin().await.map_err(err_from!())?;
+    let filter = TransactionFilter::PendingMethod("FAUCET.create".to_string());
+    let tx_existing = db_transaction
+        .get_transactions(
+            Some(from),
+            filter,
+            None,
+            TransactionOrder::default(),
+            Some(chain_id as i64),
+        )
+        .await
+        .map_err(err_from!())?;
 
     if let Some(tx) = tx_existing.first() {
         return Err(err_custom_create!(
@@ -1245,7 +1508,8 @@ pub async fn mint_golem_token(
     }
 
     let faucet_mint_tx = create_faucet_mint(from, faucet_contract_address, chain_id, None)?;
-    let mint_tx = insert_tx(&mut *db_transaction, &faucet_mint_tx)
+    let mint_tx = db_transaction
+        .insert_tx(&faucet_mint_tx)
         .await
         .map_err(err_from!())?;
     db_transaction.commit().await.map_err(err_from!())?;
@@ -1309,9 +1573,9 @@ pub struct TerminateDepositOptionsInt {
     pub deposit_id: DepositId,
 }
 
-pub async fn close_deposit(
+pub async fn close_deposit<S: PaymentStore>(
     web3: Arc<Web3RpcPool>,
-    conn: &SqlitePool,
+    conn: &S,
     chain_id: u64,
     from: Address,
     opt: CloseDepositOptionsInt,
@@ -1339,15 +1603,12 @@ pub async fn close_deposit(
         }
     }
 
-    let mut db_transaction = conn.begin().await.map_err(err_from!())?;
+    let db_transaction = conn.begin().await.map_err(err_from!())?;
 
-    let current_token_transfers = get_token_transfers_by_deposit_id(
-        &mut *db_transaction,
-        chain_id as i64,
-        &opt.deposit_id.to_db_string(),
-    )
-    .await
-    .map_err(err_from!())?;
+    let current_token_transfers = db_transaction
+        .get_token_transfers_by_deposit_id(chain_id as i64, &opt.deposit_id.to_db_string())
+        .await
+        .map_err(err_from!())?;
 
     for tt in &current_token_transfers {
         if tt.deposit_finish > 0 {
@@ -1375,7 +1636,8 @@ pub async fn close_deposit(
     if let Some(tt) = candidate_for_mark_close {
         let mut tt = tt.clone();
         tt.deposit_finish = 1;
-        update_token_transfer(&mut *db_transaction, &tt)
+        db_transaction
+            .update_token_transfer(&tt)
             .await
             .map_err(err_from!())?;
     } else {
@@ -1395,14 +1657,18 @@ pub async fn close_deposit(
             paid_date: None,
             fee_paid: None,
             error: None,
+            priority: TransferPriority::default().to_string(),
+            approved_date: None,
+            deadline: None,
         };
-        insert_token_transfer(&mut *db_transaction, &new_tt)
+        db_transaction
+            .insert_token_transfer(&new_tt)
             .await
             .map_err(err_from!())?;
     }
 
-    //let mut db_transaction = conn.begin().await.map_err(err_from!())?;
-    //let make_deposit_tx = insert_tx(&mut *db_transaction, &free_deposit_tx_id)
+    //let db_transaction = conn.begin().await.map_err(err_from!())?;
+    //let make_deposit_tx = db_transaction.insert_tx(&free_deposit_tx_id)
     //    .await
     //    .map_err(err_from!())?;
     db_transaction.commit().await.map_err(err_from!())?;
@@ -1411,9 +1677,9 @@ pub async fn close_deposit(
     Ok(())
 }
 
-pub async fn terminate_deposit(
+pub async fn terminate_deposit<S: PaymentStore>(
     web3: Arc<Web3RpcPool>,
-    conn: &SqlitePool,
+    conn: &S,
     chain_id: u64,
     from: Address,
     opt: TerminateDepositOptionsInt,
@@ -1463,8 +1729,9 @@ pub async fn terminate_deposit(
         opt.deposit_id.nonce(),
     )?;
 
-    let mut db_transaction = conn.begin().await.map_err(err_from!())?;
-    let make_deposit_tx = insert_tx(&mut *db_transaction, &free_deposit_tx_id)
+    let db_transaction = conn.begin().await.map_err(err_from!())?;
+    let make_deposit_tx = db_transaction
+        .insert_tx(&free_deposit_tx_id)
         .await
         .map_err(err_from!())?;
     db_transaction.commit().await.map_err(err_from!())?;
@@ -1484,9 +1751,9 @@ pub struct CreateDepositOptionsInt {
     pub timestamp: u64,
 }
 
-pub async fn make_deposit(
+pub async fn make_deposit<S: PaymentStore>(
     web3: Arc<Web3RpcPool>,
-    conn: &SqlitePool,
+    conn: &S,
     chain_id: u64,
     from: Address,
     glm_address: Address,
@@ -1533,18 +1800,18 @@ pub async fn make_deposit(
         };
     }
 
-    let mut db_transaction = conn.begin().await.map_err(err_from!())?;
-    let filter = "method=\"LOCK.deposit\" AND fee_paid is NULL";
-    let tx_existing = get_transactions(
-        &mut *db_transaction,
-        Some(from),
-        Some(filter),
-        None,
-        None,
-        Some(chain_id as i64),
-    )
-    .await
-    .map_err(err_from!())?;
+    let db_transaction = conn.begin().await.map_err(err_from!())?;
+    let filter = TransactionFilter::PendingMethod("LOCK.deposit".to_string());
+    let tx_existing = db_transaction
+        .get_transactions(
+            Some(from),
+            filter,
+            None,
+            TransactionOrder::default(),
+            Some(chain_id as i64),
+        )
+        .await
+        .map_err(err_from!())?;
 
     if let Some(tx) = tx_existing.first() {
         return Err(err_custom_create!(
@@ -1567,7 +1834,8 @@ pub async fn make_deposit(
         },
     )?;
 
-    let deposit_tx = insert_tx(&mut *db_transaction, &deposit_tx)
+    let deposit_tx = db_transaction
+        .insert_tx(&deposit_tx)
         .await
         .map_err(err_from!())?;
     db_transaction.commit().await.map_err(err_from!())?;
@@ -1583,13 +1851,14 @@ pub async fn get_token_balance(
     crate::eth::get_balance(web3, args).await
 }
 
-pub async fn get_unpaid_token_amount(
-    conn: &SqlitePool,
+pub async fn get_unpaid_token_amount<S: PaymentStore>(
+    conn: &S,
     chain_id: i64,
     token_address: Address,
     sender: Address,
 ) -> Result<U256, PaymentError> {
-    let transfers = get_unpaid_token_transfers(conn, chain_id, sender)
+    let transfers = conn
+        .get_unpaid_token_transfers(chain_id, sender)
         .await
         .map_err(err_from!())?;
     let mut sum = U256::default();
@@ -1669,29 +1938,29 @@ pub async fn verify_transaction(
     }
 }
 
-pub async fn remove_transaction_force(
-    conn: &SqlitePool,
+pub async fn remove_transaction_force<S: PaymentStore>(
+    conn: &S,
     tx_id: i64,
 ) -> Result<Option<Vec<i64>>, PaymentError> {
-    let mut db_transaction = conn
+    let db_transaction = conn
         .begin()
         .await
         .map_err(|err| err_custom_create!("Error beginning transaction {err}"))?;
 
-    match get_transaction_chain(&mut db_transaction, tx_id).await {
+    match db_transaction.get_transaction_chain(tx_id).await {
         Ok(txs) => {
             for tx in &txs {
                 //if tx is allowance then remove all references to it
-                cleanup_allowance_tx(&mut *db_transaction, tx.id)
+                db_transaction
+                    .cleanup_allowance_tx(tx.id)
                     .await
                     .map_err(err_from!())?;
                 //if tx is token_transfer then remove all references to it
-                cleanup_token_transfer_tx(&mut *db_transaction, tx.id)
-                    .await
-                    .map_err(err_from!())?;
-                delete_tx(&mut *db_transaction, tx.id)
+                db_transaction
+                    .cleanup_token_transfer_tx(tx.id)
                     .await
                     .map_err(err_from!())?;
+                db_transaction.delete_tx(tx.id).await.map_err(err_from!())?;
             }
             db_transaction.commit().await.map_err(err_from!())?;
             Ok(Some(txs.iter().map(|tx| tx.id).collect()))
@@ -1703,22 +1972,112 @@ pub async fn remove_transaction_force(
     }
 }
 
-pub async fn remove_last_unsent_transactions(
-    conn: SqlitePool,
+/// Transfer that can still be changed, because it is not attached to transaction
+pub async fn get_pending_token_transfer<S: PaymentStore>(
+    conn: &S,
+    id: i64,
+) -> Result<TokenTransferDbObj, PaymentError> {
+    let token_transfer = conn
+        .get_token_transfer(id)
+        .await
+        .map_err(err_from!())?
+        .ok_or_else(|| err_from!()(sqlx::Error::RowNotFound))?;
+    if let Some(tx_id) = token_transfer.tx_id {
+        return Err(err_create!(TransferNotPending {
+            id,
+            tx_id: Some(tx_id),
+        }));
+    }
+    Ok(token_transfer)
+}
+
+/// Error when conditional change did not match any row
+async fn not_pending_error<S: PaymentStore>(conn: &S, id: i64) -> PaymentError {
+    match get_pending_token_transfer(conn, id).await {
+        Ok(_) => err_create!(TransferNotPending { id, tx_id: None }),
+        Err(err) => err,
+    }
+}
+
+pub async fn cancel_token_transfer<S: PaymentStore>(
+    conn: &S,
+    id: i64,
+) -> Result<TokenTransferDbObj, PaymentError> {
+    let token_transfer = get_pending_token_transfer(conn, id).await?;
+    if !conn
+        .delete_pending_token_transfer(id)
+        .await
+        .map_err(err_from!())?
+    {
+        return Err(not_pending_error(conn, id).await);
+    }
+    Ok(token_transfer)
+}
+
+pub async fn approve_token_transfer<S: PaymentStore>(
+    conn: &S,
+    id: i64,
+) -> Result<TokenTransferDbObj, PaymentError> {
+    get_pending_token_transfer(conn, id).await?;
+    if !conn.approve_token_transfer(id).await.map_err(err_from!())? {
+        return Err(not_pending_error(conn, id).await);
+    }
+    get_pending_token_transfer(conn, id).await
+}
+
+pub async fn edit_token_transfer<S: PaymentStore>(
+    conn: &S,
+    id: i64,
+    receiver: Option<Address>,
+    amount: Option<U256>,
+    priority: Option<TransferPriority>,
+    deadline: Option<DateTime<Utc>>,
+) -> Result<TokenTransferDbObj, PaymentError> {
+    let token_transfer = get_pending_token_transfer(conn, id).await?;
+    if token_transfer.deposit_finish > 0 {
+        return Err(err_custom_create!(
+            "Transfer {} closes deposit and cannot be changed",
+            id
+        ));
+    }
+    if receiver == Some(Address::zero()) {
+        return Err(err_custom_create!("Receiver cannot be zero address"));
+    }
+    let receiver_addr = receiver
+        .map(|r| format!("{r:#x}"))
+        .unwrap_or(token_transfer.receiver_addr);
+    let token_amount = amount
+        .map(|a| a.to_string())
+        .unwrap_or(token_transfer.token_amount);
+    let priority = priority
+        .map(|p| p.to_string())
+        .unwrap_or(token_transfer.priority);
+    let deadline = deadline.or(token_transfer.deadline);
+    if !conn
+        .update_pending_token_transfer(id, &receiver_addr, &token_amount, &priority, deadline)
+        .await
+        .map_err(err_from!())?
+    {
+        return Err(not_pending_error(conn, id).await);
+    }
+    get_pending_token_transfer(conn, id).await
+}
+
+pub async fn remove_last_unsent_transactions<S: PaymentStore>(
+    conn: &S,
 ) -> Result<Option<i64>, PaymentError> {
-    let mut db_transaction = conn
+    let db_transaction = conn
         .begin()
         .await
         .map_err(|err| err_custom_create!("Error beginning transaction {err}"))?;
-    match get_last_unsent_tx(&mut *db_transaction, 0).await {
+    match db_transaction.get_last_unsent_tx().await {
         Ok(tx) => {
             if let Some(tx) = tx {
-                cleanup_token_transfer_tx(&mut *db_transaction, tx.id)
-                    .await
-                    .map_err(err_from!())?;
-                delete_tx(&mut *db_transaction, tx.id)
+                db_transaction
+                    .cleanup_token_transfer_tx(tx.id)
                     .await
                     .map_err(err_from!())?;
+                db_transaction.delete_tx(tx.id).await.map_err(err_from!())?;
                 db_transaction.commit().await.map_err(err_from!())?;
                 Ok(Some(tx.id))
             } else {
@@ -1,7 +1,12 @@
-mod allowance;
-mod batching;
-pub mod process;
-mod service;
-
-pub use allowance::*;
-pub use service::*;
+mod allowance;
+pub mod batch_policy;
+mod batching;
+pub mod estimate;
+pub mod payout;
+pub mod policy;
+pub mod process;
+pub mod scheduling;
+mod service;
+
+pub use allowance::*;
+pub use service::*;
@@ -4,21 +4,20 @@ use std::sync::Arc;
 use crate::error::{AllowanceRequest, ErrorBag, PaymentError};
 use crate::signer::Signer;
 use crate::transaction::create_erc20_approve;
-use erc20_payment_lib_common::ops::*;
 
 use crate::setup::PaymentSetup;
 use crate::{err_create, err_from};
 
+use erc20_payment_lib_common::store::{PaymentStore, PaymentStoreOps, PaymentStoreTransaction};
 use erc20_payment_lib_common::{CantSignContent, DriverEvent, DriverEventContent};
-use sqlx::SqlitePool;
 
 use crate::error::TransactionFailedError;
 use crate::eth::check_allowance;
 use erc20_payment_lib_common::model::AllowanceDbObj;
 use web3::types::{Address, U256};
 
-pub async fn process_allowance(
-    conn: &SqlitePool,
+pub async fn process_allowance<S: PaymentStore>(
+    conn: &S,
     payment_setup: &PaymentSetup,
     allowance_request: &AllowanceRequest,
     signer: Arc<Box<dyn Signer + Send + Sync + 'static>>,
@@ -27,15 +26,15 @@ pub async fn process_allowance(
     let minimum_allowance: U256 = U256::max_value() / U256::from(2);
     let web3 = payment_setup.get_provider(allowance_request.chain_id)?;
 
-    let mut db_allowance = find_allowance(
-        conn,
-        &allowance_request.owner,
-        &allowance_request.token_addr,
-        &allowance_request.spender_addr,
-        allowance_request.chain_id,
-    )
-    .await
-    .map_err(err_from!())?;
+    let mut db_allowance = conn
+        .find_allowance(
+            &allowance_request.owner,
+            &allowance_request.token_addr,
+            &allowance_request.spender_addr,
+            allowance_request.chain_id,
+        )
+        .await
+        .map_err(err_from!())?;
 
     let allowance = match db_allowance.as_mut() {
         Some(db_allowance) => match db_allowance.confirm_date {
@@ -62,7 +61,7 @@ pub async fn process_allowance(
                         db_allowance.id
                     );
                     db_allowance.confirm_date = Some(chrono::Utc::now());
-                    update_allowance(conn, db_allowance)
+                    conn.update_allowance(db_allowance)
                         .await
                         .map_err(err_from!())?;
                 }
@@ -93,7 +92,7 @@ pub async fn process_allowance(
                     error: None,
                 };
                 //allowance is confirmed on web3, update db
-                insert_allowance(conn, &db_allowance)
+                conn.insert_allowance(&db_allowance)
                     .await
                     .map_err(err_from!())?;
             }
@@ -143,12 +142,14 @@ pub async fn process_allowance(
             allowance_request.chain_id as u64,
             None,
         )?;
-        let mut db_transaction = conn.begin().await.map_err(err_from!())?;
-        let web3_tx_dao = insert_tx(&mut *db_transaction, &approve_tx)
+        let db_transaction = conn.begin().await.map_err(err_from!())?;
+        let web3_tx_dao = db_transaction
+            .insert_tx(&approve_tx)
             .await
             .map_err(err_from!())?;
         allowance.tx_id = Some(web3_tx_dao.id);
-        insert_allowance(&mut *db_transaction, &allowance)
+        db_transaction
+            .insert_allowance(&allowance)
             .await
             .map_err(err_from!())?;
 
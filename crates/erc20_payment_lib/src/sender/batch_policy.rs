@@ -0,0 +1,266 @@
+use crate::config::BatchingSettings;
+use crate::setup::ChainSetup;
+use crate::utils::U256ConvExt;
+use chrono::{DateTime, Utc};
+use erc20_payment_lib_common::model::{TokenTransferDbObj, TransferPriority};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use web3::types::{BlockId, BlockNumber, U256};
+
+/// Approximate gas usage, only used to decide if batch is worth sending
+const MULTI_TRANSFER_BASE_GAS: u64 = 50_000;
+const MULTI_TRANSFER_GAS_PER_RECEIVER: u64 = 30_000;
+const SINGLE_TRANSFER_GAS: u64 = 60_000;
+
+/// Batch of pending transfers evaluated in last gathering round
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchEstimate {
+    pub chain_id: i64,
+    pub from_addr: String,
+    pub token_addr: Option<String>,
+    pub deposit_id: Option<String>,
+    pub transfer_count: usize,
+    pub receiver_count: usize,
+    pub total_amount: String,
+    /// In wei, not set when gas price could not be checked
+    pub estimated_fee: Option<String>,
+    pub fee_per_transfer: Option<String>,
+    pub oldest_transfer_date: DateTime<Utc>,
+    pub send: bool,
+    pub reason: String,
+}
+
+impl BatchEstimate {
+    pub fn key(&self) -> String {
+        format!(
+            "{}:{}:{}:{}",
+            self.chain_id,
+            self.from_addr,
+            self.token_addr.as_deref().unwrap_or_default(),
+            self.deposit_id.as_deref().unwrap_or_default()
+        )
+    }
+}
+
+/// Base fee of the latest block, None when it could not be checked
+pub(crate) async fn latest_base_fee(chain_setup: &ChainSetup) -> Option<U256> {
+    match chain_setup
+        .provider
+        .clone()
+        .eth_block(BlockId::Number(BlockNumber::Latest))
+        .await
+    {
+        Ok(block) => block.and_then(|b| b.base_fee_per_gas),
+        Err(err) => {
+            log::warn!("Failed to get latest block for fee estimate: {}", err);
+            None
+        }
+    }
+}
+
+/// Base fee increased by priority fee, limited by max fee of the chain
+pub(crate) fn gas_price_for_base_fee(chain_setup: &ChainSetup, base_fee: U256) -> U256 {
+    (base_fee + chain_setup.priority_fee).min(chain_setup.max_fee_per_gas)
+}
+
+async fn estimate_gas_price(chain_setup: &ChainSetup) -> Option<U256> {
+    latest_base_fee(chain_setup)
+        .await
+        .map(|base_fee| gas_price_for_base_fee(chain_setup, base_fee))
+}
+
+pub(crate) fn estimate_gas(
+    receiver_count: usize,
+    max_per_batch: usize,
+    single_transfer: bool,
+) -> u64 {
+    if receiver_count == 1 && single_transfer {
+        return SINGLE_TRANSFER_GAS;
+    }
+    let tx_count = receiver_count.div_ceil(max_per_batch.max(1)) as u64;
+    tx_count * MULTI_TRANSFER_BASE_GAS + receiver_count as u64 * MULTI_TRANSFER_GAS_PER_RECEIVER
+}
+
+/// Fee as percentage of batch value, None when it cannot be compared
+fn fee_percent(
+    fee: U256,
+    total_amount: U256,
+    token_price: Option<Decimal>,
+    is_token: bool,
+) -> Option<Decimal> {
+    let price = if is_token { token_price? } else { Decimal::ONE };
+    let value = total_amount.to_eth().ok()? * price;
+    if value.is_zero() {
+        return None;
+    }
+    Some(fee.to_eth().ok()? / value * Decimal::ONE_HUNDRED)
+}
+
+/// Estimate fee of sending transfers now and decide if batch should wait for more transfers
+pub async fn evaluate_batch(
+    chain_setup: &ChainSetup,
+    single_transfer: bool,
+    receiver_count: usize,
+    token_transfers: &[&TokenTransferDbObj],
+    now: DateTime<Utc>,
+) -> BatchEstimate {
+    let first = token_transfers[0];
+    let total_amount = token_transfers
+        .iter()
+        .filter_map(|tt| U256::from_dec_str(&tt.token_amount).ok())
+        .fold(U256::zero(), |acc, amount| acc.saturating_add(amount));
+    let oldest_transfer_date = token_transfers
+        .iter()
+        .map(|tt| tt.create_date)
+        .min()
+        .unwrap_or(now);
+    let estimated_fee = estimate_gas_price(chain_setup).await.map(|gas_price| {
+        gas_price
+            * U256::from(estimate_gas(
+                receiver_count,
+                chain_setup.multi_contract_max_at_once,
+                single_transfer,
+            ))
+    });
+
+    let mut estimate = BatchEstimate {
+        chain_id: first.chain_id,
+        from_addr: first.from_addr.clone(),
+        token_addr: first.token_addr.clone(),
+        deposit_id: first.deposit_id.clone(),
+        transfer_count: token_transfers.len(),
+        receiver_count,
+        total_amount: total_amount.to_string(),
+        estimated_fee: estimated_fee.map(|fee| fee.to_string()),
+        fee_per_transfer: estimated_fee
+            .map(|fee| (fee / U256::from(token_transfers.len().max(1))).to_string()),
+        oldest_transfer_date,
+        send: true,
+        reason: "no batching limits".to_string(),
+    };
+
+    let Some(settings) = &chain_setup.batching else {
+        return estimate;
+    };
+    let (send, reason) = decide(
+        settings,
+        &estimate,
+        estimated_fee,
+        total_amount,
+        token_transfers
+            .iter()
+            .any(|tt| tt.priority == TransferPriority::Urgent.as_str()),
+        now,
+    );
+    estimate.send = send;
+    estimate.reason = reason;
+    estimate
+}
+
+fn decide(
+    settings: &BatchingSettings,
+    estimate: &BatchEstimate,
+    estimated_fee: Option<U256>,
+    total_amount: U256,
+    has_urgent: bool,
+    now: DateTime<Utc>,
+) -> (bool, String) {
+    if has_urgent {
+        return (true, "batch contains urgent transfer".to_string());
+    }
+    if let Some(max_wait_secs) = settings.max_wait_secs {
+        if (now - estimate.oldest_transfer_date).num_seconds() >= max_wait_secs as i64 {
+            return (true, "max wait time reached".to_string());
+        }
+    }
+    if let Some(min_batch_size) = settings.min_batch_size {
+        if estimate.transfer_count < min_batch_size {
+            return (
+                false,
+                format!(
+                    "waiting for {} transfers, {} pending",
+                    min_batch_size, estimate.transfer_count
+                ),
+            );
+        }
+    }
+    if let Some(max_fee_percent) = settings.max_fee_percent {
+        let Some(fee) = estimated_fee else {
+            return (false, "fee could not be estimated".to_string());
+        };
+        let is_token = estimate.token_addr.is_some();
+        match fee_percent(fee, total_amount, settings.token_price, is_token) {
+            Some(percent) if percent <= max_fee_percent => {}
+            Some(percent) => {
+                return (
+                    false,
+                    format!(
+                        "fee is {:.2}% of batch value, limit {}%",
+                        percent, max_fee_percent
+                    ),
+                )
+            }
+            None if is_token && settings.token_price.is_none() => {
+                log::warn!("max-fee-percent ignored for token batch, token-price not set");
+            }
+            None => return (false, "batch value is zero".to_string()),
+        }
+    }
+    (true, "batching limits met".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn estimate(transfer_count: usize, age_secs: i64, now: DateTime<Utc>) -> BatchEstimate {
+        BatchEstimate {
+            chain_id: 1,
+            from_addr: "0x01".to_string(),
+            token_addr: None,
+            deposit_id: None,
+            transfer_count,
+            receiver_count: transfer_count,
+            total_amount: "0".to_string(),
+            estimated_fee: None,
+            fee_per_transfer: None,
+            oldest_transfer_date: now - chrono::Duration::seconds(age_secs),
+            send: true,
+            reason: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_batching_decision() {
+        let now = Utc::now();
+        let settings = BatchingSettings {
+            min_batch_size: Some(5),
+            max_fee_percent: Some(Decimal::from(2)),
+            token_price: None,
+            max_wait_secs: Some(3600),
+        };
+        let eth = U256::exp10(18);
+
+        let (send, _) = decide(&settings, &estimate(2, 10, now), None, eth, false, now);
+        assert!(!send);
+        let (send, _) = decide(&settings, &estimate(2, 10, now), None, eth, true, now);
+        assert!(send);
+        let (send, _) = decide(&settings, &estimate(2, 3600, now), None, eth, false, now);
+        assert!(send);
+
+        // fee 0.01 of 1 eth is 1%, 0.03 is 3%
+        let fee = U256::exp10(16);
+        let (send, _) = decide(&settings, &estimate(5, 10, now), Some(fee), eth, false, now);
+        assert!(send);
+        let (send, reason) = decide(
+            &settings,
+            &estimate(5, 10, now),
+            Some(fee * 3),
+            eth,
+            false,
+            now,
+        );
+        assert!(!send, "{reason}");
+    }
+}
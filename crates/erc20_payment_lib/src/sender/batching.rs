@@ -1,9 +1,7 @@
 use std::collections::{BTreeMap, HashMap};
 use std::str::FromStr;
 
-use erc20_payment_lib_common::ops::*;
-
-use crate::error::{AllowanceRequest, ErrorBag, PaymentError};
+use crate::error::{AllowanceRequest, ErrorBag, PaymentError, TransferNotPending};
 
 use crate::transaction::{
     create_close_deposit, create_erc20_deposit_transfer, create_erc20_transfer,
@@ -11,15 +9,22 @@ use crate::transaction::{
     MultiTransferArgs, MultiTransferDepositArgs, SingleTransferDepositArgs,
 };
 
-use crate::setup::PaymentSetup;
+use crate::setup::{ChainSetup, PaymentSetup};
 use crate::{err_create, err_custom_create, err_from};
 
-use sqlx::SqlitePool;
+use erc20_payment_lib_common::store::{PaymentStore, PaymentStoreOps, PaymentStoreTransaction};
 use tokio::sync::mpsc;
 
+use crate::runtime::{send_driver_event, SharedState};
+use crate::sender::batch_policy::evaluate_batch;
+use crate::sender::payout::{find_payout_rule, is_below_threshold};
+use crate::sender::policy::{PolicyChecker, PolicyDecision};
+use crate::sender::scheduling::TransferScheduler;
 use crate::signer::SignerAccount;
-use erc20_payment_lib_common::model::{DepositId, TokenTransferDbObj};
-use erc20_payment_lib_common::DriverEvent;
+use chrono::Utc;
+use erc20_payment_lib_common::model::{DepositId, TokenTransferDbObj, TransferPriority, TxDbObj};
+use erc20_payment_lib_common::{DriverEvent, DriverEventContent, PolicyViolationInfo};
+use std::sync::Arc;
 use web3::types::{Address, U256};
 
 #[derive(Eq, Hash, PartialEq, Debug, Clone)]
@@ -41,22 +46,51 @@ pub struct TokenTransferMultiKey {
 
 type TokenTransferMap = HashMap<TokenTransferKey, Vec<TokenTransferDbObj>>;
 
+/// Fails if transfer was cancelled or edited after it was gathered, transaction
+/// has to be dropped then and transfers gathered again
+async fn attach_to_tx<T: PaymentStoreOps>(
+    db_transaction: &T,
+    token_transfer: &mut TokenTransferDbObj,
+    tx_id: i64,
+) -> Result<(), PaymentError> {
+    let attached = db_transaction
+        .attach_token_transfer_to_tx(token_transfer, tx_id)
+        .await
+        .map_err(err_from!())?;
+    if !attached {
+        return Err(err_create!(TransferNotPending {
+            id: token_transfer.id,
+            tx_id: None,
+        }));
+    }
+    token_transfer.tx_id = Some(tx_id);
+    Ok(())
+}
+
 #[derive(Debug, Clone)]
 pub struct TokenTransferMultiOrder {
     receiver: Address,
     token_transfers: Vec<TokenTransferDbObj>,
 }
 
-pub async fn gather_transactions_pre(
+pub async fn gather_transactions_pre<S: PaymentStore>(
     account: &SignerAccount,
     chain_id: i64,
-    conn: &SqlitePool,
-    _payment_setup: &PaymentSetup,
+    conn: &S,
+    payment_setup: &PaymentSetup,
+    event_sender: &Option<mpsc::Sender<DriverEvent>>,
     process_tx_needed: &mut bool,
 ) -> Result<TokenTransferMap, PaymentError> {
     let mut transfer_map = TokenTransferMap::new();
-
-    let mut token_transfers = get_pending_token_transfers(conn, account.address, chain_id)
+    let mut scheduler = TransferScheduler::new(
+        &payment_setup.scheduling_rules,
+        account.address,
+        chain_id,
+        Utc::now(),
+    );
+
+    let mut token_transfers = conn
+        .get_pending_token_transfers(account.address, chain_id)
         .await
         .map_err(err_from!())?;
 
@@ -65,20 +99,20 @@ pub async fn gather_transactions_pre(
             Ok(from_addr) => {
                 if from_addr == Address::zero() {
                     f.error = Some("from_addr is zero".to_string());
-                    update_token_transfer(conn, f).await.map_err(err_from!())?;
+                    conn.update_token_transfer(f).await.map_err(err_from!())?;
                     continue;
                 }
                 //@TODO: check if from_addr is in a wallet
                 /*
                 if from_addr != payment_setup.pub_address {
                     f.error = Some("no from_addr in wallet".to_string());
-                    update_token_transfer(conn, f).await.map_err(err_from!())?;
+                    conn.update_token_transfer(f).await.map_err(err_from!())?;
                     continue;
                 }*/
             }
             Err(_err) => {
                 f.error = Some("Invalid from address".to_string());
-                update_token_transfer(conn, f).await.map_err(err_from!())?;
+                conn.update_token_transfer(f).await.map_err(err_from!())?;
                 continue;
             }
         }
@@ -105,17 +139,14 @@ pub async fn gather_transactions_pre(
                     deposit_id_obj.deposit_id,
                 )?;
 
-                let mut transaction = conn.begin().await.map_err(err_from!())?;
+                let transaction = conn.begin().await.map_err(err_from!())?;
 
-                let new_tx = insert_tx(&mut *transaction, &close_deposit_tx_id)
+                let new_tx = transaction
+                    .insert_tx(&close_deposit_tx_id)
                     .await
                     .map_err(err_from!())?;
 
-                let mut tt = f.clone();
-                tt.tx_id = Some(new_tx.id);
-                update_token_transfer(&mut *transaction, &tt)
-                    .await
-                    .map_err(err_from!())?;
+                attach_to_tx(&transaction, f, new_tx.id).await?;
 
                 transaction.commit().await.map_err(err_from!())?;
                 *process_tx_needed = true;
@@ -126,13 +157,25 @@ pub async fn gather_transactions_pre(
             Ok(rec_address) => {
                 if rec_address == Address::zero() {
                     f.error = Some("receiver_addr is zero".to_string());
-                    update_token_transfer(conn, f).await.map_err(err_from!())?;
+                    conn.update_token_transfer(f).await.map_err(err_from!())?;
                     continue;
                 }
             }
             Err(_err) => {
                 f.error = Some("Invalid receiver address".to_string());
-                update_token_transfer(conn, f).await.map_err(err_from!())?;
+                conn.update_token_transfer(f).await.map_err(err_from!())?;
+                continue;
+            }
+        }
+        match TransferPriority::from_str(&f.priority) {
+            Ok(priority) => {
+                if scheduler.is_held_back(priority, payment_setup).await {
+                    continue;
+                }
+            }
+            Err(_err) => {
+                f.error = Some("Invalid priority".to_string());
+                conn.update_token_transfer(f).await.map_err(err_from!())?;
                 continue;
             }
         }
@@ -154,11 +197,182 @@ pub async fn gather_transactions_pre(
             }
         }
     }
+    //keep accumulating transfers to receivers until minimum payout is reached
+    let now = Utc::now();
+    transfer_map.retain(|key, transfers| {
+        let Some(rule) = find_payout_rule(
+            &payment_setup.payout_rules,
+            &key.from_addr,
+            &key.receiver_addr,
+            key.chain_id,
+            key.token_addr.as_deref(),
+        ) else {
+            return true;
+        };
+        if is_below_threshold(rule, transfers, now) {
+            log::debug!(
+                "Transfers to {} below minimum payout, waiting for more",
+                key.receiver_addr
+            );
+            return false;
+        }
+        true
+    });
+
+    let mut policy =
+        PolicyChecker::new(&payment_setup.policy_rules, account.address, chain_id, now);
+    if !policy.is_empty() {
+        for transfers in transfer_map.values_mut() {
+            let mut allowed = Vec::with_capacity(transfers.len());
+            for mut f in std::mem::take(transfers) {
+                match policy.check(&f, conn).await? {
+                    PolicyDecision::Allow => allowed.push(f),
+                    PolicyDecision::Hold(reason) => {
+                        log::info!("Transfer {} held back by policy: {}", f.id, reason);
+                    }
+                    PolicyDecision::Violation(reason) => {
+                        log::warn!("Transfer {} violates spending policy: {}", f.id, reason);
+                        f.error = Some(format!("Policy violation: {}", reason));
+                        conn.update_token_transfer(&f).await.map_err(err_from!())?;
+                        send_driver_event(
+                            event_sender,
+                            DriverEventContent::PolicyViolation(PolicyViolationInfo {
+                                token_transfer_dao: f,
+                                reason,
+                            }),
+                        )
+                        .await;
+                    }
+                }
+            }
+            *transfers = allowed;
+        }
+        transfer_map.retain(|_, transfers| !transfers.is_empty());
+    }
     Ok(transfer_map)
 }
 
-pub async fn gather_transactions_batch_multi(
-    conn: &SqlitePool,
+/// Transaction paying one chunk of multi orders, None if there is nothing to pay.
+/// `log_insert` is set when the transaction is going to be stored, not only estimated.
+fn create_multi_order_tx(
+    payment_setup: &PaymentSetup,
+    chain_setup: &ChainSetup,
+    token_transfer: &TokenTransferMultiKey,
+    token_addr: &str,
+    orders: &[TokenTransferMultiOrder],
+    log_insert: bool,
+) -> Result<Option<TxDbObj>, PaymentError> {
+    let use_direct_method = payment_setup.contract_use_direct_method;
+    let use_unpacked_method = payment_setup.contract_use_unpacked_method;
+
+    let mut erc20_to = Vec::with_capacity(orders.len());
+    let mut erc20_amounts = Vec::with_capacity(orders.len());
+    let mut is_deposit_finish = false;
+    for token_t in orders {
+        let mut sum = U256::zero();
+        for token_transfer in &token_t.token_transfers {
+            sum += U256::from_dec_str(&token_transfer.token_amount).map_err(err_from!())?;
+            //close deposit if needed
+            if token_transfer.deposit_finish > 0 {
+                is_deposit_finish = true;
+            }
+        }
+        erc20_to.push(token_t.receiver);
+        erc20_amounts.push(sum);
+    }
+
+    let mut use_transfer_for_single_payment = payment_setup.use_transfer_for_single_payment;
+    if !use_transfer_for_single_payment && chain_setup.multi_contract_address.is_none() {
+        log::warn!("Multi contract not set overwriting use_transfer_for_single_payment to true");
+        use_transfer_for_single_payment = true;
+    }
+
+    let web3tx = if erc20_to.is_empty() {
+        return Ok(None);
+    } else if use_transfer_for_single_payment && erc20_to.len() == 1 {
+        if log_insert {
+            log::info!(
+                "Inserting transaction stub for ERC20 transfer to: {:?}",
+                erc20_to[0]
+            );
+        }
+
+        if let Some(deposit_id) = token_transfer.deposit_id.as_ref() {
+            let deposit_id_obj = DepositId::from_db_string(deposit_id)
+                .map_err(|err| err_custom_create!("Invalid deposit id: {}", err))?;
+            create_erc20_deposit_transfer(SingleTransferDepositArgs {
+                from: Address::from_str(&token_transfer.from_addr).map_err(err_from!())?,
+                lock_contract: deposit_id_obj.lock_address,
+                erc20_to: erc20_to[0],
+                erc20_amount: erc20_amounts[0],
+                chain_id: token_transfer.chain_id as u64,
+                gas_limit: None,
+                deposit_id: deposit_id_obj.deposit_id,
+                deposit_finish: is_deposit_finish,
+            })?
+        } else {
+            create_erc20_transfer(
+                Address::from_str(&token_transfer.from_addr).map_err(err_from!())?,
+                Address::from_str(token_addr).map_err(err_from!())?,
+                erc20_to[0],
+                erc20_amounts[0],
+                token_transfer.chain_id as u64,
+                None,
+            )?
+        }
+    } else if let Some(deposit_id) = token_transfer.deposit_id.as_ref() {
+        let deposit_id_obj = DepositId::from_db_string(deposit_id)
+            .map_err(|err| err_custom_create!("Invalid deposit id: {}", err))?;
+        if log_insert {
+            log::info!(
+                "Inserting transaction stub for ERC20 multi payment: {:?} for {} distinct transfers",
+                deposit_id_obj.lock_address,
+                erc20_to.len(),
+            );
+        }
+
+        create_erc20_transfer_multi_deposit(MultiTransferDepositArgs {
+            from: Address::from_str(&token_transfer.from_addr).map_err(err_from!())?,
+            lock_contract: deposit_id_obj.lock_address,
+            erc20_to,
+            erc20_amount: erc20_amounts,
+            chain_id: token_transfer.chain_id as u64,
+            gas_limit: None,
+            deposit_id: deposit_id_obj.deposit_id,
+            deposit_finish: is_deposit_finish,
+        })?
+    } else if let Some(multi_contract_address) = chain_setup.multi_contract_address {
+        if log_insert {
+            log::info!(
+                "Inserting transaction stub for ERC20 multi transfer contract: {:?} for {} distinct transfers",
+                multi_contract_address,
+                erc20_to.len()
+            );
+        }
+
+        create_erc20_transfer_multi(MultiTransferArgs {
+            from: Address::from_str(&token_transfer.from_addr).map_err(err_from!())?,
+            contract: multi_contract_address,
+            erc20_to,
+            erc20_amount: erc20_amounts,
+            chain_id: token_transfer.chain_id as u64,
+            gas_limit: None,
+            direct: use_direct_method,
+            unpacked: use_unpacked_method,
+        })?
+    } else {
+        log::error!("Multi contract address not set, but it is needed to process transactions");
+        return Err(err_custom_create!(
+            "Multi contract address not set, but it is needed to process transactions"
+        ));
+    };
+    Ok(Some(web3tx))
+}
+
+/// Returns number of inserted transactions, 0 when batch waits for more transfers
+pub async fn gather_transactions_batch_multi<S: PaymentStore>(
+    shared_state: &Arc<std::sync::Mutex<SharedState>>,
+    conn: &S,
     payment_setup: &PaymentSetup,
     multi_order_vector: &mut [TokenTransferMultiOrder],
     token_transfer: &TokenTransferMultiKey,
@@ -171,9 +385,6 @@ pub async fn gather_transactions_batch_multi(
             token_transfer.chain_id
         ))?;
 
-    let use_direct_method = payment_setup.contract_use_direct_method;
-    let use_unpacked_method = payment_setup.contract_use_unpacked_method;
-
     let max_per_batch = chain_setup.multi_contract_max_at_once;
     log::debug!("Processing token transfer {:?}", token_transfer);
     if let Some(token_addr) = token_transfer.token_addr.as_ref() {
@@ -185,15 +396,15 @@ pub async fn gather_transactions_batch_multi(
                 //this is some arbitrary number.
                 let minimum_allowance: U256 = U256::max_value() / U256::from(2);
 
-                let db_allowance = find_allowance(
-                    conn,
-                    &token_transfer.from_addr,
-                    token_addr,
-                    &format!("{multi_contract_address:#x}"),
-                    token_transfer.chain_id,
-                )
-                .await
-                .map_err(err_from!())?;
+                let db_allowance = conn
+                    .find_allowance(
+                        &token_transfer.from_addr,
+                        token_addr,
+                        &format!("{multi_contract_address:#x}"),
+                        token_transfer.chain_id,
+                    )
+                    .await
+                    .map_err(err_from!())?;
 
                 let mut allowance_not_met = false;
                 match db_allowance {
@@ -232,153 +443,91 @@ pub async fn gather_transactions_batch_multi(
             }
         }
 
-        let split_orders = multi_order_vector
-            .chunks_mut(max_per_batch)
-            .collect::<Vec<_>>();
-
-        for smaller_order in split_orders {
-            let mut erc20_to = Vec::with_capacity(smaller_order.len());
-            let mut erc20_amounts = Vec::with_capacity(smaller_order.len());
-            let mut is_deposit_finish = false;
-            for token_t in &mut *smaller_order {
-                let mut sum = U256::zero();
-                for token_transfer in &token_t.token_transfers {
-                    sum += U256::from_dec_str(&token_transfer.token_amount).map_err(err_from!())?;
-                    //close deposit if needed
-                    if token_transfer.deposit_finish > 0 {
-                        is_deposit_finish = true;
-                    }
-                }
-                erc20_to.push(token_t.receiver);
-                erc20_amounts.push(sum);
-            }
-
-            let mut use_transfer_for_single_payment = payment_setup.use_transfer_for_single_payment;
-            if !use_transfer_for_single_payment && chain_setup.multi_contract_address.is_none() {
-                log::warn!(
-                    "Multi contract not set overwriting use_transfer_for_single_payment to true"
-                );
-                use_transfer_for_single_payment = true;
-            }
-
-            let web3tx = if erc20_to.is_empty() {
-                return Ok(0);
-            } else if use_transfer_for_single_payment && erc20_to.len() == 1 {
+        let estimate = evaluate_batch(
+            chain_setup,
+            payment_setup.use_transfer_for_single_payment,
+            multi_order_vector.len(),
+            &multi_order_vector
+                .iter()
+                .flat_map(|order| order.token_transfers.iter())
+                .collect::<Vec<_>>(),
+            Utc::now(),
+        )
+        .await;
+        let send = estimate.send || payment_setup.finish_when_done;
+        if !estimate.send {
+            if send {
                 log::info!(
-                    "Inserting transaction stub for ERC20 transfer to: {:?}",
-                    erc20_to[0]
+                    "Batch of {} transfers from {} sent without waiting ({}), because process finishes when done",
+                    estimate.transfer_count,
+                    estimate.from_addr,
+                    estimate.reason
                 );
-
-                if let Some(deposit_id) = token_transfer.deposit_id.as_ref() {
-                    let deposit_id_obj = DepositId::from_db_string(deposit_id)
-                        .map_err(|err| err_custom_create!("Invalid deposit id: {}", err))?;
-                    create_erc20_deposit_transfer(SingleTransferDepositArgs {
-                        from: Address::from_str(&token_transfer.from_addr).map_err(err_from!())?,
-                        lock_contract: deposit_id_obj.lock_address,
-                        erc20_to: erc20_to[0],
-                        erc20_amount: erc20_amounts[0],
-                        chain_id: token_transfer.chain_id as u64,
-                        gas_limit: None,
-                        deposit_id: deposit_id_obj.deposit_id,
-                        deposit_finish: is_deposit_finish,
-                    })?
-                } else {
-                    create_erc20_transfer(
-                        Address::from_str(&token_transfer.from_addr).map_err(err_from!())?,
-                        Address::from_str(token_addr).map_err(err_from!())?,
-                        erc20_to[0],
-                        erc20_amounts[0],
-                        token_transfer.chain_id as u64,
-                        None,
-                    )?
-                }
-            } else if let Some(deposit_id) = token_transfer.deposit_id.as_ref() {
-                let deposit_id_obj = DepositId::from_db_string(deposit_id)
-                    .map_err(|err| err_custom_create!("Invalid deposit id: {}", err))?;
+            } else {
                 log::info!(
-                    "Inserting transaction stub for ERC20 multi payment: {:?} for {} distinct transfers",
-                    deposit_id_obj.lock_address,
-                    erc20_to.len(),
+                    "Batch of {} transfers from {} waits: {}",
+                    estimate.transfer_count,
+                    estimate.from_addr,
+                    estimate.reason
                 );
+            }
+        }
+        shared_state
+            .lock()
+            .unwrap()
+            .pending_batches
+            .insert(estimate.key(), estimate);
+        if !send {
+            return Ok(0);
+        }
 
-                create_erc20_transfer_multi_deposit(MultiTransferDepositArgs {
-                    from: Address::from_str(&token_transfer.from_addr).map_err(err_from!())?,
-                    lock_contract: deposit_id_obj.lock_address,
-                    erc20_to,
-                    erc20_amount: erc20_amounts,
-                    chain_id: token_transfer.chain_id as u64,
-                    gas_limit: None,
-                    deposit_id: deposit_id_obj.deposit_id,
-                    deposit_finish: is_deposit_finish,
-                })?
-            } else if let Some(multi_contract_address) = chain_setup.multi_contract_address {
-                log::info!(
-                    "Inserting transaction stub for ERC20 multi transfer contract: {:?} for {} distinct transfers",
-                    multi_contract_address,
-                    erc20_to.len()
-                );
+        let split_orders = multi_order_vector
+            .chunks_mut(max_per_batch)
+            .collect::<Vec<_>>();
 
-                create_erc20_transfer_multi(MultiTransferArgs {
-                    from: Address::from_str(&token_transfer.from_addr).map_err(err_from!())?,
-                    contract: multi_contract_address,
-                    erc20_to,
-                    erc20_amount: erc20_amounts,
-                    chain_id: token_transfer.chain_id as u64,
-                    gas_limit: None,
-                    direct: use_direct_method,
-                    unpacked: use_unpacked_method,
-                })?
-            } else {
-                log::error!(
-                    "Multi contract address not set, but it is needed to process transactions"
-                );
-                return Err(err_custom_create!(
-                    "Multi contract address not set, but it is needed to process transactions"
-                ));
+        let mut inserted_tx_count = 0;
+        for smaller_order in split_orders {
+            let Some(web3tx) = create_multi_order_tx(
+                payment_setup,
+                chain_setup,
+                token_transfer,
+                token_addr,
+                smaller_order,
+                true,
+            )?
+            else {
+                return Ok(inserted_tx_count);
             };
-            let mut db_transaction = conn.begin().await.map_err(err_from!())?;
-            let web3_tx_dao = insert_tx(&mut *db_transaction, &web3tx)
+            let db_transaction = conn.begin().await.map_err(err_from!())?;
+            let web3_tx_dao = db_transaction
+                .insert_tx(&web3tx)
                 .await
                 .map_err(err_from!())?;
 
             for token_t in &mut *smaller_order {
                 for token_transfer in &mut token_t.token_transfers {
-                    token_transfer.tx_id = Some(web3_tx_dao.id);
-                    update_token_transfer(&mut *db_transaction, token_transfer)
-                        .await
-                        .map_err(err_from!())?;
+                    attach_to_tx(&db_transaction, token_transfer, web3_tx_dao.id).await?;
                 }
             }
             db_transaction.commit().await.map_err(err_from!())?;
+            inserted_tx_count += 1;
         }
+        Ok(inserted_tx_count)
     } else {
-        return Err(err_custom_create!("Not implemented for multi"));
-    };
-
-    Ok(1)
+        Err(err_custom_create!("Not implemented for multi"))
+    }
 }
 
-pub async fn gather_transactions_batch(
-    _event_sender: Option<mpsc::Sender<DriverEvent>>,
-    conn: &SqlitePool,
-    payment_setup: &PaymentSetup,
-    token_transfers: &mut [TokenTransferDbObj],
+/// Transaction paying summed transfers to one receiver
+fn create_batch_tx(
+    token_transfers: &[TokenTransferDbObj],
     token_transfer: &TokenTransferKey,
-) -> Result<u32, PaymentError> {
+) -> Result<TxDbObj, PaymentError> {
     let mut sum = U256::zero();
     for token_transfer in token_transfers.iter() {
         sum += U256::from_dec_str(&token_transfer.token_amount).map_err(err_from!())?;
     }
 
-    let Some(_chain_setup) = payment_setup.chain_setup.get(&token_transfer.chain_id) else {
-        return Err(err_custom_create!(
-            "No setup found for chain id: {}",
-            token_transfer.chain_id
-        ));
-    };
-
-    log::debug!("Processing token transfer {:?}", token_transfer);
-
     let web3tx = if let Some(token_addr) = token_transfer.token_addr.as_ref() {
         if let Some(deposit_id) = token_transfer.deposit_id.as_ref() {
             let deposit_id_obj = DepositId::from_db_string(deposit_id)
@@ -414,23 +563,43 @@ pub async fn gather_transactions_batch(
             sum,
         )
     };
-    let mut db_transaction = conn.begin().await.map_err(err_from!())?;
-    let web3_tx_dao = insert_tx(&mut *db_transaction, &web3tx)
+    Ok(web3tx)
+}
+
+pub async fn gather_transactions_batch<S: PaymentStore>(
+    _event_sender: Option<mpsc::Sender<DriverEvent>>,
+    conn: &S,
+    payment_setup: &PaymentSetup,
+    token_transfers: &mut [TokenTransferDbObj],
+    token_transfer: &TokenTransferKey,
+) -> Result<u32, PaymentError> {
+    let Some(_chain_setup) = payment_setup.chain_setup.get(&token_transfer.chain_id) else {
+        return Err(err_custom_create!(
+            "No setup found for chain id: {}",
+            token_transfer.chain_id
+        ));
+    };
+
+    log::debug!("Processing token transfer {:?}", token_transfer);
+
+    let web3tx = create_batch_tx(token_transfers, token_transfer)?;
+    let db_transaction = conn.begin().await.map_err(err_from!())?;
+    let web3_tx_dao = db_transaction
+        .insert_tx(&web3tx)
         .await
         .map_err(err_from!())?;
     for token_transfer in token_transfers.iter_mut() {
-        token_transfer.tx_id = Some(web3_tx_dao.id);
-        update_token_transfer(&mut *db_transaction, token_transfer)
-            .await
-            .map_err(err_from!())?;
+        attach_to_tx(&db_transaction, token_transfer, web3_tx_dao.id).await?;
     }
     db_transaction.commit().await.map_err(err_from!())?;
     Ok(1)
 }
 
-pub async fn gather_transactions_post(
+/// Returns number of inserted transactions, batches waiting for more transfers are not counted
+pub async fn gather_transactions_post<S: PaymentStore>(
+    shared_state: &Arc<std::sync::Mutex<SharedState>>,
     event_sender: Option<tokio::sync::mpsc::Sender<DriverEvent>>,
-    conn: &SqlitePool,
+    conn: &S,
     payment_setup: &PaymentSetup,
     token_transfer_map: &mut TokenTransferMap,
 ) -> Result<u32, PaymentError> {
@@ -475,11 +644,13 @@ pub async fn gather_transactions_post(
                 )
                 .await
                 {
-                    Ok(_) => {
-                        inserted_tx_count += 1;
+                    Ok(count) => {
+                        inserted_tx_count += count;
                     }
                     Err(e) => {
-                        match &e.inner {
+                        //partial transfers may be inserted, so they are processed
+                        inserted_tx_count += 1;
+                        match &*e.inner {
                             ErrorBag::NoAllowanceFound(_allowance_request) => {
                                 //pass allowance error up
                                 return Err(e);
@@ -489,7 +660,7 @@ pub async fn gather_transactions_post(
                                 /*for token_transfer in token_transfers {
                                     token_transfer.error =
                                         Some("Error in gathering transactions".to_string());
-                                    update_token_transfer(conn, token_transfer)
+                                    conn.update_token_transfer(token_transfer)
                                         .await
                                         .map_err(err_from!())?;
                                 }*/
@@ -508,7 +679,6 @@ pub async fn gather_transactions_post(
                         }
                     }
                 }
-                inserted_tx_count += 1;
                 continue;
             }
 
@@ -540,6 +710,7 @@ pub async fn gather_transactions_post(
             let mut token_transfers = key.1.clone();
             //todo fix clones
             match gather_transactions_batch_multi(
+                shared_state,
                 conn,
                 payment_setup,
                 &mut token_transfers,
@@ -547,24 +718,28 @@ pub async fn gather_transactions_post(
             )
             .await
             {
-                Ok(_) => {
-                    inserted_tx_count += 1;
+                Ok(count) => {
+                    inserted_tx_count += count;
                 }
                 Err(e) => {
-                    match &e.inner {
+                    //partial transfers may be inserted, so they are processed
+                    inserted_tx_count += 1;
+                    match &*e.inner {
                         ErrorBag::NoAllowanceFound(_allowance_request) => {
                             //pass allowance error up
                             return Err(e);
                         }
+                        ErrorBag::TransferNotPending(_) => {
+                            //transfers are gathered again in next round
+                            log::warn!("Transfers changed during gathering: {}", e);
+                        }
                         _ => {
                             //mark other errors in db to not process these failed transfers again
                             for multi in token_transfers {
                                 for token_transfer in multi.token_transfers {
                                     let mut tt = token_transfer.clone();
                                     tt.error = Some("Error in gathering transactions".to_string());
-                                    update_token_transfer(conn, &tt)
-                                        .await
-                                        .map_err(err_from!())?;
+                                    conn.update_token_transfer(&tt).await.map_err(err_from!())?;
                                 }
                             }
                             log::error!("Failed to gather transactions: {:?}", e);
@@ -576,9 +751,261 @@ pub async fn gather_transactions_post(
                     }
                 }
             }
-            inserted_tx_count += 1;
         }
     }
 
     Ok(inserted_tx_count)
 }
+
+/// Transaction that would be created when gathering transfers
+#[derive(Debug, Clone)]
+pub struct PlannedTx {
+    pub tx: TxDbObj,
+    pub transfer_count: usize,
+    pub receiver_count: usize,
+}
+
+/// Group transfers the same way as [`gather_transactions_post`] and create transaction stubs
+/// without storing anything. Batching limits, payout rules and scheduling are not applied.
+pub fn plan_transactions(
+    payment_setup: &PaymentSetup,
+    token_transfers: &[TokenTransferDbObj],
+) -> Result<Vec<PlannedTx>, PaymentError> {
+    let mut transfer_keys = Vec::<TokenTransferKey>::new();
+    let mut transfer_map = TokenTransferMap::new();
+    for f in token_transfers {
+        let key = TokenTransferKey {
+            from_addr: f.from_addr.clone(),
+            receiver_addr: f.receiver_addr.clone(),
+            chain_id: f.chain_id,
+            token_addr: f.token_addr.clone(),
+            deposit_id: f.deposit_id.clone(),
+        };
+        if !transfer_map.contains_key(&key) {
+            transfer_keys.push(key.clone());
+        }
+        transfer_map.entry(key).or_default().push(f.clone());
+    }
+
+    let mut planned = Vec::new();
+    let mut multi_keys = Vec::<TokenTransferMultiKey>::new();
+    let mut multi_key_map = HashMap::<TokenTransferMultiKey, Vec<TokenTransferMultiOrder>>::new();
+    for key in transfer_keys {
+        let transfers = transfer_map.remove(&key).unwrap_or_default();
+        if key.token_addr.is_none() {
+            planned.push(PlannedTx {
+                tx: create_batch_tx(&transfers, &key)?,
+                transfer_count: transfers.len(),
+                receiver_count: 1,
+            });
+            continue;
+        }
+        let multi_key = TokenTransferMultiKey {
+            from_addr: key.from_addr.clone(),
+            chain_id: key.chain_id,
+            token_addr: key.token_addr.clone(),
+            deposit_id: key.deposit_id.clone(),
+        };
+        if !multi_key_map.contains_key(&multi_key) {
+            multi_keys.push(multi_key.clone());
+        }
+        multi_key_map
+            .entry(multi_key)
+            .or_default()
+            .push(TokenTransferMultiOrder {
+                receiver: Address::from_str(&key.receiver_addr).map_err(err_from!())?,
+                token_transfers: transfers,
+            });
+    }
+
+    for multi_key in multi_keys {
+        let orders = multi_key_map.remove(&multi_key).unwrap_or_default();
+        let chain_setup =
+            payment_setup
+                .chain_setup
+                .get(&multi_key.chain_id)
+                .ok_or(err_custom_create!(
+                    "No setup found for chain id: {}",
+                    multi_key.chain_id
+                ))?;
+        let token_addr = multi_key.token_addr.as_deref().unwrap_or_default();
+        for chunk in orders.chunks(chain_setup.multi_contract_max_at_once.max(1)) {
+            if let Some(tx) = create_multi_order_tx(
+                payment_setup,
+                chain_setup,
+                &multi_key,
+                token_addr,
+                chunk,
+                false,
+            )? {
+                planned.push(PlannedTx {
+                    tx,
+                    transfer_count: chunk.iter().map(|o| o.token_transfers.len()).sum(),
+                    receiver_count: chunk.len(),
+                });
+            }
+        }
+    }
+    Ok(planned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{BatchingSettings, Config};
+    use erc20_payment_lib_common::store::MemoryStore;
+
+    fn transfer(from: u64, receiver: u64, token_addr: Option<Address>) -> TokenTransferDbObj {
+        TokenTransferDbObj {
+            id: 0,
+            payment_id: None,
+            from_addr: format!("{:#x}", Address::from_low_u64_be(from)),
+            receiver_addr: format!("{:#x}", Address::from_low_u64_be(receiver)),
+            chain_id: 17000,
+            token_addr: token_addr.map(|addr| format!("{:#x}", addr)),
+            token_amount: "100".to_string(),
+            deposit_id: None,
+            deposit_finish: 0,
+            create_date: Utc::now(),
+            tx_id: None,
+            paid_date: None,
+            fee_paid: None,
+            error: None,
+            priority: TransferPriority::default().to_string(),
+            approved_date: None,
+            deadline: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_plan_transactions() {
+        let mut payment_setup = PaymentSetup::new_empty(&Config::default_config()).unwrap();
+        payment_setup.use_transfer_for_single_payment = true;
+        let chain_setup = payment_setup.chain_setup.get_mut(&17000).unwrap();
+        chain_setup.multi_contract_max_at_once = 2;
+        let glm = chain_setup.glm_address;
+        let multi_contract = format!("{:#x}", chain_setup.multi_contract_address.unwrap());
+
+        let transfers = vec![
+            transfer(1, 11, Some(glm)),
+            transfer(1, 12, Some(glm)),
+            transfer(1, 11, Some(glm)),
+            transfer(1, 11, None),
+            transfer(1, 13, Some(glm)),
+            transfer(2, 11, Some(glm)),
+            transfer(1, 14, Some(glm)),
+            transfer(1, 11, None),
+            transfer(1, 15, Some(glm)),
+        ];
+        let planned = plan_transactions(&payment_setup, &transfers).unwrap();
+        let summary = planned
+            .iter()
+            .map(|p| (p.tx.from_addr.clone(), p.transfer_count, p.receiver_count))
+            .collect::<Vec<_>>();
+        let from = |n| format!("{:#x}", Address::from_low_u64_be(n));
+        assert_eq!(
+            summary,
+            vec![
+                // gas transfers to the same receiver are summed
+                (from(1), 2, 1),
+                // five receivers of sender 1 split into chunks of max-at-once
+                (from(1), 3, 2),
+                (from(1), 2, 2),
+                (from(1), 1, 1),
+                // other sender is paid separately
+                (from(2), 1, 1),
+            ]
+        );
+        assert_eq!(planned[0].tx.to_addr, from(11));
+        assert_eq!(planned[1].tx.to_addr, multi_contract);
+        assert_eq!(planned[2].tx.to_addr, multi_contract);
+        // single receiver chunk uses plain token transfer
+        assert_eq!(planned[3].tx.to_addr, format!("{:#x}", glm));
+        assert_eq!(planned[4].tx.to_addr, format!("{:#x}", glm));
+    }
+
+    #[tokio::test]
+    async fn test_gather_waiting_batch() {
+        let mut payment_setup = PaymentSetup::new_empty(&Config::default_config()).unwrap();
+        payment_setup.skip_multi_contract_check = true;
+        payment_setup.finish_when_done = false;
+        let chain_setup = payment_setup.chain_setup.get_mut(&17000).unwrap();
+        chain_setup.batching = Some(BatchingSettings {
+            min_batch_size: Some(3),
+            max_fee_percent: None,
+            token_price: None,
+            max_wait_secs: None,
+        });
+        let glm = chain_setup.glm_address;
+        let shared_state = Arc::new(std::sync::Mutex::new(SharedState {
+            accounts: vec![],
+            inserted: 0,
+            idling: false,
+            current_tx_info: BTreeMap::new(),
+            web3_pool_ref: Default::default(),
+            pending_batches: BTreeMap::new(),
+        }));
+        let conn = MemoryStore::new();
+        let mut stored = Vec::new();
+        for receiver in [11, 12] {
+            stored.push(
+                conn.insert_token_transfer(&transfer(1, receiver, Some(glm)))
+                    .await
+                    .unwrap(),
+            );
+        }
+        let transfer_map = || {
+            stored
+                .iter()
+                .map(|tt| {
+                    let key = TokenTransferKey {
+                        from_addr: tt.from_addr.clone(),
+                        receiver_addr: tt.receiver_addr.clone(),
+                        chain_id: tt.chain_id,
+                        token_addr: tt.token_addr.clone(),
+                        deposit_id: tt.deposit_id.clone(),
+                    };
+                    (key, vec![tt.clone()])
+                })
+                .collect::<TokenTransferMap>()
+        };
+        let attached = || async {
+            conn.get_all_token_transfers(None)
+                .await
+                .unwrap()
+                .iter()
+                .filter(|tt| tt.tx_id.is_some())
+                .count()
+        };
+
+        // waiting batch is reported, but not counted as inserted transaction
+        let count = gather_transactions_post(
+            &shared_state,
+            None,
+            &conn,
+            &payment_setup,
+            &mut transfer_map(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(count, 0);
+        assert_eq!(attached().await, 0);
+        let pending = shared_state.lock().unwrap().pending_batches.clone();
+        assert_eq!(pending.len(), 1);
+        assert!(!pending.values().next().unwrap().send);
+
+        // process that finishes when done does not wait for more transfers
+        payment_setup.finish_when_done = true;
+        let count = gather_transactions_post(
+            &shared_state,
+            None,
+            &conn,
+            &payment_setup,
+            &mut transfer_map(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(attached().await, 2);
+    }
+}
@@ -0,0 +1,173 @@
+//! Fee quote for transfers that are not queued yet
+
+use crate::err_custom_create;
+use crate::error::PaymentError;
+use crate::sender::batch_policy::{estimate_gas, gas_price_for_base_fee, latest_base_fee};
+use crate::sender::batching::{plan_transactions, PlannedTx};
+use crate::setup::{ChainSetup, PaymentSetup};
+use crate::transaction::dao_to_call_request;
+use crate::utils::U256ConvExt;
+use erc20_payment_lib_common::model::{TokenTransferDbObj, TxDbObj};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use web3::types::U256;
+
+const GAS_TRANSFER_GAS: u64 = 21_000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TxFeeEstimate {
+    pub from_addr: String,
+    pub to_addr: String,
+    pub method: String,
+    pub transfer_count: usize,
+    pub receiver_count: usize,
+    pub gas: u64,
+    /// False when `eth_estimateGas` failed (i.e. sender has no funds yet) and gas is approximated
+    pub gas_estimated: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub estimate_error: Option<String>,
+    /// In wei, not set when gas price could not be checked
+    pub fee: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChainFeeEstimate {
+    pub chain_id: i64,
+    pub network: String,
+    pub currency_gas_symbol: String,
+    /// Base fee of the latest block in wei
+    pub base_fee: Option<String>,
+    /// Base fee increased by priority fee, limited by max fee of the chain
+    pub gas_price: Option<String>,
+    pub total_gas: u64,
+    /// In wei
+    pub total_fee: Option<String>,
+    /// Total fee in native coin of the chain
+    pub total_fee_native: Option<String>,
+    pub transactions: Vec<TxFeeEstimate>,
+}
+
+async fn estimate_tx_gas(chain_setup: &ChainSetup, tx: &TxDbObj) -> Result<u64, PaymentError> {
+    if tx.call_data.is_none() {
+        return Ok(GAS_TRANSFER_GAS);
+    }
+    let mut call_request = dao_to_call_request(&TxDbObj {
+        max_fee_per_gas: Some(chain_setup.max_fee_per_gas.to_string()),
+        priority_fee: Some(chain_setup.priority_fee.to_string()),
+        ..tx.clone()
+    })?;
+    call_request.max_fee_per_gas = None;
+    call_request.max_priority_fee_per_gas = None;
+    let gas = chain_setup
+        .provider
+        .clone()
+        .eth_estimate_gas(call_request, None)
+        .await
+        .map_err(|err| err_custom_create!("Gas estimation failed: {}", err))?;
+    gas_to_u64(gas)
+}
+
+/// Gas estimate returned by the node is not guaranteed to fit in u64
+fn gas_to_u64(gas: U256) -> Result<u64, PaymentError> {
+    if gas > U256::from(u64::MAX) {
+        return Err(err_custom_create!("Gas estimate {} out of range", gas));
+    }
+    Ok(gas.low_u64())
+}
+
+async fn estimate_planned_tx(
+    payment_setup: &PaymentSetup,
+    chain_setup: &ChainSetup,
+    planned: &PlannedTx,
+    gas_price: Option<U256>,
+) -> TxFeeEstimate {
+    let (gas, estimate_error) = match estimate_tx_gas(chain_setup, &planned.tx).await {
+        Ok(gas) => (gas, None),
+        Err(err) => {
+            log::debug!("Using approximate gas for {}: {}", planned.tx.method, err);
+            let gas = estimate_gas(
+                planned.receiver_count,
+                planned.receiver_count,
+                payment_setup.use_transfer_for_single_payment,
+            );
+            (gas, Some(err.to_string()))
+        }
+    };
+    TxFeeEstimate {
+        from_addr: planned.tx.from_addr.clone(),
+        to_addr: planned.tx.to_addr.clone(),
+        method: planned.tx.method.clone(),
+        transfer_count: planned.transfer_count,
+        receiver_count: planned.receiver_count,
+        gas,
+        gas_estimated: estimate_error.is_none(),
+        estimate_error,
+        fee: gas_price.map(|gas_price| (gas_price * U256::from(gas)).to_string()),
+    }
+}
+
+/// Quote fees of sending transfers at the current base fee. Transactions are planned the
+/// same way as when gathering and their gas is checked with `eth_estimateGas`.
+pub async fn estimate_fees(
+    payment_setup: &PaymentSetup,
+    token_transfers: &[TokenTransferDbObj],
+) -> Result<Vec<ChainFeeEstimate>, PaymentError> {
+    let mut planned_by_chain = BTreeMap::<i64, Vec<PlannedTx>>::new();
+    for planned in plan_transactions(payment_setup, token_transfers)? {
+        planned_by_chain
+            .entry(planned.tx.chain_id)
+            .or_default()
+            .push(planned);
+    }
+
+    let mut estimates = Vec::with_capacity(planned_by_chain.len());
+    for (chain_id, planned_txs) in planned_by_chain {
+        let chain_setup = payment_setup
+            .chain_setup
+            .get(&chain_id)
+            .ok_or(err_custom_create!(
+                "No setup found for chain id: {}",
+                chain_id
+            ))?;
+        let base_fee = latest_base_fee(chain_setup).await;
+        let gas_price = base_fee.map(|base_fee| gas_price_for_base_fee(chain_setup, base_fee));
+
+        let mut transactions = Vec::with_capacity(planned_txs.len());
+        for planned in &planned_txs {
+            transactions
+                .push(estimate_planned_tx(payment_setup, chain_setup, planned, gas_price).await);
+        }
+        let total_gas = transactions
+            .iter()
+            .fold(0u64, |total, tx| total.saturating_add(tx.gas));
+        let total_fee = gas_price.map(|gas_price| gas_price * U256::from(total_gas));
+        estimates.push(ChainFeeEstimate {
+            chain_id,
+            network: chain_setup.network.clone(),
+            currency_gas_symbol: chain_setup.currency_gas_symbol.clone(),
+            base_fee: base_fee.map(|fee| fee.to_string()),
+            gas_price: gas_price.map(|price| price.to_string()),
+            total_gas,
+            total_fee: total_fee.map(|fee| fee.to_string()),
+            total_fee_native: total_fee
+                .and_then(|fee| fee.to_eth().ok())
+                .map(|fee| fee.to_string()),
+            transactions,
+        });
+    }
+    Ok(estimates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gas_to_u64() {
+        assert_eq!(gas_to_u64(U256::from(21_000)).unwrap(), 21_000);
+        assert_eq!(gas_to_u64(U256::from(u64::MAX)).unwrap(), u64::MAX);
+        assert!(gas_to_u64(U256::from(u64::MAX) + 1).is_err());
+    }
+}
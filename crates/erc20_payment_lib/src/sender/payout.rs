@@ -0,0 +1,299 @@
+use crate::config::PayoutThreshold;
+use crate::error::{ErrorBag, PaymentError};
+use crate::utils::DecimalConvExt;
+use crate::{err_custom_create, err_from};
+use chrono::{DateTime, Utc};
+use erc20_payment_lib_common::model::{TokenTransferDbObj, TransferPriority};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use web3::types::{Address, U256};
+
+/// Transfers the threshold applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PayoutToken {
+    Native,
+    /// Any token when address is not set
+    Erc20(Option<Address>),
+}
+
+impl FromStr for PayoutToken {
+    type Err = PaymentError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "native" => Ok(PayoutToken::Native),
+            "erc20" => Ok(PayoutToken::Erc20(None)),
+            _ => Address::from_str(s)
+                .map(|addr| PayoutToken::Erc20(Some(addr)))
+                .map_err(|e| {
+                    err_custom_create!(
+                        "Invalid payout token {s}, expected native, erc20 or token address: {e}"
+                    )
+                }),
+        }
+    }
+}
+
+/// Parsed [`PayoutThreshold`]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PayoutRule {
+    pub account: Option<Address>,
+    pub receiver: Option<Address>,
+    pub chain_id: Option<i64>,
+    pub token: Option<PayoutToken>,
+    pub min_amount: U256,
+    pub max_age_secs: Option<u64>,
+}
+
+impl PayoutRule {
+    pub fn from_config(threshold: &PayoutThreshold) -> Result<Self, PaymentError> {
+        Ok(PayoutRule {
+            account: threshold.account,
+            receiver: threshold.receiver,
+            chain_id: threshold.chain_id,
+            token: threshold
+                .token
+                .as_deref()
+                .map(PayoutToken::from_str)
+                .transpose()?,
+            min_amount: threshold
+                .min_amount
+                .to_u256_from_eth()
+                .map_err(err_from!())?,
+            max_age_secs: threshold.max_age_secs,
+        })
+    }
+
+    fn applies_to(
+        &self,
+        account: Address,
+        receiver: Address,
+        chain_id: i64,
+        token_addr: Option<Address>,
+    ) -> bool {
+        self.account.map(|a| a == account).unwrap_or(true)
+            && self.receiver.map(|r| r == receiver).unwrap_or(true)
+            && self.chain_id.map(|c| c == chain_id).unwrap_or(true)
+            && match (self.token, token_addr) {
+                (None, _) => true,
+                (Some(PayoutToken::Native), token_addr) => token_addr.is_none(),
+                (Some(PayoutToken::Erc20(None)), token_addr) => token_addr.is_some(),
+                (Some(PayoutToken::Erc20(Some(t))), token_addr) => token_addr == Some(t),
+            }
+    }
+
+    fn specificity(&self) -> u8 {
+        (self.receiver.is_some() as u8) << 3
+            | (self.account.is_some() as u8) << 2
+            | (self.chain_id.is_some() as u8) << 1
+            | self.token.is_some() as u8
+    }
+}
+
+/// Most specific rule matching the sender, receiver and token, receiver rules win over account rules
+pub fn find_payout_rule<'a>(
+    rules: &'a [PayoutRule],
+    account: &str,
+    receiver: &str,
+    chain_id: i64,
+    token_addr: Option<&str>,
+) -> Option<&'a PayoutRule> {
+    let account = Address::from_str(account).ok()?;
+    let receiver = Address::from_str(receiver).ok()?;
+    let token_addr = token_addr.map(Address::from_str).transpose().ok()?;
+    rules
+        .iter()
+        .filter(|r| r.applies_to(account, receiver, chain_id, token_addr))
+        .fold(None, |best: Option<&PayoutRule>, r| match best {
+            Some(b) if b.specificity() >= r.specificity() => Some(b),
+            _ => Some(r),
+        })
+}
+
+fn sum_amounts(token_transfers: &[TokenTransferDbObj]) -> U256 {
+    token_transfers
+        .iter()
+        .filter_map(|tt| U256::from_dec_str(&tt.token_amount).ok())
+        .fold(U256::zero(), |acc, amount| acc.saturating_add(amount))
+}
+
+fn oldest_date(token_transfers: &[TokenTransferDbObj]) -> Option<DateTime<Utc>> {
+    token_transfers.iter().map(|tt| tt.create_date).min()
+}
+
+/// Transfers to one receiver keep accumulating in db, because their sum is below threshold
+pub fn is_below_threshold(
+    rule: &PayoutRule,
+    token_transfers: &[TokenTransferDbObj],
+    now: DateTime<Utc>,
+) -> bool {
+    if token_transfers
+        .iter()
+        .any(|tt| tt.deposit_finish > 0 || tt.priority == TransferPriority::Urgent.as_str())
+    {
+        return false;
+    }
+    if let (Some(max_age_secs), Some(oldest)) = (rule.max_age_secs, oldest_date(token_transfers)) {
+        if (now - oldest).num_seconds() >= max_age_secs as i64 {
+            return false;
+        }
+    }
+    sum_amounts(token_transfers) < rule.min_amount
+}
+
+/// Balance accumulated for receiver from one sender
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingPayout {
+    pub chain_id: i64,
+    pub from_addr: String,
+    pub token_addr: Option<String>,
+    pub transfer_count: usize,
+    pub amount: String,
+    pub min_amount: Option<String>,
+    pub oldest_transfer_date: Option<DateTime<Utc>>,
+    /// Paid regardless of amount after this date
+    pub max_payout_date: Option<DateTime<Utc>>,
+}
+
+/// Sum pending transfers of one receiver per sender, chain and token
+pub fn pending_payouts(
+    rules: &[PayoutRule],
+    receiver: &str,
+    token_transfers: Vec<TokenTransferDbObj>,
+) -> Vec<PendingPayout> {
+    let mut grouped = BTreeMap::<(i64, String, Option<String>), Vec<TokenTransferDbObj>>::new();
+    for tt in token_transfers {
+        grouped
+            .entry((tt.chain_id, tt.from_addr.clone(), tt.token_addr.clone()))
+            .or_default()
+            .push(tt);
+    }
+    grouped
+        .into_iter()
+        .map(|((chain_id, from_addr, token_addr), transfers)| {
+            let rule =
+                find_payout_rule(rules, &from_addr, receiver, chain_id, token_addr.as_deref());
+            let oldest_transfer_date = oldest_date(&transfers);
+            PendingPayout {
+                chain_id,
+                token_addr,
+                transfer_count: transfers.len(),
+                amount: sum_amounts(&transfers).to_string(),
+                min_amount: rule.map(|r| r.min_amount.to_string()),
+                oldest_transfer_date,
+                max_payout_date: rule.and_then(|r| r.max_age_secs).and_then(|secs| {
+                    oldest_transfer_date.map(|d| d + chrono::Duration::seconds(secs as i64))
+                }),
+                from_addr,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_payout_threshold() {
+        let sender = "0x0000000000000000000000000000000000000001";
+        let receiver = "0x0000000000000000000000000000000000000002";
+        let rules = vec![
+            PayoutRule {
+                account: None,
+                receiver: None,
+                chain_id: None,
+                token: None,
+                min_amount: U256::from(100),
+                max_age_secs: Some(3600),
+            },
+            PayoutRule {
+                account: None,
+                receiver: Some(Address::from_str(receiver).unwrap()),
+                chain_id: None,
+                token: None,
+                min_amount: U256::from(10),
+                max_age_secs: None,
+            },
+        ];
+        let now = Utc::now();
+        let transfer = |amount: u64, age_secs: i64| TokenTransferDbObj {
+            id: 0,
+            payment_id: None,
+            from_addr: sender.to_string(),
+            receiver_addr: receiver.to_string(),
+            chain_id: 1,
+            token_addr: None,
+            token_amount: amount.to_string(),
+            deposit_id: None,
+            deposit_finish: 0,
+            create_date: now - chrono::Duration::seconds(age_secs),
+            tx_id: None,
+            paid_date: None,
+            fee_paid: None,
+            error: None,
+            priority: TransferPriority::Normal.to_string(),
+            approved_date: None,
+            deadline: None,
+        };
+
+        let rule = find_payout_rule(&rules, sender, receiver, 1, None).unwrap();
+        assert_eq!(rule.min_amount, U256::from(10));
+        assert!(is_below_threshold(
+            rule,
+            &[transfer(4, 10), transfer(5, 10)],
+            now
+        ));
+        assert!(!is_below_threshold(
+            rule,
+            &[transfer(4, 10), transfer(6, 10)],
+            now
+        ));
+
+        let rule = find_payout_rule(&rules, sender, sender, 1, None).unwrap();
+        assert!(is_below_threshold(rule, &[transfer(50, 10)], now));
+        assert!(!is_below_threshold(rule, &[transfer(50, 3600)], now));
+    }
+
+    #[test]
+    fn test_payout_threshold_token() {
+        let sender = "0x0000000000000000000000000000000000000001";
+        let receiver = "0x0000000000000000000000000000000000000002";
+        let glm = "0x0000000000000000000000000000000000000010";
+        let rules = vec![PayoutRule::from_config(&PayoutThreshold {
+            account: None,
+            receiver: None,
+            chain_id: None,
+            token: Some(glm.to_string()),
+            min_amount: rust_decimal::Decimal::from(10),
+            max_age_secs: None,
+        })
+        .unwrap()];
+
+        // GLM threshold does not hold native transfers, i.e. from faucet
+        assert!(find_payout_rule(&rules, sender, receiver, 1, None).is_none());
+        assert!(find_payout_rule(
+            &rules,
+            sender,
+            receiver,
+            1,
+            Some("0x0000000000000000000000000000000000000011")
+        )
+        .is_none());
+        assert!(find_payout_rule(&rules, sender, receiver, 1, Some(glm)).is_some());
+
+        assert_eq!(
+            PayoutToken::from_str("native").unwrap(),
+            PayoutToken::Native
+        );
+        assert_eq!(
+            PayoutToken::from_str("erc20").unwrap(),
+            PayoutToken::Erc20(None)
+        );
+        assert!(PayoutToken::from_str("glm").is_err());
+    }
+}
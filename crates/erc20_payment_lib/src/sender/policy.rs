@@ -0,0 +1,276 @@
+use crate::config::SpendingPolicy;
+use crate::error::{ErrorBag, PaymentError};
+use crate::utils::DecimalConvExt;
+use crate::{err_custom_create, err_from};
+use chrono::{DateTime, Duration, Utc};
+use erc20_payment_lib_common::model::TokenTransferDbObj;
+use erc20_payment_lib_common::store::PaymentStore;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::str::FromStr;
+use web3::types::{Address, U256};
+
+/// Parsed [`SpendingPolicy`]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PolicyRule {
+    pub account: Option<Address>,
+    pub chain_id: Option<i64>,
+    pub token_addr: Option<Address>,
+    pub max_transfer_amount: Option<U256>,
+    pub max_daily_amount: Option<U256>,
+    pub allowed_receivers: Option<Vec<Address>>,
+    pub denied_receivers: Vec<Address>,
+    pub new_receiver_cooldown_secs: Option<u64>,
+}
+
+impl PolicyRule {
+    pub fn from_config(policy: &SpendingPolicy) -> Result<Self, PaymentError> {
+        Ok(PolicyRule {
+            account: policy.account,
+            chain_id: policy.chain_id,
+            token_addr: policy.token_addr,
+            max_transfer_amount: policy
+                .max_transfer_amount
+                .map(|amount| amount.to_u256_from_eth())
+                .transpose()
+                .map_err(err_from!())?,
+            max_daily_amount: policy
+                .max_daily_amount
+                .map(|amount| amount.to_u256_from_eth())
+                .transpose()
+                .map_err(err_from!())?,
+            allowed_receivers: policy.allowed_receivers.clone(),
+            denied_receivers: policy.denied_receivers.clone().unwrap_or_default(),
+            new_receiver_cooldown_secs: policy.new_receiver_cooldown_secs,
+        })
+    }
+
+    fn applies_to(&self, account: Address, chain_id: i64) -> bool {
+        self.account.map(|a| a == account).unwrap_or(true)
+            && self.chain_id.map(|c| c == chain_id).unwrap_or(true)
+    }
+
+    fn applies_to_token(&self, token_addr: Option<Address>) -> bool {
+        self.token_addr.is_none() || self.token_addr == token_addr
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PolicyDecision {
+    Allow,
+    /// Transfer waits, it is checked again in next gathering round
+    Hold(String),
+    /// Transfer is marked with error until operator approves it
+    Violation(String),
+}
+
+/// Check transfer against rules, `spent` is amount of the same token sent in last 24 hours
+/// and `receiver_age` is time since first transfer to the receiver.
+fn evaluate(
+    rules: &[&PolicyRule],
+    receiver: Address,
+    amount: U256,
+    spent: U256,
+    receiver_age: Option<Duration>,
+) -> PolicyDecision {
+    for rule in rules {
+        if rule.denied_receivers.contains(&receiver) {
+            return PolicyDecision::Violation(format!("receiver {:#x} is denied", receiver));
+        }
+        if let Some(allowed) = &rule.allowed_receivers {
+            if !allowed.contains(&receiver) {
+                return PolicyDecision::Violation(format!(
+                    "receiver {:#x} is not allowed",
+                    receiver
+                ));
+            }
+        }
+        if let Some(max_transfer_amount) = rule.max_transfer_amount {
+            if amount > max_transfer_amount {
+                return PolicyDecision::Violation(format!(
+                    "amount {} exceeds limit {} per transfer",
+                    amount, max_transfer_amount
+                ));
+            }
+        }
+        if let Some(max_daily_amount) = rule.max_daily_amount {
+            if spent.saturating_add(amount) > max_daily_amount {
+                return PolicyDecision::Violation(format!(
+                    "amount {} exceeds daily limit {}, already sent {}",
+                    amount, max_daily_amount, spent
+                ));
+            }
+        }
+    }
+    for rule in rules {
+        if let Some(cooldown_secs) = rule.new_receiver_cooldown_secs {
+            let age_secs = receiver_age.map(|age| age.num_seconds()).unwrap_or(0);
+            if age_secs < cooldown_secs as i64 {
+                return PolicyDecision::Hold(format!(
+                    "receiver {:#x} is new, {} seconds of cool-down left",
+                    receiver,
+                    cooldown_secs as i64 - age_secs
+                ));
+            }
+        }
+    }
+    PolicyDecision::Allow
+}
+
+/// Checks pending transfers of the account in current gathering round.
+/// Amounts already sent are loaded once per round and increased by allowed transfers.
+pub struct PolicyChecker<'a> {
+    rules: Vec<&'a PolicyRule>,
+    account: Address,
+    chain_id: i64,
+    now: DateTime<Utc>,
+    spent: Option<HashMap<Option<String>, U256>>,
+}
+
+impl<'a> PolicyChecker<'a> {
+    pub fn new(
+        rules: &'a [PolicyRule],
+        account: Address,
+        chain_id: i64,
+        now: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            rules: rules
+                .iter()
+                .filter(|r| r.applies_to(account, chain_id))
+                .collect(),
+            account,
+            chain_id,
+            now,
+            spent: None,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    async fn spent<S: PaymentStore>(
+        &mut self,
+        conn: &S,
+    ) -> Result<&mut HashMap<Option<String>, U256>, PaymentError> {
+        if self.spent.is_none() {
+            let mut spent = HashMap::<Option<String>, U256>::new();
+            for tt in conn
+                .get_token_transfers_spent_since(
+                    self.account,
+                    self.chain_id,
+                    self.now - Duration::days(1),
+                )
+                .await
+                .map_err(err_from!())?
+            {
+                let amount = U256::from_dec_str(&tt.token_amount).unwrap_or_default();
+                let entry = spent.entry(tt.token_addr).or_default();
+                *entry = entry.saturating_add(amount);
+            }
+            self.spent = Some(spent);
+        }
+        Ok(self.spent.get_or_insert_with(HashMap::new))
+    }
+
+    /// Approved transfers are always allowed, but count to daily spend
+    pub async fn check<S: PaymentStore>(
+        &mut self,
+        token_transfer: &TokenTransferDbObj,
+        conn: &S,
+    ) -> Result<PolicyDecision, PaymentError> {
+        let receiver = Address::from_str(&token_transfer.receiver_addr).map_err(err_from!())?;
+        let amount = U256::from_dec_str(&token_transfer.token_amount)
+            .map_err(|err| err_custom_create!("Invalid token amount: {}", err))?;
+        let token_addr = token_transfer
+            .token_addr
+            .as_ref()
+            .map(|addr| Address::from_str(addr))
+            .transpose()
+            .map_err(err_from!())?;
+        let rules = self
+            .rules
+            .iter()
+            .copied()
+            .filter(|r| r.applies_to_token(token_addr))
+            .collect::<Vec<_>>();
+        if rules.is_empty() {
+            return Ok(PolicyDecision::Allow);
+        }
+
+        let spent = *self
+            .spent(conn)
+            .await?
+            .get(&token_transfer.token_addr)
+            .unwrap_or(&U256::zero());
+        let decision = if token_transfer.approved_date.is_some() {
+            PolicyDecision::Allow
+        } else {
+            let receiver_age = if rules.iter().any(|r| r.new_receiver_cooldown_secs.is_some()) {
+                conn.get_first_token_transfer_to_receiver(self.account, receiver)
+                    .await
+                    .map_err(err_from!())?
+                    .map(|first| self.now - first.create_date)
+            } else {
+                None
+            };
+            evaluate(&rules, receiver, amount, spent, receiver_age)
+        };
+        if decision == PolicyDecision::Allow {
+            let entry = self
+                .spent(conn)
+                .await?
+                .entry(token_transfer.token_addr.clone())
+                .or_default();
+            *entry = entry.saturating_add(amount);
+        }
+        Ok(decision)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy_evaluate() {
+        let receiver = Address::from_low_u64_be(2);
+        let rule = PolicyRule {
+            account: None,
+            chain_id: None,
+            token_addr: None,
+            max_transfer_amount: Some(U256::from(100)),
+            max_daily_amount: Some(U256::from(150)),
+            allowed_receivers: None,
+            denied_receivers: vec![Address::from_low_u64_be(3)],
+            new_receiver_cooldown_secs: Some(3600),
+        };
+        let rules = [&rule];
+        let old = Some(Duration::days(2));
+
+        let allow = evaluate(&rules, receiver, U256::from(100), U256::from(50), old);
+        assert_eq!(allow, PolicyDecision::Allow);
+        let too_big = evaluate(&rules, receiver, U256::from(101), U256::zero(), old);
+        assert!(matches!(too_big, PolicyDecision::Violation(_)));
+        let daily = evaluate(&rules, receiver, U256::from(100), U256::from(51), old);
+        assert!(matches!(daily, PolicyDecision::Violation(_)));
+        let denied = evaluate(
+            &rules,
+            Address::from_low_u64_be(3),
+            U256::one(),
+            U256::zero(),
+            old,
+        );
+        assert!(matches!(denied, PolicyDecision::Violation(_)));
+        let new_receiver = evaluate(
+            &rules,
+            receiver,
+            U256::one(),
+            U256::zero(),
+            Some(Duration::minutes(5)),
+        );
+        assert!(matches!(new_receiver, PolicyDecision::Hold(_)));
+    }
+}
@@ -2,18 +2,13 @@ use crate::error::PaymentError;
 use crate::error::*;
 use crate::{err_create, err_custom_create, err_from};
 use erc20_payment_lib_common::model::TxDbObj;
-use erc20_payment_lib_common::ops::{
-    delete_tx, get_transaction, get_transaction_highest_nonce, insert_tx, remap_allowance_tx,
-    remap_token_transfer_tx, update_processing_and_first_processed_tx, update_tx,
-    update_tx_stuck_date,
-};
+use erc20_payment_lib_common::store::{PaymentStore, PaymentStoreOps, PaymentStoreTransaction};
 use erc20_payment_lib_common::{
     CantSignContent, DriverEvent, DriverEventContent, GasLowInfo, NoGasDetails,
     TransactionStuckReason,
 };
 use rust_decimal::prelude::Zero;
 use rust_decimal::Decimal;
-use sqlx::SqlitePool;
 use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Duration;
@@ -51,10 +46,10 @@ pub async fn get_provider(url: &str) -> Result<Web3<Http>, PaymentError> {
     Ok(web3)
 }
 
-pub async fn process_transaction(
+pub async fn process_transaction<S: PaymentStore>(
     event_sender: Option<tokio::sync::mpsc::Sender<DriverEvent>>,
     shared_state: Arc<std::sync::Mutex<SharedState>>,
-    conn: &SqlitePool,
+    conn: &S,
     web3_tx_dao: &mut TxDbObj,
     payment_setup: &PaymentSetup,
     signer: Arc<Box<dyn Signer + Send + Sync + 'static>>,
@@ -109,7 +104,8 @@ pub async fn process_transaction(
         // do not trust blockchain for returning proper nonce, it can be lower than real one
         // potentially it could be higher, but it is very hard to work around it and hopefully it won't happen
 
-        let db_nonce = get_transaction_highest_nonce(conn, chain_id, &web3_tx_dao.from_addr)
+        let db_nonce = conn
+            .get_transaction_highest_nonce(chain_id, &web3_tx_dao.from_addr)
             .await
             .map_err(err_from!())?;
 
@@ -225,7 +221,7 @@ pub async fn process_transaction(
     } else {
         web3_tx_dao.first_processed = Some(chrono::Utc::now());
         web3_tx_dao.processing = 1;
-        update_processing_and_first_processed_tx(conn, web3_tx_dao)
+        conn.update_processing_and_first_processed_tx(web3_tx_dao)
             .await
             .map_err(err_from!())?;
     }
@@ -308,7 +304,7 @@ pub async fn process_transaction(
             .unwrap()
             .set_tx_message(web3_tx_dao.id, "Signing transaction".to_string());
         sign_transaction_with_callback(&event_sender, web3_tx_dao, from_addr, signer).await?;
-        update_tx(conn, web3_tx_dao).await.map_err(err_from!())?;
+        conn.update_tx(web3_tx_dao).await.map_err(err_from!())?;
     }
 
     if web3_tx_dao.broadcast_date.is_none() {
@@ -322,7 +318,7 @@ pub async fn process_transaction(
             .unwrap()
             .set_tx_message(web3_tx_dao.id, "Sending transaction".to_string());
         web3_tx_dao.broadcast_count += 1;
-        update_tx(conn, web3_tx_dao).await.map_err(err_from!())?;
+        conn.update_tx(web3_tx_dao).await.map_err(err_from!())?;
         send_transaction(
             conn,
             chain_setup.chain_id,
@@ -333,7 +329,7 @@ pub async fn process_transaction(
             chain_setup.wrapper_contract_address,
         )
         .await?;
-        update_tx(conn, web3_tx_dao).await.map_err(err_from!())?;
+        conn.update_tx(web3_tx_dao).await.map_err(err_from!())?;
         log::info!(
             "Transaction {} sent, tx hash: {}",
             web3_tx_dao.id,
@@ -407,7 +403,8 @@ pub async fn process_transaction(
                 }
                 if let Some(orig_tx_id) = web3_tx_dao.orig_tx_id {
                     //jump to previous transaction in chain
-                    current_tx = get_transaction(conn, orig_tx_id)
+                    current_tx = conn
+                        .get_transaction(orig_tx_id)
                         .await
                         .map_err(err_from!())?;
                 } else {
@@ -473,14 +470,14 @@ pub async fn process_transaction(
                     let orig_tx = loop {
                         if let Some(next_tx) = orig_tx.orig_tx_id {
                             let next_tx =
-                                get_transaction(conn, next_tx).await.map_err(err_from!())?;
+                                conn.get_transaction(next_tx).await.map_err(err_from!())?;
                             orig_tx = next_tx;
                         } else {
                             break orig_tx;
                         }
                     };
 
-                    let mut db_transaction = conn.begin().await.map_err(err_from!())?;
+                    let db_transaction = conn.begin().await.map_err(err_from!())?;
                     if orig_tx.id != current_tx.id {
                         log::info!(
                             "Updating orig tx: {} with confirmed tx: {}",
@@ -488,12 +485,14 @@ pub async fn process_transaction(
                             current_tx.id
                         );
                         //handle case when transaction is allowance
-                        remap_allowance_tx(&mut *db_transaction, orig_tx.id, current_tx.id)
+                        db_transaction
+                            .remap_allowance_tx(orig_tx.id, current_tx.id)
                             .await
                             .map_err(err_from!())?;
 
                         //handle case when transaction is token transfer
-                        remap_token_transfer_tx(&mut *db_transaction, orig_tx.id, current_tx.id)
+                        db_transaction
+                            .remap_token_transfer_tx(orig_tx.id, current_tx.id)
                             .await
                             .map_err(err_from!())?;
                     }
@@ -502,12 +501,14 @@ pub async fn process_transaction(
                     loop {
                         if process_tx.id != current_tx.id {
                             log::info!("Deleting tx: {}", process_tx.id);
-                            delete_tx(&mut *db_transaction, process_tx.id)
+                            db_transaction
+                                .delete_tx(process_tx.id)
                                 .await
                                 .map_err(err_from!())?;
                         }
                         if let Some(next_tx) = process_tx.orig_tx_id {
-                            process_tx = get_transaction(&mut *db_transaction, next_tx)
+                            process_tx = db_transaction
+                                .get_transaction(next_tx)
                                 .await
                                 .map_err(err_from!())?;
                         } else {
@@ -517,7 +518,8 @@ pub async fn process_transaction(
                     current_tx.orig_tx_id = None;
                     //signed raw data is no longer needed
                     current_tx.signed_raw_data = None;
-                    update_tx(&mut *db_transaction, &current_tx)
+                    db_transaction
+                        .update_tx(&current_tx)
                         .await
                         .map_err(err_from!())?;
                     db_transaction.commit().await.map_err(err_from!())?;
@@ -576,7 +578,7 @@ pub async fn process_transaction(
                 } else {
                     log::warn!("Receipt not found despite proper nonce. Probably external payment done or web3 provider not yet synchronized");
                     web3_tx_dao.first_stuck_date = Some(chrono::Utc::now());
-                    update_tx_stuck_date(conn, web3_tx_dao)
+                    conn.update_tx_stuck_date(web3_tx_dao)
                         .await
                         .map_err(err_from!())?;
                 }
@@ -757,14 +759,13 @@ pub async fn process_transaction(
                         );
                         tokio::time::sleep(erc20_lib_test_replacement_timeout).await;
                     }
-                    let mut db_transaction = conn.begin().await.map_err(err_from!())?;
-                    let new_tx_dao = insert_tx(&mut *db_transaction, &new_tx_dao)
+                    let db_transaction = conn.begin().await.map_err(err_from!())?;
+                    let new_tx_dao = db_transaction
+                        .insert_tx(&new_tx_dao)
                         .await
                         .map_err(err_from!())?;
                     tx.processing = 0;
-                    update_tx(&mut *db_transaction, &tx)
-                        .await
-                        .map_err(err_from!())?;
+                    db_transaction.update_tx(&tx).await.map_err(err_from!())?;
                     db_transaction.commit().await.map_err(err_from!())?;
                     log::warn!("Replacement transaction created {}", new_tx_dao.id);
 
@@ -802,7 +803,7 @@ pub async fn process_transaction(
             )
             .await?;
             web3_tx_dao.broadcast_count += 1;
-            update_tx(conn, web3_tx_dao).await.map_err(err_from!())?;
+            conn.update_tx(web3_tx_dao).await.map_err(err_from!())?;
             tokio::time::sleep(Duration::from_secs(
                 payment_setup.process_interval_after_send,
             ))
@@ -0,0 +1,200 @@
+use crate::config::SchedulingRule;
+use crate::error::{ErrorBag, PaymentError};
+use crate::setup::PaymentSetup;
+use crate::utils::DecimalConvExt;
+use crate::{err_custom_create, err_from};
+use chrono::{DateTime, NaiveTime, Utc};
+use erc20_payment_lib_common::model::TransferPriority;
+use serde::Serialize;
+use std::collections::HashMap;
+use web3::types::{Address, BlockId, BlockNumber, U256};
+
+/// Time of day range in UTC, end before start means range spanning midnight
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct TimeWindow {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl TimeWindow {
+    /// Parse range in format HH:MM-HH:MM
+    pub fn parse(s: &str) -> Result<Self, PaymentError> {
+        let (start, end) = s
+            .split_once('-')
+            .ok_or_else(|| err_custom_create!("Invalid time window {}, expected HH:MM-HH:MM", s))?;
+        let parse_time = |t: &str| {
+            NaiveTime::parse_from_str(t.trim(), "%H:%M")
+                .map_err(|e| err_custom_create!("Invalid time window {}: {}", s, e))
+        };
+        Ok(TimeWindow {
+            start: parse_time(start)?,
+            end: parse_time(end)?,
+        })
+    }
+
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+/// Parsed [`SchedulingRule`]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduleRule {
+    pub priority: TransferPriority,
+    pub account: Option<Address>,
+    pub chain_id: Option<i64>,
+    pub max_base_fee: Option<U256>,
+    pub time_windows: Vec<TimeWindow>,
+}
+
+impl ScheduleRule {
+    pub fn from_config(rule: &SchedulingRule) -> Result<Self, PaymentError> {
+        if rule.priority == TransferPriority::Urgent {
+            return Err(err_custom_create!(
+                "Scheduling rules cannot hold back urgent transfers"
+            ));
+        }
+        Ok(ScheduleRule {
+            priority: rule.priority,
+            account: rule.account,
+            chain_id: rule.chain_id,
+            max_base_fee: rule
+                .max_base_fee
+                .map(|fee| fee.to_u256_from_gwei())
+                .transpose()
+                .map_err(err_from!())?,
+            time_windows: rule
+                .time_windows
+                .iter()
+                .flatten()
+                .map(|w| TimeWindow::parse(w))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    fn applies_to(&self, account: Address, chain_id: i64) -> bool {
+        self.account.map(|a| a == account).unwrap_or(true)
+            && self.chain_id.map(|c| c == chain_id).unwrap_or(true)
+    }
+}
+
+/// Decides which pending transfers of the account are gathered in current round.
+/// Base fee is fetched only when some rule needs it, at most once per round.
+pub struct TransferScheduler<'a> {
+    rules: Vec<&'a ScheduleRule>,
+    chain_id: i64,
+    now: DateTime<Utc>,
+    base_fee: Option<Option<U256>>,
+    decisions: HashMap<TransferPriority, bool>,
+}
+
+impl<'a> TransferScheduler<'a> {
+    pub fn new(
+        rules: &'a [ScheduleRule],
+        account: Address,
+        chain_id: i64,
+        now: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            rules: rules
+                .iter()
+                .filter(|r| r.applies_to(account, chain_id))
+                .collect(),
+            chain_id,
+            now,
+            base_fee: None,
+            decisions: HashMap::new(),
+        }
+    }
+
+    async fn base_fee(&mut self, payment_setup: &PaymentSetup) -> Option<U256> {
+        if let Some(base_fee) = self.base_fee {
+            return base_fee;
+        }
+        let base_fee = match payment_setup.get_provider(self.chain_id) {
+            Ok(web3) => match web3.eth_block(BlockId::Number(BlockNumber::Latest)).await {
+                Ok(block) => block.and_then(|b| b.base_fee_per_gas),
+                Err(err) => {
+                    log::warn!("Failed to get latest block for scheduling: {}", err);
+                    None
+                }
+            },
+            Err(err) => {
+                log::warn!("No provider for scheduling: {}", err);
+                None
+            }
+        };
+        self.base_fee = Some(base_fee);
+        base_fee
+    }
+
+    /// Transfers of given priority should wait, because some rule is not met.
+    /// When base fee cannot be checked transfers are held back as well.
+    pub async fn is_held_back(
+        &mut self,
+        priority: TransferPriority,
+        payment_setup: &PaymentSetup,
+    ) -> bool {
+        if priority == TransferPriority::Urgent {
+            return false;
+        }
+        if let Some(held_back) = self.decisions.get(&priority) {
+            return *held_back;
+        }
+        let mut held_back = false;
+        for rule in self.rules.clone() {
+            if rule.priority != priority {
+                continue;
+            }
+            let time = self.now.time();
+            if !rule.time_windows.is_empty() && !rule.time_windows.iter().any(|w| w.contains(time))
+            {
+                held_back = true;
+                break;
+            }
+            if let Some(max_base_fee) = rule.max_base_fee {
+                match self.base_fee(payment_setup).await {
+                    Some(base_fee) if base_fee <= max_base_fee => {}
+                    _ => {
+                        held_back = true;
+                        break;
+                    }
+                }
+            }
+        }
+        if held_back {
+            log::info!(
+                "Transfers with priority {} held back by scheduling rules",
+                priority
+            );
+        }
+        self.decisions.insert(priority, held_back);
+        held_back
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_time_window() {
+        let night = TimeWindow::parse("22:00-02:00").unwrap();
+        let at = |t: &str| NaiveTime::parse_from_str(t, "%H:%M").unwrap();
+        assert!(night.contains(at("23:30")));
+        assert!(night.contains(at("01:59")));
+        assert!(!night.contains(at("02:00")));
+        assert!(!night.contains(at("12:00")));
+
+        let morning = TimeWindow::parse("02:00-06:00").unwrap();
+        assert!(morning.contains(at("02:00")));
+        assert!(!morning.contains(at("06:00")));
+        assert!(TimeWindow::parse("02:00").is_err());
+        assert!(TimeWindow::parse("25:00-06:00").is_err());
+    }
+}
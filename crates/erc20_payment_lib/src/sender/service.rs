@@ -1,5 +1,4 @@
 use crate::error::{ErrorBag, PaymentError};
-use erc20_payment_lib_common::ops::*;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::Notify;
@@ -15,15 +14,15 @@ use crate::setup::PaymentSetup;
 use crate::signer::{Signer, SignerAccount};
 use crate::{err_create, err_custom_create, err_from};
 use erc20_payment_lib_common::model::TxDbObj;
+use erc20_payment_lib_common::store::{PaymentStore, PaymentStoreOps, PaymentStoreTransaction};
 use erc20_payment_lib_common::{DriverEvent, DriverEventContent, TransactionFinishedInfo};
-use sqlx::SqlitePool;
 use tokio::select;
 use tokio::time::Instant;
 use web3::types::{Address, U256};
 
-pub async fn update_token_transfer_result(
+pub async fn update_token_transfer_result<S: PaymentStore>(
     event_sender: Option<tokio::sync::mpsc::Sender<DriverEvent>>,
-    conn: &SqlitePool,
+    conn: &S,
     tx: &mut TxDbObj,
     process_t_res: &ProcessTransactionResult,
 ) -> Result<(), PaymentError> {
@@ -31,8 +30,9 @@ pub async fn update_token_transfer_result(
         ProcessTransactionResult::Confirmed => {
             tx.processing = 0;
 
-            let mut db_transaction = conn.begin().await.map_err(err_from!())?;
-            let mut token_transfers = get_token_transfers_by_tx(&mut *db_transaction, tx.id)
+            let db_transaction = conn.begin().await.map_err(err_from!())?;
+            let mut token_transfers = db_transaction
+                .get_token_transfers_by_tx(tx.id)
                 .await
                 .map_err(err_from!())?;
 
@@ -81,13 +81,12 @@ pub async fn update_token_transfer_result(
                 token_transfer.fee_paid = fee_paid.map(|v| v.to_string());
                 token_transfer.paid_date = Some(chrono::Utc::now());
 
-                update_token_transfer(&mut *db_transaction, token_transfer)
+                db_transaction
+                    .update_token_transfer(token_transfer)
                     .await
                     .map_err(err_from!())?;
             }
-            update_tx(&mut *db_transaction, tx)
-                .await
-                .map_err(err_from!())?;
+            db_transaction.update_tx(tx).await.map_err(err_from!())?;
             db_transaction.commit().await.map_err(err_from!())?;
             //if transaction is committed emit events:
             for token_transfer in token_transfers {
@@ -104,57 +103,57 @@ pub async fn update_token_transfer_result(
         ProcessTransactionResult::NeedRetry(err) => {
             tx.processing = 0;
 
-            let mut db_transaction = conn.begin().await.map_err(err_from!())?;
-            let token_transfers = get_token_transfers_by_tx(&mut *db_transaction, tx.id)
+            let db_transaction = conn.begin().await.map_err(err_from!())?;
+            let token_transfers = db_transaction
+                .get_token_transfers_by_tx(tx.id)
                 .await
                 .map_err(err_from!())?;
             for mut token_transfer in token_transfers {
                 token_transfer.fee_paid = Some("0".to_string());
                 token_transfer.error = Some(err.clone());
-                update_token_transfer(&mut *db_transaction, &token_transfer)
+                db_transaction
+                    .update_token_transfer(&token_transfer)
                     .await
                     .map_err(err_from!())?;
             }
             tx.error = Some(err.clone());
 
-            update_tx(&mut *db_transaction, tx)
-                .await
-                .map_err(err_from!())?;
+            db_transaction.update_tx(tx).await.map_err(err_from!())?;
             db_transaction.commit().await.map_err(err_from!())?;
         }
         ProcessTransactionResult::InternalError(err) => {
             tx.processing = 0;
 
-            let mut db_transaction = conn.begin().await.map_err(err_from!())?;
-            let token_transfers = get_token_transfers_by_tx(&mut *db_transaction, tx.id)
+            let db_transaction = conn.begin().await.map_err(err_from!())?;
+            let token_transfers = db_transaction
+                .get_token_transfers_by_tx(tx.id)
                 .await
                 .map_err(err_from!())?;
             for mut token_transfer in token_transfers {
                 token_transfer.fee_paid = Some("0".to_string());
                 token_transfer.error = Some(err.clone());
-                update_token_transfer(&mut *db_transaction, &token_transfer)
+                db_transaction
+                    .update_token_transfer(&token_transfer)
                     .await
                     .map_err(err_from!())?;
             }
             tx.error = Some(err.clone());
 
-            update_tx(&mut *db_transaction, tx)
-                .await
-                .map_err(err_from!())?;
+            db_transaction.update_tx(tx).await.map_err(err_from!())?;
             db_transaction.commit().await.map_err(err_from!())?;
         }
         ProcessTransactionResult::Unknown => {
             tx.processing = 1;
-            update_tx(conn, tx).await.map_err(err_from!())?;
+            conn.update_tx(tx).await.map_err(err_from!())?;
         }
         _ => {}
     }
     Ok(())
 }
 
-pub async fn update_approve_result(
+pub async fn update_approve_result<S: PaymentStore>(
     event_sender: Option<tokio::sync::mpsc::Sender<DriverEvent>>,
-    conn: &SqlitePool,
+    conn: &S,
     tx: &mut TxDbObj,
     process_t_res: &ProcessTransactionResult,
 ) -> Result<(), PaymentError> {
@@ -162,17 +161,17 @@ pub async fn update_approve_result(
         ProcessTransactionResult::Confirmed => {
             tx.processing = 0;
 
-            let mut db_transaction = conn.begin().await.map_err(err_from!())?;
-            let mut allowance = get_allowance_by_tx(&mut *db_transaction, tx.id)
+            let db_transaction = conn.begin().await.map_err(err_from!())?;
+            let mut allowance = db_transaction
+                .get_allowance_by_tx(tx.id)
                 .await
                 .map_err(err_from!())?;
             allowance.fee_paid.clone_from(&tx.fee_paid);
-            update_allowance(&mut *db_transaction, &allowance)
-                .await
-                .map_err(err_from!())?;
-            update_tx(&mut *db_transaction, tx)
+            db_transaction
+                .update_allowance(&allowance)
                 .await
                 .map_err(err_from!())?;
+            db_transaction.update_tx(tx).await.map_err(err_from!())?;
             db_transaction.commit().await.map_err(err_from!())?;
             //if transaction is committed emit events:
             send_driver_event(
@@ -183,82 +182,82 @@ pub async fn update_approve_result(
         }
         ProcessTransactionResult::NeedRetry(err) => {
             tx.processing = 0;
-            let mut db_transaction = conn.begin().await.map_err(err_from!())?;
-            let mut allowance = get_allowance_by_tx(&mut *db_transaction, tx.id)
+            let db_transaction = conn.begin().await.map_err(err_from!())?;
+            let mut allowance = db_transaction
+                .get_allowance_by_tx(tx.id)
                 .await
                 .map_err(err_from!())?;
             allowance.fee_paid = Some("0".to_string());
             allowance.error = Some(err.clone());
             tx.error = Some(err.clone());
-            update_allowance(&mut *db_transaction, &allowance)
-                .await
-                .map_err(err_from!())?;
-            update_tx(&mut *db_transaction, tx)
+            db_transaction
+                .update_allowance(&allowance)
                 .await
                 .map_err(err_from!())?;
+            db_transaction.update_tx(tx).await.map_err(err_from!())?;
             db_transaction.commit().await.map_err(err_from!())?;
         }
         ProcessTransactionResult::InternalError(err) => {
             tx.processing = 0;
-            let mut db_transaction = conn.begin().await.map_err(err_from!())?;
-            let mut allowance = get_allowance_by_tx(&mut *db_transaction, tx.id)
+            let db_transaction = conn.begin().await.map_err(err_from!())?;
+            let mut allowance = db_transaction
+                .get_allowance_by_tx(tx.id)
                 .await
                 .map_err(err_from!())?;
             allowance.fee_paid = Some("0".to_string());
             allowance.error = Some(err.clone());
             tx.error = Some(err.clone());
-            update_allowance(&mut *db_transaction, &allowance)
-                .await
-                .map_err(err_from!())?;
-            update_tx(&mut *db_transaction, tx)
+            db_transaction
+                .update_allowance(&allowance)
                 .await
                 .map_err(err_from!())?;
+            db_transaction.update_tx(tx).await.map_err(err_from!())?;
             db_transaction.commit().await.map_err(err_from!())?;
         }
         ProcessTransactionResult::Unknown => {
             tx.processing = 1;
-            update_tx(conn, tx).await.map_err(err_from!())?;
+            conn.update_tx(tx).await.map_err(err_from!())?;
         }
         _ => {}
     }
     Ok(())
 }
 
-pub async fn update_tx_result(
-    conn: &SqlitePool,
+pub async fn update_tx_result<S: PaymentStore>(
+    conn: &S,
     tx: &mut TxDbObj,
     process_t_res: &ProcessTransactionResult,
 ) -> Result<(), PaymentError> {
     match process_t_res {
         ProcessTransactionResult::Confirmed => {
             tx.processing = 0;
-            update_tx(conn, tx).await.map_err(err_from!())?;
+            conn.update_tx(tx).await.map_err(err_from!())?;
         }
         ProcessTransactionResult::NeedRetry(_err) => {
             tx.processing = 0;
             tx.error = Some("Need retry".to_string());
-            update_tx(conn, tx).await.map_err(err_from!())?;
+            conn.update_tx(tx).await.map_err(err_from!())?;
         }
         ProcessTransactionResult::InternalError(err) => {
             tx.processing = 0;
             tx.error = Some(err.clone());
-            update_tx(conn, tx).await.map_err(err_from!())?;
+            conn.update_tx(tx).await.map_err(err_from!())?;
         }
         ProcessTransactionResult::Unknown => {
             tx.processing = 1;
-            update_tx(conn, tx).await.map_err(err_from!())?;
+            conn.update_tx(tx).await.map_err(err_from!())?;
         }
         _ => {}
     }
     Ok(())
 }
 
-pub async fn process_transactions(
+pub async fn process_transactions<S: PaymentStore>(
     signer_account: &SignerAccount,
     chain_id: i64,
     event_sender: Option<tokio::sync::mpsc::Sender<DriverEvent>>,
     shared_state: Arc<std::sync::Mutex<SharedState>>,
-    conn: &SqlitePool,
+    conn: &S,
     payment_setup: &PaymentSetup,
     signer: Arc<Box<dyn Signer + Send + Sync + 'static>>,
 ) -> Result<(), PaymentError> {
@@ -266,10 +265,10 @@ pub async fn process_transactions(
 
     let mut current_wait_time_no_gas_token: f64 = 0.0;
     loop {
-        let mut transactions =
-            get_next_transactions_to_process(conn, Some(signer_account.address), 1, chain_id)
-                .await
-                .map_err(err_from!())?;
+        let mut transactions = conn
+            .get_next_transactions_to_process(Some(signer_account.address), 1, chain_id)
+            .await
+            .map_err(err_from!())?;
 
         let Some(tx) = transactions.get_mut(0) else {
             log::debug!("No transactions to process, breaking from loop");
@@ -298,7 +297,7 @@ pub async fn process_transactions(
             .await
             {
                 Ok((tx_dao, process_result)) => (tx_dao, process_result),
-                Err(err) => match err.inner {
+                Err(err) => match *err.inner {
                     ErrorBag::TransactionFailedError(err2) => {
                         shared_state
                             .lock()
@@ -443,6 +442,37 @@ fn get_next_gather_time_and_clear_if_success(
     Some(next_gather_time)
 }
 
+/// Make gathering happen not later than the earliest deadline stored with pending transfers.
+/// Deadlines not later than `after` were already honoured by previous gathering.
+async fn schedule_stored_deadline<S: PaymentStore>(
+    conn: &S,
+    account: &SignerAccount,
+    chain_id: i64,
+    after: chrono::DateTime<chrono::Utc>,
+    payment_setup: &PaymentSetup,
+) {
+    if payment_setup.ignore_deadlines {
+        return;
+    }
+    match conn
+        .get_next_token_transfer_deadline(account.address, chain_id, after)
+        .await
+    {
+        Ok(Some(deadline)) => {
+            let mut external_gather_time_guard = account.external_gather_time.lock().unwrap();
+            *external_gather_time_guard = Some(
+                external_gather_time_guard
+                    .map(|t| t.min(deadline))
+                    .unwrap_or(deadline),
+            );
+        }
+        Ok(None) => {}
+        Err(e) => {
+            log::warn!("Error reading deadlines of pending transfers: {}", e);
+        }
+    }
+}
+
 async fn sleep_for_gather_time_or_report_alive(
     account: &SignerAccount,
     wake: Arc<Notify>,
@@ -480,12 +510,12 @@ async fn sleep_for_gather_time_or_report_alive(
     }
 }
 
-pub async fn service_loop(
+pub async fn service_loop<S: PaymentStore>(
     shared_state: Arc<std::sync::Mutex<SharedState>>,
     chain_id: i64,
     account: Address,
     wake: Arc<tokio::sync::Notify>,
-    conn: &SqlitePool,
+    conn: &S,
     payment_setup: &PaymentSetup,
     event_sender: Option<tokio::sync::mpsc::Sender<DriverEvent>>,
 ) {
@@ -512,6 +542,8 @@ pub async fn service_loop(
     metrics::counter!(metric_label_gather_post, 0, "chain_id" => chain_id.to_string());
     metrics::counter!(metric_label_gather_post_error, 0, "chain_id" => chain_id.to_string());
 
+    // overdue deadlines found at start are honoured by gathering at once
+    let mut last_deadline_gather_time = chrono::DateTime::UNIX_EPOCH;
     let mut process_tx_needed;
     let mut last_stats_time: Option<Instant> = None;
     loop {
@@ -565,6 +597,14 @@ pub async fn service_loop(
 
         //we should be here only when all pending transactions are processed
 
+        schedule_stored_deadline(
+            conn,
+            &signer_account,
+            chain_id,
+            last_deadline_gather_time,
+            payment_setup,
+        )
+        .await;
         let next_gather_time = get_next_gather_time_and_clear_if_success(
             &signer_account,
             last_gather_time,
@@ -597,17 +637,20 @@ pub async fn service_loop(
 
         log::debug!("Gathering payments...");
 
+        let gather_guard = signer_account.gather_lock.lock().await;
         let mut token_transfer_map = match gather_transactions_pre(
             &signer_account,
             chain_id,
             conn,
             payment_setup,
+            &event_sender,
             &mut process_tx_needed,
         )
         .await
         {
             Ok(token_transfer_map) => token_transfer_map,
             Err(e) => {
+                drop(gather_guard);
                 metrics::counter!(metric_label_gather_pre_error, 1);
                 log::error!(
                     "Error in gather transactions, driver will be stuck, Fix DB to continue {:?}",
@@ -622,21 +665,29 @@ pub async fn service_loop(
         };
         metrics::counter!(metric_label_gather_post, 1);
 
-        match gather_transactions_post(
+        let from_addr = format!("{:#x}", account);
+        shared_state
+            .lock()
+            .unwrap()
+            .pending_batches
+            .retain(|_, batch| batch.chain_id != chain_id || batch.from_addr != from_addr);
+        let gather_result = gather_transactions_post(
+            &shared_state,
             event_sender.clone(),
             conn,
             payment_setup,
             &mut token_transfer_map,
         )
-        .await
-        {
+        .await;
+        drop(gather_guard);
+        match gather_result {
             Ok(count) => {
                 if count > 0 {
                     process_tx_needed = true;
                 }
             }
             Err(e) => {
-                match &e.inner {
+                match &*e.inner {
                     ErrorBag::NoAllowanceFound(allowance_request) => {
                         log::info!(
                             "No allowance found for contract {} to spend token {} for owner: {}",
@@ -680,6 +731,7 @@ pub async fn service_loop(
             }
         };
         last_gather_time = current_time;
+        last_deadline_gather_time = current_time;
         if payment_setup.finish_when_done && !process_tx_needed {
             log::info!("No more work to do, exiting...");
             break;
@@ -1,2 +1,8 @@
-pub mod web;
-pub mod ws;
+pub mod api;
+pub mod auth;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod openapi;
+pub mod sse;
+pub mod web;
+pub mod ws;
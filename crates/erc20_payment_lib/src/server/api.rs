@@ -0,0 +1,441 @@
+//! Request and response bodies of the http api, shared by the server and `erc20_payment_client`.
+//! Handlers returning [`ApiError`] still respond with status 200, as the frontend expects.
+
+use crate::eth::{Attestation, AttestationSchema};
+use crate::sender::batch_policy::BatchEstimate;
+use crate::sender::estimate::ChainFeeEstimate;
+use crate::sender::payout::PendingPayout;
+use chrono::{DateTime, Utc};
+use erc20_payment_lib_common::model::{
+    AllowanceDbObj, DepositId, FaucetRequestDbObj, TokenTransferDbObj, TransferInDbObj,
+    TransferPriority, TxDbObj, WebhookDeliveryDbObj,
+};
+use erc20_payment_lib_common::SharedInfoTx;
+use erc20_rpc_pool::{Web3RpcEndpoint, Web3RpcInfo, Web3RpcSingleParams};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiError {
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VersionResponse {
+    pub name: String,
+    pub version: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TxResponse {
+    pub tx: TxDbObj,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcPoolNetwork {
+    pub chain_id: i64,
+    pub chain_network: String,
+    pub endpoints: Vec<Web3RpcEndpoint>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RpcPoolResponse {
+    pub networks: Vec<RpcPoolNetwork>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RpcEndpointInfo {
+    pub params: Web3RpcSingleParams,
+    pub info: Web3RpcInfo,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcPoolEndpointsResponse {
+    pub chain_id: i64,
+    pub endpoints: Vec<RpcEndpointInfo>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AllowancesResponse {
+    pub allowances: Vec<AllowanceDbObj>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionsCountResponse {
+    pub transfers_queued: usize,
+    pub transfers_processing: usize,
+    pub transfers_done: usize,
+    pub tx_queued: usize,
+    pub tx_done: usize,
+}
+
+/// Payment setup of the server, not typed as it follows the config file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigResponse {
+    pub config: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DebugResponse {
+    pub shared_state: serde_json::Value,
+}
+
+/// Page size of list endpoints when limit is not given
+pub const DEFAULT_PAGE_LIMIT: i64 = 100;
+pub const MAX_PAGE_LIMIT: i64 = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ListStatus {
+    Queued,
+    Processing,
+    Done,
+}
+
+/// Query of `/transactions`, rows are returned newest first.
+/// `cursor` is `nextCursor` of previous page, dates are RFC 3339.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TxListQuery {
+    pub status: Option<ListStatus>,
+    pub chain: Option<i64>,
+    pub account: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub cursor: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+/// Additional filters of transaction feed, next and last endpoints
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TxFeedQuery {
+    pub chain: Option<i64>,
+    pub account: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TxsResponse {
+    pub txs: Vec<TxDbObj>,
+    /// Set when there may be more rows, pass as `cursor` to get next page
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NextTxsResponse {
+    pub txs: Vec<TxDbObj>,
+    pub batches: Vec<BatchEstimate>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TxsFeedResponse {
+    pub txs: Vec<TxDbObj>,
+    pub current: BTreeMap<i64, SharedInfoTx>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SkipTxResponse {
+    pub success: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionRequest {
+    pub from: String,
+    pub to: String,
+    pub token: Option<String>,
+    pub amount: String,
+    pub chain: i64,
+    pub due_date: Option<String>,
+    pub payment_id: Option<String>,
+    pub deposit_id: Option<DepositId>,
+    pub priority: Option<TransferPriority>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewTransferResponse {
+    pub created: bool,
+    pub transfer: TokenTransferDbObj,
+}
+
+/// Row of `text/csv` body of `/transfers/bulk`, header names are the same as fields
+/// of [`TransactionRequest`]. Deposit id is written as `depositId-lockAddress`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkTransferCsvRow {
+    pub from: String,
+    pub to: String,
+    pub token: Option<String>,
+    pub amount: String,
+    pub chain: i64,
+    pub due_date: Option<String>,
+    pub payment_id: Option<String>,
+    pub deposit_id: Option<String>,
+    pub priority: Option<TransferPriority>,
+}
+
+impl TryFrom<BulkTransferCsvRow> for TransactionRequest {
+    type Error = String;
+
+    fn try_from(row: BulkTransferCsvRow) -> Result<Self, Self::Error> {
+        let deposit_id = row
+            .deposit_id
+            .as_deref()
+            .map(DepositId::from_db_string)
+            .transpose()
+            .map_err(|err| err.to_string())?;
+        Ok(TransactionRequest {
+            from: row.from,
+            to: row.to,
+            token: row.token,
+            amount: row.amount,
+            chain: row.chain,
+            due_date: row.due_date,
+            payment_id: row.payment_id,
+            deposit_id,
+            priority: row.priority,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkTransferResult {
+    /// Position in the request, starting from 0
+    pub row: usize,
+    /// False if transfer with the same payment id was submitted before
+    pub created: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transfer: Option<TokenTransferDbObj>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Result of `/transfers/bulk`. Transfers are inserted only if all rows are valid,
+/// otherwise `inserted` is false and failed rows have `error` set.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BulkTransferResponse {
+    pub inserted: bool,
+    pub results: Vec<BulkTransferResult>,
+}
+
+/// Result of `/estimate`, one entry for every chain of requested transfers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EstimateResponse {
+    pub chains: Vec<ChainFeeEstimate>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferChangeRequest {
+    pub to: Option<String>,
+    pub amount: Option<String>,
+    pub due_date: Option<String>,
+    pub priority: Option<TransferPriority>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferResponse {
+    pub transfer: TokenTransferDbObj,
+}
+
+/// Query of `/transfers`, see [`TxListQuery`]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferListQuery {
+    pub status: Option<ListStatus>,
+    pub chain: Option<i64>,
+    pub sender: Option<String>,
+    pub receiver: Option<String>,
+    pub payment_id: Option<String>,
+    pub deposit_id: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub cursor: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransfersResponse {
+    pub transfers: Vec<TokenTransferDbObj>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<i64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StatsTransferRequest {
+    pub receiver: Option<String>,
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub chain: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatsTransferResult {
+    pub request_time: f64,
+    pub transfers: Vec<ChainTransferRespObj>,
+}
+
+#[derive(Serialize, Deserialize, sqlx::FromRow, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ChainTransferRespObj {
+    pub id: i64,
+    pub from_addr: String,
+    pub receiver_addr: String,
+    pub chain_id: i64,
+    pub token_addr: Option<String>,
+    pub token_amount: String,
+    pub tx_hash: String,
+    pub block_number: i64,
+    pub fee_paid: Option<String>,
+    pub block_date: DateTime<Utc>,
+    pub block_timestamp: i64,
+    pub to_addr: String,
+    pub caller_addr: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountBalanceResponse {
+    pub network_id: i64,
+    pub account: String,
+    pub gas_balance: String,
+    pub token_balance: String,
+    pub block_number: u64,
+    pub block_date: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountsResponse {
+    pub public_addr: Vec<String>,
+}
+
+/// Query of `/account/{account}/in`, see [`TxListQuery`]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferInListQuery {
+    pub chain: Option<i64>,
+    pub sender: Option<String>,
+    pub payment_id: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub cursor: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountTransfersInResponse {
+    pub transfers_in: Vec<TransferInDbObj>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountDetailsResponse {
+    pub account: String,
+    pub allowances: Vec<AllowanceDbObj>,
+    pub transfers_queued: usize,
+    pub transfers_processing: usize,
+    pub transfers_done: usize,
+    pub received_transfers: usize,
+    pub pending_payouts: Vec<PendingPayout>,
+}
+
+/// Query of `/event_stream` and `/event_stream/sse`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EventStreamQuery {
+    /// Id of the last event seen, stored events after it are sent before live ones
+    pub since: Option<i64>,
+    /// Comma separated event types, i.e. `transferFinished,cantSign`, all when not set
+    pub events: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeliveryStatus {
+    Pending,
+    Delivered,
+    Failed,
+}
+
+/// Query of `/webhooks/deliveries`, see [`TxListQuery`]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookDeliveryListQuery {
+    pub status: Option<DeliveryStatus>,
+    pub webhook: Option<String>,
+    pub event: Option<String>,
+    pub cursor: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookDeliveriesResponse {
+    pub deliveries: Vec<WebhookDeliveryDbObj>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookDeliveryResponse {
+    pub delivery: WebhookDeliveryDbObj,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FaucetStatusResponse {
+    pub status: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FaucetResponse {
+    pub transfer_gas_id: i64,
+    pub transfer_gas_payment_id: Option<String>,
+    pub transfer_glm_id: i64,
+    pub transfer_glm_payment_id: Option<String>,
+}
+
+/// Query of `/faucet/history`, see [`TxListQuery`]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FaucetHistoryQuery {
+    pub chain: Option<i64>,
+    pub address: Option<String>,
+    pub cursor: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FaucetHistoryResponse {
+    pub requests: Vec<FaucetRequestDbObj>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttestationItemInfo {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub typ: String,
+    pub value: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AttestationCheckResult {
+    pub chain_id: u64,
+    pub chain: String,
+    pub attestation: Attestation,
+    pub schema: AttestationSchema,
+    pub params: Vec<AttestationItemInfo>,
+}
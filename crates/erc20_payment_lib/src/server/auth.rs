@@ -0,0 +1,485 @@
+use crate::config::ApiAuthSettings;
+use actix_web::dev::{forward_ready, Payload, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::error::{ErrorForbidden, ErrorUnauthorized, PayloadError};
+use actix_web::http::header;
+use actix_web::{web, Error, HttpMessage};
+use futures_util::future::{ready, LocalBoxFuture, Ready};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use subtle::ConstantTimeEq;
+
+pub const HMAC_KEY_ID_HEADER: &str = "X-Api-Key-Id";
+pub const HMAC_TIMESTAMP_HEADER: &str = "X-Api-Timestamp";
+pub const HMAC_SIGNATURE_HEADER: &str = "X-Api-Signature";
+const DEFAULT_HMAC_MAX_SKEW_SECS: u64 = 300;
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "kebab-case")]
+pub enum ApiRole {
+    /// Read endpoints, implied by payer and operator
+    ReadOnly,
+    /// Create, edit and cancel transfers
+    Payer,
+    /// Manage the service, i.e. skip transactions, approve transfers, change rpc endpoints
+    Operator,
+    /// Request test tokens from faucet
+    Faucet,
+}
+
+/// Caller of the api, stored in request extensions after successful authentication
+#[derive(Debug, Clone)]
+pub struct ApiPrincipal {
+    pub name: String,
+    pub roles: Vec<ApiRole>,
+}
+
+impl ApiPrincipal {
+    pub fn has_role(&self, role: ApiRole) -> bool {
+        self.roles.contains(&role)
+            || (role == ApiRole::ReadOnly
+                && self
+                    .roles
+                    .iter()
+                    .any(|r| matches!(r, ApiRole::Payer | ApiRole::Operator)))
+    }
+
+    pub fn is_anonymous(&self) -> bool {
+        self.name.is_empty()
+    }
+}
+
+/// Signature of request, hex encoded HMAC-SHA256 of
+/// `{timestamp}\n{METHOD}\n{path and query}\n{hex sha256 of body}`
+pub fn sign_request(
+    secret: &str,
+    timestamp: i64,
+    method: &str,
+    path_and_query: &str,
+    body: &[u8],
+) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(signed_message(timestamp, method, path_and_query, body).as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+fn signed_message(timestamp: i64, method: &str, path_and_query: &str, body: &[u8]) -> String {
+    format!(
+        "{}\n{}\n{}\n{}",
+        timestamp,
+        method.to_uppercase(),
+        path_and_query,
+        hex::encode(Sha256::digest(body))
+    )
+}
+
+fn header_str<'a>(req: &'a ServiceRequest, name: &str) -> Option<&'a str> {
+    req.headers().get(name).and_then(|h| h.to_str().ok())
+}
+
+/// Credentials accepted by http api, see [`ApiAuthSettings`]
+pub struct ApiAuth {
+    anonymous: ApiPrincipal,
+    tokens: Vec<(String, ApiPrincipal)>,
+    hmac_keys: HashMap<String, (String, ApiPrincipal)>,
+    hmac_max_skew_secs: u64,
+    /// Signatures accepted within skew window with their timestamps, used to reject replays
+    hmac_seen: Mutex<HashMap<Vec<u8>, i64>>,
+    client_cert_header: Option<String>,
+    trusted_proxies: Vec<IpAddr>,
+    client_certs: HashMap<String, ApiPrincipal>,
+}
+
+impl ApiAuth {
+    /// Admin token has all roles. When api auth is not configured, or anonymous roles are
+    /// not set, requests without credentials can only read.
+    pub fn new(settings: Option<&ApiAuthSettings>, admin_token: Option<String>) -> Self {
+        let all_roles = vec![
+            ApiRole::ReadOnly,
+            ApiRole::Payer,
+            ApiRole::Operator,
+            ApiRole::Faucet,
+        ];
+        let principal = |name: &str, roles: &[ApiRole]| ApiPrincipal {
+            name: name.to_string(),
+            roles: roles.to_vec(),
+        };
+        let mut tokens = Vec::new();
+        if let Some(admin_token) = admin_token {
+            tokens.push((admin_token, principal("admin", &all_roles)));
+        }
+        let Some(settings) = settings else {
+            log::warn!(
+                "Api authentication not configured, requests without credentials are read-only. \
+                 Set anonymous-roles in [api-auth] to allow creating transfers or using faucet"
+            );
+            return Self {
+                anonymous: principal("", &[ApiRole::ReadOnly]),
+                tokens,
+                hmac_keys: HashMap::new(),
+                hmac_max_skew_secs: DEFAULT_HMAC_MAX_SKEW_SECS,
+                hmac_seen: Mutex::new(HashMap::new()),
+                client_cert_header: None,
+                trusted_proxies: Vec::new(),
+                client_certs: HashMap::new(),
+            };
+        };
+        let anonymous_roles = settings
+            .anonymous_roles
+            .clone()
+            .unwrap_or_else(|| vec![ApiRole::ReadOnly]);
+        if anonymous_roles
+            .iter()
+            .any(|r| matches!(r, ApiRole::Payer | ApiRole::Operator))
+        {
+            log::warn!(
+                "Requests without credentials have roles {:?}, anyone reaching the api can use them",
+                anonymous_roles
+            );
+        }
+        let trusted_proxies = settings.trusted_proxies.clone().unwrap_or_default();
+        if settings.client_cert_header.is_some() && trusted_proxies.is_empty() {
+            log::warn!(
+                "Client certificate header set without trusted-proxies, it will be rejected"
+            );
+        }
+        for token in settings.tokens.iter().flatten() {
+            tokens.push((
+                token.token.expose().to_string(),
+                principal(&token.name, &token.roles),
+            ));
+        }
+        Self {
+            anonymous: principal("", &anonymous_roles),
+            tokens,
+            hmac_keys: settings
+                .hmac_keys
+                .iter()
+                .flatten()
+                .map(|key| {
+                    (
+                        key.key_id.clone(),
+                        (
+                            key.secret.expose().to_string(),
+                            principal(&key.key_id, &key.roles),
+                        ),
+                    )
+                })
+                .collect(),
+            hmac_max_skew_secs: settings
+                .hmac_max_skew_secs
+                .unwrap_or(DEFAULT_HMAC_MAX_SKEW_SECS),
+            hmac_seen: Mutex::new(HashMap::new()),
+            client_cert_header: settings.client_cert_header.clone(),
+            trusted_proxies,
+            client_certs: settings
+                .client_certs
+                .iter()
+                .flatten()
+                .map(|cert| (cert.subject.clone(), principal(&cert.subject, &cert.roles)))
+                .collect(),
+        }
+    }
+
+    /// Middleware rejecting requests without given role, used on every api route
+    pub fn require(self: &Arc<Self>, role: ApiRole) -> RequireRole {
+        RequireRole {
+            auth: self.clone(),
+            role,
+        }
+    }
+
+    /// Check value of `Authorization` header, only bearer tokens are accepted.
+    /// Anonymous principal is returned when header is missing.
+    pub fn authenticate_bearer(
+        &self,
+        authorization: Option<&str>,
+    ) -> Result<ApiPrincipal, &'static str> {
+        let Some(authorization) = authorization else {
+            return Ok(self.anonymous.clone());
+        };
+        let token = authorization
+            .strip_prefix("Bearer ")
+            .ok_or("Only bearer authorization is supported")?;
+        self.tokens
+            .iter()
+            .find(|(t, _)| bool::from(t.as_bytes().ct_eq(token.as_bytes())))
+            .map(|(_, principal)| principal.clone())
+            .ok_or("Invalid token")
+    }
+
+    /// Signed request can be sent only once, its signature is remembered while
+    /// the timestamp is within skew window
+    fn check_replay(&self, signature: Vec<u8>, timestamp: i64, now: i64) -> Result<(), Error> {
+        let mut seen = self.hmac_seen.lock().unwrap();
+        let max_skew_secs = self.hmac_max_skew_secs;
+        seen.retain(|_, ts| (now - *ts).unsigned_abs() <= max_skew_secs);
+        if seen.insert(signature, timestamp).is_some() {
+            return Err(ErrorUnauthorized(
+                "Signed request already used, sign again with new timestamp",
+            ));
+        }
+        Ok(())
+    }
+
+    async fn authenticate(&self, req: &mut ServiceRequest) -> Result<ApiPrincipal, Error> {
+        if let Some(auth_header) = header_str(req, header::AUTHORIZATION.as_str()) {
+            return self
+                .authenticate_bearer(Some(auth_header))
+                .map_err(ErrorUnauthorized);
+        }
+        if let Some(key_id) = header_str(req, HMAC_KEY_ID_HEADER) {
+            let (secret, principal) = self
+                .hmac_keys
+                .get(key_id)
+                .ok_or_else(|| ErrorUnauthorized("Unknown key id"))?;
+            let timestamp = header_str(req, HMAC_TIMESTAMP_HEADER)
+                .and_then(|t| t.parse::<i64>().ok())
+                .ok_or_else(|| ErrorUnauthorized("Missing or invalid timestamp"))?;
+            let now = chrono::Utc::now().timestamp();
+            if (now - timestamp).unsigned_abs() > self.hmac_max_skew_secs {
+                return Err(ErrorUnauthorized(
+                    "Request timestamp too far from server time",
+                ));
+            }
+            let signature = header_str(req, HMAC_SIGNATURE_HEADER)
+                .and_then(|s| hex::decode(s).ok())
+                .ok_or_else(|| ErrorUnauthorized("Missing or invalid signature"))?;
+            let path_and_query = req
+                .uri()
+                .path_and_query()
+                .map(|p| p.as_str().to_string())
+                .unwrap_or_default();
+            let method = req.method().to_string();
+            let body = req.extract::<web::Bytes>().await?;
+            let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+                .expect("HMAC accepts keys of any size");
+            mac.update(signed_message(timestamp, &method, &path_and_query, &body).as_bytes());
+            mac.verify_slice(&signature)
+                .map_err(|_| ErrorUnauthorized("Invalid signature"))?;
+            self.check_replay(signature, timestamp, now)?;
+            //body was consumed, put it back for handler
+            let stream: std::pin::Pin<
+                Box<dyn futures_util::Stream<Item = Result<web::Bytes, PayloadError>>>,
+            > = Box::pin(futures_util::stream::once(ready(Ok(body))));
+            req.set_payload(Payload::from(stream));
+            return Ok(principal.clone());
+        }
+        if let Some(cert_header) = &self.client_cert_header {
+            if let Some(subject) = header_str(req, cert_header) {
+                // anyone can send the header, only proxy terminating TLS is trusted
+                let peer = req.peer_addr().map(|addr| addr.ip());
+                if !peer.is_some_and(|ip| self.trusted_proxies.contains(&ip)) {
+                    return Err(ErrorUnauthorized(
+                        "Client certificate header not accepted from this address",
+                    ));
+                }
+                return self
+                    .client_certs
+                    .get(subject)
+                    .cloned()
+                    .ok_or_else(|| ErrorUnauthorized("Unknown client certificate"));
+            }
+        }
+        Ok(self.anonymous.clone())
+    }
+}
+
+pub struct RequireRole {
+    auth: Arc<ApiAuth>,
+    role: ApiRole,
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RequireRole
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = RequireRoleMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequireRoleMiddleware {
+            service: Rc::new(service),
+            auth: self.auth.clone(),
+            role: self.role,
+        }))
+    }
+}
+
+pub struct RequireRoleMiddleware<S> {
+    service: Rc<S>,
+    auth: Arc<ApiAuth>,
+    role: ApiRole,
+}
+
+impl<S, B> Service<ServiceRequest> for RequireRoleMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let auth = self.auth.clone();
+        let role = self.role;
+        Box::pin(async move {
+            let principal = auth.authenticate(&mut req).await?;
+            if !principal.has_role(role) {
+                return Err(if principal.is_anonymous() {
+                    ErrorUnauthorized("Authentication required")
+                } else {
+                    ErrorForbidden(format!("Role {:?} required", role))
+                });
+            }
+            req.extensions_mut().insert(principal);
+            service.call(req).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ApiClientCertSettings, ApiHmacKeySettings, ApiSecret, ApiTokenSettings};
+    use actix_web::http::StatusCode;
+    use actix_web::{test, App, HttpResponse};
+
+    #[actix_web::test]
+    async fn test_api_roles() {
+        let settings = ApiAuthSettings {
+            anonymous_roles: Some(vec![ApiRole::ReadOnly]),
+            tokens: Some(vec![ApiTokenSettings {
+                name: "payer".to_string(),
+                token: ApiSecret::new("payer-token".to_string()),
+                roles: vec![ApiRole::Payer],
+            }]),
+            hmac_keys: Some(vec![ApiHmacKeySettings {
+                key_id: "ops".to_string(),
+                secret: ApiSecret::new("ops-secret".to_string()),
+                roles: vec![ApiRole::Operator],
+            }]),
+            hmac_max_skew_secs: None,
+            client_cert_header: Some("X-Client-Cert-Subject".to_string()),
+            trusted_proxies: Some(vec!["10.0.0.1".parse().unwrap()]),
+            client_certs: Some(vec![ApiClientCertSettings {
+                subject: "CN=billing".to_string(),
+                roles: vec![ApiRole::Payer],
+            }]),
+        };
+        let auth = Arc::new(ApiAuth::new(Some(&settings), None));
+        let app = test::init_service(
+            App::new()
+                .route(
+                    "/read",
+                    web::get()
+                        .to(HttpResponse::Ok)
+                        .wrap(auth.require(ApiRole::ReadOnly)),
+                )
+                .route(
+                    "/pay",
+                    web::post()
+                        .to(HttpResponse::Ok)
+                        .wrap(auth.require(ApiRole::Payer)),
+                )
+                .route(
+                    "/skip",
+                    web::post()
+                        .to(|body: web::Bytes| async move {
+                            //signed body has to reach the handler
+                            if body.as_ref() == b"{}" {
+                                HttpResponse::Ok().finish()
+                            } else {
+                                HttpResponse::BadRequest().finish()
+                            }
+                        })
+                        .wrap(auth.require(ApiRole::Operator)),
+                ),
+        )
+        .await;
+
+        let status = |req: test::TestRequest| {
+            let app = &app;
+            async move {
+                match test::try_call_service(app, req.to_request()).await {
+                    Ok(res) => res.status(),
+                    Err(err) => err.as_response_error().status_code(),
+                }
+            }
+        };
+        assert_eq!(
+            status(test::TestRequest::get().uri("/read")).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            status(test::TestRequest::post().uri("/pay")).await,
+            StatusCode::UNAUTHORIZED
+        );
+        let payer = || {
+            test::TestRequest::post().insert_header((header::AUTHORIZATION, "Bearer payer-token"))
+        };
+        assert_eq!(status(payer().uri("/pay")).await, StatusCode::OK);
+        assert_eq!(status(payer().uri("/skip")).await, StatusCode::FORBIDDEN);
+
+        let now = chrono::Utc::now().timestamp();
+        let signed = |secret: &str| {
+            test::TestRequest::post()
+                .uri("/skip")
+                .insert_header((HMAC_KEY_ID_HEADER, "ops"))
+                .insert_header((HMAC_TIMESTAMP_HEADER, now.to_string()))
+                .insert_header((
+                    HMAC_SIGNATURE_HEADER,
+                    sign_request(secret, now, "POST", "/skip", b"{}"),
+                ))
+                .set_payload("{}")
+        };
+        assert_eq!(status(signed("ops-secret")).await, StatusCode::OK);
+        assert_eq!(status(signed("wrong")).await, StatusCode::UNAUTHORIZED);
+        // captured request can't be sent again
+        assert_eq!(status(signed("ops-secret")).await, StatusCode::UNAUTHORIZED);
+        let old = now - 301;
+        let expired = test::TestRequest::post()
+            .uri("/skip")
+            .insert_header((HMAC_KEY_ID_HEADER, "ops"))
+            .insert_header((HMAC_TIMESTAMP_HEADER, old.to_string()))
+            .insert_header((
+                HMAC_SIGNATURE_HEADER,
+                sign_request("ops-secret", old, "POST", "/skip", b"{}"),
+            ))
+            .set_payload("{}");
+        assert_eq!(status(expired).await, StatusCode::UNAUTHORIZED);
+
+        let with_cert = |peer: &str| {
+            test::TestRequest::post()
+                .uri("/pay")
+                .peer_addr(peer.parse().unwrap())
+                .insert_header(("X-Client-Cert-Subject", "CN=billing"))
+        };
+        assert_eq!(status(with_cert("10.0.0.1:443")).await, StatusCode::OK);
+        assert_eq!(
+            status(with_cert("10.0.0.2:443")).await,
+            StatusCode::UNAUTHORIZED
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_anonymous_read_only_by_default() {
+        let anonymous = ApiAuth::new(None, None).authenticate_bearer(None).unwrap();
+        assert!(anonymous.has_role(ApiRole::ReadOnly));
+        assert!(!anonymous.has_role(ApiRole::Payer));
+        assert!(!anonymous.has_role(ApiRole::Faucet));
+    }
+}
@@ -0,0 +1,595 @@
+//! gRPC interface of the payment runtime, see `proto/payment.proto`.
+//! Exposes the same operations as the http api with the same roles, credentials are
+//! passed as bearer token in `authorization` metadata.
+// tonic::Status is the error type required by generated service traits and streams
+#![allow(clippy::result_large_err)]
+
+use super::api::TransactionRequest;
+use super::auth::{ApiPrincipal, ApiRole};
+use super::web::{transfer_args_from_request, ServerData};
+use crate::err_custom_create;
+use crate::eth::{get_balance, DepositDetails, GetBalanceArgs};
+use crate::event_log::{filtered_event_stream, parse_event_types, EventLogEntry};
+use crate::runtime::ValidateDepositResult;
+use actix_web::web::Data;
+use erc20_payment_lib_common::error::{ErrorBag, PaymentError};
+use erc20_payment_lib_common::model::{DepositId, TokenTransferDbObj, TxDbObj};
+use erc20_payment_lib_common::store::{PaymentStore, TransferInsertResult};
+use futures::{Stream, StreamExt};
+use proto::payment_service_server::{PaymentService, PaymentServiceServer};
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+use tonic::transport::server::TcpIncoming;
+use tonic::{Request, Response, Status};
+use web3::types::Address;
+
+pub mod proto {
+    tonic::include_proto!("erc20_payment");
+}
+
+/// Keeps idle event streams open behind proxies, same purpose as sse heartbeat
+const HTTP2_KEEPALIVE_INTERVAL: Duration = Duration::from_secs(30);
+
+impl From<TokenTransferDbObj> for proto::TokenTransfer {
+    fn from(t: TokenTransferDbObj) -> Self {
+        Self {
+            id: t.id,
+            payment_id: t.payment_id,
+            from_addr: t.from_addr,
+            receiver_addr: t.receiver_addr,
+            chain_id: t.chain_id,
+            token_addr: t.token_addr,
+            token_amount: t.token_amount,
+            deposit_id: t.deposit_id,
+            create_date: t.create_date.to_rfc3339(),
+            tx_id: t.tx_id,
+            paid_date: t.paid_date.map(|d| d.to_rfc3339()),
+            fee_paid: t.fee_paid,
+            error: t.error,
+            priority: t.priority,
+            approved_date: t.approved_date.map(|d| d.to_rfc3339()),
+            deadline: t.deadline.map(|d| d.to_rfc3339()),
+        }
+    }
+}
+
+impl From<TxDbObj> for proto::Transaction {
+    fn from(tx: TxDbObj) -> Self {
+        Self {
+            id: tx.id,
+            method: tx.method,
+            from_addr: tx.from_addr,
+            to_addr: tx.to_addr,
+            chain_id: tx.chain_id,
+            gas_limit: tx.gas_limit,
+            val: tx.val,
+            nonce: tx.nonce,
+            processing: tx.processing != 0,
+            created_date: tx.created_date.to_rfc3339(),
+            tx_hash: tx.tx_hash,
+            broadcast_date: tx.broadcast_date.map(|d| d.to_rfc3339()),
+            confirm_date: tx.confirm_date.map(|d| d.to_rfc3339()),
+            block_number: tx.block_number,
+            chain_status: tx.chain_status,
+            fee_paid: tx.fee_paid,
+            error: tx.error,
+            orig_tx_id: tx.orig_tx_id,
+        }
+    }
+}
+
+impl From<DepositDetails> for proto::DepositDetailsResponse {
+    fn from(d: DepositDetails) -> Self {
+        Self {
+            deposit_id: d.deposit_id,
+            deposit_nonce: d.deposit_nonce,
+            funder: format!("{:#x}", d.funder),
+            spender: format!("{:#x}", d.spender),
+            amount: d.amount,
+            valid_to: d.valid_to.to_rfc3339(),
+            current_block: d.current_block,
+            current_block_date: d.current_block_datetime.map(|d| d.to_rfc3339()),
+        }
+    }
+}
+
+impl From<EventLogEntry> for proto::DriverEvent {
+    fn from(entry: EventLogEntry) -> Self {
+        Self {
+            id: entry.id,
+            event_type: entry.event_type,
+            json: entry.json,
+        }
+    }
+}
+
+impl TryFrom<proto::TransferRequest> for TransactionRequest {
+    type Error = Status;
+
+    fn try_from(request: proto::TransferRequest) -> Result<Self, Self::Error> {
+        Ok(TransactionRequest {
+            from: request.from,
+            to: request.to,
+            token: request.token,
+            amount: request.amount,
+            chain: request.chain,
+            due_date: request.due_date,
+            payment_id: request.payment_id,
+            deposit_id: request
+                .deposit_id
+                .as_deref()
+                .map(DepositId::from_db_string)
+                .transpose()
+                .map_err(|err| Status::invalid_argument(err.to_string()))?,
+            priority: request
+                .priority
+                .as_deref()
+                .map(FromStr::from_str)
+                .transpose()
+                .map_err(|err: PaymentError| Status::invalid_argument(err.to_string()))?,
+        })
+    }
+}
+
+fn payment_error_status(err: PaymentError) -> Status {
+    match *err.inner {
+        ErrorBag::SQLxError(sqlx::Error::RowNotFound) => Status::not_found("Not found"),
+        ErrorBag::PaymentIdConflict(_) => Status::already_exists(err.to_string()),
+        ErrorBag::TransferNotPending(_) => Status::failed_precondition(err.to_string()),
+        ErrorBag::CustomError(_) => Status::invalid_argument(err.to_string()),
+        _ => Status::internal(err.to_string()),
+    }
+}
+
+fn parse_address(name: &str, addr: &str) -> Result<Address, Status> {
+    Address::from_str(addr).map_err(|err| {
+        Status::invalid_argument(format!("Invalid {} address {}: {}", name, addr, err))
+    })
+}
+
+fn parse_deposit_id(deposit: &proto::DepositRequest) -> Result<DepositId, Status> {
+    DepositId::from_db_string(&format!("{}-{}", deposit.deposit_id, deposit.lock_address))
+        .map_err(|err| Status::invalid_argument(err.to_string()))
+}
+
+pub struct GrpcPaymentService<S: PaymentStore> {
+    data: Data<Box<ServerData<S>>>,
+    enable_transfers: bool,
+}
+
+impl<S: PaymentStore> GrpcPaymentService<S> {
+    pub fn new(data: Data<Box<ServerData<S>>>, enable_transfers: bool) -> Self {
+        Self {
+            data,
+            enable_transfers,
+        }
+    }
+
+    /// Same check as `RequireRole` middleware of http api
+    fn require<T>(&self, request: &Request<T>, role: ApiRole) -> Result<ApiPrincipal, Status> {
+        let authorization = request
+            .metadata()
+            .get("authorization")
+            .map(|value| {
+                value
+                    .to_str()
+                    .map_err(|_| Status::unauthenticated("Invalid authorization metadata"))
+            })
+            .transpose()?;
+        let principal = self
+            .data
+            .auth
+            .authenticate_bearer(authorization)
+            .map_err(Status::unauthenticated)?;
+        if !principal.has_role(role) {
+            return Err(if principal.is_anonymous() {
+                Status::unauthenticated("Authentication required")
+            } else {
+                Status::permission_denied(format!("Role {:?} required", role))
+            });
+        }
+        Ok(principal)
+    }
+
+    fn require_transfers_enabled(&self) -> Result<(), Status> {
+        if self.enable_transfers {
+            Ok(())
+        } else {
+            Err(Status::unimplemented("Transfers are not enabled"))
+        }
+    }
+
+    fn network_name(&self, chain_id: i64) -> Result<String, Status> {
+        self.data
+            .payment_runtime
+            .network_name(chain_id)
+            .map(str::to_string)
+            .ok_or_else(|| {
+                Status::invalid_argument(format!("No config found for chain {}", chain_id))
+            })
+    }
+}
+
+type EventStreamResponse = Pin<Box<dyn Stream<Item = Result<proto::DriverEvent, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl<S: PaymentStore + Send + Sync> PaymentService for GrpcPaymentService<S> {
+    async fn version(
+        &self,
+        _request: Request<proto::VersionRequest>,
+    ) -> Result<Response<proto::VersionResponse>, Status> {
+        Ok(Response::new(proto::VersionResponse {
+            name: "erc20_payment_lib".to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+        }))
+    }
+
+    async fn new_transfer(
+        &self,
+        request: Request<proto::TransferRequest>,
+    ) -> Result<Response<proto::NewTransferResponse>, Status> {
+        self.require(&request, ApiRole::Payer)?;
+        self.require_transfers_enabled()?;
+        let request = TransactionRequest::try_from(request.into_inner())?;
+        let (account, transfer_args) = transfer_args_from_request(&self.data, &request, true)
+            .map_err(Status::invalid_argument)?;
+        let res = self
+            .data
+            .payment_runtime
+            .transfer_with_account(&account, transfer_args.clone())
+            .await
+            .map_err(payment_error_status)?;
+        if let TransferInsertResult::Created(_) = res {
+            log::warn!("Created transfer: {:?}", transfer_args);
+        }
+        Ok(Response::new(proto::NewTransferResponse {
+            created: matches!(res, TransferInsertResult::Created(_)),
+            transfer: Some(res.transfer().clone().into()),
+        }))
+    }
+
+    async fn cancel_transfer(
+        &self,
+        request: Request<proto::TransferIdRequest>,
+    ) -> Result<Response<proto::TransferResponse>, Status> {
+        self.require(&request, ApiRole::Payer)?;
+        self.require_transfers_enabled()?;
+        let transfer = self
+            .data
+            .payment_runtime
+            .cancel_transfer(request.into_inner().id)
+            .await
+            .map_err(payment_error_status)?;
+        log::warn!("Cancelled transfer: {:?}", transfer);
+        Ok(Response::new(proto::TransferResponse {
+            transfer: Some(transfer.into()),
+        }))
+    }
+
+    async fn get_transfer(
+        &self,
+        request: Request<proto::TransferIdRequest>,
+    ) -> Result<Response<proto::TransferResponse>, Status> {
+        self.require(&request, ApiRole::ReadOnly)?;
+        let id = request.into_inner().id;
+        let transfer = self
+            .data
+            .db_connection
+            .lock()
+            .await
+            .get_token_transfer(id)
+            .await
+            .map_err(|err| Status::internal(err.to_string()))?
+            .ok_or_else(|| Status::not_found(format!("Transfer {} not found", id)))?;
+        Ok(Response::new(proto::TransferResponse {
+            transfer: Some(transfer.into()),
+        }))
+    }
+
+    async fn get_transfers_by_tx(
+        &self,
+        request: Request<proto::TxIdRequest>,
+    ) -> Result<Response<proto::TransfersResponse>, Status> {
+        self.require(&request, ApiRole::ReadOnly)?;
+        let transfers = self
+            .data
+            .db_connection
+            .lock()
+            .await
+            .get_token_transfers_by_tx(request.into_inner().tx_id)
+            .await
+            .map_err(|err| Status::internal(err.to_string()))?;
+        Ok(Response::new(proto::TransfersResponse {
+            transfers: transfers.into_iter().map(Into::into).collect(),
+        }))
+    }
+
+    async fn get_transaction(
+        &self,
+        request: Request<proto::TxIdRequest>,
+    ) -> Result<Response<proto::TxResponse>, Status> {
+        self.require(&request, ApiRole::ReadOnly)?;
+        let tx_id = request.into_inner().tx_id;
+        let tx = self
+            .data
+            .db_connection
+            .lock()
+            .await
+            .get_transaction(tx_id)
+            .await
+            .map_err(|err| match err {
+                sqlx::Error::RowNotFound => {
+                    Status::not_found(format!("Transaction {} not found", tx_id))
+                }
+                err => Status::internal(err.to_string()),
+            })?;
+        Ok(Response::new(proto::TxResponse {
+            tx: Some(tx.into()),
+        }))
+    }
+
+    async fn get_balance(
+        &self,
+        request: Request<proto::BalanceRequest>,
+    ) -> Result<Response<proto::BalanceResponse>, Status> {
+        self.require(&request, ApiRole::ReadOnly)?;
+        let request = request.into_inner();
+        let account = parse_address("account", &request.account)?;
+        let chain = self
+            .data
+            .payment_setup
+            .chain_setup
+            .get(&request.chain_id)
+            .ok_or_else(|| {
+                Status::invalid_argument(format!("No config found for chain {}", request.chain_id))
+            })?;
+        let balance = get_balance(
+            chain.provider.clone(),
+            GetBalanceArgs {
+                address: account,
+                token_address: Some(chain.glm_address),
+                call_with_details: chain.wrapper_contract_address,
+                block_number: None,
+                chain_id: Some(chain.chain_id as u64),
+            },
+        )
+        .await
+        .map_err(|err| Status::unavailable(format!("Failed to get balance {}", err)))?;
+        Ok(Response::new(proto::BalanceResponse {
+            chain_id: request.chain_id,
+            account: format!("{:#x}", account),
+            gas_balance: balance.gas_balance.unwrap_or_default().to_string(),
+            token_balance: balance.token_balance.unwrap_or_default().to_string(),
+            block_number: balance.block_number,
+            block_date: balance.block_datetime.to_rfc3339(),
+        }))
+    }
+
+    async fn deposit_details(
+        &self,
+        request: Request<proto::DepositRequest>,
+    ) -> Result<Response<proto::DepositDetailsResponse>, Status> {
+        self.require(&request, ApiRole::ReadOnly)?;
+        let request = request.into_inner();
+        let deposit_id = parse_deposit_id(&request)?;
+        let details = self
+            .data
+            .payment_runtime
+            .deposit_details(self.network_name(request.chain_id)?, deposit_id)
+            .await
+            .map_err(payment_error_status)?;
+        Ok(Response::new(details.into()))
+    }
+
+    async fn validate_deposit(
+        &self,
+        request: Request<proto::ValidateDepositRequest>,
+    ) -> Result<Response<proto::ValidateDepositResponse>, Status> {
+        self.require(&request, ApiRole::ReadOnly)?;
+        let request = request.into_inner();
+        let deposit = request
+            .deposit
+            .ok_or_else(|| Status::invalid_argument("Missing deposit"))?;
+        let deposit_id = parse_deposit_id(&deposit)?;
+        let result = self
+            .data
+            .payment_runtime
+            .validate_deposit(
+                self.network_name(deposit.chain_id)?,
+                deposit_id,
+                request.args.into_iter().collect::<BTreeMap<_, _>>(),
+            )
+            .await
+            .map_err(payment_error_status)?;
+        Ok(Response::new(match result {
+            ValidateDepositResult::Valid => proto::ValidateDepositResponse {
+                valid: true,
+                reason: None,
+            },
+            ValidateDepositResult::Invalid(reason) => proto::ValidateDepositResponse {
+                valid: false,
+                reason: Some(reason),
+            },
+        }))
+    }
+
+    async fn close_deposit(
+        &self,
+        request: Request<proto::CloseDepositRequest>,
+    ) -> Result<Response<proto::CloseDepositResponse>, Status> {
+        let principal = self.require(&request, ApiRole::Operator)?;
+        let request = request.into_inner();
+        let deposit = request
+            .deposit
+            .ok_or_else(|| Status::invalid_argument("Missing deposit"))?;
+        let deposit_id = parse_deposit_id(&deposit)?;
+        let from = parse_address("from", &request.from)?;
+        let network = self.network_name(deposit.chain_id)?;
+        self.data
+            .payment_runtime
+            .close_deposit(&network, from, deposit_id)
+            .await
+            .map_err(payment_error_status)?;
+        log::warn!(
+            "Closing deposit {} on {} requested by {}",
+            deposit_id.to_db_string(),
+            network,
+            principal.name
+        );
+        Ok(Response::new(proto::CloseDepositResponse {}))
+    }
+
+    type EventStreamStream = EventStreamResponse;
+
+    async fn event_stream(
+        &self,
+        request: Request<proto::EventStreamRequest>,
+    ) -> Result<Response<Self::EventStreamStream>, Status> {
+        self.require(&request, ApiRole::ReadOnly)?;
+        let request = request.into_inner();
+        let Some(driver_broadcast_sender) = &self.data.payment_runtime.driver_broadcast_sender
+        else {
+            return Err(Status::unavailable("Driver event sender not available"));
+        };
+        let event_types = if request.events.is_empty() {
+            None
+        } else {
+            Some(parse_event_types(&request.events.join(",")).map_err(Status::invalid_argument)?)
+        };
+        let rx = driver_broadcast_sender.subscribe();
+        let conn = self.data.db_connection.lock().await.clone();
+        let events = filtered_event_stream(conn, rx, request.since, event_types)
+            .map(|entry| Ok(entry.into()));
+        Ok(Response::new(Box::pin(events)))
+    }
+}
+
+/// Serve gRPC interface on bound listener until the server fails, transfer operations
+/// are rejected when `enable_transfers` is false, same as in the http api
+pub async fn run_grpc_server<S: PaymentStore + Send + Sync>(
+    data: Data<Box<ServerData<S>>>,
+    listener: TcpListener,
+    enable_transfers: bool,
+) -> Result<(), PaymentError> {
+    let incoming = TcpIncoming::from_listener(listener, true, None)
+        .map_err(|err| err_custom_create!("Failed to accept gRPC connections: {}", err))?;
+    tonic::transport::Server::builder()
+        .http2_keepalive_interval(Some(HTTP2_KEEPALIVE_INTERVAL))
+        .add_service(PaymentServiceServer::new(GrpcPaymentService::new(
+            data,
+            enable_transfers,
+        )))
+        .serve_with_incoming(incoming)
+        .await
+        .map_err(|err| err_custom_create!("gRPC server failed: {}", err))
+}
+
+/// Bind `addr` and serve gRPC interface in background. Address is bound before returning,
+/// so startup fails when it is not available. Returns bound address and the server task.
+pub async fn start_grpc_server<S: PaymentStore + Send + Sync>(
+    data: Data<Box<ServerData<S>>>,
+    addr: SocketAddr,
+    enable_transfers: bool,
+) -> Result<(SocketAddr, JoinHandle<()>), PaymentError> {
+    let listener = TcpListener::bind(addr)
+        .await
+        .map_err(|err| err_custom_create!("Cannot bind gRPC server to {}: {}", addr, err))?;
+    let local_addr = listener
+        .local_addr()
+        .map_err(|err| err_custom_create!("Cannot bind gRPC server to {}: {}", addr, err))?;
+    log::info!("Starting gRPC server on {}", local_addr);
+    let handle = tokio::spawn(async move {
+        if let Err(err) = run_grpc_server(data, listener, enable_transfers).await {
+            log::error!("{}", err);
+        }
+    });
+    Ok((local_addr, handle))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::web::tests::{test_server_data, ADMIN_TOKEN, TEST_CHAIN_ID};
+    use proto::payment_service_client::PaymentServiceClient;
+    use tonic::Code;
+
+    fn with_token<T>(message: T, token: &str) -> Request<T> {
+        let mut request = Request::new(message);
+        request.metadata_mut().insert(
+            "authorization",
+            format!("Bearer {}", token).parse().unwrap(),
+        );
+        request
+    }
+
+    #[tokio::test]
+    async fn test_grpc_auth_and_new_transfer() {
+        let (data, from) = test_server_data().await;
+        let (addr, _server) = start_grpc_server(data.clone(), "127.0.0.1:0".parse().unwrap(), true)
+            .await
+            .unwrap();
+        // address in use fails startup instead of the background task
+        assert!(start_grpc_server(data, addr, true).await.is_err());
+
+        let mut client = PaymentServiceClient::connect(format!("http://{}", addr))
+            .await
+            .unwrap();
+        let transfer = proto::TransferRequest {
+            from: format!("{:#x}", from),
+            to: format!("{:#x}", Address::from_low_u64_be(0x1234)),
+            amount: "1000".to_string(),
+            chain: TEST_CHAIN_ID,
+            payment_id: Some("grpc-1".to_string()),
+            ..Default::default()
+        };
+
+        // version is public, anonymous requests are read-only
+        client
+            .version(proto::VersionRequest::default())
+            .await
+            .unwrap();
+        let status = client.new_transfer(transfer.clone()).await.unwrap_err();
+        assert_eq!(status.code(), Code::Unauthenticated);
+        let status = client
+            .new_transfer(with_token(transfer.clone(), "wrong-token"))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::Unauthenticated);
+
+        let res = client
+            .new_transfer(with_token(transfer.clone(), ADMIN_TOKEN))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(res.created);
+        let created = res.transfer.unwrap();
+        assert_eq!(created.payment_id.as_deref(), Some("grpc-1"));
+        assert_eq!(created.token_amount, "1000");
+
+        // the same request returns existing transfer
+        let res = client
+            .new_transfer(with_token(transfer.clone(), ADMIN_TOKEN))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(!res.created);
+        assert_eq!(res.transfer.unwrap().id, created.id);
+
+        let status = client
+            .new_transfer(with_token(
+                proto::TransferRequest {
+                    payment_id: None,
+                    ..transfer
+                },
+                ADMIN_TOKEN,
+            ))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+    }
+}
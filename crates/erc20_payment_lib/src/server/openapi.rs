@@ -0,0 +1,1077 @@
+//! Hand-maintained OpenAPI 3 description of the http api, served at `/api/openapi.json`.
+//! Schemas follow the types in [`crate::server::api`], keep them in sync when changing routes.
+
+use crate::server::auth::{ApiRole, HMAC_KEY_ID_HEADER};
+use serde_json::{json, Map, Value};
+
+pub struct ApiOperation {
+    pub method: &'static str,
+    pub path: &'static str,
+    pub role: ApiRole,
+    pub summary: &'static str,
+    /// Path parameters, all of them are strings in the url
+    pub params: &'static [&'static str],
+    pub query: &'static [&'static str],
+    pub request: Option<&'static str>,
+    /// Schema name of the response, `None` for metrics, websocket and this document
+    pub response: Option<&'static str>,
+}
+
+const fn op(
+    method: &'static str,
+    path: &'static str,
+    role: ApiRole,
+    summary: &'static str,
+    response: Option<&'static str>,
+) -> ApiOperation {
+    ApiOperation {
+        method,
+        path,
+        role,
+        summary,
+        params: &[],
+        query: &[],
+        request: None,
+        response,
+    }
+}
+
+const FEED_QUERY: &[&str] = &["chain", "account"];
+
+/// Routes registered in [`crate::server::web::runtime_web_scope`], relative to `/api`
+pub const API_OPERATIONS: &[ApiOperation] = &[
+    op(
+        "get",
+        "/",
+        ApiRole::ReadOnly,
+        "Server name and version",
+        Some("VersionResponse"),
+    ),
+    op(
+        "get",
+        "/version",
+        ApiRole::ReadOnly,
+        "Server name and version",
+        Some("VersionResponse"),
+    ),
+    op(
+        "get",
+        "/openapi.json",
+        ApiRole::ReadOnly,
+        "This document",
+        None,
+    ),
+    ApiOperation {
+        params: &["chain", "uid"],
+        ..op(
+            "get",
+            "/attestation/{chain}/{uid}",
+            ApiRole::ReadOnly,
+            "Decode attestation",
+            Some("AttestationCheckResult"),
+        )
+    },
+    op(
+        "get",
+        "/allowances",
+        ApiRole::ReadOnly,
+        "All allowances",
+        Some("AllowancesResponse"),
+    ),
+    ApiOperation {
+        params: &["account", "chain"],
+        ..op(
+            "get",
+            "/balance/{account}/{chain}",
+            ApiRole::ReadOnly,
+            "Gas and token balance",
+            Some("AccountBalanceResponse"),
+        )
+    },
+    op(
+        "get",
+        "/rpc_pool",
+        ApiRole::ReadOnly,
+        "Rpc endpoints with statistics",
+        Some("RpcPoolResponse"),
+    ),
+    op(
+        "get",
+        "/rpc_pool/metrics",
+        ApiRole::ReadOnly,
+        "Rpc pool metrics in prometheus format",
+        None,
+    ),
+    ApiOperation {
+        params: &["chain"],
+        request: Some("Web3RpcEndpointChange"),
+        ..op(
+            "post",
+            "/rpc_pool/{chain}/endpoints",
+            ApiRole::Operator,
+            "Add, remove or change rpc endpoints",
+            Some("RpcPoolEndpointsResponse"),
+        )
+    },
+    op(
+        "get",
+        "/config",
+        ApiRole::ReadOnly,
+        "Payment setup",
+        Some("ConfigResponse"),
+    ),
+    ApiOperation {
+        query: &["receiver", "from", "to", "chain"],
+        ..op(
+            "get",
+            "/stats/transfers",
+            ApiRole::ReadOnly,
+            "Transfers found on chain",
+            Some("StatsTransferResult"),
+        )
+    },
+    ApiOperation {
+        query: &[
+            "status", "chain", "account", "from", "to", "cursor", "limit",
+        ],
+        ..op(
+            "get",
+            "/transactions",
+            ApiRole::ReadOnly,
+            "Page of transactions, newest first",
+            Some("TxsResponse"),
+        )
+    },
+    op(
+        "get",
+        "/transactions/count",
+        ApiRole::ReadOnly,
+        "Transaction and transfer counts",
+        Some("TransactionsCountResponse"),
+    ),
+    ApiOperation {
+        query: FEED_QUERY,
+        ..op(
+            "get",
+            "/transactions/next",
+            ApiRole::ReadOnly,
+            "Queued transactions and pending batches",
+            Some("NextTxsResponse"),
+        )
+    },
+    ApiOperation {
+        params: &["count"],
+        query: FEED_QUERY,
+        ..op(
+            "get",
+            "/transactions/next/{count}",
+            ApiRole::ReadOnly,
+            "Queued transactions and pending batches",
+            Some("NextTxsResponse"),
+        )
+    },
+    ApiOperation {
+        params: &["prev", "next"],
+        query: FEED_QUERY,
+        ..op(
+            "get",
+            "/transactions/feed/{prev}/{next}",
+            ApiRole::ReadOnly,
+            "Done, processing and queued transactions",
+            Some("TxsFeedResponse"),
+        )
+    },
+    ApiOperation {
+        query: FEED_QUERY,
+        ..op(
+            "get",
+            "/transactions/current",
+            ApiRole::ReadOnly,
+            "Transactions in processing",
+            Some("TxsResponse"),
+        )
+    },
+    ApiOperation {
+        query: FEED_QUERY,
+        ..op(
+            "get",
+            "/transactions/last",
+            ApiRole::ReadOnly,
+            "Last processed transactions",
+            Some("TxsResponse"),
+        )
+    },
+    ApiOperation {
+        params: &["count"],
+        query: FEED_QUERY,
+        ..op(
+            "get",
+            "/transactions/last/{count}",
+            ApiRole::ReadOnly,
+            "Last processed transactions",
+            Some("TxsResponse"),
+        )
+    },
+    ApiOperation {
+        params: &["tx_id"],
+        ..op(
+            "post",
+            "/tx/skip/{tx_id}",
+            ApiRole::Operator,
+            "Skip pending transaction",
+            Some("SkipTxResponse"),
+        )
+    },
+    ApiOperation {
+        params: &["tx_id"],
+        ..op(
+            "get",
+            "/tx/{tx_id}",
+            ApiRole::ReadOnly,
+            "Transaction details",
+            Some("TxResponse"),
+        )
+    },
+    ApiOperation {
+        query: &[
+            "status",
+            "chain",
+            "sender",
+            "receiver",
+            "paymentId",
+            "depositId",
+            "from",
+            "to",
+            "cursor",
+            "limit",
+        ],
+        ..op(
+            "get",
+            "/transfers",
+            ApiRole::ReadOnly,
+            "Page of transfers, newest first",
+            Some("TransfersResponse"),
+        )
+    },
+    ApiOperation {
+        params: &["tx_id"],
+        ..op(
+            "get",
+            "/transfers/{tx_id}",
+            ApiRole::ReadOnly,
+            "Transfers of transaction",
+            Some("TransfersResponse"),
+        )
+    },
+    ApiOperation {
+        request: Some("BulkTransferRequest"),
+        ..op(
+            "post",
+            "/estimate",
+            ApiRole::ReadOnly,
+            "Quote fees of sending transfers at the current base fee, nothing is stored",
+            Some("EstimateResponse"),
+        )
+    },
+    ApiOperation {
+        request: Some("TransactionRequest"),
+        ..op(
+            "post",
+            "/transfers/new",
+            ApiRole::Payer,
+            "Create transfer, when transfers are enabled. Legacy route responding with \"success\", \
+             retry without paymentId creates another transfer",
+            None,
+        )
+    },
+    ApiOperation {
+        request: Some("TransactionRequest"),
+        ..op(
+            "post",
+            "/transfers/create",
+            ApiRole::Payer,
+            "Create transfer idempotently, paymentId is required and repeated request returns \
+             the existing transfer with created: false",
+            Some("NewTransferResponse"),
+        )
+    },
+    ApiOperation {
+        request: Some("BulkTransferRequest"),
+        ..op(
+            "post",
+            "/transfers/bulk",
+            ApiRole::Payer,
+            "Create all transfers or none of them, also accepts text/csv with the same columns",
+            Some("BulkTransferResponse"),
+        )
+    },
+    ApiOperation {
+        params: &["id"],
+        ..op(
+            "get",
+            "/transfers/id/{id}",
+            ApiRole::ReadOnly,
+            "Transfer by id",
+            Some("TransferResponse"),
+        )
+    },
+    ApiOperation {
+        params: &["id"],
+        ..op(
+            "delete",
+            "/transfers/id/{id}",
+            ApiRole::Payer,
+            "Cancel pending transfer, when transfers are enabled",
+            Some("TransferResponse"),
+        )
+    },
+    ApiOperation {
+        params: &["id"],
+        request: Some("TransferChangeRequest"),
+        ..op(
+            "patch",
+            "/transfers/id/{id}",
+            ApiRole::Payer,
+            "Change pending transfer, when transfers are enabled. \
+             dueDate is stored with the transfer and it is gathered not later than that",
+            Some("TransferResponse"),
+        )
+    },
+    ApiOperation {
+        params: &["id"],
+        ..op(
+            "post",
+            "/transfers/id/{id}/approve",
+            ApiRole::Operator,
+            "Approve transfer held by spending policy",
+            Some("TransferResponse"),
+        )
+    },
+    op(
+        "get",
+        "/accounts",
+        ApiRole::ReadOnly,
+        "Sender accounts",
+        Some("AccountsResponse"),
+    ),
+    ApiOperation {
+        params: &["account"],
+        ..op(
+            "get",
+            "/account/{account}",
+            ApiRole::ReadOnly,
+            "Account details",
+            Some("AccountDetailsResponse"),
+        )
+    },
+    ApiOperation {
+        params: &["account"],
+        query: &[
+            "chain",
+            "sender",
+            "paymentId",
+            "from",
+            "to",
+            "cursor",
+            "limit",
+        ],
+        ..op(
+            "get",
+            "/account/{account}/in",
+            ApiRole::ReadOnly,
+            "Page of incoming transfers, newest first",
+            Some("AccountTransfersInResponse"),
+        )
+    },
+    ApiOperation {
+        query: &["status", "webhook", "event", "cursor", "limit"],
+        ..op(
+            "get",
+            "/webhooks/deliveries",
+            ApiRole::ReadOnly,
+            "Page of webhook deliveries, newest first",
+            Some("WebhookDeliveriesResponse"),
+        )
+    },
+    ApiOperation {
+        params: &["id"],
+        ..op(
+            "post",
+            "/webhooks/deliveries/{id}/retry",
+            ApiRole::Operator,
+            "Retry failed webhook delivery",
+            Some("WebhookDeliveryResponse"),
+        )
+    },
+    op(
+        "get",
+        "/metrics",
+        ApiRole::ReadOnly,
+        "Metrics in prometheus format",
+        None,
+    ),
+    ApiOperation {
+        query: &["since", "events"],
+        ..op(
+            "get",
+            "/event_stream",
+            ApiRole::ReadOnly,
+            "Websocket with driver events, resumed after event id given in since",
+            None,
+        )
+    },
+    ApiOperation {
+        query: &["since", "events"],
+        ..op(
+            "get",
+            "/event_stream/sse",
+            ApiRole::ReadOnly,
+            "Server-Sent Events with driver events, resumed after since or Last-Event-ID",
+            None,
+        )
+    },
+    op(
+        "get",
+        "/faucet",
+        ApiRole::Faucet,
+        "Faucet status, when faucet is enabled",
+        Some("FaucetStatusResponse"),
+    ),
+    ApiOperation {
+        query: &["chain", "address", "cursor", "limit"],
+        ..op(
+            "get",
+            "/faucet/history",
+            ApiRole::Faucet,
+            "Page of served faucet requests, newest first, when faucet is enabled",
+            Some("FaucetHistoryResponse"),
+        )
+    },
+    ApiOperation {
+        params: &["chain", "addr"],
+        ..op(
+            "get",
+            "/faucet/{chain}/{addr}",
+            ApiRole::Faucet,
+            "Send test tokens, when faucet is enabled. Responds with 429 when limited",
+            Some("FaucetResponse"),
+        )
+    },
+    op(
+        "get",
+        "/debug",
+        ApiRole::Operator,
+        "Shared state of the runtime, when debug is enabled",
+        Some("DebugResponse"),
+    ),
+];
+
+fn string() -> Value {
+    json!({"type": "string"})
+}
+
+fn integer() -> Value {
+    json!({"type": "integer"})
+}
+
+fn boolean() -> Value {
+    json!({"type": "boolean"})
+}
+
+fn date() -> Value {
+    json!({"type": "string", "format": "date-time"})
+}
+
+fn nullable(schema: Value) -> Value {
+    let mut schema = schema;
+    schema["nullable"] = json!(true);
+    schema
+}
+
+fn array(items: Value) -> Value {
+    json!({"type": "array", "items": items})
+}
+
+fn reference(name: &str) -> Value {
+    json!({"$ref": format!("#/components/schemas/{}", name)})
+}
+
+/// Object schema, nullable properties are not required
+fn object(properties: &[(&str, Value)]) -> Value {
+    let required = properties
+        .iter()
+        .filter(|(_, schema)| schema.get("nullable").is_none())
+        .map(|(name, _)| json!(name))
+        .collect::<Vec<_>>();
+    let properties = properties
+        .iter()
+        .map(|(name, schema)| (name.to_string(), schema.clone()))
+        .collect::<Map<_, _>>();
+    json!({"type": "object", "required": required, "properties": properties})
+}
+
+fn schemas() -> Map<String, Value> {
+    let any_object = json!({"type": "object"});
+    let txs = || array(reference("Tx"));
+    let transfer_counts = [
+        ("transfersQueued", integer()),
+        ("transfersProcessing", integer()),
+        ("transfersDone", integer()),
+    ];
+    [
+        ("ApiError", object(&[("error", string())])),
+        (
+            "VersionResponse",
+            object(&[("name", string()), ("version", string())]),
+        ),
+        (
+            "Tx",
+            object(&[
+                ("id", integer()),
+                ("method", string()),
+                ("fromAddr", string()),
+                ("toAddr", string()),
+                ("chainId", integer()),
+                ("gasLimit", nullable(integer())),
+                ("maxFeePerGas", nullable(string())),
+                ("priorityFee", nullable(string())),
+                ("val", string()),
+                ("nonce", nullable(integer())),
+                ("processing", integer()),
+                ("createdDate", date()),
+                ("firstProcessed", nullable(date())),
+                ("txHash", nullable(string())),
+                ("signedDate", nullable(date())),
+                ("broadcastDate", nullable(date())),
+                ("broadcastCount", integer()),
+                ("firstStuckDate", nullable(date())),
+                ("confirmDate", nullable(date())),
+                ("blockchainDate", nullable(date())),
+                ("gasUsed", nullable(integer())),
+                ("blockNumber", nullable(integer())),
+                ("chainStatus", nullable(integer())),
+                ("blockGasPrice", nullable(string())),
+                ("effectiveGasPrice", nullable(string())),
+                ("feePaid", nullable(string())),
+                ("error", nullable(string())),
+                ("origTxId", nullable(integer())),
+                ("engineMessage", nullable(string())),
+                ("engineError", nullable(string())),
+            ]),
+        ),
+        (
+            "TokenTransfer",
+            object(&[
+                ("id", integer()),
+                ("paymentId", nullable(string())),
+                ("fromAddr", string()),
+                ("receiverAddr", string()),
+                ("chainId", integer()),
+                ("tokenAddr", nullable(string())),
+                ("tokenAmount", string()),
+                ("depositId", nullable(string())),
+                ("depositFinish", integer()),
+                ("createDate", date()),
+                ("txId", nullable(integer())),
+                ("paidDate", nullable(date())),
+                ("feePaid", nullable(string())),
+                ("error", nullable(string())),
+                ("priority", reference("TransferPriority")),
+                ("approvedDate", nullable(date())),
+                ("deadline", nullable(date())),
+            ]),
+        ),
+        (
+            "TransferPriority",
+            json!({"type": "string", "enum": ["urgent", "normal", "bulk"]}),
+        ),
+        (
+            "Allowance",
+            object(&[
+                ("id", integer()),
+                ("owner", string()),
+                ("tokenAddr", string()),
+                ("spender", string()),
+                ("allowance", string()),
+                ("chainId", integer()),
+                ("txId", nullable(integer())),
+                ("feePaid", nullable(string())),
+                ("confirmDate", nullable(date())),
+                ("error", nullable(string())),
+            ]),
+        ),
+        (
+            "TransferIn",
+            object(&[
+                ("id", integer()),
+                ("paymentId", string()),
+                ("fromAddr", string()),
+                ("receiverAddr", string()),
+                ("chainId", integer()),
+                ("tokenAddr", nullable(string())),
+                ("tokenAmount", string()),
+                ("txHash", nullable(string())),
+                ("requestedDate", date()),
+                ("receivedDate", nullable(date())),
+            ]),
+        ),
+        (
+            "WebhookDelivery",
+            object(&[
+                ("id", integer()),
+                ("webhook", string()),
+                ("eventType", string()),
+                ("payload", string()),
+                ("createdDate", date()),
+                ("attempts", integer()),
+                ("nextAttemptDate", nullable(date())),
+                ("lastAttemptDate", nullable(date())),
+                ("deliveredDate", nullable(date())),
+                ("responseStatus", nullable(integer())),
+                ("error", nullable(string())),
+            ]),
+        ),
+        (
+            "BatchEstimate",
+            object(&[
+                ("chainId", integer()),
+                ("fromAddr", string()),
+                ("tokenAddr", nullable(string())),
+                ("depositId", nullable(string())),
+                ("transferCount", integer()),
+                ("receiverCount", integer()),
+                ("totalAmount", string()),
+                ("estimatedFee", nullable(string())),
+                ("feePerTransfer", nullable(string())),
+                ("oldestTransferDate", date()),
+                ("send", boolean()),
+                ("reason", string()),
+            ]),
+        ),
+        (
+            "PendingPayout",
+            object(&[
+                ("chainId", integer()),
+                ("fromAddr", string()),
+                ("tokenAddr", nullable(string())),
+                ("transferCount", integer()),
+                ("amount", string()),
+                ("minAmount", nullable(string())),
+                ("oldestTransferDate", nullable(date())),
+                ("maxPayoutDate", nullable(date())),
+            ]),
+        ),
+        (
+            "SharedInfoTx",
+            object(&[
+                ("message", string()),
+                ("error", nullable(string())),
+                ("skip", boolean()),
+            ]),
+        ),
+        (
+            "ChainTransfer",
+            object(&[
+                ("id", integer()),
+                ("fromAddr", string()),
+                ("receiverAddr", string()),
+                ("chainId", integer()),
+                ("tokenAddr", nullable(string())),
+                ("tokenAmount", string()),
+                ("txHash", string()),
+                ("blockNumber", integer()),
+                ("feePaid", nullable(string())),
+                ("blockDate", date()),
+                ("blockTimestamp", integer()),
+                ("toAddr", string()),
+                ("callerAddr", string()),
+            ]),
+        ),
+        ("TxResponse", object(&[("tx", reference("Tx"))])),
+        (
+            "TxsResponse",
+            object(&[("txs", txs()), ("nextCursor", nullable(integer()))]),
+        ),
+        (
+            "NextTxsResponse",
+            object(&[
+                ("txs", txs()),
+                ("batches", array(reference("BatchEstimate"))),
+            ]),
+        ),
+        (
+            "TxsFeedResponse",
+            object(&[
+                ("txs", txs()),
+                (
+                    "current",
+                    json!({"type": "object", "additionalProperties": reference("SharedInfoTx")}),
+                ),
+            ]),
+        ),
+        ("SkipTxResponse", object(&[("success", string())])),
+        (
+            "RpcPoolResponse",
+            object(&[(
+                "networks",
+                array(object(&[
+                    ("chainId", integer()),
+                    ("chainNetwork", string()),
+                    ("endpoints", array(any_object.clone())),
+                ])),
+            )]),
+        ),
+        ("Web3RpcEndpointChange", any_object.clone()),
+        (
+            "RpcPoolEndpointsResponse",
+            object(&[
+                ("chainId", integer()),
+                (
+                    "endpoints",
+                    array(object(&[
+                        ("params", any_object.clone()),
+                        ("info", any_object.clone()),
+                    ])),
+                ),
+            ]),
+        ),
+        (
+            "AllowancesResponse",
+            object(&[("allowances", array(reference("Allowance")))]),
+        ),
+        (
+            "TransactionsCountResponse",
+            object(
+                &[
+                    &transfer_counts[..],
+                    &[("txQueued", integer()), ("txDone", integer())],
+                ]
+                .concat(),
+            ),
+        ),
+        ("ConfigResponse", object(&[("config", any_object.clone())])),
+        (
+            "DebugResponse",
+            object(&[("sharedState", any_object.clone())]),
+        ),
+        (
+            "TransactionRequest",
+            object(&[
+                ("from", string()),
+                ("to", string()),
+                ("token", nullable(string())),
+                ("amount", string()),
+                ("chain", integer()),
+                ("dueDate", nullable(date())),
+                ("paymentId", nullable(string())),
+                (
+                    "depositId",
+                    nullable(object(&[
+                        ("deposit_id", string()),
+                        ("lock_address", string()),
+                    ])),
+                ),
+                ("priority", nullable(reference("TransferPriority"))),
+            ]),
+        ),
+        (
+            "NewTransferResponse",
+            object(&[
+                ("created", boolean()),
+                ("transfer", reference("TokenTransfer")),
+            ]),
+        ),
+        (
+            "EstimateResponse",
+            object(&[(
+                "chains",
+                array(object(&[
+                    ("chainId", integer()),
+                    ("network", string()),
+                    ("currencyGasSymbol", string()),
+                    ("baseFee", nullable(string())),
+                    ("gasPrice", nullable(string())),
+                    ("totalGas", integer()),
+                    ("totalFee", nullable(string())),
+                    ("totalFeeNative", nullable(string())),
+                    (
+                        "transactions",
+                        array(object(&[
+                            ("fromAddr", string()),
+                            ("toAddr", string()),
+                            ("method", string()),
+                            ("transferCount", integer()),
+                            ("receiverCount", integer()),
+                            ("gas", integer()),
+                            ("gasEstimated", boolean()),
+                            ("estimateError", nullable(string())),
+                            ("fee", nullable(string())),
+                        ])),
+                    ),
+                ])),
+            )]),
+        ),
+        (
+            "BulkTransferRequest",
+            array(reference("TransactionRequest")),
+        ),
+        (
+            "BulkTransferResult",
+            object(&[
+                ("row", integer()),
+                ("created", boolean()),
+                ("transfer", nullable(reference("TokenTransfer"))),
+                ("error", nullable(string())),
+            ]),
+        ),
+        (
+            "BulkTransferResponse",
+            object(&[
+                ("inserted", boolean()),
+                ("results", array(reference("BulkTransferResult"))),
+            ]),
+        ),
+        (
+            "TransferChangeRequest",
+            object(&[
+                ("to", nullable(string())),
+                ("amount", nullable(string())),
+                ("dueDate", nullable(date())),
+                ("priority", nullable(reference("TransferPriority"))),
+            ]),
+        ),
+        (
+            "WebhookDeliveriesResponse",
+            object(&[
+                ("deliveries", array(reference("WebhookDelivery"))),
+                ("nextCursor", nullable(integer())),
+            ]),
+        ),
+        (
+            "WebhookDeliveryResponse",
+            object(&[("delivery", reference("WebhookDelivery"))]),
+        ),
+        (
+            "TransferResponse",
+            object(&[("transfer", reference("TokenTransfer"))]),
+        ),
+        (
+            "TransfersResponse",
+            object(&[
+                ("transfers", array(reference("TokenTransfer"))),
+                ("nextCursor", nullable(integer())),
+            ]),
+        ),
+        (
+            "StatsTransferResult",
+            object(&[
+                ("request_time", json!({"type": "number"})),
+                ("transfers", array(reference("ChainTransfer"))),
+            ]),
+        ),
+        (
+            "AccountBalanceResponse",
+            object(&[
+                ("networkId", integer()),
+                ("account", string()),
+                ("gasBalance", string()),
+                ("tokenBalance", string()),
+                ("blockNumber", integer()),
+                ("blockDate", date()),
+            ]),
+        ),
+        (
+            "AccountsResponse",
+            object(&[("publicAddr", array(string()))]),
+        ),
+        (
+            "AccountTransfersInResponse",
+            object(&[
+                ("transfersIn", array(reference("TransferIn"))),
+                ("nextCursor", nullable(integer())),
+            ]),
+        ),
+        (
+            "AccountDetailsResponse",
+            object(
+                &[
+                    &[
+                        ("account", string()),
+                        ("allowances", array(reference("Allowance"))),
+                    ][..],
+                    &transfer_counts[..],
+                    &[
+                        ("receivedTransfers", integer()),
+                        ("pendingPayouts", array(reference("PendingPayout"))),
+                    ],
+                ]
+                .concat(),
+            ),
+        ),
+        ("FaucetStatusResponse", object(&[("status", string())])),
+        (
+            "FaucetResponse",
+            object(&[
+                ("transfer_gas_id", integer()),
+                ("transfer_gas_payment_id", nullable(string())),
+                ("transfer_glm_id", integer()),
+                ("transfer_glm_payment_id", nullable(string())),
+            ]),
+        ),
+        (
+            "FaucetRequest",
+            object(&[
+                ("id", integer()),
+                ("chainId", integer()),
+                ("receiverAddr", string()),
+                ("gasTransferId", nullable(integer())),
+                ("gasAmount", string()),
+                ("glmTransferId", nullable(integer())),
+                ("glmAmount", string()),
+                ("createDate", date()),
+            ]),
+        ),
+        (
+            "FaucetHistoryResponse",
+            object(&[
+                ("requests", array(reference("FaucetRequest"))),
+                ("nextCursor", nullable(integer())),
+            ]),
+        ),
+        (
+            "AttestationCheckResult",
+            object(&[
+                ("chainId", integer()),
+                ("chain", string()),
+                ("attestation", any_object.clone()),
+                ("schema", any_object.clone()),
+                (
+                    "params",
+                    array(object(&[
+                        ("name", string()),
+                        ("type", string()),
+                        ("value", json!({})),
+                    ])),
+                ),
+            ]),
+        ),
+    ]
+    .into_iter()
+    .map(|(name, schema)| (name.to_string(), schema))
+    .collect()
+}
+
+fn operation(api_op: &ApiOperation) -> Value {
+    let mut parameters = api_op
+        .params
+        .iter()
+        .map(|name| json!({"name": name, "in": "path", "required": true, "schema": string()}))
+        .collect::<Vec<_>>();
+    parameters.extend(
+        api_op.query.iter().map(
+            |name| json!({"name": name, "in": "query", "required": false, "schema": string()}),
+        ),
+    );
+    let mut success =
+        json!({"description": "Success, some routes report errors with `ApiError` body"});
+    if let Some(response) = api_op.response {
+        success["content"] = json!({
+            "application/json": {
+                "schema": {"oneOf": [reference(response), reference("ApiError")]}
+            }
+        });
+    }
+    let mut operation = json!({
+        "summary": api_op.summary,
+        "parameters": parameters,
+        "responses": {
+            "200": success,
+            "400": {"description": "Invalid request"},
+            "401": {"description": "Missing or invalid credentials"},
+            "403": {"description": "Caller lacks required role"},
+        },
+        "x-required-role": api_op.role,
+    });
+    if let Some(request) = api_op.request {
+        operation["requestBody"] = json!({
+            "required": true,
+            "content": {"application/json": {"schema": reference(request)}}
+        });
+    }
+    operation
+}
+
+pub fn openapi_document() -> Value {
+    let mut paths = Map::new();
+    for api_op in API_OPERATIONS {
+        let path = paths
+            .entry(format!("/api{}", api_op.path))
+            .or_insert_with(|| json!({}));
+        path[api_op.method] = operation(api_op);
+    }
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "erc20_payment_lib",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "paths": paths,
+        "components": {
+            "schemas": schemas(),
+            "securitySchemes": {
+                "bearer": {"type": "http", "scheme": "bearer"},
+                "hmac": {
+                    "type": "apiKey",
+                    "in": "header",
+                    "name": HMAC_KEY_ID_HEADER,
+                    "description": "Requests signed with X-Api-Timestamp and X-Api-Signature headers",
+                },
+            },
+        },
+        "security": [{"bearer": []}, {"hmac": []}, {}],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collect_refs(value: &Value, refs: &mut Vec<String>) {
+        match value {
+            Value::Object(map) => {
+                if let Some(Value::String(r)) = map.get("$ref") {
+                    refs.push(r.clone());
+                }
+                map.values().for_each(|v| collect_refs(v, refs));
+            }
+            Value::Array(items) => items.iter().for_each(|v| collect_refs(v, refs)),
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn test_openapi_refs_resolve() {
+        let doc = openapi_document();
+        let mut refs = Vec::new();
+        collect_refs(&doc, &mut refs);
+        assert!(!refs.is_empty());
+        for r in refs {
+            let name = r.trim_start_matches("#/components/schemas/");
+            assert!(
+                doc["components"]["schemas"].get(name).is_some(),
+                "missing schema {}",
+                name
+            );
+        }
+        assert!(doc["paths"]["/api/transfers/id/{id}"].get("get").is_some());
+        assert!(doc["paths"]["/api/transfers/id/{id}"]
+            .get("patch")
+            .is_some());
+        assert!(doc["paths"]["/api/transfers/id/{id}"]
+            .get("delete")
+            .is_some());
+        assert!(doc["paths"]["/api/transfers/{tx_id}"]
+            .get("patch")
+            .is_none());
+    }
+}
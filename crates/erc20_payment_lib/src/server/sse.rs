@@ -0,0 +1,78 @@
+use super::api::EventStreamQuery;
+use super::web::ServerData;
+use crate::event_log::{filtered_event_stream, parse_event_types, EventLogEntry};
+use actix_web::http::header;
+use actix_web::web::{Bytes, Data};
+use actix_web::{web, Error, HttpRequest, HttpResponse};
+use erc20_payment_lib_common::store::PaymentStore;
+use futures::StreamExt;
+use std::time::Duration;
+
+/// Reconnecting `EventSource` sends id of the last received event in this header
+const LAST_EVENT_ID_HEADER: &str = "Last-Event-ID";
+/// Comment line, ignored by clients but keeps proxies from closing idle connection
+const HEARTBEAT: &[u8] = b": heartbeat\n\n";
+
+fn sse_message(entry: &EventLogEntry) -> String {
+    match entry.id {
+        Some(id) => format!("id: {}\ndata: {}\n\n", id, entry.json),
+        None => format!("data: {}\n\n", entry.json),
+    }
+}
+
+/// Same events as websocket endpoint, sent as Server-Sent Events.
+/// Stream resumes after `since` query parameter or `Last-Event-ID` header,
+/// heartbeat comment is sent every `report-alive-interval`.
+pub async fn event_stream_sse_endpoint<S: PaymentStore>(
+    data: Data<Box<ServerData<S>>>,
+    req: HttpRequest,
+    query: web::Query<EventStreamQuery>,
+) -> Result<HttpResponse, Error> {
+    let Some(driver_broadcast_sender) = &data.payment_runtime.driver_broadcast_sender else {
+        return Err(actix_web::error::ErrorInternalServerError(
+            "Driver event sender not available",
+        ));
+    };
+    let since = match query.since {
+        Some(since) => Some(since),
+        None => req
+            .headers()
+            .get(LAST_EVENT_ID_HEADER)
+            .map(|h| {
+                h.to_str()
+                    .ok()
+                    .and_then(|id| id.trim().parse::<i64>().ok())
+                    .ok_or_else(|| actix_web::error::ErrorBadRequest("Invalid Last-Event-ID"))
+            })
+            .transpose()?,
+    };
+    let event_types = query
+        .events
+        .as_deref()
+        .map(parse_event_types)
+        .transpose()
+        .map_err(actix_web::error::ErrorBadRequest)?;
+    let rx = driver_broadcast_sender.subscribe();
+    let conn = data.db_connection.lock().await.clone();
+    let events = filtered_event_stream(conn, rx, since, event_types).boxed_local();
+
+    let heartbeat_interval = Duration::from_secs(data.payment_setup.report_alive_interval.max(1));
+    let mut heartbeat = tokio::time::interval(heartbeat_interval);
+    heartbeat.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let body = futures::stream::unfold(
+        (events, heartbeat),
+        |(mut events, mut heartbeat)| async move {
+            let message = tokio::select! {
+                entry = events.next() => Bytes::from(sse_message(&entry?)),
+                _ = heartbeat.tick() => Bytes::from_static(HEARTBEAT),
+            };
+            Some((Ok::<_, Error>(message), (events, heartbeat)))
+        },
+    );
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header((header::CACHE_CONTROL, "no-cache"))
+        // disable response buffering in nginx
+        .insert_header(("X-Accel-Buffering", "no"))
+        .streaming(body))
+}
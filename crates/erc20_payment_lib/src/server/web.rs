@@ -1,27 +1,36 @@
-use crate::eth::{
-    get_attestation_details, get_balance, get_schema_details, Attestation, AttestationSchema,
-    GetBalanceArgs,
-};
-use crate::runtime::{PaymentRuntime, SharedState, TransferArgs, TransferType};
+use crate::eth::{get_attestation_details, get_balance, get_schema_details, GetBalanceArgs};
+use crate::faucet::{FaucetDenied, FaucetLimits, CAPTCHA_TOKEN_HEADER};
+use crate::runtime::{PaymentRuntime, SharedState, TransferArgs, TransferChange, TransferType};
+use crate::sender::payout::pending_payouts;
+use crate::server::api::*;
+use crate::server::auth::{ApiAuth, ApiPrincipal, ApiRole};
+use crate::server::openapi::openapi_document;
+use crate::server::sse::event_stream_sse_endpoint;
 use crate::server::ws::event_stream_websocket_endpoint;
 use crate::setup::{ChainSetup, PaymentSetup};
+use crate::signer::SignerAccount;
 use crate::transaction::create_token_transfer;
+use crate::webhook::retry_webhook_delivery;
 use actix_files::NamedFile;
 use actix_web::dev::{ServiceRequest, ServiceResponse};
 use actix_web::error::ErrorBadRequest;
 use actix_web::http::header::HeaderValue;
 use actix_web::http::{header, StatusCode};
 use actix_web::web::Data;
-use actix_web::{web, HttpRequest, HttpResponse, Responder, Scope};
-use chrono::{DateTime, Utc};
-use erc20_payment_lib_common::model::DepositId;
-use erc20_payment_lib_common::ops::*;
-use erc20_payment_lib_common::{export_metrics_to_prometheus, FaucetData};
-use erc20_rpc_pool::VerifyEndpointResult;
-use serde::{Deserialize, Serialize};
-use serde_json::json;
-use sqlx::SqlitePool;
-use std::collections::BTreeMap;
+use actix_web::{web, HttpMessage, HttpRequest, HttpResponse, Responder, Scope};
+use chrono::Utc;
+use erc20_payment_lib_common::error::{ErrorBag, PaymentError};
+use erc20_payment_lib_common::export_metrics_to_prometheus;
+use erc20_payment_lib_common::model::FaucetRequestDbObj;
+use erc20_payment_lib_common::store::{
+    FaucetRequestListFilter, PaymentDbStore, PaymentStore, PaymentStoreOps,
+    PaymentStoreTransaction, TransactionFilter, TransactionOrder, TransferFilter,
+    TransferInListFilter, TransferInsertResult, TransferListFilter, TxListFilter,
+    WebhookDeliveryListFilter, WebhookDeliveryStatus,
+};
+use erc20_rpc_pool::{VerifyEndpointResult, Web3RpcEndpointChange};
+use std::collections::{BTreeMap, HashMap};
+use std::net::SocketAddr;
 use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Duration;
@@ -29,11 +38,15 @@ use tokio::sync::Mutex;
 use web3::ethabi;
 use web3::types::{Address, H256, U256};
 
-pub struct ServerData {
+pub struct ServerData<S: PaymentStore = PaymentDbStore> {
     pub shared_state: Arc<std::sync::Mutex<SharedState>>,
-    pub db_connection: Arc<Mutex<SqlitePool>>,
+    pub db_connection: Arc<Mutex<S>>,
     pub payment_setup: PaymentSetup,
-    pub payment_runtime: PaymentRuntime,
+    pub payment_runtime: PaymentRuntime<S>,
+    /// Credentials and roles checked on every api route
+    pub auth: Arc<ApiAuth>,
+    /// Limits of faucet endpoint
+    pub faucet: FaucetLimits,
 }
 
 macro_rules! return_on_error {
@@ -41,15 +54,52 @@ macro_rules! return_on_error {
         match $e {
             Ok(x) => x,
             Err(err) => {
-                return web::Json(json!({
-                    "error": err.to_string()
-                }));
+                return HttpResponse::Ok().json(ApiError {
+                    error: err.to_string(),
+                });
             }
         }
+    };
+}
+
+fn api_error(error: impl ToString) -> HttpResponse {
+    HttpResponse::Ok().json(ApiError {
+        error: error.to_string(),
+    })
+}
+
+fn page_limit(limit: Option<i64>) -> i64 {
+    limit.unwrap_or(DEFAULT_PAGE_LIMIT).clamp(1, MAX_PAGE_LIMIT)
+}
+
+/// Id of the last row when page is full, rows are ordered by id descending
+fn next_cursor(
+    ids: impl DoubleEndedIterator<Item = i64> + ExactSizeIterator,
+    limit: i64,
+) -> Option<i64> {
+    if ids.len() as i64 >= limit {
+        ids.last()
+    } else {
+        None
     }
 }
 
-pub async fn tx_details(data: Data<Box<ServerData>>, req: HttpRequest) -> impl Responder {
+fn parse_address(addr: Option<&String>) -> Result<Option<Address>, String> {
+    addr.map(|addr| {
+        Address::from_str(addr).map_err(|err| format!("Invalid address {}: {}", addr, err))
+    })
+    .transpose()
+}
+
+/// Addresses are stored lowercase
+fn address_filter(addr: Option<&String>) -> Result<Option<String>, String> {
+    Ok(parse_address(addr)?.map(|addr| format!("{:#x}", addr)))
+}
+
+pub async fn tx_details<S: PaymentStore>(
+    data: Data<Box<ServerData<S>>>,
+    req: HttpRequest,
+) -> impl Responder {
     let tx_id = req
         .match_info()
         .get("tx_id")
@@ -58,26 +108,18 @@ pub async fn tx_details(data: Data<Box<ServerData>>, req: HttpRequest) -> impl R
 
     let tx_id = match tx_id {
         Some(tx_id) => tx_id,
-        None => return web::Json(json!({"error": "failed to parse tx_id"})),
+        None => return api_error("failed to parse tx_id"),
     };
 
     let tx = {
         let db_conn = data.db_connection.lock().await;
-        match get_transaction(&*db_conn, tx_id).await {
-            Ok(allowances) => allowances,
-            Err(err) => {
-                return web::Json(json!({
-                    "error": err.to_string()
-                }));
-                //return format!("Error getting allowances: {:?}", err);
-            }
-        }
+        return_on_error!(db_conn.get_transaction(tx_id).await)
     };
 
     /*
     let transfers = {
         let db_conn = data.db_connection.lock().await;
-        match get_token_transfers_by_tx(&db_conn, tx_id).await {
+        match db_conn.get_token_transfers_by_tx(tx_id).await {
             Ok(allowances) => allowances,
             Err(err) => {
                 return web::Json(json!({
@@ -102,12 +144,13 @@ pub async fn tx_details(data: Data<Box<ServerData>>, req: HttpRequest) -> impl R
     })
     .collect::<Vec<_>>();*/
 
-    web::Json(json!({
-        "tx": tx,
-    }))
+    HttpResponse::Ok().json(TxResponse { tx })
 }
 
-pub async fn rpc_pool(data: Data<Box<ServerData>>, _req: HttpRequest) -> impl Responder {
+pub async fn rpc_pool<S: PaymentStore>(
+    data: Data<Box<ServerData<S>>>,
+    _req: HttpRequest,
+) -> impl Responder {
     let my_data = data.shared_state.lock().unwrap();
     //synchronize rpc_pool statistics with server
     /*shared_state.lock().await.web3_rpc_pool.insert(
@@ -145,7 +188,7 @@ pub async fn rpc_pool(data: Data<Box<ServerData>>, _req: HttpRequest) -> impl Re
     for (idx, val) in web3_rpc_pool_info {
         let val = val
             .iter()
-            .map(|v| json!(*v.try_read_for(Duration::from_secs(5)).unwrap()))
+            .map(|v| v.try_read_for(Duration::from_secs(5)).unwrap().clone())
             .collect::<Vec<_>>();
         let chain_network = data
             .payment_setup
@@ -153,16 +196,42 @@ pub async fn rpc_pool(data: Data<Box<ServerData>>, _req: HttpRequest) -> impl Re
             .get(&idx)
             .map(|s| s.network.clone())
             .unwrap_or("unknown".to_string());
-        array.push(json!(
-            {
-                "chainId": idx,
-                "chainNetwork": chain_network,
-                "endpoints": val,
-            }
-        ));
+        array.push(RpcPoolNetwork {
+            chain_id: idx,
+            chain_network,
+            endpoints: val,
+        });
     }
-    web::Json(json!({
-        "networks": array,
+    web::Json(RpcPoolResponse { networks: array })
+}
+
+pub async fn rpc_pool_change_endpoints<S: PaymentStore>(
+    data: Data<Box<ServerData<S>>>,
+    req: HttpRequest,
+    change: web::Json<Web3RpcEndpointChange>,
+) -> actix_web::Result<web::Json<RpcPoolEndpointsResponse>> {
+    let chain_id = i64::from_str(
+        req.match_info()
+            .get("chain")
+            .ok_or(ErrorBadRequest("chain-id not found"))?,
+    )
+    .map_err(|err| ErrorBadRequest(format!("chain-id has to be int {err}")))?;
+
+    data.payment_runtime
+        .change_rpc_endpoint(chain_id, change.into_inner())
+        .map_err(|err| ErrorBadRequest(err.to_string()))?;
+
+    let endpoints = data
+        .payment_setup
+        .get_provider(chain_id)
+        .map_err(|err| ErrorBadRequest(err.to_string()))?
+        .get_endpoints_info()
+        .into_iter()
+        .map(|(_idx, params, info)| RpcEndpointInfo { params, info })
+        .collect::<Vec<_>>();
+    Ok(web::Json(RpcPoolEndpointsResponse {
+        chain_id,
+        endpoints,
     }))
 }
 
@@ -178,7 +247,10 @@ struct Metric {
     value: String,
 }
 
-pub async fn rpc_pool_metrics(data: Data<Box<ServerData>>, _req: HttpRequest) -> impl Responder {
+pub async fn rpc_pool_metrics<S: PaymentStore>(
+    data: Data<Box<ServerData<S>>>,
+    _req: HttpRequest,
+) -> impl Responder {
     let pool_ref = data
         .shared_state
         .lock()
@@ -224,6 +296,23 @@ pub async fn rpc_pool_metrics(data: Data<Box<ServerData>>, _req: HttpRequest) ->
         metrics: Vec::new(),
     });
 
+    metrics.push(MetricGroup {
+        metric_help: "# HELP rpc_endpoint_daily_budget_remaining Requests left in daily budget (-1 when no budget)".to_string(),
+        metric_type: "# TYPE rpc_endpoint_daily_budget_remaining gauge".to_string(),
+        metrics: Vec::new(),
+    });
+    metrics.push(MetricGroup {
+        metric_help: "# HELP rpc_endpoint_monthly_budget_remaining Requests left in monthly budget (-1 when no budget)".to_string(),
+        metric_type: "# TYPE rpc_endpoint_monthly_budget_remaining gauge".to_string(),
+        metrics: Vec::new(),
+    });
+    metrics.push(MetricGroup {
+        metric_help: "# HELP rpc_endpoint_rate_limited_count Number of times endpoint was skipped due to rate limit".to_string(),
+        metric_type: "# TYPE rpc_endpoint_rate_limited_count counter".to_string(),
+        metrics: Vec::new(),
+    });
+
+    let now = Utc::now();
     for (_idx, vec) in pool_ref {
         for (_idx, endpoint) in vec.try_lock_for(Duration::from_secs(5)).unwrap().iter() {
             let endpoint = endpoint
@@ -292,6 +381,37 @@ pub async fn rpc_pool_metrics(data: Data<Box<ServerData>>, _req: HttpRequest) ->
                 value: head_behind.to_string(),
             };
             metrics[5].metrics.push(new_metric);
+
+            let endpoint_params = &endpoint.web3_rpc_params.web3_endpoint_params;
+            let rate_limit = &endpoint.web3_rpc_info.rate_limit;
+            let new_metric = Metric {
+                name: "rpc_endpoint_daily_budget_remaining".into(),
+                params: params.clone(),
+                value: rate_limit
+                    .remaining_daily_budget(endpoint_params, now)
+                    .map(|v| v as i64)
+                    .unwrap_or(-1)
+                    .to_string(),
+            };
+            metrics[6].metrics.push(new_metric);
+
+            let new_metric = Metric {
+                name: "rpc_endpoint_monthly_budget_remaining".into(),
+                params: params.clone(),
+                value: rate_limit
+                    .remaining_monthly_budget(endpoint_params, now)
+                    .map(|v| v as i64)
+                    .unwrap_or(-1)
+                    .to_string(),
+            };
+            metrics[7].metrics.push(new_metric);
+
+            let new_metric = Metric {
+                name: "rpc_endpoint_rate_limited_count".into(),
+                params: params.clone(),
+                value: rate_limit.rate_limited_count.to_string(),
+            };
+            metrics[8].metrics.push(new_metric);
         }
     }
 
@@ -321,91 +441,111 @@ pub async fn rpc_pool_metrics(data: Data<Box<ServerData>>, _req: HttpRequest) ->
     resp
 }
 
-pub async fn allowances(data: Data<Box<ServerData>>, _req: HttpRequest) -> impl Responder {
+pub async fn allowances<S: PaymentStore>(
+    data: Data<Box<ServerData<S>>>,
+    _req: HttpRequest,
+) -> impl Responder {
     data.shared_state.lock().unwrap().inserted += 1;
     let allowances = {
         let db_conn = data.db_connection.lock().await;
-        match get_all_allowances(&db_conn).await {
-            Ok(allowances) => allowances,
-            Err(err) => {
-                return web::Json(json!({
-                    "error": err.to_string()
-                }));
-                //return format!("Error getting allowances: {:?}", err);
-            }
-        }
+        return_on_error!(db_conn.get_all_allowances().await)
     };
 
-    web::Json(json!({
-        "allowances": allowances,
-    }))
+    HttpResponse::Ok().json(AllowancesResponse { allowances })
 }
 
-pub async fn transactions_count(data: Data<Box<ServerData>>, _req: HttpRequest) -> impl Responder {
+pub async fn transactions_count<S: PaymentStore>(
+    data: Data<Box<ServerData<S>>>,
+    _req: HttpRequest,
+) -> impl Responder {
     let queued_tx_count = {
         let db_conn = data.db_connection.lock().await;
-        return_on_error!(get_transaction_count(&db_conn, Some(TRANSACTION_FILTER_QUEUED)).await)
+        return_on_error!(
+            db_conn
+                .get_transaction_count(TransactionFilter::Queued)
+                .await
+        )
     };
     let done_tx_count = {
         let db_conn = data.db_connection.lock().await;
-        return_on_error!(get_transaction_count(&db_conn, Some(TRANSACTION_FILTER_DONE)).await)
+        return_on_error!(db_conn.get_transaction_count(TransactionFilter::Done).await)
     };
 
     let queued_transfer_count = {
         let db_conn = data.db_connection.lock().await;
         return_on_error!(
-            get_transfer_count(&db_conn, Some(TRANSFER_FILTER_QUEUED), None, None).await
+            db_conn
+                .get_transfer_count(TransferFilter::Queued, None, None)
+                .await
         )
     };
     let processed_transfer_count = {
         let db_conn = data.db_connection.lock().await;
         return_on_error!(
-            get_transfer_count(&db_conn, Some(TRANSFER_FILTER_PROCESSING), None, None).await
+            db_conn
+                .get_transfer_count(TransferFilter::Processing, None, None)
+                .await
         )
     };
     let done_transfer_count = {
         let db_conn = data.db_connection.lock().await;
-        return_on_error!(get_transfer_count(&db_conn, Some(TRANSFER_FILTER_DONE), None, None).await)
+        return_on_error!(
+            db_conn
+                .get_transfer_count(TransferFilter::Done, None, None)
+                .await
+        )
     };
 
-    web::Json(json!({
-        "transfersQueued": queued_transfer_count,
-        "transfersProcessing": processed_transfer_count,
-        "transfersDone": done_transfer_count,
-        "txQueued": queued_tx_count,
-        "txDone": done_tx_count,
-    }))
+    HttpResponse::Ok().json(TransactionsCountResponse {
+        transfers_queued: queued_transfer_count,
+        transfers_processing: processed_transfer_count,
+        transfers_done: done_transfer_count,
+        tx_queued: queued_tx_count,
+        tx_done: done_tx_count,
+    })
 }
 
-pub async fn config_endpoint(data: Data<Box<ServerData>>) -> impl Responder {
-    let payment_setup = data.payment_setup.clone();
+pub async fn config_endpoint<S: PaymentStore>(data: Data<Box<ServerData<S>>>) -> impl Responder {
+    let config = return_on_error!(serde_json::to_value(&data.payment_setup));
 
-    web::Json(json!({
-        "config": payment_setup,
-    }))
+    HttpResponse::Ok().json(ConfigResponse { config })
 }
 
-pub async fn debug_endpoint(data: Data<Box<ServerData>>) -> impl Responder {
+pub async fn debug_endpoint<S: PaymentStore>(data: Data<Box<ServerData<S>>>) -> impl Responder {
     let shared_state = data.shared_state.lock().unwrap().clone();
+    let shared_state = return_on_error!(serde_json::to_value(&shared_state));
 
-    web::Json(json!({
-        "sharedState": shared_state,
-    }))
+    HttpResponse::Ok().json(DebugResponse { shared_state })
 }
 
-pub async fn transactions(data: Data<Box<ServerData>>, _req: HttpRequest) -> impl Responder {
-    //todo: add limits
+pub async fn transactions<S: PaymentStore>(
+    data: Data<Box<ServerData<S>>>,
+    query: web::Query<TxListQuery>,
+) -> impl Responder {
+    let limit = page_limit(query.limit);
+    let filter = TxListFilter {
+        status: query.status.map(|status| match status {
+            ListStatus::Queued => TransactionFilter::Queued,
+            ListStatus::Processing => TransactionFilter::Processing,
+            ListStatus::Done => TransactionFilter::Done,
+        }),
+        chain_id: query.chain,
+        account: return_on_error!(address_filter(query.account.as_ref())),
+        created_from: query.from,
+        created_to: query.to,
+        before_id: query.cursor,
+        limit: Some(limit),
+    };
     let txs = {
         let db_conn = data.db_connection.lock().await;
-        return_on_error!(get_transactions(&*db_conn, None, None, None, None, None).await)
+        return_on_error!(db_conn.list_transactions(&filter).await)
     };
-    web::Json(json!({
-        "txs": txs,
-    }))
+    let next_cursor = next_cursor(txs.iter().map(|tx| tx.id), limit);
+    HttpResponse::Ok().json(TxsResponse { txs, next_cursor })
 }
 
-pub async fn skip_pending_operation(
-    data: Data<Box<ServerData>>,
+pub async fn skip_pending_operation<S: PaymentStore>(
+    data: Data<Box<ServerData<S>>>,
     req: HttpRequest,
 ) -> impl Responder {
     let tx_id = req
@@ -415,145 +555,166 @@ pub async fn skip_pending_operation(
         .unwrap_or(None);
     if let Some(tx_id) = tx_id {
         if data.shared_state.lock().unwrap().skip_tx(tx_id) {
-            web::Json(json!({
-                "success": "true",
-            }))
+            HttpResponse::Ok().json(SkipTxResponse {
+                success: "true".to_string(),
+            })
         } else {
-            web::Json(json!({
-                "error": "Tx not found",
-            }))
+            api_error("Tx not found")
         }
     } else {
-        web::Json(json!({
-            "error": "failed to parse tx_id",
-        }))
+        api_error("failed to parse tx_id")
     }
 }
 
-pub async fn transactions_next(data: Data<Box<ServerData>>, req: HttpRequest) -> impl Responder {
+pub async fn transactions_next<S: PaymentStore>(
+    data: Data<Box<ServerData<S>>>,
+    req: HttpRequest,
+    query: web::Query<TxFeedQuery>,
+) -> impl Responder {
+    let account = return_on_error!(parse_address(query.account.as_ref()));
     let limit = req
         .match_info()
         .get("count")
         .map(|tx_id| i64::from_str(tx_id).ok())
-        .unwrap_or(Some(10));
+        .unwrap_or(Some(10))
+        .map(|limit| limit.min(MAX_PAGE_LIMIT));
 
     let txs = {
         let db_conn = data.db_connection.lock().await;
         return_on_error!(
-            get_transactions(
-                &*db_conn,
-                None,
-                Some(TRANSACTION_FILTER_QUEUED),
-                limit,
-                Some(TRANSACTION_ORDER_BY_CREATE_DATE),
-                None
-            )
-            .await
+            db_conn
+                .get_transactions(
+                    account,
+                    TransactionFilter::Queued,
+                    limit,
+                    TransactionOrder::CreateDate,
+                    query.chain
+                )
+                .await
         )
     };
-    web::Json(json!({
-        "txs": txs,
-    }))
+    let batches = data
+        .shared_state
+        .lock()
+        .unwrap()
+        .pending_batches
+        .values()
+        .cloned()
+        .collect::<Vec<_>>();
+    HttpResponse::Ok().json(NextTxsResponse { txs, batches })
 }
 
-pub async fn transactions_current(
-    data: Data<Box<ServerData>>,
-    _req: HttpRequest,
+pub async fn transactions_current<S: PaymentStore>(
+    data: Data<Box<ServerData<S>>>,
+    query: web::Query<TxFeedQuery>,
 ) -> impl Responder {
+    let account = return_on_error!(parse_address(query.account.as_ref()));
     let txs = {
         let db_conn = data.db_connection.lock().await;
         return_on_error!(
-            get_transactions(
-                &*db_conn,
-                None,
-                Some(TRANSACTION_FILTER_PROCESSING),
-                None,
-                Some(TRANSACTION_ORDER_BY_CREATE_DATE),
-                None
-            )
-            .await
+            db_conn
+                .get_transactions(
+                    account,
+                    TransactionFilter::Processing,
+                    None,
+                    TransactionOrder::CreateDate,
+                    query.chain
+                )
+                .await
         )
     };
-    web::Json(json!({
-        "txs": txs,
-    }))
+    HttpResponse::Ok().json(TxsResponse {
+        txs,
+        next_cursor: None,
+    })
 }
 
-pub async fn transactions_last_processed(
-    data: Data<Box<ServerData>>,
+pub async fn transactions_last_processed<S: PaymentStore>(
+    data: Data<Box<ServerData<S>>>,
     req: HttpRequest,
+    query: web::Query<TxFeedQuery>,
 ) -> impl Responder {
+    let account = return_on_error!(parse_address(query.account.as_ref()));
     let limit = req
         .match_info()
         .get("count")
         .map(|tx_id| i64::from_str(tx_id).ok())
-        .unwrap_or(Some(10));
+        .unwrap_or(Some(10))
+        .map(|limit| limit.min(MAX_PAGE_LIMIT));
 
     let txs = {
         let db_conn = data.db_connection.lock().await;
         return_on_error!(
-            get_transactions(
-                &*db_conn,
-                None,
-                Some(TRANSACTION_FILTER_DONE),
-                limit,
-                Some(TRANSACTION_ORDER_BY_FIRST_PROCESSED_DATE_DESC),
-                None
-            )
-            .await
+            db_conn
+                .get_transactions(
+                    account,
+                    TransactionFilter::Done,
+                    limit,
+                    TransactionOrder::FirstProcessedDateDesc,
+                    query.chain
+                )
+                .await
         )
     };
-    web::Json(json!({
-        "txs": txs,
-    }))
+    HttpResponse::Ok().json(TxsResponse {
+        txs,
+        next_cursor: None,
+    })
 }
 
-pub async fn transactions_feed(data: Data<Box<ServerData>>, req: HttpRequest) -> impl Responder {
+pub async fn transactions_feed<S: PaymentStore>(
+    data: Data<Box<ServerData<S>>>,
+    req: HttpRequest,
+    query: web::Query<TxFeedQuery>,
+) -> impl Responder {
+    let account = return_on_error!(parse_address(query.account.as_ref()));
     let limit_prev = req
         .match_info()
         .get("prev")
         .map(|tx_id| i64::from_str(tx_id).ok())
-        .unwrap_or(Some(10));
+        .unwrap_or(Some(10))
+        .map(|limit| limit.min(MAX_PAGE_LIMIT));
     let limit_next = req
         .match_info()
         .get("next")
         .map(|tx_id| i64::from_str(tx_id).ok())
-        .unwrap_or(Some(10));
+        .unwrap_or(Some(10))
+        .map(|limit| limit.min(MAX_PAGE_LIMIT));
     let mut txs = {
         let db_conn = data.db_connection.lock().await;
-        let mut db_transaction = return_on_error!(db_conn.begin().await);
+        let db_transaction = return_on_error!(db_conn.begin().await);
         let mut txs = return_on_error!(
-            get_transactions(
-                &mut *db_transaction,
-                None,
-                Some(TRANSACTION_FILTER_DONE),
-                limit_prev,
-                Some(TRANSACTION_ORDER_BY_FIRST_PROCESSED_DATE_DESC),
-                None
-            )
-            .await
+            db_transaction
+                .get_transactions(
+                    account,
+                    TransactionFilter::Done,
+                    limit_prev,
+                    TransactionOrder::FirstProcessedDateDesc,
+                    query.chain
+                )
+                .await
         );
         let txs_current = return_on_error!(
-            get_transactions(
-                &mut *db_transaction,
-                None,
-                Some(TRANSACTION_FILTER_PROCESSING),
-                None,
-                Some(TRANSACTION_ORDER_BY_CREATE_DATE),
-                None
-            )
-            .await
+            db_transaction
+                .get_transactions(
+                    account,
+                    TransactionFilter::Processing,
+                    None,
+                    TransactionOrder::CreateDate,
+                    query.chain
+                )
+                .await
         );
         let tx_next = return_on_error!(
-            get_transactions(
-                &mut *db_transaction,
-                None,
-                Some(TRANSACTION_FILTER_QUEUED),
-                limit_next,
-                Some(TRANSACTION_ORDER_BY_CREATE_DATE),
-                None
-            )
-            .await
+            db_transaction
+                .get_transactions(
+                    account,
+                    TransactionFilter::Queued,
+                    limit_next,
+                    TransactionOrder::CreateDate,
+                    query.chain
+                )
+                .await
         );
         return_on_error!(db_transaction.commit().await);
         //join transactions
@@ -571,51 +732,52 @@ pub async fn transactions_feed(data: Data<Box<ServerData>>, req: HttpRequest) ->
         }
     }
 
-    web::Json(json!({
-        "txs": txs,
-        "current": current_tx,
-    }))
-}
-
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct TransactionRequest {
-    from: String,
-    to: String,
-    token: Option<String>,
-    amount: String,
-    chain: i64,
-    due_date: Option<String>,
-    payment_id: Option<String>,
-    deposit_id: Option<DepositId>,
+    HttpResponse::Ok().json(TxsFeedResponse {
+        txs,
+        current: current_tx,
+    })
 }
 
-async fn new_transfer(
-    data: Data<Box<ServerData>>,
-    _req: HttpRequest,
-    new_transfer: web::Json<TransactionRequest>,
-) -> actix_web::Result<String> {
-    //println!("new_transfer: {:?}", new_transfer);
-
+/// Check transfer request against payment setup and active accounts.
+/// Payment id makes the request idempotent, without `require_payment_id` a random id
+/// is generated when missing, so retrying such request creates another transfer.
+pub(crate) fn transfer_args_from_request<S: PaymentStore>(
+    data: &ServerData<S>,
+    request: &TransactionRequest,
+    require_payment_id: bool,
+) -> Result<(SignerAccount, TransferArgs), String> {
     let chain = data
         .payment_setup
         .chain_setup
-        .get(&new_transfer.chain)
-        .ok_or(actix_web::error::ErrorBadRequest("No config found"))?
-        .clone();
-
-    let tx_type = if let Some(_token) = &new_transfer.token {
+        .get(&request.chain)
+        .ok_or_else(|| format!("No config found for chain {}", request.chain))?;
+
+    let from = Address::from_str(&request.from)
+        .map_err(|err| format!("Invalid from address {}: {}", request.from, err))?;
+    let receiver = Address::from_str(&request.to)
+        .map_err(|err| format!("Invalid to address {}: {}", request.to, err))?;
+
+    let tx_type = if let Some(token) = &request.token {
+        let token = Address::from_str(token)
+            .map_err(|err| format!("Invalid token address {}: {}", token, err))?;
+        if token != chain.glm_address {
+            return Err(format!(
+                "Token {:#x} is not supported on chain {}, expected {:#x}",
+                token, request.chain, chain.glm_address
+            ));
+        }
         TransferType::Token
     } else {
         TransferType::Gas
     };
 
-    let due_date = if let Some(due_date) = &new_transfer.due_date {
+    let amount = U256::from_dec_str(&request.amount)
+        .map_err(|err| format!("Invalid amount {}: {:?}", request.amount, err))?;
+
+    let due_date = if let Some(due_date) = &request.due_date {
         Some(
             chrono::DateTime::parse_from_rfc3339(due_date)
-                .map_err(|err| {
-                    actix_web::error::ErrorBadRequest(format!("Invalid due_date: {}", err))
-                })?
+                .map_err(|err| format!("Invalid due_date: {}", err))?
                 .naive_utc()
                 .and_utc(),
         )
@@ -623,89 +785,370 @@ async fn new_transfer(
         None
     };
 
-    let payment_id = if let Some(payment_id) = &new_transfer.payment_id {
+    let payment_id = if let Some(payment_id) = &request.payment_id {
         payment_id.clone()
+    } else if require_payment_id {
+        return Err("paymentId is required".to_string());
     } else {
         uuid::Uuid::new_v4().to_string()
     };
 
-    let transfer_args = TransferArgs {
-        network: chain.network,
-        from: Address::from_str(&new_transfer.from).unwrap(),
-        receiver: Address::from_str(&new_transfer.to).unwrap(),
-        tx_type,
-        amount: U256::from_dec_str(&new_transfer.amount).unwrap(),
-        payment_id,
-        deadline: due_date,
-        deposit_id: new_transfer.deposit_id,
-    };
-
-    let account = match data
+    let account = data
         .shared_state
         .lock()
         .unwrap()
         .accounts
         .iter()
-        .find(|acc| acc.address == transfer_args.from)
-    {
-        Some(acc) => acc.clone(),
-        None => {
-            return Err(actix_web::error::ErrorBadRequest(format!(
-                "Account not found: {:#x}",
-                transfer_args.from
-            )));
-        }
-    };
+        .find(|acc| acc.address == from)
+        .cloned()
+        .ok_or_else(|| format!("Account not found: {:#x}", from))?;
+
+    Ok((
+        account,
+        TransferArgs {
+            network: chain.network.clone(),
+            from,
+            receiver,
+            tx_type,
+            amount,
+            payment_id,
+            deadline: due_date,
+            deposit_id: request.deposit_id,
+            priority: request.priority.unwrap_or_default(),
+        },
+    ))
+}
 
-    if let Err(err) = data
+async fn insert_transfer<S: PaymentStore>(
+    data: &ServerData<S>,
+    request: &TransactionRequest,
+    require_payment_id: bool,
+) -> actix_web::Result<TransferInsertResult> {
+    let (account, transfer_args) =
+        transfer_args_from_request(data, request, require_payment_id).map_err(ErrorBadRequest)?;
+
+    let res = match data
         .payment_runtime
         .transfer_with_account(&account, transfer_args.clone())
         .await
     {
-        return Err(actix_web::error::ErrorInternalServerError(format!(
-            "Failed to create transfer: {}",
-            err
-        )));
+        Ok(res) => res,
+        Err(err) => {
+            if let ErrorBag::PaymentIdConflict(_) = *err.inner {
+                return Err(actix_web::error::ErrorConflict(err.to_string()));
+            }
+            return Err(actix_web::error::ErrorInternalServerError(format!(
+                "Failed to create transfer: {}",
+                err
+            )));
+        }
     };
-    log::warn!("Created transfer: {:?}", transfer_args);
+    if let TransferInsertResult::Created(_) = res {
+        log::warn!("Created transfer: {:?}", transfer_args);
+    }
+    Ok(res)
+}
 
+/// Legacy route, responds with `"success"` only. Missing payment id is generated,
+/// so use `/transfers/create` when the request may be retried.
+async fn new_transfer<S: PaymentStore>(
+    data: Data<Box<ServerData<S>>>,
+    _req: HttpRequest,
+    new_transfer: web::Json<TransactionRequest>,
+) -> actix_web::Result<String> {
+    insert_transfer(&data, &new_transfer, false).await?;
     Ok("success".to_string())
 }
 
-#[derive(Deserialize)]
-pub struct StatsTransferRequest {
-    receiver: Option<String>,
-    from: Option<String>,
-    to: Option<String>,
-    chain: Option<String>,
+/// Idempotent transfer creation, payment id is required and the same request
+/// sent again returns the existing transfer with `created: false`
+async fn create_transfer<S: PaymentStore>(
+    data: Data<Box<ServerData<S>>>,
+    _req: HttpRequest,
+    new_transfer: web::Json<TransactionRequest>,
+) -> actix_web::Result<web::Json<NewTransferResponse>> {
+    let res = insert_transfer(&data, &new_transfer, true).await?;
+    Ok(web::Json(NewTransferResponse {
+        created: matches!(res, TransferInsertResult::Created(_)),
+        transfer: res.transfer().clone(),
+    }))
+}
+
+/// Upper limit of rows accepted by `/transfers/bulk`
+const MAX_BULK_TRANSFERS: usize = 10000;
+
+/// Body size limit of `/transfers/bulk` and `/estimate`, fits [`MAX_BULK_TRANSFERS`] rows
+/// with all optional fields, default limit of actix allows only few thousands of them
+const MAX_BULK_BODY_SIZE: usize = 8 * 1024 * 1024;
+
+/// Rows of `/transfers/bulk` body, JSON array of [`TransactionRequest`] or CSV with
+/// [`BulkTransferCsvRow`] columns when sent as `text/csv`
+fn parse_bulk_transfers(
+    req: &HttpRequest,
+    body: &[u8],
+) -> Result<Vec<Result<TransactionRequest, String>>, String> {
+    if req.content_type() == "text/csv" {
+        let mut rdr = csv::ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .from_reader(body);
+        Ok(rdr
+            .deserialize::<BulkTransferCsvRow>()
+            .map(|row| {
+                row.map_err(|err| format!("Invalid CSV row: {}", err))
+                    .and_then(TransactionRequest::try_from)
+            })
+            .collect())
+    } else {
+        let rows = serde_json::from_slice::<Vec<TransactionRequest>>(body)
+            .map_err(|err| format!("Invalid JSON array of transfers: {}", err))?;
+        Ok(rows.into_iter().map(Ok).collect())
+    }
+}
+
+async fn bulk_transfer<S: PaymentStore>(
+    data: Data<Box<ServerData<S>>>,
+    req: HttpRequest,
+    body: web::Bytes,
+) -> actix_web::Result<HttpResponse> {
+    let rows = parse_bulk_transfers(&req, &body).map_err(ErrorBadRequest)?;
+    if rows.is_empty() {
+        return Err(ErrorBadRequest("No transfers in request"));
+    }
+    if rows.len() > MAX_BULK_TRANSFERS {
+        return Err(ErrorBadRequest(format!(
+            "Too many transfers in request, limit is {}",
+            MAX_BULK_TRANSFERS
+        )));
+    }
+
+    let mut payment_ids = HashMap::new();
+    let validated = rows
+        .into_iter()
+        .enumerate()
+        .map(|(row, request)| {
+            let (account, transfer_args) =
+                request.and_then(|request| transfer_args_from_request(&data, &request, false))?;
+            // payment id is unique per chain and sender
+            let key = (
+                transfer_args.network.clone(),
+                transfer_args.from,
+                transfer_args.payment_id.clone(),
+            );
+            if let Some(first_row) = payment_ids.insert(key, row) {
+                return Err(format!(
+                    "Duplicate payment id {}, already used in row {}",
+                    transfer_args.payment_id, first_row
+                ));
+            }
+            Ok((account, transfer_args))
+        })
+        .collect::<Vec<_>>();
+
+    let failed_response = |status: StatusCode, errors: BTreeMap<usize, String>, rows: usize| {
+        HttpResponse::build(status).json(BulkTransferResponse {
+            inserted: false,
+            results: (0..rows)
+                .map(|row| BulkTransferResult {
+                    row,
+                    created: false,
+                    transfer: None,
+                    error: errors.get(&row).cloned(),
+                })
+                .collect(),
+        })
+    };
+    let row_count = validated.len();
+    let errors = validated
+        .iter()
+        .enumerate()
+        .filter_map(|(row, res)| res.as_ref().err().map(|err| (row, err.clone())))
+        .collect::<BTreeMap<_, _>>();
+    if !errors.is_empty() {
+        return Ok(failed_response(StatusCode::BAD_REQUEST, errors, row_count));
+    }
+    let transfers = validated
+        .into_iter()
+        .filter_map(Result::ok)
+        .collect::<Vec<_>>();
+
+    let results = match data.payment_runtime.transfer_bulk(&transfers).await {
+        Ok(results) => results,
+        Err(err) => {
+            let Some(row) = err.index else {
+                return Err(actix_web::error::ErrorInternalServerError(format!(
+                    "Failed to create transfers: {}",
+                    err.error
+                )));
+            };
+            let status = match *err.error.inner {
+                ErrorBag::PaymentIdConflict(_) => StatusCode::CONFLICT,
+                ErrorBag::CustomError(_) => StatusCode::BAD_REQUEST,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            let errors = BTreeMap::from([(row, err.error.to_string())]);
+            return Ok(failed_response(status, errors, row_count));
+        }
+    };
+    let created = results
+        .iter()
+        .filter(|res| matches!(res, TransferInsertResult::Created(_)))
+        .count();
+    log::warn!(
+        "Created {} transfers in bulk, {} already existed",
+        created,
+        results.len() - created
+    );
+
+    Ok(HttpResponse::Ok().json(BulkTransferResponse {
+        inserted: true,
+        results: results
+            .into_iter()
+            .enumerate()
+            .map(|(row, res)| BulkTransferResult {
+                row,
+                created: matches!(res, TransferInsertResult::Created(_)),
+                transfer: Some(res.transfer().clone()),
+                error: None,
+            })
+            .collect(),
+    }))
+}
+
+/// Fee quote for transfers, takes the same body as `/transfers/bulk` but only JSON
+async fn estimate_transfers<S: PaymentStore>(
+    data: Data<Box<ServerData<S>>>,
+    requests: web::Json<Vec<TransactionRequest>>,
+) -> actix_web::Result<web::Json<EstimateResponse>> {
+    if requests.is_empty() {
+        return Err(ErrorBadRequest("No transfers in request"));
+    }
+    if requests.len() > MAX_BULK_TRANSFERS {
+        return Err(ErrorBadRequest(format!(
+            "Too many transfers in request, limit is {}",
+            MAX_BULK_TRANSFERS
+        )));
+    }
+    let transfers = requests
+        .iter()
+        .enumerate()
+        .map(|(row, request)| {
+            transfer_args_from_request(&data, request, false)
+                .map(|(_account, transfer_args)| transfer_args)
+                .map_err(|err| ErrorBadRequest(format!("Row {}: {}", row, err)))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let chains = data
+        .payment_runtime
+        .estimate_transfers(&transfers)
+        .await
+        .map_err(|err| {
+            actix_web::error::ErrorInternalServerError(format!("Failed to estimate fees: {}", err))
+        })?;
+    Ok(web::Json(EstimateResponse { chains }))
+}
+
+fn transfer_change_error(err: PaymentError) -> actix_web::Error {
+    match *err.inner {
+        ErrorBag::SQLxError(sqlx::Error::RowNotFound) => {
+            actix_web::error::ErrorNotFound("Transfer not found")
+        }
+        ErrorBag::TransferNotPending(_) => actix_web::error::ErrorConflict(err.to_string()),
+        ErrorBag::CustomError(_) => actix_web::error::ErrorBadRequest(err.to_string()),
+        _ => actix_web::error::ErrorInternalServerError(err.to_string()),
+    }
+}
+
+async fn transfer<S: PaymentStore>(
+    data: Data<Box<ServerData<S>>>,
+    id: web::Path<i64>,
+) -> actix_web::Result<web::Json<TransferResponse>> {
+    let transfer = data
+        .db_connection
+        .lock()
+        .await
+        .get_token_transfer(id.into_inner())
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?
+        .ok_or_else(|| actix_web::error::ErrorNotFound("Transfer not found"))?;
+    Ok(web::Json(TransferResponse { transfer }))
+}
+
+async fn cancel_transfer<S: PaymentStore>(
+    data: Data<Box<ServerData<S>>>,
+    id: web::Path<i64>,
+) -> actix_web::Result<web::Json<TransferResponse>> {
+    let transfer = data
+        .payment_runtime
+        .cancel_transfer(id.into_inner())
+        .await
+        .map_err(transfer_change_error)?;
+    log::warn!("Cancelled transfer: {:?}", transfer);
+    Ok(web::Json(TransferResponse { transfer }))
 }
 
-#[derive(Debug, Serialize)]
-pub struct StatsTransferResult {
-    request_time: f64,
-    transfers: Vec<ChainTransferRespObj>,
+async fn approve_transfer<S: PaymentStore>(
+    data: Data<Box<ServerData<S>>>,
+    req: HttpRequest,
+    id: web::Path<i64>,
+) -> actix_web::Result<web::Json<TransferResponse>> {
+    let transfer = data
+        .payment_runtime
+        .approve_transfer(id.into_inner())
+        .await
+        .map_err(transfer_change_error)?;
+    let approved_by = req
+        .extensions()
+        .get::<ApiPrincipal>()
+        .map(|principal| principal.name.clone())
+        .unwrap_or_default();
+    log::warn!("Transfer approved by {}: {:?}", approved_by, transfer);
+    Ok(web::Json(TransferResponse { transfer }))
 }
 
-#[derive(Serialize, sqlx::FromRow, Debug, Clone)]
-#[serde(rename_all = "camelCase")]
-pub struct ChainTransferRespObj {
-    pub id: i64,
-    pub from_addr: String,
-    pub receiver_addr: String,
-    pub chain_id: i64,
-    pub token_addr: Option<String>,
-    pub token_amount: String,
-    pub tx_hash: String,
-    pub block_number: i64,
-    pub fee_paid: Option<String>,
-    pub block_date: DateTime<Utc>,
-    pub block_timestamp: i64,
-    pub to_addr: String,
-    pub caller_addr: String,
+async fn edit_transfer<S: PaymentStore>(
+    data: Data<Box<ServerData<S>>>,
+    id: web::Path<i64>,
+    change: web::Json<TransferChangeRequest>,
+) -> actix_web::Result<web::Json<TransferResponse>> {
+    let receiver = change
+        .to
+        .as_ref()
+        .map(|to| Address::from_str(to))
+        .transpose()
+        .map_err(|err| ErrorBadRequest(format!("Invalid to: {}", err)))?;
+    let amount = change
+        .amount
+        .as_ref()
+        .map(|amount| U256::from_dec_str(amount))
+        .transpose()
+        .map_err(|err| ErrorBadRequest(format!("Invalid amount: {:?}", err)))?;
+    let deadline = change
+        .due_date
+        .as_ref()
+        .map(|due_date| chrono::DateTime::parse_from_rfc3339(due_date))
+        .transpose()
+        .map_err(|err| ErrorBadRequest(format!("Invalid due_date: {}", err)))?
+        .map(|due_date| due_date.naive_utc().and_utc());
+
+    let transfer = data
+        .payment_runtime
+        .edit_transfer(
+            id.into_inner(),
+            TransferChange {
+                receiver,
+                amount,
+                deadline,
+                priority: change.priority,
+            },
+        )
+        .await
+        .map_err(transfer_change_error)?;
+    log::warn!("Changed transfer: {:?}", transfer);
+    Ok(web::Json(TransferResponse { transfer }))
 }
 
-pub async fn stats_transfers(
-    data: Data<Box<ServerData>>,
+pub async fn stats_transfers<S: PaymentStore>(
+    data: Data<Box<ServerData<S>>>,
     info: web::Query<StatsTransferRequest>,
 ) -> actix_web::Result<web::Json<StatsTransferResult>> {
     let time_start = std::time::Instant::now();
@@ -766,14 +1209,16 @@ pub async fn stats_transfers(
 
     let conn = data.db_connection.lock().await.clone();
     let transf = if let Some(receiver) = account_str.as_ref() {
-        let transf =
-            get_all_chain_transfers_by_receiver_ext(&conn, chain_id, from, to, receiver, None)
-                .await;
+        let transf = conn
+            .get_all_chain_transfers_by_receiver_ext(chain_id, from, to, receiver, None)
+            .await;
         transf.map_err(|err| {
             actix_web::error::ErrorBadRequest(format!("Unknown server error: {}", err))
         })?
     } else {
-        let transf = get_all_chain_transfers_ext(&conn, chain_id, from, to, None).await;
+        let transf = conn
+            .get_all_chain_transfers_ext(chain_id, from, to, None)
+            .await;
         transf.map_err(|err| {
             actix_web::error::ErrorBadRequest(format!("Unknown server error: {}", err))
         })?
@@ -819,35 +1264,43 @@ pub async fn stats_transfers(
     }))
 }
 
-pub async fn transfers(data: Data<Box<ServerData>>, req: HttpRequest) -> impl Responder {
+pub async fn transfers<S: PaymentStore>(
+    data: Data<Box<ServerData<S>>>,
+    req: HttpRequest,
+    query: web::Query<TransferListQuery>,
+) -> impl Responder {
     let tx_id = req
         .match_info()
         .get("tx_id")
         .map(|tx_id| i64::from_str(tx_id).ok())
         .unwrap_or(None);
 
-    //let my_data = data.shared_state.lock().await;
-
-    let transfers = {
+    let limit = page_limit(query.limit);
+    let (transfers, next_cursor) = {
         let db_conn = data.db_connection.lock().await;
         if let Some(tx_id) = tx_id {
-            match get_token_transfers_by_tx(&*db_conn, tx_id).await {
-                Ok(allowances) => allowances,
-                Err(err) => {
-                    return web::Json(json!({
-                        "error": err.to_string()
-                    }));
-                }
-            }
+            let transfers = return_on_error!(db_conn.get_token_transfers_by_tx(tx_id).await);
+            (transfers, None)
         } else {
-            match get_all_token_transfers(&db_conn, None).await {
-                Ok(allowances) => allowances,
-                Err(err) => {
-                    return web::Json(json!({
-                        "error": err.to_string()
-                    }));
-                }
-            }
+            let filter = TransferListFilter {
+                status: query.status.map(|status| match status {
+                    ListStatus::Queued => TransferFilter::Queued,
+                    ListStatus::Processing => TransferFilter::Processing,
+                    ListStatus::Done => TransferFilter::Done,
+                }),
+                chain_id: query.chain,
+                sender: return_on_error!(address_filter(query.sender.as_ref())),
+                receiver: return_on_error!(address_filter(query.receiver.as_ref())),
+                payment_id: query.payment_id.clone(),
+                deposit_id: query.deposit_id.clone(),
+                created_from: query.from,
+                created_to: query.to,
+                before_id: query.cursor,
+                limit: Some(limit),
+            };
+            let transfers = return_on_error!(db_conn.list_token_transfers(&filter).await);
+            let next_cursor = next_cursor(transfers.iter().map(|tt| tt.id), limit);
+            (transfers, next_cursor)
         }
     };
 
@@ -868,24 +1321,14 @@ pub async fn transfers(data: Data<Box<ServerData>>, req: HttpRequest) -> impl Re
             })
             .collect::<Vec<_>>();
     */
-    web::Json(json!({
-        "transfers": transfers,
-    }))
-}
-
-#[derive(Serialize)]
-#[serde(rename_all = "camelCase")]
-struct AccountBalanceResponse {
-    network_id: i64,
-    account: String,
-    gas_balance: String,
-    token_balance: String,
-    block_number: u64,
-    block_date: chrono::DateTime<chrono::Utc>,
+    HttpResponse::Ok().json(TransfersResponse {
+        transfers,
+        next_cursor,
+    })
 }
 
-async fn account_balance(
-    data: Data<Box<ServerData>>,
+async fn account_balance<S: PaymentStore>(
+    data: Data<Box<ServerData<S>>>,
     req: HttpRequest,
 ) -> actix_web::Result<web::Json<AccountBalanceResponse>> {
     let account = Address::from_str(
@@ -938,7 +1381,10 @@ async fn account_balance(
     }))
 }
 
-pub async fn accounts(data: Data<Box<ServerData>>, _req: HttpRequest) -> impl Responder {
+pub async fn accounts<S: PaymentStore>(
+    data: Data<Box<ServerData<S>>>,
+    _req: HttpRequest,
+) -> impl Responder {
     //let name = req.match_info().get("name").unwrap_or("World");
     //let mut my_data = data.shared_state.lock().await;
     //my_data.inserted += 1;
@@ -952,32 +1398,49 @@ pub async fn accounts(data: Data<Box<ServerData>>, _req: HttpRequest) -> impl Re
         .map(|sk| format!("{:#x}", sk.address))
         .collect::<Vec<String>>();
 
-    web::Json(json!({
-        "publicAddr": public_addr
-    }))
+    web::Json(AccountsResponse { public_addr })
 }
 
-pub async fn account_payments_in(data: Data<Box<ServerData>>, req: HttpRequest) -> impl Responder {
+pub async fn account_payments_in<S: PaymentStore>(
+    data: Data<Box<ServerData<S>>>,
+    req: HttpRequest,
+    query: web::Query<TransferInListQuery>,
+) -> impl Responder {
     let account = return_on_error!(req.match_info().get("account").ok_or("No account provided"));
     let web3_account = return_on_error!(Address::from_str(account));
     let account = format!("{web3_account:#x}");
 
+    let limit = page_limit(query.limit);
+    let filter = TransferInListFilter {
+        chain_id: query.chain,
+        sender: return_on_error!(address_filter(query.sender.as_ref())),
+        receiver: Some(account),
+        payment_id: query.payment_id.clone(),
+        requested_from: query.from,
+        requested_to: query.to,
+        before_id: query.cursor,
+        limit: Some(limit),
+    };
     let transfers_in = {
         let db_conn = data.db_connection.lock().await;
-        return_on_error!(get_account_transfers_in(&db_conn, &account, None).await)
+        return_on_error!(db_conn.list_transfers_in(&filter).await)
     };
+    let next_cursor = next_cursor(transfers_in.iter().map(|ti| ti.id), limit);
     /*let chain_transfers = {
         let db_conn = data.db_connection.lock().await;
-        return_on_error!(get_account_chain_transfers(&db_conn, &account).await)
+        return_on_error!(get_account_chain_transfers(&*db_conn, &account).await)
     };*/
 
-    web::Json(json!({
-        "transfersIn": transfers_in,
-     //   "chainTransfers": chain_transfers,
-    }))
+    HttpResponse::Ok().json(AccountTransfersInResponse {
+        transfers_in,
+        next_cursor,
+    })
 }
 
-pub async fn account_details(data: Data<Box<ServerData>>, req: HttpRequest) -> impl Responder {
+pub async fn account_details<S: PaymentStore>(
+    data: Data<Box<ServerData<S>>>,
+    req: HttpRequest,
+) -> impl Responder {
     let account = return_on_error!(req.match_info().get("account").ok_or("No account provided"));
 
     let web3_account = return_on_error!(Address::from_str(account));
@@ -1000,7 +1463,7 @@ pub async fn account_details(data: Data<Box<ServerData>>, req: HttpRequest) -> i
     };
     let allowances = {
         let db_conn = data.db_connection.lock().await;
-        return_on_error!(get_allowances_by_owner(&db_conn, &account).await)
+        return_on_error!(db_conn.get_allowances_by_owner(&account).await)
     };
 
     let mut queued_transfer_count = 0;
@@ -1011,26 +1474,24 @@ pub async fn account_details(data: Data<Box<ServerData>>, req: HttpRequest) -> i
         queued_transfer_count = {
             let db_conn = data.db_connection.lock().await;
             return_on_error!(
-                get_transfer_count(&db_conn, Some(TRANSFER_FILTER_QUEUED), Some(&account), None)
+                db_conn
+                    .get_transfer_count(TransferFilter::Queued, Some(&account), None)
                     .await
             )
         };
         processed_transfer_count = {
             let db_conn = data.db_connection.lock().await;
             return_on_error!(
-                get_transfer_count(
-                    &db_conn,
-                    Some(TRANSFER_FILTER_PROCESSING),
-                    Some(&account),
-                    None
-                )
-                .await
+                db_conn
+                    .get_transfer_count(TransferFilter::Processing, Some(&account), None)
+                    .await
             )
         };
         done_transfer_count = {
             let db_conn = data.db_connection.lock().await;
             return_on_error!(
-                get_transfer_count(&db_conn, Some(TRANSFER_FILTER_DONE), Some(&account), None)
+                db_conn
+                    .get_transfer_count(TransferFilter::Done, Some(&account), None)
                     .await
             )
         };
@@ -1039,18 +1500,81 @@ pub async fn account_details(data: Data<Box<ServerData>>, req: HttpRequest) -> i
         let db_conn = data.db_connection.lock().await;
 
         return_on_error!(
-            get_transfer_count(&db_conn, Some(TRANSFER_FILTER_ALL), None, Some(&account)).await
+            db_conn
+                .get_transfer_count(TransferFilter::All, None, Some(&account))
+                .await
         )
     };
+    let pending_payouts = {
+        let db_conn = data.db_connection.lock().await;
+        let pending = return_on_error!(
+            db_conn
+                .get_pending_token_transfers_by_receiver(web3_account)
+                .await
+        );
+        pending_payouts(&data.payment_setup.payout_rules, &account, pending)
+    };
 
-    web::Json(json!({
-        "account": account,
-        "allowances": allowances,
-        "transfersQueued": queued_transfer_count,
-        "transfersProcessing": processed_transfer_count,
-        "transfersDone": done_transfer_count,
-        "receivedTransfers": received_transfer_count,
-    }))
+    HttpResponse::Ok().json(AccountDetailsResponse {
+        account,
+        allowances,
+        transfers_queued: queued_transfer_count,
+        transfers_processing: processed_transfer_count,
+        transfers_done: done_transfer_count,
+        received_transfers: received_transfer_count,
+        pending_payouts,
+    })
+}
+
+pub async fn webhook_deliveries<S: PaymentStore>(
+    data: Data<Box<ServerData<S>>>,
+    query: web::Query<WebhookDeliveryListQuery>,
+) -> impl Responder {
+    let limit = page_limit(query.limit);
+    let filter = WebhookDeliveryListFilter {
+        status: query.status.map(|status| match status {
+            DeliveryStatus::Pending => WebhookDeliveryStatus::Pending,
+            DeliveryStatus::Delivered => WebhookDeliveryStatus::Delivered,
+            DeliveryStatus::Failed => WebhookDeliveryStatus::Failed,
+        }),
+        webhook: query.webhook.clone(),
+        event_type: query.event.clone(),
+        before_id: query.cursor,
+        limit: Some(limit),
+    };
+    let deliveries = {
+        let db_conn = data.db_connection.lock().await;
+        return_on_error!(db_conn.list_webhook_deliveries(&filter).await)
+    };
+    let next_cursor = next_cursor(deliveries.iter().map(|d| d.id), limit);
+    HttpResponse::Ok().json(WebhookDeliveriesResponse {
+        deliveries,
+        next_cursor,
+    })
+}
+
+async fn retry_webhook<S: PaymentStore>(
+    data: Data<Box<ServerData<S>>>,
+    id: web::Path<i64>,
+) -> actix_web::Result<web::Json<WebhookDeliveryResponse>> {
+    let delivery = {
+        let db_conn = data.db_connection.lock().await;
+        retry_webhook_delivery(&*db_conn, id.into_inner())
+            .await
+            .map_err(|err| match *err.inner {
+                ErrorBag::SQLxError(sqlx::Error::RowNotFound) => {
+                    actix_web::error::ErrorNotFound("Delivery not found")
+                }
+                ErrorBag::CustomError(_) => actix_web::error::ErrorConflict(err.to_string()),
+                _ => actix_web::error::ErrorInternalServerError(err.to_string()),
+            })?
+    };
+    log::info!(
+        "Webhook {} delivery {} scheduled for retry",
+        delivery.webhook,
+        delivery.id
+    );
+    Ok(web::Json(WebhookDeliveryResponse { delivery }))
 }
 
 pub async fn redirect_to_slash(req: HttpRequest) -> impl Responder {
@@ -1075,15 +1599,51 @@ pub async fn metrics(_req: HttpRequest) -> impl Responder {
     })
 }
 
+pub async fn openapi_endpoint(_req: HttpRequest) -> impl Responder {
+    web::Json(openapi_document())
+}
+
 pub async fn greet(_req: HttpRequest) -> impl Responder {
     const VERSION: &str = env!("CARGO_PKG_VERSION");
-    web::Json(json!({
-        "name": "erc20_payment_lib",
-        "version": VERSION,
-    }))
+    web::Json(VersionResponse {
+        name: "erc20_payment_lib".to_string(),
+        version: VERSION.to_string(),
+    })
 }
 
-pub async fn faucet(data: Data<Box<ServerData>>, req: HttpRequest) -> impl Responder {
+/// Client IP used for faucet limits, see `trust-forwarded-for` faucet setting
+fn client_ip(req: &HttpRequest, trust_forwarded_for: bool) -> Option<String> {
+    if trust_forwarded_for {
+        if let Some(addr) = req.connection_info().realip_remote_addr() {
+            // peer address is returned with port when there is no forwarding header
+            return Some(
+                SocketAddr::from_str(addr)
+                    .map(|addr| addr.ip().to_string())
+                    .unwrap_or_else(|_| addr.to_string()),
+            );
+        }
+    }
+    req.peer_addr().map(|addr| addr.ip().to_string())
+}
+
+fn faucet_denied(denied: FaucetDenied) -> HttpResponse {
+    let error = ApiError {
+        error: denied.to_string(),
+    };
+    match denied {
+        FaucetDenied::Limited {
+            retry_after_secs, ..
+        } => HttpResponse::TooManyRequests()
+            .insert_header((header::RETRY_AFTER, retry_after_secs.to_string()))
+            .json(error),
+        FaucetDenied::Captcha(_) => HttpResponse::Forbidden().json(error),
+    }
+}
+
+pub async fn faucet<S: PaymentStore>(
+    data: Data<Box<ServerData<S>>>,
+    req: HttpRequest,
+) -> impl Responder {
     let target_addr = req.match_info().get("addr").unwrap_or("");
     let chain_id = req.match_info().get("chain").unwrap_or("");
     if !target_addr.is_empty() {
@@ -1096,54 +1656,6 @@ pub async fn faucet(data: Data<Box<ServerData>>, req: HttpRequest) -> impl Respo
             .chain_setup
             .get(&(chain_id))
             .ok_or("No config for given chain id"));
-        let faucet_event_idx = format!("{receiver_addr:#x}_{chain_id}");
-
-        {
-            let mut shared_state = data.shared_state.lock().unwrap();
-            let faucet_data = match shared_state.faucet {
-                Some(ref mut faucet_data) => faucet_data,
-                None => {
-                    shared_state.faucet = Some(FaucetData {
-                        faucet_events: BTreeMap::new(),
-                        last_cleanup: chrono::Utc::now(),
-                    });
-                    shared_state
-                        .faucet
-                        .as_mut()
-                        .expect("Faucet data should be set here")
-                }
-            };
-
-            const MIN_SECONDS: i64 = 120;
-            if let Some(el) = faucet_data.faucet_events.get(&faucet_event_idx) {
-                let ago = (chrono::Utc::now().time() - el.time()).num_seconds();
-                if ago < MIN_SECONDS {
-                    return web::Json(json!({
-                        "error": format!("Already sent to this address {ago} seconds ago. Try again after {MIN_SECONDS} seconds")
-                    }));
-                } else {
-                    faucet_data
-                        .faucet_events
-                        .insert(faucet_event_idx, chrono::Utc::now());
-                }
-            } else {
-                faucet_data
-                    .faucet_events
-                    .insert(faucet_event_idx, chrono::Utc::now());
-            }
-
-            //faucet data cleanup
-            const FAUCET_CLEANUP_AFTER: i64 = 120;
-            let curr_time = chrono::Utc::now();
-            if (curr_time.time() - faucet_data.last_cleanup.time()).num_seconds()
-                > FAUCET_CLEANUP_AFTER
-            {
-                faucet_data.last_cleanup = curr_time;
-                faucet_data
-                    .faucet_events
-                    .retain(|_, v| (curr_time.time() - v.time()).num_seconds() < MIN_SECONDS);
-            }
-        }
 
         let glm_address = chain.glm_address;
 
@@ -1163,6 +1675,45 @@ pub async fn faucet(data: Data<Box<ServerData>>, req: HttpRequest) -> impl Respo
             .faucet_glm_amount
             .ok_or("Faucet GLM amount not set on chain"));
 
+        let client_ip = client_ip(&req, data.faucet.trust_forwarded_for());
+        let captcha_token = req
+            .headers()
+            .get(CAPTCHA_TOKEN_HEADER)
+            .and_then(|h| h.to_str().ok());
+        if let Err(denied) = data
+            .faucet
+            .verify_captcha(captcha_token, client_ip.as_deref())
+            .await
+        {
+            return faucet_denied(denied);
+        }
+
+        // connection stays locked until the request is stored, so parallel requests
+        // cannot pass the limits together
+        let db_conn = data.db_connection.lock().await;
+        let now = Utc::now();
+        if let Some(denied) = return_on_error!(
+            data.faucet
+                .check(
+                    &*db_conn,
+                    chain_id,
+                    chain.faucet_daily_limit,
+                    &format!("{:#x}", receiver_addr),
+                    client_ip.as_deref(),
+                    now,
+                )
+                .await
+        ) {
+            log::info!(
+                "Faucet request for {:#x} on chain {} denied: {}",
+                receiver_addr,
+                chain_id,
+                denied
+            );
+            return faucet_denied(denied);
+        }
+
+        let db_transaction = return_on_error!(db_conn.begin().await);
         let token_transfer_eth = {
             let tt = create_token_transfer(
                 from,
@@ -1173,8 +1724,7 @@ pub async fn faucet(data: Data<Box<ServerData>>, req: HttpRequest) -> impl Respo
                 faucet_eth_amount,
                 None,
             );
-            let db_conn = data.db_connection.lock().await;
-            return_on_error!(insert_token_transfer(&*db_conn, &tt).await)
+            return_on_error!(db_transaction.insert_token_transfer(&tt).await)
         };
         let token_transfer_glm = {
             let tt = create_token_transfer(
@@ -1186,39 +1736,59 @@ pub async fn faucet(data: Data<Box<ServerData>>, req: HttpRequest) -> impl Respo
                 faucet_glm_amount,
                 None,
             );
-            let db_conn = data.db_connection.lock().await;
-            return_on_error!(insert_token_transfer(&*db_conn, &tt).await)
+            return_on_error!(db_transaction.insert_token_transfer(&tt).await)
         };
+        return_on_error!(
+            db_transaction
+                .insert_faucet_request(&FaucetRequestDbObj {
+                    id: 0,
+                    chain_id,
+                    receiver_addr: format!("{:#x}", receiver_addr),
+                    client_ip,
+                    gas_transfer_id: Some(token_transfer_eth.id),
+                    gas_amount: faucet_eth_amount.to_string(),
+                    glm_transfer_id: Some(token_transfer_glm.id),
+                    glm_amount: faucet_glm_amount.to_string(),
+                    create_date: now,
+                })
+                .await
+        );
+        return_on_error!(db_transaction.commit().await);
 
-        return web::Json(json!({
-        "transfer_gas_id": token_transfer_eth.id,
-        "transfer_gas_payment_id": token_transfer_eth.payment_id,
-        "transfer_glm_id": token_transfer_glm.id,
-        "transfer_glm_payment_id": token_transfer_glm.payment_id,
-                }));
+        return HttpResponse::Ok().json(FaucetResponse {
+            transfer_gas_id: token_transfer_eth.id,
+            transfer_gas_payment_id: token_transfer_eth.payment_id,
+            transfer_glm_id: token_transfer_glm.id,
+            transfer_glm_payment_id: token_transfer_glm.payment_id,
+        });
     }
 
-    web::Json(json!({
-        "status": "faucet enabled"
-    }))
-}
-
-#[derive(Debug, Serialize)]
-struct AttestationItemInfo {
-    name: String,
-    #[serde(rename = "type")]
-    typ: String,
-    value: serde_json::Value,
+    HttpResponse::Ok().json(FaucetStatusResponse {
+        status: "faucet enabled".to_string(),
+    })
 }
 
-#[derive(Debug, Serialize)]
-#[serde(rename_all = "camelCase")]
-pub struct AttestationCheckResult {
-    chain_id: u64,
-    chain: String,
-    attestation: Attestation,
-    schema: AttestationSchema,
-    params: Vec<AttestationItemInfo>,
+pub async fn faucet_history<S: PaymentStore>(
+    data: Data<Box<ServerData<S>>>,
+    query: web::Query<FaucetHistoryQuery>,
+) -> impl Responder {
+    let limit = page_limit(query.limit);
+    let filter = FaucetRequestListFilter {
+        chain_id: query.chain,
+        receiver: return_on_error!(address_filter(query.address.as_ref())),
+        before_id: query.cursor,
+        limit: Some(limit),
+        ..Default::default()
+    };
+    let requests = {
+        let db_conn = data.db_connection.lock().await;
+        return_on_error!(db_conn.list_faucet_requests(&filter).await)
+    };
+    let next_cursor = next_cursor(requests.iter().map(|r| r.id), limit);
+    HttpResponse::Ok().json(FaucetHistoryResponse {
+        requests,
+        next_cursor,
+    })
 }
 
 fn ethabi_token_to_json(token: &ethabi::Token) -> serde_json::Value {
@@ -1256,8 +1826,8 @@ fn ethabi_token_to_json(token: &ethabi::Token) -> serde_json::Value {
     }
 }
 
-pub async fn check_attestation(
-    data: Data<Box<ServerData>>,
+pub async fn check_attestation<S: PaymentStore>(
+    data: Data<Box<ServerData<S>>>,
     req: HttpRequest,
 ) -> actix_web::Result<web::Json<AttestationCheckResult>> {
     let attestation_uid = req.match_info().get("uid").unwrap_or("");
@@ -1410,73 +1980,286 @@ pub async fn check_attestation(
     }))
 }
 
-pub fn runtime_web_scope(
+pub fn runtime_web_scope<S: PaymentStore>(
     scope: Scope,
-    server_data: Data<Box<ServerData>>,
+    server_data: Data<Box<ServerData<S>>>,
     enable_faucet: bool,
     enable_transfers: bool,
     debug: bool,
     frontend: bool,
 ) -> Scope {
+    let auth = server_data.auth.clone();
     let api_scope = Scope::new("/api");
     let mut api_scope = api_scope
         .app_data(server_data)
         .route(
             "/attestation/{chain}/{uid}",
-            web::get().to(check_attestation),
+            web::get()
+                .to(check_attestation::<S>)
+                .wrap(auth.require(ApiRole::ReadOnly)),
+        )
+        .route(
+            "/allowances",
+            web::get()
+                .to(allowances::<S>)
+                .wrap(auth.require(ApiRole::ReadOnly)),
+        )
+        .route(
+            "/balance/{account}/{chain}",
+            web::get()
+                .to(account_balance::<S>)
+                .wrap(auth.require(ApiRole::ReadOnly)),
+        )
+        .route(
+            "/rpc_pool",
+            web::get()
+                .to(rpc_pool::<S>)
+                .wrap(auth.require(ApiRole::ReadOnly)),
+        )
+        .route(
+            "/rpc_pool/metrics",
+            web::get()
+                .to(rpc_pool_metrics::<S>)
+                .wrap(auth.require(ApiRole::ReadOnly)),
+        )
+        .route(
+            "/rpc_pool/{chain}/endpoints",
+            web::post()
+                .to(rpc_pool_change_endpoints::<S>)
+                .wrap(auth.require(ApiRole::Operator)),
+        )
+        .route(
+            "/config",
+            web::get()
+                .to(config_endpoint::<S>)
+                .wrap(auth.require(ApiRole::ReadOnly)),
+        )
+        .route(
+            "/stats/transfers",
+            web::get()
+                .to(stats_transfers::<S>)
+                .wrap(auth.require(ApiRole::ReadOnly)),
+        )
+        .route(
+            "/transactions",
+            web::get()
+                .to(transactions::<S>)
+                .wrap(auth.require(ApiRole::ReadOnly)),
+        )
+        .route(
+            "/transactions/count",
+            web::get()
+                .to(transactions_count::<S>)
+                .wrap(auth.require(ApiRole::ReadOnly)),
+        )
+        .route(
+            "/transactions/next",
+            web::get()
+                .to(transactions_next::<S>)
+                .wrap(auth.require(ApiRole::ReadOnly)),
         )
-        .route("/allowances", web::get().to(allowances))
-        .route("/balance/{account}/{chain}", web::get().to(account_balance))
-        .route("/rpc_pool", web::get().to(rpc_pool))
-        .route("/rpc_pool/metrics", web::get().to(rpc_pool_metrics))
-        .route("/config", web::get().to(config_endpoint))
-        .route("/stats/transfers", web::get().to(stats_transfers))
-        .route("/transactions", web::get().to(transactions))
-        .route("/transactions/count", web::get().to(transactions_count))
-        .route("/transactions/next", web::get().to(transactions_next))
         .route(
             "/transactions/feed/{prev}/{next}",
-            web::get().to(transactions_feed),
+            web::get()
+                .to(transactions_feed::<S>)
+                .wrap(auth.require(ApiRole::ReadOnly)),
         )
         .route(
             "/transactions/next/{count}",
-            web::get().to(transactions_next),
+            web::get()
+                .to(transactions_next::<S>)
+                .wrap(auth.require(ApiRole::ReadOnly)),
+        )
+        .route(
+            "/transactions/current",
+            web::get()
+                .to(transactions_current::<S>)
+                .wrap(auth.require(ApiRole::ReadOnly)),
         )
-        .route("/transactions/current", web::get().to(transactions_current))
         .route(
             "/transactions/last",
-            web::get().to(transactions_last_processed),
+            web::get()
+                .to(transactions_last_processed::<S>)
+                .wrap(auth.require(ApiRole::ReadOnly)),
         )
         .route(
             "/transactions/last/{count}",
-            web::get().to(transactions_last_processed),
+            web::get()
+                .to(transactions_last_processed::<S>)
+                .wrap(auth.require(ApiRole::ReadOnly)),
+        )
+        .route(
+            "/tx/skip/{tx_id}",
+            web::post()
+                .to(skip_pending_operation::<S>)
+                .wrap(auth.require(ApiRole::Operator)),
+        )
+        .route(
+            "/tx/{tx_id}",
+            web::get()
+                .to(tx_details::<S>)
+                .wrap(auth.require(ApiRole::ReadOnly)),
+        )
+        .service(
+            web::resource("/estimate")
+                .app_data(web::JsonConfig::default().limit(MAX_BULK_BODY_SIZE))
+                .route(
+                    web::post()
+                        .to(estimate_transfers::<S>)
+                        .wrap(auth.require(ApiRole::ReadOnly)),
+                ),
+        )
+        .route(
+            "/transfers",
+            web::get()
+                .to(transfers::<S>)
+                .wrap(auth.require(ApiRole::ReadOnly)),
+        )
+        .route(
+            "/transfers/{tx_id}",
+            web::get()
+                .to(transfers::<S>)
+                .wrap(auth.require(ApiRole::ReadOnly)),
+        )
+        .route(
+            "/transfers/id/{id}",
+            web::get()
+                .to(transfer::<S>)
+                .wrap(auth.require(ApiRole::ReadOnly)),
+        )
+        .route(
+            "/transfers/id/{id}/approve",
+            web::post()
+                .to(approve_transfer::<S>)
+                .wrap(auth.require(ApiRole::Operator)),
+        )
+        .route(
+            "/accounts",
+            web::get()
+                .to(accounts::<S>)
+                .wrap(auth.require(ApiRole::ReadOnly)),
+        )
+        .route(
+            "/account/{account}",
+            web::get()
+                .to(account_details::<S>)
+                .wrap(auth.require(ApiRole::ReadOnly)),
+        )
+        .route(
+            "/account/{account}/in",
+            web::get()
+                .to(account_payments_in::<S>)
+                .wrap(auth.require(ApiRole::ReadOnly)),
+        )
+        .route(
+            "/webhooks/deliveries",
+            web::get()
+                .to(webhook_deliveries::<S>)
+                .wrap(auth.require(ApiRole::ReadOnly)),
+        )
+        .route(
+            "/webhooks/deliveries/{id}/retry",
+            web::post()
+                .to(retry_webhook::<S>)
+                .wrap(auth.require(ApiRole::Operator)),
+        )
+        .route(
+            "/metrics",
+            web::get().to(metrics).wrap(auth.require(ApiRole::ReadOnly)),
+        )
+        .route(
+            "/",
+            web::get().to(greet).wrap(auth.require(ApiRole::ReadOnly)),
         )
-        .route("/tx/skip/{tx_id}", web::post().to(skip_pending_operation))
-        .route("/tx/{tx_id}", web::get().to(tx_details))
-        .route("/transfers", web::get().to(transfers))
-        .route("/transfers/{tx_id}", web::get().to(transfers))
-        .route("/accounts", web::get().to(accounts))
-        .route("/account/{account}", web::get().to(account_details))
-        .route("/account/{account}/in", web::get().to(account_payments_in))
-        .route("/metrics", web::get().to(metrics))
-        .route("/", web::get().to(greet))
         .route(
             "/event_stream",
-            web::get().to(event_stream_websocket_endpoint),
+            web::get()
+                .to(event_stream_websocket_endpoint::<S>)
+                .wrap(auth.require(ApiRole::ReadOnly)),
+        )
+        .route(
+            "/event_stream/sse",
+            web::get()
+                .to(event_stream_sse_endpoint::<S>)
+                .wrap(auth.require(ApiRole::ReadOnly)),
         )
-        .route("/version", web::get().to(greet));
+        .route(
+            "/version",
+            web::get().to(greet).wrap(auth.require(ApiRole::ReadOnly)),
+        )
+        .route(
+            "/openapi.json",
+            web::get()
+                .to(openapi_endpoint)
+                .wrap(auth.require(ApiRole::ReadOnly)),
+        );
 
     if enable_transfers {
-        api_scope = api_scope.route("/transfers/new", web::post().to(new_transfer))
+        api_scope = api_scope
+            .route(
+                "/transfers/new",
+                web::post()
+                    .to(new_transfer::<S>)
+                    .wrap(auth.require(ApiRole::Payer)),
+            )
+            .route(
+                "/transfers/create",
+                web::post()
+                    .to(create_transfer::<S>)
+                    .wrap(auth.require(ApiRole::Payer)),
+            )
+            .service(
+                // body signature is checked on the same limited payload
+                web::resource("/transfers/bulk")
+                    .app_data(web::PayloadConfig::new(MAX_BULK_BODY_SIZE))
+                    .route(
+                        web::post()
+                            .to(bulk_transfer::<S>)
+                            .wrap(auth.require(ApiRole::Payer)),
+                    ),
+            )
+            .route(
+                "/transfers/id/{id}",
+                web::delete()
+                    .to(cancel_transfer::<S>)
+                    .wrap(auth.require(ApiRole::Payer)),
+            )
+            .route(
+                "/transfers/id/{id}",
+                web::patch()
+                    .to(edit_transfer::<S>)
+                    .wrap(auth.require(ApiRole::Payer)),
+            )
     }
     if enable_faucet {
         log::info!("Faucet endpoints enabled");
-        api_scope = api_scope.route("/faucet", web::get().to(faucet));
-        api_scope = api_scope.route("/faucet/{chain}/{addr}", web::get().to(faucet));
+        api_scope = api_scope.route(
+            "/faucet",
+            web::get()
+                .to(faucet::<S>)
+                .wrap(auth.require(ApiRole::Faucet)),
+        );
+        api_scope = api_scope.route(
+            "/faucet/history",
+            web::get()
+                .to(faucet_history::<S>)
+                .wrap(auth.require(ApiRole::Faucet)),
+        );
+        api_scope = api_scope.route(
+            "/faucet/{chain}/{addr}",
+            web::get()
+                .to(faucet::<S>)
+                .wrap(auth.require(ApiRole::Faucet)),
+        );
     }
     if debug {
         log::info!("Debug endpoints enabled");
-        api_scope = api_scope.route("/debug", web::get().to(debug_endpoint));
+        api_scope = api_scope.route(
+            "/debug",
+            web::get()
+                .to(debug_endpoint::<S>)
+                .wrap(auth.require(ApiRole::Operator)),
+        );
     }
 
     // Add version endpoint to /api, /api/ and /api/version
@@ -1504,3 +2287,323 @@ pub fn runtime_web_scope(
     }
     scope
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::config::{
+        AdditionalOptions, ApiAuthSettings, ApiHmacKeySettings, ApiSecret, Config,
+    };
+    use crate::misc::load_private_keys;
+    use crate::runtime::PaymentRuntimeArgs;
+    use crate::server::auth::{
+        sign_request, HMAC_KEY_ID_HEADER, HMAC_SIGNATURE_HEADER, HMAC_TIMESTAMP_HEADER,
+    };
+    use crate::signer::PrivateKeySigner;
+    use actix_web::{test, App};
+    use erc20_payment_lib_common::store::MemoryStore;
+
+    pub(crate) const TEST_CHAIN_ID: i64 = 17000;
+    pub(crate) const ADMIN_TOKEN: &str = "admin-token";
+
+    /// Server data on memory store with one account and no service loop, so nothing is sent
+    pub(crate) async fn test_server_data() -> (Data<Box<ServerData<MemoryStore>>>, Address) {
+        test_server_data_with_auth(ApiAuth::new(None, Some(ADMIN_TOKEN.to_string()))).await
+    }
+
+    pub(crate) async fn test_server_data_with_auth(
+        auth: ApiAuth,
+    ) -> (Data<Box<ServerData<MemoryStore>>>, Address) {
+        let (secret_keys, addresses) =
+            load_private_keys("0101010101010101010101010101010101010101010101010101010101010101")
+                .unwrap();
+        let signer = PrivateKeySigner::new(secret_keys.clone());
+        let conn = MemoryStore::new();
+        let runtime = PaymentRuntime::new_with_store(
+            PaymentRuntimeArgs {
+                secret_keys,
+                db_filename: Default::default(),
+                config: Config::default_config(),
+                conn: None,
+                options: Some(AdditionalOptions {
+                    skip_service_loop: true,
+                    ..Default::default()
+                }),
+                broadcast_sender: None,
+                mspc_sender: None,
+                extra_testing: None,
+            },
+            conn.clone(),
+            Arc::new(Box::new(signer)),
+        )
+        .await
+        .unwrap();
+        let data = Data::new(Box::new(ServerData {
+            shared_state: runtime.shared_state.clone(),
+            db_connection: Arc::new(Mutex::new(conn)),
+            payment_setup: runtime.setup.clone(),
+            payment_runtime: runtime,
+            auth: Arc::new(auth),
+            faucet: FaucetLimits::new(None).unwrap(),
+        }));
+        (data, addresses[0])
+    }
+
+    pub(crate) fn transfer_request(from: Address, payment_id: &str) -> TransactionRequest {
+        TransactionRequest {
+            from: format!("{:#x}", from),
+            to: format!("{:#x}", Address::from_low_u64_be(0x1234)),
+            token: None,
+            amount: "1000".to_string(),
+            chain: TEST_CHAIN_ID,
+            due_date: None,
+            payment_id: Some(payment_id.to_string()),
+            deposit_id: None,
+            priority: None,
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_parse_bulk_transfers() {
+        let req = test::TestRequest::default()
+            .insert_header((header::CONTENT_TYPE, "text/csv"))
+            .to_http_request();
+        let csv = "from,to,amount,chain,paymentId\n\
+                   0x01,0x02,1,17000,p1\n\
+                   0x01,0x02,2,not-a-chain,p2\n\
+                   0x01,0x02,3,17000,p3\n";
+        let rows = parse_bulk_transfers(&req, csv.as_bytes()).unwrap();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0].as_ref().unwrap().payment_id.as_deref(), Some("p1"));
+        // error is reported at position of the bad row, following rows are still parsed
+        let err = rows[1].as_ref().unwrap_err();
+        assert!(err.starts_with("Invalid CSV row"), "{err}");
+        assert_eq!(rows[2].as_ref().unwrap().amount, "3");
+
+        let req = test::TestRequest::default().to_http_request();
+        assert!(parse_bulk_transfers(&req, br#"{"from": "0x01"}"#).is_err());
+        let rows = parse_bulk_transfers(
+            &req,
+            br#"[{"from": "0x01", "to": "0x02", "amount": "1", "chain": 17000}]"#,
+        )
+        .unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].as_ref().unwrap().payment_id, None);
+    }
+
+    #[actix_web::test]
+    async fn test_bulk_transfer_all_or_nothing() {
+        let (data, from) = test_server_data().await;
+        let app = test::init_service(App::new().service(runtime_web_scope(
+            Scope::new(""),
+            data.clone(),
+            false,
+            true,
+            false,
+            false,
+        )))
+        .await;
+        let bulk = |body: Vec<TransactionRequest>| {
+            test::TestRequest::post()
+                .uri("/api/transfers/bulk")
+                .insert_header((header::AUTHORIZATION, format!("Bearer {}", ADMIN_TOKEN)))
+                .set_json(body)
+                .to_request()
+        };
+        let stored = || async {
+            data.db_connection
+                .lock()
+                .await
+                .get_all_token_transfers(None)
+                .await
+                .unwrap()
+                .len()
+        };
+
+        // duplicate payment id is reported on the second row, nothing is inserted
+        let res: BulkTransferResponse = test::call_and_read_body_json(
+            &app,
+            bulk(vec![
+                transfer_request(from, "bulk-1"),
+                transfer_request(from, "bulk-2"),
+                transfer_request(from, "bulk-1"),
+            ]),
+        )
+        .await;
+        assert!(!res.inserted);
+        assert_eq!(res.results.len(), 3);
+        assert!(res.results[..2].iter().all(|r| r.error.is_none()));
+        assert_eq!(res.results[2].row, 2);
+        let err = res.results[2].error.as_deref().unwrap();
+        assert!(err.contains("already used in row 0"), "{err}");
+        assert_eq!(stored().await, 0);
+
+        // single invalid row rejects the whole request
+        let mut invalid = transfer_request(from, "bulk-3");
+        invalid.amount = "-1".to_string();
+        let resp =
+            test::call_service(&app, bulk(vec![transfer_request(from, "bulk-1"), invalid])).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let res: BulkTransferResponse = test::read_body_json(resp).await;
+        assert!(!res.inserted);
+        assert!(res.results[0].error.is_none());
+        assert!(res.results[1].error.is_some());
+        assert_eq!(stored().await, 0);
+
+        let res: BulkTransferResponse = test::call_and_read_body_json(
+            &app,
+            bulk(vec![
+                transfer_request(from, "bulk-1"),
+                transfer_request(from, "bulk-2"),
+            ]),
+        )
+        .await;
+        assert!(res.inserted);
+        assert!(res.results.iter().all(|r| r.created));
+        assert_eq!(stored().await, 2);
+    }
+
+    #[actix_web::test]
+    async fn test_bulk_transfer_large_body() {
+        let settings = ApiAuthSettings {
+            anonymous_roles: None,
+            tokens: None,
+            hmac_keys: Some(vec![ApiHmacKeySettings {
+                key_id: "billing".to_string(),
+                secret: ApiSecret::new("billing-secret".to_string()),
+                roles: vec![ApiRole::Payer],
+            }]),
+            hmac_max_skew_secs: None,
+            client_cert_header: None,
+            trusted_proxies: None,
+            client_certs: None,
+        };
+        let (data, from) = test_server_data_with_auth(ApiAuth::new(Some(&settings), None)).await;
+        let app = test::init_service(App::new().service(runtime_web_scope(
+            Scope::new(""),
+            data.clone(),
+            false,
+            true,
+            false,
+            false,
+        )))
+        .await;
+        // body signature is checked over the whole body, above default payload limit
+        let signed = |body: Vec<u8>| {
+            let now = Utc::now().timestamp();
+            let signature =
+                sign_request("billing-secret", now, "POST", "/api/transfers/bulk", &body);
+            test::TestRequest::post()
+                .uri("/api/transfers/bulk")
+                .insert_header((header::CONTENT_TYPE, "application/json"))
+                .insert_header((HMAC_KEY_ID_HEADER, "billing"))
+                .insert_header((HMAC_TIMESTAMP_HEADER, now.to_string()))
+                .insert_header((HMAC_SIGNATURE_HEADER, signature))
+                .set_payload(body)
+                .to_request()
+        };
+
+        let requests = (0..2000)
+            .map(|idx| transfer_request(from, &format!("large-{}", idx)))
+            .collect::<Vec<_>>();
+        let body = serde_json::to_vec(&requests).unwrap();
+        assert!(body.len() > 256 * 1024);
+        let res: BulkTransferResponse = test::call_and_read_body_json(&app, signed(body)).await;
+        assert!(res.inserted);
+        assert_eq!(res.results.len(), 2000);
+
+        let status =
+            match test::try_call_service(&app, signed(vec![b' '; MAX_BULK_BODY_SIZE + 1])).await {
+                Ok(res) => res.status(),
+                Err(err) => err.as_response_error().status_code(),
+            };
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[actix_web::test]
+    async fn test_transfer_by_id_routes() {
+        let (data, from) = test_server_data().await;
+        let app = test::init_service(App::new().service(runtime_web_scope(
+            Scope::new(""),
+            data.clone(),
+            false,
+            true,
+            false,
+            false,
+        )))
+        .await;
+        let request = |req: test::TestRequest, uri: &str| {
+            req.uri(uri)
+                .insert_header((header::AUTHORIZATION, format!("Bearer {}", ADMIN_TOKEN)))
+                .to_request()
+        };
+
+        let mut new_transfer = transfer_request(from, "by-id-1");
+        new_transfer.due_date = Some("2030-01-01T00:00:00Z".to_string());
+        let resp = test::call_service(
+            &app,
+            request(
+                test::TestRequest::post().set_json(new_transfer),
+                "/api/transfers/create",
+            ),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let id = data
+            .db_connection
+            .lock()
+            .await
+            .get_all_token_transfers(None)
+            .await
+            .unwrap()[0]
+            .id;
+        let uri = format!("/api/transfers/id/{}", id);
+
+        let res: TransferResponse =
+            test::call_and_read_body_json(&app, request(test::TestRequest::get(), &uri)).await;
+        assert_eq!(
+            res.transfer.deadline.map(|d| d.to_rfc3339()).as_deref(),
+            Some("2030-01-01T00:00:00+00:00")
+        );
+
+        // deadline is stored with the transfer, other fields are kept
+        let change = TransferChangeRequest {
+            due_date: Some("2031-01-01T00:00:00Z".to_string()),
+            ..Default::default()
+        };
+        let res: TransferResponse = test::call_and_read_body_json(
+            &app,
+            request(test::TestRequest::patch().set_json(&change), &uri),
+        )
+        .await;
+        assert_eq!(res.transfer.token_amount, "1000");
+        let stored = data
+            .db_connection
+            .lock()
+            .await
+            .get_token_transfer(id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            stored.deadline.map(|d| d.to_rfc3339()).as_deref(),
+            Some("2031-01-01T00:00:00+00:00")
+        );
+
+        // transfer id is not accepted where transaction id is expected
+        let resp = test::call_service(
+            &app,
+            request(
+                test::TestRequest::patch().set_json(&change),
+                &format!("/api/transfers/{}", id),
+            ),
+        )
+        .await;
+        assert!(!resp.status().is_success());
+
+        let resp = test::call_service(&app, request(test::TestRequest::delete(), &uri)).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = test::call_service(&app, request(test::TestRequest::get(), &uri)).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -1,20 +1,23 @@
+use super::api::EventStreamQuery;
 use super::web::ServerData;
+use crate::event_log::{filtered_event_stream, parse_event_types, EventLogEntry};
 use actix::{Actor, StreamHandler};
 use actix_web::web::Data;
 use actix_web::{web, Error, HttpRequest, HttpResponse};
 use actix_web_actors::ws;
-use erc20_payment_lib_common::DriverEvent;
-use tokio::sync::broadcast;
-use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
-use tokio_stream::wrappers::BroadcastStream;
+use erc20_payment_lib_common::store::PaymentStore;
+use futures::stream::LocalBoxStream;
+use futures::StreamExt;
 
 struct MainWebsocketActor {
-    rx: Option<broadcast::Receiver<DriverEvent>>,
+    events: Option<LocalBoxStream<'static, EventLogEntry>>,
 }
 
 impl MainWebsocketActor {
-    pub fn new(rec: broadcast::Receiver<DriverEvent>) -> Self {
-        Self { rx: Some(rec) }
+    pub fn new(events: LocalBoxStream<'static, EventLogEntry>) -> Self {
+        Self {
+            events: Some(events),
+        }
     }
 }
 
@@ -22,20 +25,9 @@ impl Actor for MainWebsocketActor {
     type Context = ws::WebsocketContext<Self>;
 }
 
-impl StreamHandler<Result<DriverEvent, BroadcastStreamRecvError>> for MainWebsocketActor {
-    fn handle(
-        &mut self,
-        msg: Result<DriverEvent, BroadcastStreamRecvError>,
-        ctx: &mut Self::Context,
-    ) {
-        match msg {
-            Ok(event) => {
-                ctx.text(serde_json::to_string(&event).expect("Failed to serialize DriverEvent"));
-            }
-            Err(BroadcastStreamRecvError::Lagged(n)) => {
-                log::warn!("Websocket actor skipped {} messages", n);
-            }
-        }
+impl StreamHandler<EventLogEntry> for MainWebsocketActor {
+    fn handle(&mut self, entry: EventLogEntry, ctx: &mut Self::Context) {
+        ctx.text(entry.json);
     }
 }
 
@@ -55,26 +47,28 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for MainWebsocketActo
     }
 
     fn started(&mut self, ctx: &mut Self::Context) {
-        let stream_wrapper: BroadcastStream<DriverEvent> =
-            BroadcastStream::new(self.rx.take().unwrap());
-        <Self as StreamHandler<Result<DriverEvent, BroadcastStreamRecvError>>>::add_stream(
-            stream_wrapper,
-            ctx,
-        );
+        <Self as StreamHandler<EventLogEntry>>::add_stream(self.events.take().unwrap(), ctx);
     }
 }
 
-pub async fn event_stream_websocket_endpoint(
-    data: Data<Box<ServerData>>,
+/// Events stored after `since` are sent first, then live events of requested types
+pub async fn event_stream_websocket_endpoint<S: PaymentStore>(
+    data: Data<Box<ServerData<S>>>,
     req: HttpRequest,
     stream: web::Payload,
+    query: web::Query<EventStreamQuery>,
 ) -> Result<HttpResponse, Error> {
     if let Some(driver_broadcast_sender) = &data.payment_runtime.driver_broadcast_sender {
-        ws::start(
-            MainWebsocketActor::new(driver_broadcast_sender.subscribe()),
-            &req,
-            stream,
-        )
+        let event_types = query
+            .events
+            .as_deref()
+            .map(parse_event_types)
+            .transpose()
+            .map_err(actix_web::error::ErrorBadRequest)?;
+        let rx = driver_broadcast_sender.subscribe();
+        let conn = data.db_connection.lock().await.clone();
+        let events = filtered_event_stream(conn, rx, query.since, event_types);
+        ws::start(MainWebsocketActor::new(events.boxed_local()), &req, stream)
     } else {
         Err(actix_web::error::ErrorInternalServerError(
             "Driver event sender not available",
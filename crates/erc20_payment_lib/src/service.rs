@@ -2,7 +2,6 @@ use std::str::FromStr;
 use std::sync::Arc;
 
 use crate::error::{ErrorBag, PaymentError};
-use erc20_payment_lib_common::ops::*;
 
 use crate::transaction::{find_receipt_extended, FindReceiptParseResult};
 use crate::utils::{ConversionError, U256ConvExt};
@@ -12,12 +11,12 @@ use crate::setup::ChainSetup;
 
 use crate::contracts::encode_erc20_balance_of;
 use erc20_payment_lib_common::model::{ChainTxDbObj, TransferInDbObj};
+use erc20_payment_lib_common::store::{PaymentStore, PaymentStoreOps, PaymentStoreTransaction};
 use erc20_rpc_pool::Web3RpcPool;
-use sqlx::SqlitePool;
 use web3::types::{Address, BlockNumber, CallRequest, U256};
 
-pub async fn add_payment_request_2(
-    conn: &SqlitePool,
+pub async fn add_payment_request_2<S: PaymentStore>(
+    conn: &S,
     token_address: Option<Address>,
     token_amount: U256,
     payment_id: &str,
@@ -37,13 +36,13 @@ pub async fn add_payment_request_2(
         requested_date: chrono::Utc::now(),
         received_date: None,
     };
-    insert_transfer_in(conn, &transfer_in)
+    conn.insert_transfer_in(&transfer_in)
         .await
         .map_err(err_from!())
 }
 
-pub async fn add_glm_request(
-    conn: &SqlitePool,
+pub async fn add_glm_request<S: PaymentStore>(
+    conn: &S,
     chain_setup: &ChainSetup,
     token_amount: U256,
     payment_id: &str,
@@ -62,14 +61,14 @@ pub async fn add_glm_request(
         requested_date: chrono::Utc::now(),
         received_date: None,
     };
-    insert_transfer_in(conn, &transfer_in)
+    conn.insert_transfer_in(&transfer_in)
         .await
         .map_err(err_from!())
 }
 
-pub async fn transaction_from_chain_and_into_db(
+pub async fn transaction_from_chain_and_into_db<S: PaymentStore>(
     web3: Arc<Web3RpcPool>,
-    conn: &SqlitePool,
+    conn: &S,
     chain_id: i64,
     tx_hash: &str,
     glm_address: Address,
@@ -80,7 +79,8 @@ pub async fn transaction_from_chain_and_into_db(
         .map_err(|_err| ConversionError::from("Cannot parse tx_hash".to_string()))
         .map_err(err_from!())?;
 
-    if let Some(chain_tx) = get_chain_tx_hash(conn, format!("{:#x}", tx_hash))
+    if let Some(chain_tx) = conn
+        .get_chain_tx_hash(format!("{:#x}", tx_hash))
         .await
         .map_err(err_from!())?
     {
@@ -177,9 +177,10 @@ pub async fn transaction_from_chain_and_into_db(
         chain_tx_dao.balance_glm = token_balance.map(|v| v.to_string());
     }
 
-    let mut db_transaction = conn.begin().await.map_err(err_from!())?;
+    let db_transaction = conn.begin().await.map_err(err_from!())?;
 
-    let tx = insert_chain_tx(&mut *db_transaction, &chain_tx_dao)
+    let tx = db_transaction
+        .insert_chain_tx(&chain_tx_dao)
         .await
         .map_err(err_from!())?;
 
@@ -214,7 +215,8 @@ pub async fn transaction_from_chain_and_into_db(
         for (mut transfer, fee_paid) in transfers.into_iter().zip(distribute_fee) {
             transfer.chain_tx_id = tx.id;
             transfer.fee_paid = fee_paid.map(|v| v.to_string());
-            insert_chain_transfer(&mut *db_transaction, &transfer)
+            db_transaction
+                .insert_chain_transfer(&transfer)
                 .await
                 .map_err(err_from!())?;
         }
@@ -1,13 +1,19 @@
-use crate::config::{AdditionalOptions, Config, EasContractSettings, EasSchemaRegistrySettings};
+use crate::config::{
+    AdditionalOptions, BatchingSettings, Chain, Config, EasContractSettings,
+    EasSchemaRegistrySettings,
+};
 use crate::error::ErrorBag;
 use crate::error::PaymentError;
 
+use crate::sender::payout::PayoutRule;
+use crate::sender::policy::PolicyRule;
+use crate::sender::scheduling::ScheduleRule;
 use crate::utils::DecimalConvExt;
 use crate::{err_custom_create, err_from};
-use erc20_payment_lib_common::DriverEvent;
+use erc20_payment_lib_common::{set_dns_resolver_settings, DriverEvent};
 use erc20_rpc_pool::{
-    Web3EndpointParams, Web3ExternalDnsSource, Web3ExternalJsonSource, Web3PoolType, Web3RpcPool,
-    Web3RpcSingleParams,
+    redact_url, Web3ExternalDnsSource, Web3ExternalJsonSource, Web3PoolType, Web3RpcEndpointOrigin,
+    Web3RpcPool, Web3RpcSingleParams,
 };
 use rust_decimal::Decimal;
 use serde::Serialize;
@@ -62,9 +68,11 @@ pub struct ChainSetup {
     pub confirmation_blocks: u64,
     pub faucet_eth_amount: Option<U256>,
     pub faucet_glm_amount: Option<U256>,
+    pub faucet_daily_limit: Option<u64>,
     pub block_explorer_url: Option<String>,
     pub replacement_timeout: Option<f64>,
     pub external_source_check_interval: Option<u64>,
+    pub batching: Option<BatchingSettings>,
 }
 
 #[derive(Serialize, Clone, Debug)]
@@ -98,6 +106,9 @@ pub struct PaymentSetup {
     pub contract_use_unpacked_method: bool,
     pub use_transfer_for_single_payment: bool,
     pub extra_options_for_testing: Option<ExtraOptionsForTesting>,
+    pub scheduling_rules: Vec<ScheduleRule>,
+    pub payout_rules: Vec<PayoutRule>,
+    pub policy_rules: Vec<PolicyRule>,
 }
 
 const MARK_AS_UNRECOVERABLE_AFTER_SECONDS: u64 = 300;
@@ -111,6 +122,55 @@ fn split_string_by_coma(s: &Option<String>) -> Option<Vec<String>> {
     })
 }
 
+/// Endpoints defined directly in the chain config (without dns and json sources)
+pub fn static_rpc_endpoints(
+    chain_config: &Chain,
+) -> Result<Vec<Web3RpcSingleParams>, PaymentError> {
+    let mut single_endpoints = Vec::new();
+    for rpc_settings in &chain_config.rpc_endpoints {
+        let endpoint_names = split_string_by_coma(&rpc_settings.names).unwrap_or_default();
+        if let Some(endpoints) = split_string_by_coma(&rpc_settings.endpoints) {
+            let endpoint_params = rpc_settings.endpoint_params()?;
+            for (idx, endpoint) in endpoints.iter().enumerate() {
+                single_endpoints.push(Web3RpcSingleParams {
+                    chain_id: chain_config.chain_id as u64,
+                    endpoint: endpoint.clone(),
+                    name: endpoint_names
+                        .get(idx)
+                        .cloned()
+                        .unwrap_or_else(|| redact_url(endpoint)),
+                    web3_endpoint_params: endpoint_params.clone(),
+                    source_id: None,
+                    origin: if rpc_settings.from_env {
+                        Web3RpcEndpointOrigin::Env
+                    } else {
+                        Web3RpcEndpointOrigin::Config
+                    },
+                });
+            }
+        }
+    }
+    Ok(single_endpoints)
+}
+
+/// Update endpoints defined directly in config of already running pools.
+/// New chains and changes of dns/json sources require restart.
+pub fn reload_rpc_endpoints(setup: &PaymentSetup, config: &Config) -> Result<(), PaymentError> {
+    for (network, chain_config) in &config.chain {
+        let Some(chain_setup) = setup.chain_setup.get(&chain_config.chain_id) else {
+            log::warn!(
+                "Chain {} not configured at startup, restart is needed to use it",
+                network
+            );
+            continue;
+        };
+        chain_setup
+            .provider
+            .sync_static_endpoints(static_rpc_endpoints(chain_config)?);
+    }
+    Ok(())
+}
+
 impl PaymentSetup {
     pub fn new(
         config: &Config,
@@ -118,6 +178,9 @@ impl PaymentSetup {
         web3_rpc_pool_info: Arc<std::sync::Mutex<BTreeMap<i64, Web3PoolType>>>,
         mpsc_sender: Option<mpsc::Sender<DriverEvent>>,
     ) -> Result<Self, PaymentError> {
+        if let Some(dns) = &config.dns {
+            set_dns_resolver_settings(dns)?;
+        }
         let mut ps = PaymentSetup {
             chain_setup: BTreeMap::new(),
             //pub_address: get_eth_addr_from_secret(secret_key),
@@ -149,75 +212,48 @@ impl PaymentSetup {
             contract_use_unpacked_method: false,
             extra_options_for_testing: None,
             use_transfer_for_single_payment: true,
+            scheduling_rules: config
+                .scheduling
+                .iter()
+                .flatten()
+                .map(ScheduleRule::from_config)
+                .collect::<Result<_, _>>()?,
+            payout_rules: config
+                .payout
+                .iter()
+                .flatten()
+                .map(PayoutRule::from_config)
+                .collect::<Result<_, _>>()?,
+            policy_rules: config
+                .policy
+                .iter()
+                .flatten()
+                .map(PolicyRule::from_config)
+                .collect::<Result<_, _>>()?,
         };
         for chain_config in &config.chain {
-            let mut single_endpoints = Vec::new();
+            let single_endpoints = static_rpc_endpoints(chain_config.1)?;
             let mut json_sources = Vec::new();
             let mut dns_sources = Vec::new();
             for rpc_settings in &chain_config.1.rpc_endpoints {
-                let endpoint_names = split_string_by_coma(&rpc_settings.names).unwrap_or_default();
-                let max_head_behind_secs = rpc_settings.allowed_head_behind_secs.unwrap_or(120);
-                let max_head_behind_secs = if max_head_behind_secs < 0 {
-                    None
-                } else {
-                    Some(max_head_behind_secs as u64)
-                };
-                if let Some(endpoints) = split_string_by_coma(&rpc_settings.endpoints) {
-                    for (idx, endpoint) in endpoints.iter().enumerate() {
-                        let endpoint = Web3RpcSingleParams {
-                            chain_id: chain_config.1.chain_id as u64,
-                            endpoint: endpoint.clone(),
-                            name: endpoint_names.get(idx).unwrap_or(&endpoint.clone()).clone(),
-                            web3_endpoint_params: Web3EndpointParams {
-                                backup_level: rpc_settings.backup_level.unwrap_or(0),
-                                skip_validation: rpc_settings.skip_validation.unwrap_or(false),
-                                verify_interval_secs: rpc_settings
-                                    .verify_interval_secs
-                                    .unwrap_or(120),
-                                max_response_time_ms: rpc_settings.max_timeout_ms.unwrap_or(10000),
-                                max_head_behind_secs,
-                                max_number_of_consecutive_errors: rpc_settings
-                                    .max_consecutive_errors
-                                    .unwrap_or(5),
-                                min_interval_requests_ms: rpc_settings.min_interval_ms,
-                            },
-                            source_id: None,
-                        };
-                        single_endpoints.push(endpoint);
-                    }
+                let endpoint_params = rpc_settings.endpoint_params()?;
+                if split_string_by_coma(&rpc_settings.endpoints).is_some() {
+                    //already added to single_endpoints
                 } else if let Some(dns_source) = &rpc_settings.dns_source {
                     dns_sources.push(Web3ExternalDnsSource {
                         chain_id: chain_config.1.chain_id as u64,
                         unique_source_id: Uuid::new_v4(),
                         dns_url: dns_source.clone(),
-                        endpoint_params: Web3EndpointParams {
-                            backup_level: rpc_settings.backup_level.unwrap_or(0),
-                            skip_validation: rpc_settings.skip_validation.unwrap_or(false),
-                            verify_interval_secs: rpc_settings.verify_interval_secs.unwrap_or(120),
-                            max_response_time_ms: rpc_settings.max_timeout_ms.unwrap_or(10000),
-                            max_head_behind_secs,
-                            max_number_of_consecutive_errors: rpc_settings
-                                .max_consecutive_errors
-                                .unwrap_or(5),
-                            min_interval_requests_ms: rpc_settings.min_interval_ms,
-                        },
+                        endpoint_params: endpoint_params.clone(),
+                        signer: rpc_settings.source_signer()?,
                     });
                 } else if let Some(json_source) = &rpc_settings.json_source {
                     json_sources.push(Web3ExternalJsonSource {
                         chain_id: chain_config.1.chain_id as u64,
                         unique_source_id: Uuid::new_v4(),
                         url: json_source.clone(),
-                        endpoint_params: Web3EndpointParams {
-                            backup_level: rpc_settings.backup_level.unwrap_or(0),
-                            skip_validation: rpc_settings.skip_validation.unwrap_or(false),
-                            verify_interval_secs: rpc_settings.verify_interval_secs.unwrap_or(120),
-                            max_response_time_ms: rpc_settings.max_timeout_ms.unwrap_or(10000),
-                            max_head_behind_secs,
-                            max_number_of_consecutive_errors: rpc_settings
-                                .max_consecutive_errors
-                                .unwrap_or(5),
-                            min_interval_requests_ms: rpc_settings.min_interval_ms,
-                        },
+                        endpoint_params: endpoint_params.clone(),
+                        signer: rpc_settings.source_signer()?,
                     });
                 }
             }
@@ -333,10 +369,12 @@ impl PaymentSetup {
                     currency_gas_symbol: chain_config.1.currency_symbol.clone(),
                     faucet_eth_amount,
                     faucet_glm_amount,
+                    faucet_daily_limit: chain_config.1.faucet_daily_limit,
                     block_explorer_url: chain_config.1.block_explorer_url.clone(),
                     chain_id: chain_config.1.chain_id,
                     replacement_timeout: chain_config.1.replacement_timeout,
                     external_source_check_interval: chain_config.1.external_source_check_interval,
+                    batching: chain_config.1.batching.clone(),
                 },
             );
         }
@@ -16,6 +16,10 @@ pub struct SignerAccount {
     #[serde(skip)]
     pub signer: Arc<Box<dyn Signer + Send + Sync>>,
     pub(crate) external_gather_time: Arc<Mutex<Option<DateTime<Utc>>>>,
+    /// Held while pending transfers are gathered into transactions,
+    /// so that they are not changed in the middle of gathering
+    #[serde(skip)]
+    pub(crate) gather_lock: Arc<tokio::sync::Mutex<()>>,
     #[serde(skip)]
     pub(crate) jh: Arc<Mutex<Vec<Option<JoinHandle<()>>>>>,
 }
@@ -38,6 +42,7 @@ impl SignerAccount {
             address,
             signer,
             external_gather_time: Arc::new(Mutex::new(None)),
+            gather_lock: Arc::new(tokio::sync::Mutex::new(())),
             jh: Arc::new(Mutex::new(Vec::new())),
         }
     }
@@ -10,8 +10,9 @@ use crate::utils::{datetime_from_u256_timestamp, ConversionError, StringConvExt,
 use crate::{err_custom_create, err_from};
 use chrono::Utc;
 use erc20_payment_lib_common::model::{
-    ChainTransferDbObj, ChainTxDbObj, DepositId, TokenTransferDbObj, TxDbObj,
+    ChainTransferDbObj, ChainTxDbObj, DepositId, TokenTransferDbObj, TransferPriority, TxDbObj,
 };
+use erc20_payment_lib_common::store::PaymentStore;
 use erc20_payment_lib_common::CantSignContent;
 use erc20_payment_lib_common::{
     DriverEvent, DriverEventContent, NoGasDetails, NoTokenDetails, TransactionStuckReason,
@@ -19,7 +20,6 @@ use erc20_payment_lib_common::{
 use erc20_rpc_pool::Web3RpcPool;
 use rust_decimal::Decimal;
 use secp256k1::SecretKey;
-use sqlx::SqlitePool;
 use std::collections::HashMap;
 use std::str::FromStr;
 use std::sync::Arc;
@@ -137,6 +137,9 @@ pub fn create_token_transfer(
         paid_date: None,
         fee_paid: None,
         error: None,
+        priority: TransferPriority::default().to_string(),
+        approved_date: None,
+        deadline: None,
     }
 }
 
@@ -448,9 +451,9 @@ pub fn create_erc20_approve(
     })
 }
 
-pub async fn get_no_token_details(
+pub async fn get_no_token_details<S: PaymentStore>(
     web3: Arc<Web3RpcPool>,
-    conn: &SqlitePool,
+    conn: &S,
     web3_tx_dao: &TxDbObj,
     glm_token: Address,
     wrapper_contract_address: Option<Address>,
@@ -484,9 +487,9 @@ pub async fn get_no_token_details(
     })
 }
 
-pub async fn check_transaction(
+pub async fn check_transaction<S: PaymentStore>(
     event_sender: &Option<mpsc::Sender<DriverEvent>>,
-    conn: &SqlitePool,
+    conn: &S,
     glm_token: Address,
     web3: Arc<Web3RpcPool>,
     web3_tx_dao: &mut TxDbObj,
@@ -645,8 +648,8 @@ pub async fn sign_transaction_with_callback(
     Ok(())
 }
 
-pub async fn send_transaction(
-    conn: &SqlitePool,
+pub async fn send_transaction<S: PaymentStore>(
+    conn: &S,
     chain_id: i64,
     glm_token: Address,
     event_sender: Option<mpsc::Sender<DriverEvent>>,
@@ -1150,6 +1153,7 @@ pub async fn import_erc20_txs(import_args: ImportErc20TxsArgs) -> Result<Vec<H25
 
     //return transactions sorted by block number
     let mut vec = txs.into_iter().collect::<Vec<(H256, u64)>>();
+    #[allow(clippy::unnecessary_sort_by)]
     vec.sort_by(|a, b| a.1.cmp(&b.1));
     Ok(vec.into_iter().map(|(tx, _)| tx).collect())
 }
@@ -0,0 +1,644 @@
+//! Delivery of driver events to http endpoints configured in `[[webhook]]` sections.
+//! Events are read from the driver event log (see [`crate::event_log`]) starting after the stored
+//! offset, so no event is missed when the job lags behind or is restarted.
+//! Every event is stored in `webhook_delivery` table before it is sent, so pending deliveries
+//! survive restarts and failed ones are retried with exponential backoff.
+
+use crate::config::WebhookSettings;
+use crate::err_custom_create;
+use crate::error::PaymentError;
+use crate::event_log::EventLogEntry;
+use chrono::{DateTime, Utc};
+use erc20_payment_lib_common::error::*;
+use erc20_payment_lib_common::model::WebhookDeliveryDbObj;
+use erc20_payment_lib_common::store::{
+    PaymentStore, PaymentStoreOps, PaymentStoreTransaction, WebhookDeliveryStatus,
+};
+use erc20_payment_lib_common::{err_from, DriverEvent, DRIVER_EVENT_TYPES};
+use erc20_rpc_pool::redact_url;
+use futures::{StreamExt, TryStreamExt};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, Notify};
+use tokio::task::JoinHandle;
+
+pub const WEBHOOK_ID_HEADER: &str = "X-Webhook-Id";
+pub const WEBHOOK_EVENT_HEADER: &str = "X-Webhook-Event";
+pub const WEBHOOK_TIMESTAMP_HEADER: &str = "X-Webhook-Timestamp";
+pub const WEBHOOK_SIGNATURE_HEADER: &str = "X-Webhook-Signature";
+
+const DEFAULT_MAX_ATTEMPTS: u32 = 10;
+const DEFAULT_TIMEOUT_SECS: u64 = 10;
+const DEFAULT_CONCURRENCY: usize = 4;
+const RETRY_BASE_DELAY_SECS: i64 = 10;
+const RETRY_MAX_DELAY_SECS: i64 = 3600;
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+const DELIVERY_BATCH_SIZE: i64 = 50;
+/// Number of logged events turned into deliveries at once
+const EVENT_BATCH_SIZE: i64 = 100;
+/// Name of the webhook job offset in `event_offset` table
+const EVENT_OFFSET_CONSUMER: &str = "webhook";
+/// Part of response body stored as error of failed delivery
+const MAX_ERROR_BODY_LEN: usize = 500;
+
+/// Signature of payload, hex encoded HMAC-SHA256 of `{timestamp}.{body}`
+pub fn sign_webhook_payload(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(format!("{}.", timestamp).as_bytes());
+    mac.update(body);
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Delay before next attempt after `attempts` failed ones: 10s, 20s, 40s ... up to one hour
+fn retry_delay(attempts: i64) -> chrono::Duration {
+    let exp = attempts.clamp(1, 20) - 1;
+    chrono::Duration::seconds((RETRY_BASE_DELAY_SECS << exp).min(RETRY_MAX_DELAY_SECS))
+}
+
+struct Webhook {
+    settings: WebhookSettings,
+    client: reqwest::Client,
+}
+
+impl Webhook {
+    fn accepts(&self, event_type: &str) -> bool {
+        match &self.settings.events {
+            Some(events) => events.iter().any(|e| e == event_type),
+            None => event_type != "alive",
+        }
+    }
+
+    fn max_attempts(&self) -> i64 {
+        self.settings.max_attempts.unwrap_or(DEFAULT_MAX_ATTEMPTS) as i64
+    }
+
+    fn concurrency(&self) -> usize {
+        self.settings.concurrency.unwrap_or(DEFAULT_CONCURRENCY)
+    }
+}
+
+struct Webhooks {
+    webhooks: Vec<Webhook>,
+}
+
+impl Webhooks {
+    fn new(settings: Vec<WebhookSettings>) -> Result<Self, PaymentError> {
+        let mut names = HashSet::new();
+        let mut webhooks = Vec::with_capacity(settings.len());
+        for settings in settings {
+            if settings.name.is_empty() || !names.insert(settings.name.clone()) {
+                return Err(err_custom_create!(
+                    "Webhook name has to be unique and not empty: '{}'",
+                    settings.name
+                ));
+            }
+            let url = url::Url::parse(&settings.url).map_err(|e| {
+                err_custom_create!("Invalid url of webhook {}: {}", settings.name, e)
+            })?;
+            if url.scheme() != "http" && url.scheme() != "https" {
+                return Err(err_custom_create!(
+                    "Webhook {} has to use http or https url",
+                    settings.name
+                ));
+            }
+            if let Some(unknown) = settings
+                .events
+                .iter()
+                .flatten()
+                .find(|e| !DRIVER_EVENT_TYPES.contains(&e.as_str()))
+            {
+                return Err(err_custom_create!(
+                    "Unknown event type {} of webhook {}, expected one of: {}",
+                    unknown,
+                    settings.name,
+                    DRIVER_EVENT_TYPES.join(", ")
+                ));
+            }
+            if settings.max_attempts == Some(0) {
+                return Err(err_custom_create!(
+                    "Webhook {} max-attempts has to be positive",
+                    settings.name
+                ));
+            }
+            if settings.concurrency == Some(0) {
+                return Err(err_custom_create!(
+                    "Webhook {} concurrency has to be positive",
+                    settings.name
+                ));
+            }
+            let client = reqwest::Client::builder()
+                .timeout(Duration::from_secs(
+                    settings.timeout_secs.unwrap_or(DEFAULT_TIMEOUT_SECS),
+                ))
+                .build()
+                .map_err(|e| err_custom_create!("Failed to build webhook client: {}", e))?;
+            webhooks.push(Webhook { settings, client });
+        }
+        Ok(Self { webhooks })
+    }
+
+    fn get(&self, name: &str) -> Option<&Webhook> {
+        self.webhooks.iter().find(|w| w.settings.name == name)
+    }
+}
+
+/// Store delivery of the event for every webhook interested in it
+async fn enqueue_event<S: PaymentStoreOps>(
+    conn: &S,
+    webhooks: &Webhooks,
+    event_type: &str,
+    created_date: DateTime<Utc>,
+    payload: &str,
+) -> Result<usize, PaymentError> {
+    let mut count = 0;
+    for webhook in webhooks.webhooks.iter().filter(|w| w.accepts(event_type)) {
+        conn.insert_webhook_delivery(&WebhookDeliveryDbObj {
+            id: 0,
+            webhook: webhook.settings.name.clone(),
+            event_type: event_type.to_string(),
+            payload: payload.to_string(),
+            created_date,
+            attempts: 0,
+            next_attempt_date: Some(created_date),
+            last_attempt_date: None,
+            delivered_date: None,
+            response_status: None,
+            error: None,
+        })
+        .await
+        .map_err(err_from!())?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Store deliveries of event that is not in the event log (alive events or events that failed
+/// to be stored)
+async fn enqueue_unlogged_event<S: PaymentStoreOps>(
+    conn: &S,
+    webhooks: &Webhooks,
+    event: &DriverEvent,
+) -> Result<usize, PaymentError> {
+    let payload = serde_json::to_string(event)
+        .map_err(|e| err_custom_create!("Failed to serialize event: {}", e))?;
+    enqueue_event(
+        conn,
+        webhooks,
+        event.content.event_type(),
+        event.create_date,
+        &payload,
+    )
+    .await
+}
+
+/// Offset of the webhook job in the event log. When the job runs for the first time
+/// it starts with the newest event, so history of the log is not sent.
+async fn init_event_offset<S: PaymentStoreOps>(conn: &S) -> Result<i64, PaymentError> {
+    if let Some(offset) = conn
+        .get_event_offset(EVENT_OFFSET_CONSUMER)
+        .await
+        .map_err(err_from!())?
+    {
+        return Ok(offset);
+    }
+    let offset = conn
+        .get_last_driver_event_id()
+        .await
+        .map_err(err_from!())?
+        .unwrap_or(0);
+    conn.set_event_offset(EVENT_OFFSET_CONSUMER, offset)
+        .await
+        .map_err(err_from!())?;
+    Ok(offset)
+}
+
+/// Store deliveries of logged events following the stored offset and move the offset
+/// in the same database transaction. Returns number of processed events and number of
+/// created deliveries.
+async fn enqueue_logged_events<S: PaymentStore>(
+    conn: &S,
+    webhooks: &Webhooks,
+) -> Result<(usize, usize), PaymentError> {
+    let offset = init_event_offset(conn).await?;
+    let rows = conn
+        .get_driver_events_after(offset, EVENT_BATCH_SIZE)
+        .await
+        .map_err(err_from!())?;
+    let Some(last_id) = rows.last().map(|row| row.id) else {
+        return Ok((0, 0));
+    };
+    let transaction = conn.begin().await.map_err(err_from!())?;
+    let mut deliveries = 0;
+    for row in &rows {
+        let (event_type, created_date) = (row.event_type.clone(), row.create_date);
+        let entry = EventLogEntry::from_row(row.clone());
+        deliveries += enqueue_event(
+            &transaction,
+            webhooks,
+            &event_type,
+            created_date,
+            &entry.json,
+        )
+        .await?;
+    }
+    transaction
+        .set_event_offset(EVENT_OFFSET_CONSUMER, last_id)
+        .await
+        .map_err(err_from!())?;
+    transaction.commit().await.map_err(err_from!())?;
+    Ok((rows.len(), deliveries))
+}
+
+/// Returns http status and error of failed attempt
+async fn send_delivery(
+    webhook: &Webhook,
+    delivery: &WebhookDeliveryDbObj,
+) -> Result<u16, (Option<u16>, String)> {
+    let mut request = webhook
+        .client
+        .post(&webhook.settings.url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header(WEBHOOK_ID_HEADER, delivery.id.to_string())
+        .header(WEBHOOK_EVENT_HEADER, &delivery.event_type);
+    if let Some(secret) = &webhook.settings.secret {
+        let timestamp = Utc::now().timestamp();
+        request = request
+            .header(WEBHOOK_TIMESTAMP_HEADER, timestamp.to_string())
+            .header(
+                WEBHOOK_SIGNATURE_HEADER,
+                sign_webhook_payload(secret.expose(), timestamp, delivery.payload.as_bytes()),
+            );
+    }
+    let response = request
+        .body(delivery.payload.clone())
+        .send()
+        .await
+        .map_err(|e| (None, e.without_url().to_string()))?;
+    let status = response.status();
+    if status.is_success() {
+        return Ok(status.as_u16());
+    }
+    let body = response.text().await.unwrap_or_default();
+    let body = body.chars().take(MAX_ERROR_BODY_LEN).collect::<String>();
+    Err((
+        Some(status.as_u16()),
+        format!("Http status {}: {}", status, body),
+    ))
+}
+
+/// Send delivery and store result of the attempt
+async fn attempt_delivery<S: PaymentStoreOps>(
+    conn: &S,
+    webhooks: &Webhooks,
+    mut delivery: WebhookDeliveryDbObj,
+    now: DateTime<Utc>,
+) -> Result<(), PaymentError> {
+    delivery.attempts += 1;
+    delivery.last_attempt_date = Some(Utc::now());
+    let Some(webhook) = webhooks.get(&delivery.webhook) else {
+        delivery.next_attempt_date = None;
+        delivery.error = Some("Webhook is no longer configured".to_string());
+        return conn
+            .update_webhook_delivery(&delivery)
+            .await
+            .map_err(err_from!());
+    };
+    match send_delivery(webhook, &delivery).await {
+        Ok(status) => {
+            delivery.next_attempt_date = None;
+            delivery.delivered_date = delivery.last_attempt_date;
+            delivery.response_status = Some(status as i64);
+            delivery.error = None;
+        }
+        Err((status, error)) => {
+            log::warn!(
+                "Webhook {} delivery {} to {} failed (attempt {}): {}",
+                delivery.webhook,
+                delivery.id,
+                redact_url(&webhook.settings.url),
+                delivery.attempts,
+                error
+            );
+            delivery.next_attempt_date = if delivery.attempts >= webhook.max_attempts() {
+                log::error!(
+                    "Webhook {} delivery {} abandoned after {} attempts",
+                    delivery.webhook,
+                    delivery.id,
+                    delivery.attempts
+                );
+                None
+            } else {
+                Some(now + retry_delay(delivery.attempts))
+            };
+            delivery.response_status = status.map(|s| s as i64);
+            delivery.error = Some(error);
+        }
+    }
+    conn.update_webhook_delivery(&delivery)
+        .await
+        .map_err(err_from!())
+}
+
+/// Attempt all deliveries scheduled not later than `now`, returns number of processed deliveries.
+/// Webhooks are served in parallel, each with at most `concurrency` requests in flight.
+async fn deliver_due<S: PaymentStoreOps>(
+    conn: &S,
+    webhooks: &Webhooks,
+    now: DateTime<Utc>,
+) -> Result<usize, PaymentError> {
+    let deliveries = conn
+        .get_webhook_deliveries_due(now, DELIVERY_BATCH_SIZE)
+        .await
+        .map_err(err_from!())?;
+    let count = deliveries.len();
+    let mut by_webhook = BTreeMap::<String, Vec<WebhookDeliveryDbObj>>::new();
+    for delivery in deliveries {
+        by_webhook
+            .entry(delivery.webhook.clone())
+            .or_default()
+            .push(delivery);
+    }
+    futures::future::try_join_all(by_webhook.into_iter().map(|(name, deliveries)| {
+        let concurrency = webhooks.get(&name).map(Webhook::concurrency).unwrap_or(1);
+        futures::stream::iter(deliveries)
+            .map(|delivery| attempt_delivery(conn, webhooks, delivery, now))
+            .buffer_unordered(concurrency)
+            .try_collect::<Vec<_>>()
+    }))
+    .await?;
+    Ok(count)
+}
+
+/// Schedule failed delivery to be sent again with full number of attempts
+pub async fn retry_webhook_delivery<S: PaymentStoreOps>(
+    conn: &S,
+    id: i64,
+) -> Result<WebhookDeliveryDbObj, PaymentError> {
+    let mut delivery = conn
+        .get_webhook_delivery(id)
+        .await
+        .map_err(err_from!())?
+        .ok_or_else(|| err_from!()(sqlx::Error::RowNotFound))?;
+    if WebhookDeliveryStatus::of(&delivery) != WebhookDeliveryStatus::Failed {
+        return Err(err_custom_create!(
+            "Only failed delivery can be retried, delivery {} is {:?}",
+            id,
+            WebhookDeliveryStatus::of(&delivery)
+        ));
+    }
+    delivery.attempts = 0;
+    delivery.next_attempt_date = Some(Utc::now());
+    conn.update_webhook_delivery(&delivery)
+        .await
+        .map_err(err_from!())?;
+    Ok(delivery)
+}
+
+/// Start background job storing deliveries of logged driver events and delivering them
+/// to webhooks. `events` is used to wake up the job and to pass events that are not in the log.
+pub fn start_webhook_job<S: PaymentStore>(
+    conn: S,
+    settings: Vec<WebhookSettings>,
+    mut events: broadcast::Receiver<DriverEvent>,
+) -> Result<JoinHandle<()>, PaymentError> {
+    let webhooks = Arc::new(Webhooks::new(settings)?);
+    let notify = Arc::new(Notify::new());
+
+    let enqueue = {
+        let conn = conn.clone();
+        let webhooks = webhooks.clone();
+        let notify = notify.clone();
+        async move {
+            loop {
+                match enqueue_logged_events(&conn, &webhooks).await {
+                    Ok((events, deliveries)) => {
+                        if deliveries > 0 {
+                            notify.notify_one();
+                        }
+                        if events as i64 >= EVENT_BATCH_SIZE {
+                            continue;
+                        }
+                    }
+                    Err(err) => log::error!("Failed to store webhook deliveries: {}", err),
+                }
+                let event = tokio::select! {
+                    event = events.recv() => event,
+                    _ = tokio::time::sleep(POLL_INTERVAL) => continue,
+                };
+                match event {
+                    Ok(event) if event.id.is_none() => {
+                        match enqueue_unlogged_event(&conn, &webhooks, &event).await {
+                            Ok(0) => {}
+                            Ok(_) => notify.notify_one(),
+                            Err(err) => log::error!("Failed to store webhook delivery: {}", err),
+                        }
+                    }
+                    // logged events are read from the log, including ones skipped on lag
+                    Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        }
+    };
+    let deliver = async move {
+        loop {
+            match deliver_due(&conn, &webhooks, Utc::now()).await {
+                Ok(n) if n as i64 >= DELIVERY_BATCH_SIZE => continue,
+                Ok(_) => {}
+                Err(err) => log::error!("Failed to process webhook deliveries: {}", err),
+            }
+            tokio::select! {
+                _ = notify.notified() => {}
+                _ = tokio::time::sleep(POLL_INTERVAL) => {}
+            }
+        }
+    };
+    Ok(tokio::spawn(async move {
+        tokio::join!(enqueue, deliver);
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ApiSecret;
+    use crate::event_log::EventLog;
+    use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer};
+    use erc20_payment_lib_common::store::MemoryStore;
+    use erc20_payment_lib_common::DriverEventContent;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[actix_web::test]
+    async fn test_webhook_delivery_retry() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let received = Arc::new(std::sync::Mutex::new(Vec::<(String, String, String)>::new()));
+        let (calls_, received_) = (calls.clone(), received.clone());
+        let server = HttpServer::new(move || {
+            let (calls, received) = (calls_.clone(), received_.clone());
+            App::new().route(
+                "/hook",
+                web::post().to(move |req: HttpRequest, body: web::Bytes| {
+                    let (calls, received) = (calls.clone(), received.clone());
+                    async move {
+                        let header = |name| {
+                            req.headers()
+                                .get(name)
+                                .and_then(|h| h.to_str().ok())
+                                .unwrap_or_default()
+                                .to_string()
+                        };
+                        received.lock().unwrap().push((
+                            header(WEBHOOK_TIMESTAMP_HEADER),
+                            header(WEBHOOK_SIGNATURE_HEADER),
+                            String::from_utf8_lossy(&body).to_string(),
+                        ));
+                        // first attempt fails
+                        if calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                            HttpResponse::ServiceUnavailable().body("try later")
+                        } else {
+                            HttpResponse::Ok().finish()
+                        }
+                    }
+                }),
+            )
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+        let addr = server.addrs()[0];
+        actix_web::rt::spawn(server.run());
+
+        let webhooks = Webhooks::new(vec![WebhookSettings {
+            name: "test".to_string(),
+            url: format!("http://{}/hook", addr),
+            events: None,
+            secret: Some(ApiSecret::new("secret".to_string())),
+            max_attempts: Some(3),
+            timeout_secs: None,
+            concurrency: None,
+        }])
+        .unwrap();
+        let conn = MemoryStore::new();
+        let mut event_log = EventLog::new(conn.clone(), None);
+
+        // events logged before the job first started are not sent
+        event_log
+            .append(DriverEvent::now(DriverEventContent::StatusChanged(vec![])))
+            .await;
+        assert_eq!(init_event_offset(&conn).await.unwrap(), 1);
+
+        let alive = DriverEvent::now(DriverEventContent::Alive);
+        assert_eq!(
+            enqueue_unlogged_event(&conn, &webhooks, &alive)
+                .await
+                .unwrap(),
+            0
+        );
+        let event = event_log
+            .append(DriverEvent::now(DriverEventContent::StatusChanged(vec![])))
+            .await;
+        assert_eq!(event.id, Some(2));
+        assert_eq!(
+            enqueue_logged_events(&conn, &webhooks).await.unwrap(),
+            (1, 1)
+        );
+        assert_eq!(
+            enqueue_logged_events(&conn, &webhooks).await.unwrap(),
+            (0, 0)
+        );
+        assert_eq!(
+            conn.get_event_offset(EVENT_OFFSET_CONSUMER).await.unwrap(),
+            Some(2)
+        );
+
+        let now = Utc::now();
+        assert_eq!(deliver_due(&conn, &webhooks, now).await.unwrap(), 1);
+        let delivery = conn.get_webhook_delivery(1).await.unwrap().unwrap();
+        assert_eq!(delivery.attempts, 1);
+        assert_eq!(delivery.response_status, Some(503));
+        assert_eq!(delivery.next_attempt_date, Some(now + retry_delay(1)));
+
+        // not due yet
+        assert_eq!(deliver_due(&conn, &webhooks, now).await.unwrap(), 0);
+        let later = now + retry_delay(1);
+        assert_eq!(deliver_due(&conn, &webhooks, later).await.unwrap(), 1);
+        let delivery = conn.get_webhook_delivery(1).await.unwrap().unwrap();
+        assert_eq!(
+            WebhookDeliveryStatus::of(&delivery),
+            WebhookDeliveryStatus::Delivered
+        );
+        assert_eq!(delivery.attempts, 2);
+        assert!(retry_webhook_delivery(&conn, 1).await.is_err());
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 2);
+        let (timestamp, signature, body) = &received[1];
+        assert_eq!(body, &delivery.payload);
+        let sent: serde_json::Value = serde_json::from_str(body).unwrap();
+        assert_eq!(sent["id"], 2);
+        assert_eq!(
+            signature,
+            &sign_webhook_payload("secret", timestamp.parse().unwrap(), body.as_bytes())
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_webhook_delivery_concurrency() {
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let max_in_flight = Arc::new(AtomicUsize::new(0));
+        let (in_flight_, max_in_flight_) = (in_flight.clone(), max_in_flight.clone());
+        let server = HttpServer::new(move || {
+            let (in_flight, max_in_flight) = (in_flight_.clone(), max_in_flight_.clone());
+            App::new().route(
+                "/hook",
+                web::post().to(move || {
+                    let (in_flight, max_in_flight) = (in_flight.clone(), max_in_flight.clone());
+                    async move {
+                        let current = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                        max_in_flight.fetch_max(current, Ordering::SeqCst);
+                        tokio::time::sleep(Duration::from_millis(100)).await;
+                        in_flight.fetch_sub(1, Ordering::SeqCst);
+                        HttpResponse::Ok().finish()
+                    }
+                }),
+            )
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+        let addr = server.addrs()[0];
+        actix_web::rt::spawn(server.run());
+
+        let webhooks = Webhooks::new(vec![WebhookSettings {
+            name: "test".to_string(),
+            url: format!("http://{}/hook", addr),
+            events: None,
+            secret: None,
+            max_attempts: None,
+            timeout_secs: None,
+            concurrency: Some(2),
+        }])
+        .unwrap();
+        let conn = MemoryStore::new();
+        for _ in 0..6 {
+            let event = DriverEvent::now(DriverEventContent::StatusChanged(vec![]));
+            enqueue_unlogged_event(&conn, &webhooks, &event)
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(deliver_due(&conn, &webhooks, Utc::now()).await.unwrap(), 6);
+        assert_eq!(max_in_flight.load(Ordering::SeqCst), 2);
+        for id in 1..=6 {
+            let delivery = conn.get_webhook_delivery(id).await.unwrap().unwrap();
+            assert_eq!(
+                WebhookDeliveryStatus::of(&delivery),
+                WebhookDeliveryStatus::Delivered
+            );
+        }
+    }
+}
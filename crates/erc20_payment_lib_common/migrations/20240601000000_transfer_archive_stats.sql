@@ -0,0 +1,23 @@
+-- Totals of token_transfer / chain_transfer rows moved to archive, so that stats stay correct after pruning.
+-- Rows with receiver_addr NULL hold totals for the whole sender (tx_count can't be summed over receivers).
+CREATE TABLE "transfer_archive_stats"
+(
+    id                  INTEGER     NOT NULL     PRIMARY KEY AUTOINCREMENT,
+    source              TEXT        NOT NULL,
+    chain_id            INTEGER     NOT NULL,
+    from_addr           TEXT        NOT NULL,
+    receiver_addr       TEXT        NULL,
+    token_addr          TEXT        NULL,
+    transfer_count      INTEGER     NOT NULL,
+    done_count          INTEGER     NOT NULL,
+    tx_count            INTEGER     NOT NULL,
+    token_amount        TEXT        NOT NULL,
+    fee_paid            TEXT        NOT NULL,
+    first_transfer_date TEXT        NULL,
+    last_transfer_date  TEXT        NULL,
+    first_paid_date     TEXT        NULL,
+    last_paid_date      TEXT        NULL,
+    max_payment_delay   INTEGER     NULL
+) strict;
+
+CREATE INDEX "idx_transfer_archive_stats_chain_id" ON "transfer_archive_stats" (chain_id, source);
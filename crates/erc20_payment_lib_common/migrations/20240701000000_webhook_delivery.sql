@@ -0,0 +1,19 @@
+-- Driver events queued for delivery to configured webhooks.
+-- next_attempt_date is NULL when delivery is finished (delivered or attempts exhausted).
+CREATE TABLE "webhook_delivery"
+(
+    id                  INTEGER     NOT NULL     PRIMARY KEY AUTOINCREMENT,
+    webhook             TEXT        NOT NULL,
+    event_type          TEXT        NOT NULL,
+    payload             TEXT        NOT NULL,
+    created_date        TEXT        NOT NULL,
+    attempts            INTEGER     NOT NULL,
+    next_attempt_date   TEXT        NULL,
+    last_attempt_date   TEXT        NULL,
+    delivered_date      TEXT        NULL,
+    response_status     INTEGER     NULL,
+    error               TEXT        NULL
+) strict;
+
+CREATE INDEX "idx_webhook_delivery_next_attempt_date" ON "webhook_delivery" (next_attempt_date);
+CREATE INDEX "idx_webhook_delivery_webhook" ON "webhook_delivery" (webhook);
@@ -0,0 +1,10 @@
+-- Append-only log of driver events, consumers of event stream resume from last seen id
+CREATE TABLE "driver_event"
+(
+    id                  INTEGER     NOT NULL     PRIMARY KEY AUTOINCREMENT,
+    event_type          TEXT        NOT NULL,
+    create_date         TEXT        NOT NULL,
+    payload             TEXT        NOT NULL
+) strict;
+
+CREATE INDEX "idx_driver_event_create_date" ON "driver_event" (create_date);
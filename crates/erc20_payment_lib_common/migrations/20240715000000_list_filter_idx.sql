@@ -0,0 +1,6 @@
+-- Filters of transfer and transaction lists, deposit_id is already covered by idx_deposit_id
+-- and payment_id lookup without sender can't use idx_token_transfer_payment_id
+CREATE INDEX "idx_token_transfer_receiver_addr" ON "token_transfer" (receiver_addr);
+CREATE INDEX "idx_token_transfer_payment_id_only" ON "token_transfer" (payment_id);
+CREATE INDEX "idx_token_transfer_create_date" ON "token_transfer" (create_date);
+CREATE INDEX "idx_tx_from_addr" ON "tx" (from_addr);
@@ -0,0 +1,23 @@
+-- Totals of token_transfer / chain_transfer rows moved to archive, so that stats stay correct after pruning.
+-- Rows with receiver_addr NULL hold totals for the whole sender (tx_count can't be summed over receivers).
+CREATE TABLE "transfer_archive_stats"
+(
+    id                  BIGSERIAL       NOT NULL     PRIMARY KEY,
+    source              TEXT            NOT NULL,
+    chain_id            BIGINT          NOT NULL,
+    from_addr           TEXT            NOT NULL,
+    receiver_addr       TEXT            NULL,
+    token_addr          TEXT            NULL,
+    transfer_count      BIGINT          NOT NULL,
+    done_count          BIGINT          NOT NULL,
+    tx_count            BIGINT          NOT NULL,
+    token_amount        TEXT            NOT NULL,
+    fee_paid            TEXT            NOT NULL,
+    first_transfer_date TIMESTAMPTZ     NULL,
+    last_transfer_date  TIMESTAMPTZ     NULL,
+    first_paid_date     TIMESTAMPTZ     NULL,
+    last_paid_date      TIMESTAMPTZ     NULL,
+    max_payment_delay   BIGINT          NULL
+);
+
+CREATE INDEX "idx_transfer_archive_stats_chain_id" ON "transfer_archive_stats" (chain_id, source);
@@ -0,0 +1,26 @@
+-- Payment id identifies transfer of given sender, duplicates created before are renamed.
+-- Renamed transfers are kept in payment_id_rename and reported on startup, as callers
+-- looking them up by the original payment id will find only the first one.
+CREATE TABLE "payment_id_rename"
+(
+    transfer_id         BIGINT           NOT NULL     PRIMARY KEY,
+    chain_id            BIGINT           NOT NULL,
+    from_addr           TEXT             NOT NULL,
+    payment_id          TEXT             NOT NULL,
+    new_payment_id      TEXT             NOT NULL
+);
+
+INSERT INTO payment_id_rename (transfer_id, chain_id, from_addr, payment_id, new_payment_id)
+SELECT id, chain_id, from_addr, payment_id, payment_id || '-dup-' || id FROM token_transfer
+WHERE payment_id IS NOT NULL AND id NOT IN (
+    SELECT MIN(id) FROM token_transfer WHERE payment_id IS NOT NULL
+    GROUP BY chain_id, from_addr, payment_id
+);
+
+UPDATE token_transfer SET payment_id = payment_id || '-dup-' || id
+WHERE payment_id IS NOT NULL AND id NOT IN (
+    SELECT MIN(id) FROM token_transfer WHERE payment_id IS NOT NULL
+    GROUP BY chain_id, from_addr, payment_id
+);
+
+CREATE UNIQUE INDEX "idx_token_transfer_payment_id" ON "token_transfer" ("chain_id", "from_addr", "payment_id");
@@ -0,0 +1,2 @@
+-- Priority class of the transfer (urgent, normal or bulk), see TransferPriority
+ALTER TABLE token_transfer ADD COLUMN priority TEXT NOT NULL DEFAULT 'normal';
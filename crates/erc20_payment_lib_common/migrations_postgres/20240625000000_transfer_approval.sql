@@ -0,0 +1,2 @@
+-- Set when transfer violating spending policy was approved by operator
+ALTER TABLE token_transfer ADD COLUMN approved_date TIMESTAMPTZ NULL;
@@ -0,0 +1,19 @@
+-- Driver events queued for delivery to configured webhooks.
+-- next_attempt_date is NULL when delivery is finished (delivered or attempts exhausted).
+CREATE TABLE "webhook_delivery"
+(
+    id                  BIGSERIAL       NOT NULL     PRIMARY KEY,
+    webhook             TEXT            NOT NULL,
+    event_type          TEXT            NOT NULL,
+    payload             TEXT            NOT NULL,
+    created_date        TIMESTAMPTZ     NOT NULL,
+    attempts            BIGINT          NOT NULL,
+    next_attempt_date   TIMESTAMPTZ     NULL,
+    last_attempt_date   TIMESTAMPTZ     NULL,
+    delivered_date      TIMESTAMPTZ     NULL,
+    response_status     BIGINT          NULL,
+    error               TEXT            NULL
+);
+
+CREATE INDEX "idx_webhook_delivery_next_attempt_date" ON "webhook_delivery" (next_attempt_date);
+CREATE INDEX "idx_webhook_delivery_webhook" ON "webhook_delivery" (webhook);
@@ -0,0 +1,17 @@
+-- Faucet requests, used for per-address, per-IP and per-chain limits and faucet history
+CREATE TABLE "faucet_request"
+(
+    id                  BIGSERIAL       NOT NULL     PRIMARY KEY,
+    chain_id            BIGINT          NOT NULL,
+    receiver_addr       TEXT            NOT NULL,
+    client_ip           TEXT            NULL,
+    gas_transfer_id     BIGINT          NULL,
+    gas_amount          TEXT            NOT NULL,
+    glm_transfer_id     BIGINT          NULL,
+    glm_amount          TEXT            NOT NULL,
+    create_date         TIMESTAMPTZ     NOT NULL
+);
+
+CREATE INDEX "idx_faucet_request_receiver_addr" ON "faucet_request" (chain_id, receiver_addr);
+CREATE INDEX "idx_faucet_request_client_ip" ON "faucet_request" (chain_id, client_ip);
+CREATE INDEX "idx_faucet_request_create_date" ON "faucet_request" (create_date);
@@ -0,0 +1,6 @@
+-- Id of the last driver event processed by consumers of the event log (i.e. webhook job)
+CREATE TABLE "event_offset"
+(
+    consumer            TEXT        NOT NULL     PRIMARY KEY,
+    last_event_id       BIGINT      NOT NULL
+);
@@ -0,0 +1,2 @@
+-- Gather transfer not later than this date, kept across restarts
+ALTER TABLE token_transfer ADD COLUMN deadline TIMESTAMPTZ NULL;
@@ -0,0 +1,326 @@
+use chrono::{DateTime, Utc};
+use futures::future::BoxFuture;
+use sqlx::postgres::PgRow;
+use sqlx::sqlite::SqliteRow;
+use sqlx::{Database, Executor, FromRow, Postgres, Sqlite};
+use std::marker::PhantomData;
+
+/// Database engine used for storing payments
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DbBackend {
+    Sqlite,
+    Postgres,
+}
+
+impl DbBackend {
+    /// SQL expression returning current time in format used by date columns
+    pub fn now(self) -> &'static str {
+        match self {
+            DbBackend::Sqlite => "strftime('%Y-%m-%dT%H:%M:%f', 'now')",
+            DbBackend::Postgres => "now()",
+        }
+    }
+}
+
+/// Query parameter, limited to types used by payment tables so that every backend can bind it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DbValue {
+    Int(Option<i64>),
+    Text(Option<String>),
+    Date(Option<DateTime<Utc>>),
+}
+
+impl From<i64> for DbValue {
+    fn from(value: i64) -> Self {
+        DbValue::Int(Some(value))
+    }
+}
+
+impl From<Option<i64>> for DbValue {
+    fn from(value: Option<i64>) -> Self {
+        DbValue::Int(value)
+    }
+}
+
+impl From<&str> for DbValue {
+    fn from(value: &str) -> Self {
+        DbValue::Text(Some(value.to_string()))
+    }
+}
+
+impl From<String> for DbValue {
+    fn from(value: String) -> Self {
+        DbValue::Text(Some(value))
+    }
+}
+
+impl From<&String> for DbValue {
+    fn from(value: &String) -> Self {
+        DbValue::Text(Some(value.clone()))
+    }
+}
+
+impl From<Option<String>> for DbValue {
+    fn from(value: Option<String>) -> Self {
+        DbValue::Text(value)
+    }
+}
+
+impl From<&Option<String>> for DbValue {
+    fn from(value: &Option<String>) -> Self {
+        DbValue::Text(value.clone())
+    }
+}
+
+impl From<DateTime<Utc>> for DbValue {
+    fn from(value: DateTime<Utc>) -> Self {
+        DbValue::Date(Some(value))
+    }
+}
+
+impl From<Option<DateTime<Utc>>> for DbValue {
+    fn from(value: Option<DateTime<Utc>>) -> Self {
+        DbValue::Date(value)
+    }
+}
+
+/// Row that can be read from every supported backend
+pub trait PaymentRow:
+    for<'r> FromRow<'r, SqliteRow> + for<'r> FromRow<'r, PgRow> + Send + Unpin
+{
+}
+
+impl<T> PaymentRow for T where
+    T: for<'r> FromRow<'r, SqliteRow> + for<'r> FromRow<'r, PgRow> + Send + Unpin
+{
+}
+
+/// Database that can hold payment tables. Operations in `ops` are written once
+/// against this trait, dialect differences are handled by checking `BACKEND`.
+pub trait PaymentDatabase: Database {
+    const BACKEND: DbBackend;
+
+    fn fetch_all<'e, 'c: 'e, E, O>(
+        executor: E,
+        sql: &'e str,
+        args: Vec<DbValue>,
+    ) -> BoxFuture<'e, Result<Vec<O>, sqlx::Error>>
+    where
+        E: 'e + Executor<'c, Database = Self>,
+        O: PaymentRow + 'e;
+
+    fn fetch_optional<'e, 'c: 'e, E, O>(
+        executor: E,
+        sql: &'e str,
+        args: Vec<DbValue>,
+    ) -> BoxFuture<'e, Result<Option<O>, sqlx::Error>>
+    where
+        E: 'e + Executor<'c, Database = Self>,
+        O: PaymentRow + 'e;
+
+    /// Returns number of affected rows
+    fn execute<'e, 'c: 'e, E>(
+        executor: E,
+        sql: &'e str,
+        args: Vec<DbValue>,
+    ) -> BoxFuture<'e, Result<u64, sqlx::Error>>
+    where
+        E: 'e + Executor<'c, Database = Self>;
+}
+
+macro_rules! bind_values {
+    ($query:expr, $args:expr) => {{
+        let mut query = $query;
+        for arg in $args {
+            query = match arg {
+                DbValue::Int(value) => query.bind(value),
+                DbValue::Text(value) => query.bind(value),
+                DbValue::Date(value) => query.bind(value),
+            };
+        }
+        query
+    }};
+}
+
+macro_rules! impl_payment_database {
+    ($db:ty, $backend:expr) => {
+        impl PaymentDatabase for $db {
+            const BACKEND: DbBackend = $backend;
+
+            fn fetch_all<'e, 'c: 'e, E, O>(
+                executor: E,
+                sql: &'e str,
+                args: Vec<DbValue>,
+            ) -> BoxFuture<'e, Result<Vec<O>, sqlx::Error>>
+            where
+                E: 'e + Executor<'c, Database = Self>,
+                O: PaymentRow + 'e,
+            {
+                let query = bind_values!(sqlx::query_as::<$db, O>(sql), args);
+                Box::pin(async move { query.fetch_all(executor).await })
+            }
+
+            fn fetch_optional<'e, 'c: 'e, E, O>(
+                executor: E,
+                sql: &'e str,
+                args: Vec<DbValue>,
+            ) -> BoxFuture<'e, Result<Option<O>, sqlx::Error>>
+            where
+                E: 'e + Executor<'c, Database = Self>,
+                O: PaymentRow + 'e,
+            {
+                let query = bind_values!(sqlx::query_as::<$db, O>(sql), args);
+                Box::pin(async move { query.fetch_optional(executor).await })
+            }
+
+            fn execute<'e, 'c: 'e, E>(
+                executor: E,
+                sql: &'e str,
+                args: Vec<DbValue>,
+            ) -> BoxFuture<'e, Result<u64, sqlx::Error>>
+            where
+                E: 'e + Executor<'c, Database = Self>,
+            {
+                let query = bind_values!(sqlx::query::<$db>(sql), args);
+                Box::pin(async move { Ok(query.execute(executor).await?.rows_affected()) })
+            }
+        }
+    };
+}
+
+impl_payment_database!(Sqlite, DbBackend::Sqlite);
+impl_payment_database!(Postgres, DbBackend::Postgres);
+
+/// Same as `sqlx::query_as`, but not tied to single database
+pub fn query_as<O: PaymentRow>(sql: &str) -> DbQueryAs<'_, O> {
+    DbQueryAs {
+        sql,
+        args: Vec::new(),
+        row: PhantomData,
+    }
+}
+
+/// Same as `sqlx::query_scalar`, but not tied to single database
+pub fn query_scalar<O>(sql: &str) -> DbQueryScalar<'_, O>
+where
+    (O,): PaymentRow,
+{
+    DbQueryScalar {
+        inner: query_as(sql),
+    }
+}
+
+/// Same as `sqlx::query`, but not tied to single database
+pub fn query(sql: &str) -> DbQuery<'_> {
+    DbQuery {
+        sql,
+        args: Vec::new(),
+    }
+}
+
+pub struct DbQueryAs<'q, O> {
+    sql: &'q str,
+    args: Vec<DbValue>,
+    row: PhantomData<O>,
+}
+
+impl<'q, O: PaymentRow> DbQueryAs<'q, O> {
+    pub fn bind<T: Into<DbValue>>(mut self, value: T) -> Self {
+        self.args.push(value.into());
+        self
+    }
+
+    pub fn fetch_all<'e, 'c: 'e, E>(self, executor: E) -> BoxFuture<'e, Result<Vec<O>, sqlx::Error>>
+    where
+        'q: 'e,
+        O: 'e,
+        E: 'e + Executor<'c>,
+        E::Database: PaymentDatabase,
+    {
+        E::Database::fetch_all(executor, self.sql, self.args)
+    }
+
+    pub fn fetch_optional<'e, 'c: 'e, E>(
+        self,
+        executor: E,
+    ) -> BoxFuture<'e, Result<Option<O>, sqlx::Error>>
+    where
+        'q: 'e,
+        O: 'e,
+        E: 'e + Executor<'c>,
+        E::Database: PaymentDatabase,
+    {
+        E::Database::fetch_optional(executor, self.sql, self.args)
+    }
+
+    pub fn fetch_one<'e, 'c: 'e, E>(self, executor: E) -> BoxFuture<'e, Result<O, sqlx::Error>>
+    where
+        'q: 'e,
+        O: 'e,
+        E: 'e + Executor<'c>,
+        E::Database: PaymentDatabase,
+    {
+        let row = self.fetch_optional(executor);
+        Box::pin(async move { row.await?.ok_or(sqlx::Error::RowNotFound) })
+    }
+}
+
+pub struct DbQueryScalar<'q, O> {
+    inner: DbQueryAs<'q, (O,)>,
+}
+
+impl<'q, O: Send + 'q> DbQueryScalar<'q, O>
+where
+    (O,): PaymentRow,
+{
+    pub fn bind<T: Into<DbValue>>(self, value: T) -> Self {
+        Self {
+            inner: self.inner.bind(value),
+        }
+    }
+
+    pub fn fetch_optional<'e, 'c: 'e, E>(
+        self,
+        executor: E,
+    ) -> BoxFuture<'e, Result<Option<O>, sqlx::Error>>
+    where
+        'q: 'e,
+        E: 'e + Executor<'c>,
+        E::Database: PaymentDatabase,
+    {
+        let row = self.inner.fetch_optional(executor);
+        Box::pin(async move { Ok(row.await?.map(|(value,)| value)) })
+    }
+
+    pub fn fetch_one<'e, 'c: 'e, E>(self, executor: E) -> BoxFuture<'e, Result<O, sqlx::Error>>
+    where
+        'q: 'e,
+        E: 'e + Executor<'c>,
+        E::Database: PaymentDatabase,
+    {
+        let row = self.inner.fetch_one(executor);
+        Box::pin(async move { Ok(row.await?.0) })
+    }
+}
+
+pub struct DbQuery<'q> {
+    sql: &'q str,
+    args: Vec<DbValue>,
+}
+
+impl<'q> DbQuery<'q> {
+    pub fn bind<T: Into<DbValue>>(mut self, value: T) -> Self {
+        self.args.push(value.into());
+        self
+    }
+
+    /// Returns number of affected rows
+    pub fn execute<'e, 'c: 'e, E>(self, executor: E) -> BoxFuture<'e, Result<u64, sqlx::Error>>
+    where
+        'q: 'e,
+        E: 'e + Executor<'c>,
+        E::Database: PaymentDatabase,
+    {
+        E::Database::execute(executor, self.sql, self.args)
+    }
+}
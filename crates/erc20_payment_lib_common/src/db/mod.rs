@@ -1,3 +1,5 @@
 pub mod connection;
+pub mod database;
 pub mod model;
 pub mod ops;
+pub mod store;
@@ -1,17 +1,17 @@
-use chrono::{DateTime, Utc};
-use serde::Serialize;
-
-#[derive(Serialize, sqlx::FromRow, Debug, Clone)]
-#[serde(rename_all = "camelCase")]
-pub struct AllowanceDbObj {
-    pub id: i64,
-    pub owner: String,
-    pub token_addr: String,
-    pub spender: String,
-    pub allowance: String,
-    pub chain_id: i64,
-    pub tx_id: Option<i64>,
-    pub fee_paid: Option<String>,
-    pub confirm_date: Option<DateTime<Utc>>,
-    pub error: Option<String>,
-}
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, sqlx::FromRow, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AllowanceDbObj {
+    pub id: i64,
+    pub owner: String,
+    pub token_addr: String,
+    pub spender: String,
+    pub allowance: String,
+    pub chain_id: i64,
+    pub tx_id: Option<i64>,
+    pub fee_paid: Option<String>,
+    pub confirm_date: Option<DateTime<Utc>>,
+    pub error: Option<String>,
+}
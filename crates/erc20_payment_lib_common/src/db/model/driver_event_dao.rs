@@ -0,0 +1,13 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Entry of the event log
+#[derive(Serialize, Deserialize, sqlx::FromRow, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct DriverEventDbObj {
+    pub id: i64,
+    pub event_type: String,
+    pub create_date: DateTime<Utc>,
+    /// Serialized [`crate::DriverEvent`] without id
+    pub payload: String,
+}
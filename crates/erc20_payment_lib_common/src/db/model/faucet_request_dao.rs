@@ -0,0 +1,19 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Test tokens sent by the faucet to a single receiver
+#[derive(Serialize, Deserialize, sqlx::FromRow, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct FaucetRequestDbObj {
+    pub id: i64,
+    pub chain_id: i64,
+    pub receiver_addr: String,
+    /// Address of the requesting client, used only for rate limiting
+    #[serde(skip_serializing)]
+    pub client_ip: Option<String>,
+    pub gas_transfer_id: Option<i64>,
+    pub gas_amount: String,
+    pub glm_transfer_id: Option<i64>,
+    pub glm_amount: String,
+    pub create_date: DateTime<Utc>,
+}
@@ -2,16 +2,28 @@ mod allowance_dao;
 mod chain_transfer_dao;
 mod chain_tx_dao;
 mod deposit_id;
+mod driver_event_dao;
+mod faucet_request_dao;
 mod scan_dao;
 mod token_transfer_dao;
+mod transfer_archive_stats_dao;
 mod transfer_in_dao;
+mod transfer_priority;
 mod tx_dao;
+mod webhook_delivery_dao;
 
 pub use allowance_dao::AllowanceDbObj;
 pub use chain_transfer_dao::{ChainTransferDbObj, ChainTransferDbObjExt};
 pub use chain_tx_dao::ChainTxDbObj;
 pub use deposit_id::DepositId;
+pub use driver_event_dao::DriverEventDbObj;
+pub use faucet_request_dao::FaucetRequestDbObj;
 pub use scan_dao::ScanDaoDbObj;
 pub use token_transfer_dao::TokenTransferDbObj;
+pub use transfer_archive_stats_dao::{
+    TransferArchiveStatsDbObj, ARCHIVE_SOURCE_CHAIN_TRANSFER, ARCHIVE_SOURCE_TOKEN_TRANSFER,
+};
 pub use transfer_in_dao::TransferInDbObj;
+pub use transfer_priority::TransferPriority;
 pub use tx_dao::TxDbObj;
+pub use webhook_delivery_dao::WebhookDeliveryDbObj;
@@ -1,3 +1,4 @@
+use super::TransferPriority;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
@@ -21,4 +22,17 @@ pub struct TokenTransferDbObj {
     pub paid_date: Option<DateTime<Utc>>,
     pub fee_paid: Option<String>,
     pub error: Option<String>,
+    /// See [`TransferPriority`], normal when missing in imported data
+    #[serde(default = "default_priority")]
+    pub priority: String,
+    /// Set when transfer held by spending policy was approved
+    #[serde(default)]
+    pub approved_date: Option<DateTime<Utc>>,
+    /// Transfer is gathered into transaction not later than this date
+    #[serde(default)]
+    pub deadline: Option<DateTime<Utc>>,
+}
+
+fn default_priority() -> String {
+    TransferPriority::default().to_string()
 }
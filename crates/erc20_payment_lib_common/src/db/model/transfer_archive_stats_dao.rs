@@ -0,0 +1,33 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// Stats of archived token_transfer rows
+pub const ARCHIVE_SOURCE_TOKEN_TRANSFER: &str = "token_transfer";
+/// Stats of archived chain_transfer rows
+pub const ARCHIVE_SOURCE_CHAIN_TRANSFER: &str = "chain_transfer";
+
+/// Aggregated totals of transfers moved to archive, grouped by sender, receiver and token.
+/// Row with `receiver_addr` set to None contains totals for the sender.
+#[derive(Serialize, sqlx::FromRow, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferArchiveStatsDbObj {
+    pub id: i64,
+    pub source: String,
+    pub chain_id: i64,
+    pub from_addr: String,
+    pub receiver_addr: Option<String>,
+    /// None means native token
+    pub token_addr: Option<String>,
+    pub transfer_count: i64,
+    pub done_count: i64,
+    pub tx_count: i64,
+    /// Sum of amounts of done transfers
+    pub token_amount: String,
+    pub fee_paid: String,
+    pub first_transfer_date: Option<DateTime<Utc>>,
+    pub last_transfer_date: Option<DateTime<Utc>>,
+    pub first_paid_date: Option<DateTime<Utc>>,
+    pub last_paid_date: Option<DateTime<Utc>>,
+    /// In milliseconds
+    pub max_payment_delay: Option<i64>,
+}
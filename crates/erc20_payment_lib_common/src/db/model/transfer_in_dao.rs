@@ -1,7 +1,7 @@
 use chrono::{DateTime, Utc};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, sqlx::FromRow, Debug, Clone)]
+#[derive(Serialize, Deserialize, sqlx::FromRow, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct TransferInDbObj {
     pub id: i64,
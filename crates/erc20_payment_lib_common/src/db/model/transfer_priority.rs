@@ -0,0 +1,48 @@
+use crate::err_custom_create;
+use crate::error::PaymentError;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// Priority class of token transfer. Urgent transfers are gathered immediately,
+/// others can be held back by scheduling rules of the sender.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransferPriority {
+    Urgent,
+    #[default]
+    Normal,
+    Bulk,
+}
+
+impl TransferPriority {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransferPriority::Urgent => "urgent",
+            TransferPriority::Normal => "normal",
+            TransferPriority::Bulk => "bulk",
+        }
+    }
+}
+
+impl Display for TransferPriority {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for TransferPriority {
+    type Err = PaymentError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "urgent" => Ok(TransferPriority::Urgent),
+            "normal" => Ok(TransferPriority::Normal),
+            "bulk" => Ok(TransferPriority::Bulk),
+            _ => Err(err_custom_create!(
+                "Invalid priority {}, expected urgent, normal or bulk",
+                s
+            )),
+        }
+    }
+}
@@ -0,0 +1,24 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Driver event queued for delivery to a webhook
+#[derive(Serialize, Deserialize, sqlx::FromRow, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookDeliveryDbObj {
+    pub id: i64,
+    /// Name of the webhook from config
+    pub webhook: String,
+    pub event_type: String,
+    /// Serialized [`crate::DriverEvent`], sent as request body
+    pub payload: String,
+    pub created_date: DateTime<Utc>,
+    pub attempts: i64,
+    /// None when delivery is finished (delivered or attempts exhausted)
+    pub next_attempt_date: Option<DateTime<Utc>>,
+    pub last_attempt_date: Option<DateTime<Utc>>,
+    pub delivered_date: Option<DateTime<Utc>>,
+    /// Http status of the last attempt
+    pub response_status: Option<i64>,
+    /// Error of the last failed attempt
+    pub error: Option<String>,
+}
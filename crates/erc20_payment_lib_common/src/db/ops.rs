@@ -1,21 +1,29 @@
 mod allowance_ops;
 mod chain_transfer_ops;
 mod chain_tx_ops;
+mod driver_event_ops;
+mod faucet_request_ops;
 mod scan_ops;
 mod token_transfer_ops;
+mod transfer_archive_stats_ops;
 mod transfer_in_ops;
 mod tx_ops;
+mod webhook_delivery_ops;
 
 use super::model;
 pub use allowance_ops::*;
 pub use chain_transfer_ops::*;
 pub use chain_tx_ops::*;
+pub use driver_event_ops::*;
+pub use faucet_request_ops::*;
 pub use scan_ops::*;
 use std::future::Future;
 use std::time::Duration;
 pub use token_transfer_ops::*;
+pub use transfer_archive_stats_ops::*;
 pub use transfer_in_ops::*;
 pub use tx_ops::*;
+pub use webhook_delivery_ops::*;
 
 const LOCKED_TIMEOUT: Duration = std::time::Duration::from_secs(300);
 
@@ -1,16 +1,16 @@
 use super::model::AllowanceDbObj;
+use crate::db::database::{query, query_as, PaymentDatabase};
 use sqlx::Executor;
-use sqlx::Sqlite;
-use sqlx::SqlitePool;
 
 pub async fn insert_allowance<'c, E>(
     executor: E,
     allowance: &AllowanceDbObj,
 ) -> Result<AllowanceDbObj, sqlx::Error>
 where
-    E: Executor<'c, Database = Sqlite>,
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
 {
-    let res = sqlx::query_as::<_, AllowanceDbObj>(
+    let res = query_as::<AllowanceDbObj>(
         r"INSERT INTO allowance
 (
 owner,
@@ -45,9 +45,10 @@ pub async fn update_allowance<'c, E>(
     allowance: &AllowanceDbObj,
 ) -> Result<(), sqlx::Error>
 where
-    E: Executor<'c, Database = Sqlite>,
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
 {
-    let _res = sqlx::query(
+    let _res = query(
         r"UPDATE allowance SET
 owner = $2,
 token_addr = $3,
@@ -76,8 +77,12 @@ WHERE id = $1
     Ok(())
 }
 
-pub async fn get_all_allowances(conn: &SqlitePool) -> Result<Vec<AllowanceDbObj>, sqlx::Error> {
-    let rows = sqlx::query_as::<_, AllowanceDbObj>(r"SELECT * FROM allowance")
+pub async fn get_all_allowances<'c, E>(conn: E) -> Result<Vec<AllowanceDbObj>, sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
+    let rows = query_as::<AllowanceDbObj>(r"SELECT * FROM allowance")
         .fetch_all(conn)
         .await?;
     Ok(rows)
@@ -88,23 +93,28 @@ pub async fn get_allowance_by_tx<'c, E>(
     tx_id: i64,
 ) -> Result<AllowanceDbObj, sqlx::Error>
 where
-    E: Executor<'c, Database = Sqlite>,
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
 {
-    let row = sqlx::query_as::<_, AllowanceDbObj>(r"SELECT * FROM allowance WHERE tx_id=$1")
+    let row = query_as::<AllowanceDbObj>(r"SELECT * FROM allowance WHERE tx_id=$1")
         .bind(tx_id)
         .fetch_one(executor)
         .await?;
     Ok(row)
 }
 
-pub async fn find_allowance(
-    conn: &SqlitePool,
+pub async fn find_allowance<'c, E>(
+    conn: E,
     owner: &str,
     token_addr: &str,
     spender: &str,
     chain_id: i64,
-) -> Result<Option<AllowanceDbObj>, sqlx::Error> {
-    let row = sqlx::query_as::<_, AllowanceDbObj>(
+) -> Result<Option<AllowanceDbObj>, sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
+    let row = query_as::<AllowanceDbObj>(
         r"SELECT * FROM allowance
 WHERE
 owner = $1 AND
@@ -122,11 +132,15 @@ chain_id = $4
     Ok(row)
 }
 
-pub async fn get_allowances_by_owner(
-    conn: &SqlitePool,
+pub async fn get_allowances_by_owner<'c, E>(
+    conn: E,
     owner: &str,
-) -> Result<Vec<AllowanceDbObj>, sqlx::Error> {
-    let row = sqlx::query_as::<_, AllowanceDbObj>(
+) -> Result<Vec<AllowanceDbObj>, sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
+    let row = query_as::<AllowanceDbObj>(
         r"SELECT * FROM allowance
 WHERE
 owner = $1
@@ -144,9 +158,10 @@ pub async fn remap_allowance_tx<'c, E>(
     new_tx_id: i64,
 ) -> Result<(), sqlx::Error>
 where
-    E: Executor<'c, Database = Sqlite>,
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
 {
-    let _res = sqlx::query(
+    let _res = query(
         r"UPDATE allowance SET
             tx_id = $2
             WHERE tx_id = $1
@@ -161,9 +176,10 @@ where
 
 pub async fn cleanup_allowance_tx<'c, E>(executor: E, tx_id: i64) -> Result<(), sqlx::Error>
 where
-    E: Executor<'c, Database = Sqlite>,
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
 {
-    let _res = sqlx::query(
+    let _res = query(
         r"UPDATE allowance SET
             tx_id = NULL,
             fee_paid = NULL,
@@ -1,18 +1,18 @@
 use super::model::ChainTransferDbObj;
+use crate::db::database::{query, query_as, PaymentDatabase};
 use crate::model::ChainTransferDbObjExt;
 use chrono::{DateTime, Utc};
 use sqlx::Executor;
-use sqlx::Sqlite;
-use sqlx::SqlitePool;
 
 pub async fn insert_chain_transfer<'c, E>(
     executor: E,
     chain_transfer: &ChainTransferDbObj,
 ) -> Result<ChainTransferDbObj, sqlx::Error>
 where
-    E: Executor<'c, Database = Sqlite>,
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
 {
-    let res = sqlx::query_as::<_, ChainTransferDbObj>(
+    let res = query_as::<ChainTransferDbObj>(
         r"INSERT INTO chain_transfer
 (from_addr, receiver_addr, chain_id, token_addr, token_amount, chain_tx_id, fee_paid, blockchain_date)
 VALUES ($1, $2, $3, $4, $5, $6, $7, $8) RETURNING *;
@@ -30,15 +30,19 @@ VALUES ($1, $2, $3, $4, $5, $6, $7, $8) RETURNING *;
     .await?;
     Ok(res)
 }
-pub async fn get_all_chain_transfers_ext(
-    conn: &SqlitePool,
+pub async fn get_all_chain_transfers_ext<'c, E>(
+    conn: E,
     chain_id: i64,
     from: DateTime<Utc>,
     to: DateTime<Utc>,
     limit: Option<i64>,
-) -> Result<Vec<ChainTransferDbObjExt>, sqlx::Error> {
+) -> Result<Vec<ChainTransferDbObjExt>, sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
     let limit = limit.unwrap_or(i64::MAX);
-    let rows = sqlx::query_as::<_, ChainTransferDbObjExt>(
+    let rows = query_as::<ChainTransferDbObjExt>(
         r"SELECT ct.*, cx.tx_hash, cx.block_number, cx.to_addr, cx.from_addr as caller_addr FROM chain_transfer as ct JOIN chain_tx as cx ON ct.chain_tx_id = cx.id WHERE ct.chain_id = $1 AND ct.blockchain_date >= $2 AND ct.blockchain_date <= $3 ORDER by id DESC LIMIT $4",
     )
         .bind(chain_id)
@@ -50,16 +54,20 @@ pub async fn get_all_chain_transfers_ext(
     Ok(rows)
 }
 
-pub async fn get_all_chain_transfers_by_receiver_ext(
-    conn: &SqlitePool,
+pub async fn get_all_chain_transfers_by_receiver_ext<'c, E>(
+    conn: E,
     chain_id: i64,
     from: DateTime<Utc>,
     to: DateTime<Utc>,
     receiver: &str,
     limit: Option<i64>,
-) -> Result<Vec<ChainTransferDbObjExt>, sqlx::Error> {
+) -> Result<Vec<ChainTransferDbObjExt>, sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
     let limit = limit.unwrap_or(i64::MAX);
-    let rows = sqlx::query_as::<_, ChainTransferDbObjExt>(
+    let rows = query_as::<ChainTransferDbObjExt>(
         r"SELECT ct.*, cx.tx_hash, cx.block_number, cx.to_addr, cx.from_addr as caller_addr FROM chain_transfer as ct JOIN chain_tx as cx ON ct.chain_tx_id = cx.id WHERE ct.chain_id = $1 AND ct.blockchain_date >= $2 AND ct.blockchain_date <= $3 AND ct.receiver_addr = $4 ORDER by id DESC LIMIT $5",
     )
         .bind(chain_id)
@@ -72,15 +80,19 @@ pub async fn get_all_chain_transfers_by_receiver_ext(
     Ok(rows)
 }
 
-pub async fn get_all_chain_transfers(
-    conn: &SqlitePool,
+pub async fn get_all_chain_transfers<'c, E>(
+    conn: E,
     chain_id: i64,
     from: DateTime<Utc>,
     to: DateTime<Utc>,
     limit: Option<i64>,
-) -> Result<Vec<ChainTransferDbObj>, sqlx::Error> {
+) -> Result<Vec<ChainTransferDbObj>, sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
     let limit = limit.unwrap_or(i64::MAX);
-    let rows = sqlx::query_as::<_, ChainTransferDbObj>(
+    let rows = query_as::<ChainTransferDbObj>(
         r"SELECT * FROM chain_transfer WHERE chain_id = $1 AND blockchain_date >= $2 AND blockchain_date <= $3 ORDER by id DESC LIMIT $4",
     )
         .bind(chain_id)
@@ -92,13 +104,17 @@ pub async fn get_all_chain_transfers(
     Ok(rows)
 }
 
-pub async fn get_chain_transfers_by_chain_id(
-    conn: &SqlitePool,
+pub async fn get_chain_transfers_by_chain_id<'c, E>(
+    conn: E,
     chain_id: i64,
     limit: Option<i64>,
-) -> Result<Vec<ChainTransferDbObj>, sqlx::Error> {
+) -> Result<Vec<ChainTransferDbObj>, sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
     let limit = limit.unwrap_or(i64::MAX);
-    let rows = sqlx::query_as::<_, ChainTransferDbObj>(
+    let rows = query_as::<ChainTransferDbObj>(
         r"SELECT * FROM chain_transfer WHERE chain_id = $1 ORDER by id DESC LIMIT $2",
     )
     .bind(chain_id)
@@ -107,3 +123,35 @@ pub async fn get_chain_transfers_by_chain_id(
     .await?;
     Ok(rows)
 }
+
+pub async fn get_chain_transfers_by_chain_tx<'c, E>(
+    conn: E,
+    chain_tx_id: i64,
+) -> Result<Vec<ChainTransferDbObj>, sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
+    let rows = query_as::<ChainTransferDbObj>(
+        r"SELECT * FROM chain_transfer WHERE chain_tx_id = $1 ORDER by id ASC",
+    )
+    .bind(chain_tx_id)
+    .fetch_all(conn)
+    .await?;
+    Ok(rows)
+}
+
+pub async fn delete_chain_transfers_by_chain_tx<'c, E>(
+    conn: E,
+    chain_tx_id: i64,
+) -> Result<(), sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
+    query(r"DELETE FROM chain_transfer WHERE chain_tx_id = $1")
+        .bind(chain_tx_id)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
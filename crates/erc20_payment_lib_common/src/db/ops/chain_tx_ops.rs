@@ -1,17 +1,17 @@
 use super::model::ChainTxDbObj;
+use crate::db::database::{query, query_as, query_scalar, PaymentDatabase};
 use chrono::{DateTime, Utc};
 use sqlx::Executor;
-use sqlx::Sqlite;
-use sqlx::SqlitePool;
 
 pub async fn insert_chain_tx<'c, E>(
     executor: E,
     tx: &ChainTxDbObj,
 ) -> Result<ChainTxDbObj, sqlx::Error>
 where
-    E: Executor<'c, Database = Sqlite>,
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
 {
-    let res = sqlx::query_as::<_, ChainTxDbObj>(
+    let res = query_as::<ChainTxDbObj>(
         r"INSERT INTO chain_tx
 (tx_hash, method, from_addr, to_addr, chain_id, gas_used, gas_limit, block_gas_price, effective_gas_price, max_fee_per_gas, priority_fee, val, nonce, checked_date, blockchain_date, block_number, chain_status, fee_paid, error, balance_eth, balance_glm)
 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21) RETURNING *",
@@ -42,13 +42,17 @@ VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $
     Ok(res)
 }
 
-pub async fn get_chain_txs_by_chain_id(
-    conn: &SqlitePool,
+pub async fn get_chain_txs_by_chain_id<'c, E>(
+    conn: E,
     chain_id: i64,
     limit: Option<i64>,
-) -> Result<Vec<ChainTxDbObj>, sqlx::Error> {
+) -> Result<Vec<ChainTxDbObj>, sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
     let limit = limit.unwrap_or(i64::MAX);
-    let rows = sqlx::query_as::<_, ChainTxDbObj>(
+    let rows = query_as::<ChainTxDbObj>(
         r"SELECT * FROM chain_tx WHERE chain_id = $1 ORDER by id DESC LIMIT $2",
     )
     .bind(chain_id)
@@ -58,15 +62,19 @@ pub async fn get_chain_txs_by_chain_id(
     Ok(rows)
 }
 
-pub async fn get_chain_txs_by_chain_id_and_dates(
-    conn: &SqlitePool,
+pub async fn get_chain_txs_by_chain_id_and_dates<'c, E>(
+    conn: E,
     chain_id: i64,
     from: DateTime<Utc>,
     to: DateTime<Utc>,
     limit: Option<i64>,
-) -> Result<Vec<ChainTxDbObj>, sqlx::Error> {
+) -> Result<Vec<ChainTxDbObj>, sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
     let limit = limit.unwrap_or(i64::MAX);
-    let rows = sqlx::query_as::<_, ChainTxDbObj>(
+    let rows = query_as::<ChainTxDbObj>(
         r"SELECT * FROM chain_tx WHERE chain_id = $1 AND blockchain_date >= $2 AND blockchain_date <= $3 ORDER by id DESC LIMIT $4",
     )
     .bind(chain_id)
@@ -78,22 +86,59 @@ pub async fn get_chain_txs_by_chain_id_and_dates(
     Ok(rows)
 }
 
-pub async fn get_chain_tx(conn: &SqlitePool, id: i64) -> Result<ChainTxDbObj, sqlx::Error> {
-    let row = sqlx::query_as::<_, ChainTxDbObj>(r"SELECT * FROM chain_tx WHERE id = $1")
+pub async fn get_chain_tx<'c, E>(conn: E, id: i64) -> Result<ChainTxDbObj, sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
+    let row = query_as::<ChainTxDbObj>(r"SELECT * FROM chain_tx WHERE id = $1")
         .bind(id)
         .fetch_one(conn)
         .await?;
     Ok(row)
 }
 
+pub async fn get_chain_txs_before<'c, E>(
+    conn: E,
+    before: DateTime<Utc>,
+    limit: i64,
+) -> Result<Vec<ChainTxDbObj>, sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
+    let rows = query_as::<ChainTxDbObj>(
+        r"SELECT * FROM chain_tx WHERE blockchain_date < $1 ORDER BY id ASC LIMIT $2",
+    )
+    .bind(before)
+    .bind(limit)
+    .fetch_all(conn)
+    .await?;
+    Ok(rows)
+}
+
+/// Chain transfers referencing the transaction have to be deleted first
+pub async fn delete_chain_tx<'c, E>(conn: E, id: i64) -> Result<(), sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
+    query(r"DELETE FROM chain_tx WHERE id = $1")
+        .bind(id)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
 pub async fn get_chain_tx_hash<'c, E>(
     executor: E,
     tx_hash: String,
 ) -> Result<Option<ChainTxDbObj>, sqlx::Error>
 where
-    E: Executor<'c, Database = Sqlite>,
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
 {
-    let row = sqlx::query_as::<_, ChainTxDbObj>(r"SELECT * FROM chain_tx WHERE tx_hash = $1")
+    let row = query_as::<ChainTxDbObj>(r"SELECT * FROM chain_tx WHERE tx_hash = $1")
         .bind(tx_hash)
         .fetch_optional(executor)
         .await?;
@@ -105,9 +150,10 @@ pub async fn get_last_scanned_block<'c, E>(
     chain_id: i64,
 ) -> Result<Option<i64>, sqlx::Error>
 where
-    E: Executor<'c, Database = Sqlite>,
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
 {
-    sqlx::query_scalar::<_, i64>(r"SELECT MAX(block_number) FROM chain_tx WHERE chain_id = $1")
+    query_scalar::<i64>(r"SELECT MAX(block_number) FROM chain_tx WHERE chain_id = $1")
         .bind(chain_id)
         .fetch_optional(executor)
         .await
@@ -0,0 +1,105 @@
+use super::model::DriverEventDbObj;
+use crate::db::database::{query, query_as, query_scalar, PaymentDatabase};
+use chrono::{DateTime, Utc};
+use sqlx::Executor;
+
+pub async fn insert_driver_event<'c, E>(
+    conn: E,
+    event: &DriverEventDbObj,
+) -> Result<DriverEventDbObj, sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
+    let res = query_as::<DriverEventDbObj>(
+        r"INSERT INTO driver_event
+(event_type, create_date, payload)
+VALUES ($1, $2, $3) RETURNING *;
+",
+    )
+    .bind(&event.event_type)
+    .bind(event.create_date)
+    .bind(&event.payload)
+    .fetch_one(conn)
+    .await?;
+    Ok(res)
+}
+
+/// Events with id greater than `after_id`, oldest first
+pub async fn get_driver_events_after<'c, E>(
+    conn: E,
+    after_id: i64,
+    limit: i64,
+) -> Result<Vec<DriverEventDbObj>, sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
+    let rows = query_as::<DriverEventDbObj>(
+        r"SELECT * FROM driver_event WHERE id > $1 ORDER BY id ASC LIMIT $2",
+    )
+    .bind(after_id)
+    .bind(limit)
+    .fetch_all(conn)
+    .await?;
+    Ok(rows)
+}
+
+pub async fn delete_driver_events_before<'c, E>(
+    conn: E,
+    before: DateTime<Utc>,
+) -> Result<(), sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
+    query(r"DELETE FROM driver_event WHERE create_date < $1")
+        .bind(before)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+/// Id of the newest stored event, None when the log is empty
+pub async fn get_last_driver_event_id<'c, E>(conn: E) -> Result<Option<i64>, sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
+    query_scalar::<Option<i64>>(r"SELECT MAX(id) FROM driver_event")
+        .fetch_one(conn)
+        .await
+}
+
+/// Id of the last event processed by `consumer`, None when it has not processed any yet
+pub async fn get_event_offset<'c, E>(conn: E, consumer: &str) -> Result<Option<i64>, sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
+    query_scalar::<i64>(r"SELECT last_event_id FROM event_offset WHERE consumer = $1")
+        .bind(consumer)
+        .fetch_optional(conn)
+        .await
+}
+
+pub async fn set_event_offset<'c, E>(
+    conn: E,
+    consumer: &str,
+    last_event_id: i64,
+) -> Result<(), sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
+    query(
+        r"INSERT INTO event_offset (consumer, last_event_id) VALUES ($1, $2)
+ON CONFLICT (consumer) DO UPDATE SET last_event_id = excluded.last_event_id
+",
+    )
+    .bind(consumer)
+    .bind(last_event_id)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,60 @@
+use super::model::FaucetRequestDbObj;
+use crate::db::database::{query_as, PaymentDatabase};
+use crate::db::store::FaucetRequestListFilter;
+use sqlx::Executor;
+
+pub async fn insert_faucet_request<'c, E>(
+    conn: E,
+    request: &FaucetRequestDbObj,
+) -> Result<FaucetRequestDbObj, sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
+    let res = query_as::<FaucetRequestDbObj>(
+        r"INSERT INTO faucet_request
+(chain_id, receiver_addr, client_ip, gas_transfer_id, gas_amount, glm_transfer_id, glm_amount, create_date)
+VALUES ($1, $2, $3, $4, $5, $6, $7, $8) RETURNING *;
+",
+    )
+    .bind(request.chain_id)
+    .bind(&request.receiver_addr)
+    .bind(&request.client_ip)
+    .bind(request.gas_transfer_id)
+    .bind(&request.gas_amount)
+    .bind(request.glm_transfer_id)
+    .bind(&request.glm_amount)
+    .bind(request.create_date)
+    .fetch_one(conn)
+    .await?;
+    Ok(res)
+}
+
+pub async fn list_faucet_requests<'c, E>(
+    conn: E,
+    filter: &FaucetRequestListFilter,
+) -> Result<Vec<FaucetRequestDbObj>, sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
+    let rows = query_as::<FaucetRequestDbObj>(
+        r"SELECT * FROM faucet_request
+WHERE ($1 IS NULL OR id < $1)
+    AND ($2 IS NULL OR chain_id = $2)
+    AND ($3 IS NULL OR receiver_addr = $3)
+    AND ($4 IS NULL OR client_ip = $4)
+    AND ($5 IS NULL OR create_date >= $5)
+ORDER BY id DESC
+LIMIT $6",
+    )
+    .bind(filter.before_id)
+    .bind(filter.chain_id)
+    .bind(&filter.receiver)
+    .bind(&filter.client_ip)
+    .bind(filter.created_from)
+    .bind(filter.limit.unwrap_or(i64::MAX))
+    .fetch_all(conn)
+    .await?;
+    Ok(rows)
+}
@@ -1,5 +1,6 @@
 use super::model::ScanDaoDbObj;
-use sqlx::{Executor, Sqlite};
+use crate::db::database::{query, query_as, DbBackend, PaymentDatabase};
+use sqlx::Executor;
 
 pub async fn get_scan_info<'c, E>(
     executor: E,
@@ -7,15 +8,15 @@ pub async fn get_scan_info<'c, E>(
     filter: &str,
 ) -> Result<Option<ScanDaoDbObj>, sqlx::Error>
 where
-    E: Executor<'c, Database = Sqlite>,
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
 {
-    let row = sqlx::query_as::<_, ScanDaoDbObj>(
-        r"SELECT * FROM scan_info WHERE chain_id = $1 AND filter = $2",
-    )
-    .bind(chain_id)
-    .bind(filter)
-    .fetch_optional(executor)
-    .await?;
+    let row =
+        query_as::<ScanDaoDbObj>(r"SELECT * FROM scan_info WHERE chain_id = $1 AND filter = $2")
+            .bind(chain_id)
+            .bind(filter)
+            .fetch_optional(executor)
+            .await?;
     Ok(row)
 }
 
@@ -25,9 +26,10 @@ pub async fn delete_scan_info<'c, E>(
     filter: &str,
 ) -> Result<(), sqlx::Error>
 where
-    E: Executor<'c, Database = Sqlite>,
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
 {
-    sqlx::query(r"DELETE FROM scan_info WHERE chain_id = $1 AND filter = $2")
+    query(r"DELETE FROM scan_info WHERE chain_id = $1 AND filter = $2")
         .bind(chain_id)
         .bind(filter)
         .execute(executor)
@@ -40,20 +42,32 @@ pub async fn upsert_scan_info<'c, E>(
     scan_dao: &ScanDaoDbObj,
 ) -> Result<ScanDaoDbObj, sqlx::Error>
 where
-    E: Executor<'c, Database = Sqlite>,
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
 {
-    let res = sqlx::query_as::<_, ScanDaoDbObj>(
-        r"INSERT OR REPLACE INTO scan_info
+    let sql = match E::Database::BACKEND {
+        DbBackend::Sqlite => {
+            r"INSERT OR REPLACE INTO scan_info
 (chain_id, filter, start_block, last_block)
 VALUES ($1, $2, $3, $4) RETURNING *;
-",
-    )
-    .bind(scan_dao.chain_id)
-    .bind(&scan_dao.filter)
-    .bind(scan_dao.start_block)
-    .bind(scan_dao.last_block)
-    .fetch_one(executor)
-    .await?;
+"
+        }
+        DbBackend::Postgres => {
+            r"INSERT INTO scan_info
+(chain_id, filter, start_block, last_block)
+VALUES ($1, $2, $3, $4)
+ON CONFLICT (chain_id, filter) DO UPDATE SET start_block = excluded.start_block, last_block = excluded.last_block
+RETURNING *;
+"
+        }
+    };
+    let res = query_as::<ScanDaoDbObj>(sql)
+        .bind(scan_dao.chain_id)
+        .bind(&scan_dao.filter)
+        .bind(scan_dao.start_block)
+        .bind(scan_dao.last_block)
+        .fetch_one(executor)
+        .await?;
     Ok(res)
 }
 #[tokio::test]
@@ -1,12 +1,15 @@
-use super::model::TokenTransferDbObj;
-use crate::db::ops::get_chain_transfers_by_chain_id;
+use super::model::{
+    TokenTransferDbObj, TransferArchiveStatsDbObj, ARCHIVE_SOURCE_CHAIN_TRANSFER,
+    ARCHIVE_SOURCE_TOKEN_TRANSFER,
+};
+use crate::db::database::{query, query_as, query_scalar, PaymentDatabase};
+use crate::db::ops::{get_chain_transfers_by_chain_id, get_transfer_archive_stats};
+use crate::db::store::TransferListFilter;
 use crate::error::PaymentError;
 use crate::error::*;
 use crate::{err_custom_create, err_from};
 use chrono::{DateTime, Duration, Utc};
-use sqlx::Executor;
-use sqlx::Sqlite;
-use sqlx::SqlitePool;
+use sqlx::{Executor, Pool};
 use std::collections::{BTreeMap, HashSet};
 use std::ops::AddAssign;
 use std::str::FromStr;
@@ -18,9 +21,10 @@ pub async fn check_if_deposit_closed<'c, E>(
     deposit_id: &str,
 ) -> Result<bool, sqlx::Error>
 where
-    E: Executor<'c, Database = Sqlite>,
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
 {
-    let finished_count = sqlx::query_as::<_, (i64,)>(
+    let finished_count = query_as::<(i64,)>(
         "SELECT COUNT(*) FROM token_transfer WHERE chain_id=$1 AND deposit_id=$2 AND deposit_finish=1",
     )
     .bind(chain_id)
@@ -35,12 +39,49 @@ pub async fn insert_token_transfer<'c, E>(
     token_transfer: &TokenTransferDbObj,
 ) -> Result<TokenTransferDbObj, sqlx::Error>
 where
-    E: Executor<'c, Database = Sqlite>,
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
 {
-    sqlx::query_as::<_, TokenTransferDbObj>(
+    let sql = format!(
         r"INSERT INTO token_transfer
-(payment_id, from_addr, receiver_addr, chain_id, token_addr, token_amount, deposit_id, deposit_finish, create_date, tx_id, paid_date, fee_paid, error)
-VALUES ($1, $2, $3, $4, $5, $6, $7, $8, strftime('%Y-%m-%dT%H:%M:%f', 'now'), $9, $10, $11, $12) RETURNING *;
+(payment_id, from_addr, receiver_addr, chain_id, token_addr, token_amount, deposit_id, deposit_finish, create_date, tx_id, paid_date, fee_paid, error, priority, approved_date, deadline)
+VALUES ($1, $2, $3, $4, $5, $6, $7, $8, {}, $9, $10, $11, $12, $13, $14, $15) RETURNING *;
+",
+        E::Database::BACKEND.now()
+    );
+    query_as::<TokenTransferDbObj>(&sql)
+        .bind(&token_transfer.payment_id)
+        .bind(&token_transfer.from_addr)
+        .bind(&token_transfer.receiver_addr)
+        .bind(token_transfer.chain_id)
+        .bind(&token_transfer.token_addr)
+        .bind(&token_transfer.token_amount)
+        .bind(&token_transfer.deposit_id)
+        .bind(token_transfer.deposit_finish)
+        .bind(token_transfer.tx_id)
+        .bind(token_transfer.paid_date)
+        .bind(&token_transfer.fee_paid)
+        .bind(&token_transfer.error)
+        .bind(&token_transfer.priority)
+        .bind(token_transfer.approved_date)
+        .bind(token_transfer.deadline)
+        .fetch_one(executor)
+        .await
+}
+
+/// Insert transfer keeping its original create date, used when moving rows to archive
+pub async fn insert_archived_token_transfer<'c, E>(
+    executor: E,
+    token_transfer: &TokenTransferDbObj,
+) -> Result<TokenTransferDbObj, sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
+    query_as::<TokenTransferDbObj>(
+        r"INSERT INTO token_transfer
+(payment_id, from_addr, receiver_addr, chain_id, token_addr, token_amount, deposit_id, deposit_finish, create_date, tx_id, paid_date, fee_paid, error, priority, approved_date, deadline)
+VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16) RETURNING *;
 ",
     )
     .bind(&token_transfer.payment_id)
@@ -51,18 +92,26 @@ VALUES ($1, $2, $3, $4, $5, $6, $7, $8, strftime('%Y-%m-%dT%H:%M:%f', 'now'), $9
     .bind(&token_transfer.token_amount)
     .bind(&token_transfer.deposit_id)
     .bind(token_transfer.deposit_finish)
+    .bind(token_transfer.create_date)
     .bind(token_transfer.tx_id)
     .bind(token_transfer.paid_date)
     .bind(&token_transfer.fee_paid)
     .bind(&token_transfer.error)
+    .bind(&token_transfer.priority)
+    .bind(token_transfer.approved_date)
+    .bind(token_transfer.deadline)
     .fetch_one(executor)
     .await
 }
 
-pub async fn insert_token_transfer_with_deposit_check(
-    conn: &SqlitePool,
+pub async fn insert_token_transfer_with_deposit_check<DB>(
+    conn: &Pool<DB>,
     token_transfer: &TokenTransferDbObj,
-) -> Result<TokenTransferDbObj, PaymentError> {
+) -> Result<TokenTransferDbObj, PaymentError>
+where
+    DB: PaymentDatabase,
+    for<'c> &'c mut DB::Connection: Executor<'c, Database = DB>,
+{
     if let Some(deposit_id) = token_transfer.deposit_id.as_ref() {
         let mut transaction = conn.begin().await.map_err(err_from!())?;
         let is_finished =
@@ -74,26 +123,9 @@ pub async fn insert_token_transfer_with_deposit_check(
                 "Cannot add token_transfer to already finished deposit"
             ));
         }
-        let res = sqlx::query_as::<_, TokenTransferDbObj>(
-            r"INSERT INTO token_transfer
-(payment_id, from_addr, receiver_addr, chain_id, token_addr, token_amount, deposit_id, deposit_finish, create_date, tx_id, paid_date, fee_paid, error)
-VALUES ($1, $2, $3, $4, $5, $6, $7, $8, strftime('%Y-%m-%dT%H:%M:%f', 'now'), $9, $10, $11, $12) RETURNING *;
-",
-        )
-            .bind(&token_transfer.payment_id)
-            .bind(&token_transfer.from_addr)
-            .bind(&token_transfer.receiver_addr)
-            .bind(token_transfer.chain_id)
-            .bind(&token_transfer.token_addr)
-            .bind(&token_transfer.token_amount)
-            .bind(&token_transfer.deposit_id)
-            .bind(token_transfer.deposit_finish)
-            .bind(token_transfer.tx_id)
-            .bind(token_transfer.paid_date)
-            .bind(&token_transfer.fee_paid)
-            .bind(&token_transfer.error)
-            .fetch_one(&mut *transaction)
-            .await.map_err(err_from!())?;
+        let res = insert_token_transfer(&mut *transaction, token_transfer)
+            .await
+            .map_err(err_from!())?;
         transaction.commit().await.map_err(err_from!())?;
         Ok(res)
     } else {
@@ -109,9 +141,10 @@ pub async fn remap_token_transfer_tx<'c, E>(
     new_tx_id: i64,
 ) -> Result<(), sqlx::Error>
 where
-    E: Executor<'c, Database = Sqlite>,
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
 {
-    let _res = sqlx::query(
+    let _res = query(
         r"UPDATE token_transfer SET
             tx_id = $2
             WHERE tx_id = $1
@@ -126,9 +159,10 @@ where
 
 pub async fn cleanup_token_transfer_tx<'c, E>(executor: E, tx_id: i64) -> Result<(), sqlx::Error>
 where
-    E: Executor<'c, Database = Sqlite>,
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
 {
-    let _res = sqlx::query(
+    let _res = query(
         r"UPDATE token_transfer SET
             tx_id = NULL,
             fee_paid = NULL,
@@ -143,14 +177,157 @@ where
     Ok(())
 }
 
+pub async fn get_token_transfer_by_payment_id<'c, E>(
+    executor: E,
+    chain_id: i64,
+    from_addr: &str,
+    payment_id: &str,
+) -> Result<Option<TokenTransferDbObj>, sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
+    let row = query_as::<TokenTransferDbObj>(
+        r"SELECT * FROM token_transfer WHERE chain_id = $1 AND from_addr = $2 AND payment_id = $3",
+    )
+    .bind(chain_id)
+    .bind(from_addr)
+    .bind(payment_id)
+    .fetch_optional(executor)
+    .await?;
+    Ok(row)
+}
+
+pub async fn get_token_transfer<'c, E>(
+    executor: E,
+    id: i64,
+) -> Result<Option<TokenTransferDbObj>, sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
+    let row = query_as::<TokenTransferDbObj>(r"SELECT * FROM token_transfer WHERE id = $1")
+        .bind(id)
+        .fetch_optional(executor)
+        .await?;
+    Ok(row)
+}
+
+/// Delete transfer only if it is not yet attached to transaction, returns false otherwise
+pub async fn delete_pending_token_transfer<'c, E>(executor: E, id: i64) -> Result<bool, sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
+    let affected = query(r"DELETE FROM token_transfer WHERE id = $1 AND tx_id IS NULL")
+        .bind(id)
+        .execute(executor)
+        .await?;
+    Ok(affected > 0)
+}
+
+/// Change receiver, amount, priority and deadline of transfer only if it is not yet attached
+/// to transaction, returns false otherwise. Error is cleared, so that transfer is picked up again,
+/// policy approval is cleared when receiver, amount or priority changes, as it was given to previous values.
+pub async fn update_pending_token_transfer<'c, E>(
+    executor: E,
+    id: i64,
+    receiver_addr: &str,
+    token_amount: &str,
+    priority: &str,
+    deadline: Option<DateTime<Utc>>,
+) -> Result<bool, sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
+    let affected = query(
+        r"UPDATE token_transfer SET
+receiver_addr = $2,
+token_amount = $3,
+priority = $4,
+deadline = $5,
+error = NULL,
+approved_date = CASE
+    WHEN receiver_addr = $2 AND token_amount = $3 AND priority = $4 THEN approved_date
+    ELSE NULL
+END
+WHERE id = $1 AND tx_id IS NULL
+",
+    )
+    .bind(id)
+    .bind(receiver_addr)
+    .bind(token_amount)
+    .bind(priority)
+    .bind(deadline)
+    .execute(executor)
+    .await?;
+    Ok(affected > 0)
+}
+
+/// Mark pending transfer as approved, so that spending policy does not hold it back.
+/// Error is cleared, returns false if transfer is not pending.
+pub async fn approve_token_transfer<'c, E>(executor: E, id: i64) -> Result<bool, sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
+    let sql = format!(
+        r"UPDATE token_transfer SET approved_date = {}, error = NULL
+WHERE id = $1 AND tx_id IS NULL
+",
+        E::Database::BACKEND.now()
+    );
+    let affected = query(&sql).bind(id).execute(executor).await?;
+    Ok(affected > 0)
+}
+
+/// Attach transfer to transaction, but only if it is still pending and was not changed
+/// since it was read. Returns false if transfer was cancelled or edited in the meantime.
+pub async fn attach_token_transfer_to_tx<'c, E>(
+    executor: E,
+    token_transfer: &TokenTransferDbObj,
+    tx_id: i64,
+) -> Result<bool, sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
+    let affected = query(
+        r"UPDATE token_transfer SET tx_id = $2
+WHERE id = $1 AND tx_id IS NULL AND receiver_addr = $3 AND token_amount = $4
+",
+    )
+    .bind(token_transfer.id)
+    .bind(tx_id)
+    .bind(&token_transfer.receiver_addr)
+    .bind(&token_transfer.token_amount)
+    .execute(executor)
+    .await?;
+    Ok(affected > 0)
+}
+
+pub async fn delete_token_transfers_by_tx<'c, E>(executor: E, tx_id: i64) -> Result<(), sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
+    query(r"DELETE FROM token_transfer WHERE tx_id = $1")
+        .bind(tx_id)
+        .execute(executor)
+        .await?;
+    Ok(())
+}
+
 pub async fn update_token_transfer<'c, E>(
     executor: E,
     token_transfer: &TokenTransferDbObj,
 ) -> Result<TokenTransferDbObj, sqlx::Error>
 where
-    E: Executor<'c, Database = Sqlite>,
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
 {
-    let _res = sqlx::query(
+    let _res = query(
         r"UPDATE token_transfer SET
 payment_id = $2,
 from_addr = $3,
@@ -163,7 +340,10 @@ deposit_finish = $9,
 tx_id = $10,
 paid_date = $11,
 fee_paid = $12,
-error = $13
+error = $13,
+priority = $14,
+approved_date = $15,
+deadline = $16
 WHERE id = $1
 ",
     )
@@ -180,32 +360,85 @@ WHERE id = $1
     .bind(token_transfer.paid_date)
     .bind(&token_transfer.fee_paid)
     .bind(&token_transfer.error)
+    .bind(&token_transfer.priority)
+    .bind(token_transfer.approved_date)
+    .bind(token_transfer.deadline)
     .execute(executor)
     .await?;
     Ok(token_transfer.clone())
 }
 
-pub async fn get_all_token_transfers(
-    conn: &SqlitePool,
+pub async fn get_all_token_transfers<'c, E>(
+    conn: E,
     limit: Option<i64>,
-) -> Result<Vec<TokenTransferDbObj>, sqlx::Error> {
+) -> Result<Vec<TokenTransferDbObj>, sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
     let limit = limit.unwrap_or(i64::MAX);
-    let rows = sqlx::query_as::<_, TokenTransferDbObj>(
-        r"SELECT * FROM token_transfer ORDER by id DESC LIMIT $1",
+    let rows =
+        query_as::<TokenTransferDbObj>(r"SELECT * FROM token_transfer ORDER by id DESC LIMIT $1")
+            .bind(limit)
+            .fetch_all(conn)
+            .await?;
+    Ok(rows)
+}
+
+pub async fn list_token_transfers<'c, E>(
+    conn: E,
+    filter: &TransferListFilter,
+) -> Result<Vec<TokenTransferDbObj>, sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
+    let status = filter
+        .status
+        .map(|s| s.to_sql())
+        .unwrap_or(TRANSFER_FILTER_ALL);
+    let rows = query_as::<TokenTransferDbObj>(
+        format!(
+            r"SELECT * FROM token_transfer
+WHERE {status}
+    AND ($1 IS NULL OR id < $1)
+    AND ($2 IS NULL OR chain_id = $2)
+    AND ($3 IS NULL OR from_addr = $3)
+    AND ($4 IS NULL OR receiver_addr = $4)
+    AND ($5 IS NULL OR payment_id = $5)
+    AND ($6 IS NULL OR deposit_id = $6)
+    AND ($7 IS NULL OR create_date >= $7)
+    AND ($8 IS NULL OR create_date < $8)
+ORDER BY id DESC
+LIMIT $9"
+        )
+        .as_str(),
     )
-    .bind(limit)
+    .bind(filter.before_id)
+    .bind(filter.chain_id)
+    .bind(&filter.sender)
+    .bind(&filter.receiver)
+    .bind(&filter.payment_id)
+    .bind(&filter.deposit_id)
+    .bind(filter.created_from)
+    .bind(filter.created_to)
+    .bind(filter.limit.unwrap_or(i64::MAX))
     .fetch_all(conn)
     .await?;
     Ok(rows)
 }
 
-pub async fn get_token_transfers_by_chain_id(
-    conn: &SqlitePool,
+pub async fn get_token_transfers_by_chain_id<'c, E>(
+    conn: E,
     chain_id: i64,
     limit: Option<i64>,
-) -> Result<Vec<TokenTransferDbObj>, sqlx::Error> {
+) -> Result<Vec<TokenTransferDbObj>, sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
     let limit = limit.unwrap_or(i64::MAX);
-    let rows = sqlx::query_as::<_, TokenTransferDbObj>(
+    let rows = query_as::<TokenTransferDbObj>(
         r"SELECT * FROM token_transfer WHERE chain_id = $1 ORDER by id DESC LIMIT $2",
     )
     .bind(chain_id)
@@ -221,9 +454,10 @@ pub async fn get_token_transfers_by_deposit_id<'c, E>(
     deposit_id: &str,
 ) -> Result<Vec<TokenTransferDbObj>, sqlx::Error>
 where
-    E: Executor<'c, Database = Sqlite>,
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
 {
-    let rows = sqlx::query_as::<_, TokenTransferDbObj>(
+    let rows = query_as::<TokenTransferDbObj>(
         r"SELECT * FROM token_transfer WHERE chain_id = $1 AND deposit_id = $2 ORDER by id DESC",
     )
     .bind(chain_id)
@@ -233,12 +467,16 @@ where
     Ok(rows)
 }
 
-pub async fn get_pending_token_transfers(
-    conn: &SqlitePool,
+pub async fn get_pending_token_transfers<'c, E>(
+    conn: E,
     account: Address,
     chain_id: i64,
-) -> Result<Vec<TokenTransferDbObj>, sqlx::Error> {
-    let rows = sqlx::query_as::<_, TokenTransferDbObj>(
+) -> Result<Vec<TokenTransferDbObj>, sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
+    let rows = query_as::<TokenTransferDbObj>(
         r"SELECT * FROM token_transfer
 WHERE tx_id is null
 AND error is null
@@ -254,12 +492,122 @@ ORDER by id ASC
     Ok(rows)
 }
 
-pub async fn get_unpaid_token_transfers(
-    conn: &SqlitePool,
+/// Earliest deadline of pending transfers of the account which is later than given date
+pub async fn get_next_token_transfer_deadline<'c, E>(
+    conn: E,
+    account: Address,
+    chain_id: i64,
+    after: DateTime<Utc>,
+) -> Result<Option<DateTime<Utc>>, sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
+    let row = query_as::<TokenTransferDbObj>(
+        r"SELECT * FROM token_transfer
+WHERE tx_id is null
+AND error is null
+AND from_addr = $1
+AND chain_id = $2
+AND deadline > $3
+ORDER by deadline ASC
+LIMIT 1
+",
+    )
+    .bind(format!("{:#x}", account))
+    .bind(chain_id)
+    .bind(after)
+    .fetch_optional(conn)
+    .await?;
+    Ok(row.and_then(|row| row.deadline))
+}
+
+pub async fn get_pending_token_transfers_by_receiver<'c, E>(
+    conn: E,
+    receiver: Address,
+) -> Result<Vec<TokenTransferDbObj>, sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
+    let rows = query_as::<TokenTransferDbObj>(
+        r"SELECT * FROM token_transfer
+WHERE tx_id is null
+AND error is null
+AND receiver_addr = $1
+ORDER by id ASC
+",
+    )
+    .bind(format!("{:#x}", receiver))
+    .fetch_all(conn)
+    .await?;
+    Ok(rows)
+}
+
+/// Transfers of the account attached to transaction which is in progress or was paid after given date
+pub async fn get_token_transfers_spent_since<'c, E>(
+    conn: E,
+    account: Address,
+    chain_id: i64,
+    since: DateTime<Utc>,
+) -> Result<Vec<TokenTransferDbObj>, sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
+    let rows = query_as::<TokenTransferDbObj>(
+        r"SELECT * FROM token_transfer
+WHERE tx_id is not null
+AND error is null
+AND from_addr = $1
+AND chain_id = $2
+AND (paid_date is null OR paid_date >= $3)
+ORDER by id ASC
+",
+    )
+    .bind(format!("{:#x}", account))
+    .bind(chain_id)
+    .bind(since)
+    .fetch_all(conn)
+    .await?;
+    Ok(rows)
+}
+
+/// Oldest transfer from account to receiver, used to tell if receiver is new
+pub async fn get_first_token_transfer_to_receiver<'c, E>(
+    conn: E,
+    account: Address,
+    receiver: Address,
+) -> Result<Option<TokenTransferDbObj>, sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
+    let row = query_as::<TokenTransferDbObj>(
+        r"SELECT * FROM token_transfer
+WHERE from_addr = $1
+AND receiver_addr = $2
+ORDER by create_date ASC, id ASC
+LIMIT 1
+",
+    )
+    .bind(format!("{:#x}", account))
+    .bind(format!("{:#x}", receiver))
+    .fetch_optional(conn)
+    .await?;
+    Ok(row)
+}
+
+pub async fn get_unpaid_token_transfers<'c, E>(
+    conn: E,
     chain_id: i64,
     sender: Address,
-) -> Result<Vec<TokenTransferDbObj>, sqlx::Error> {
-    sqlx::query_as::<_, TokenTransferDbObj>(
+) -> Result<Vec<TokenTransferDbObj>, sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
+    query_as::<TokenTransferDbObj>(
         r"SELECT * FROM token_transfer
 WHERE fee_paid is null
 AND chain_id = $1
@@ -277,13 +625,13 @@ pub async fn get_token_transfers_by_tx<'c, E>(
     tx_id: i64,
 ) -> Result<Vec<TokenTransferDbObj>, sqlx::Error>
 where
-    E: Executor<'c, Database = Sqlite>,
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
 {
-    let rows =
-        sqlx::query_as::<_, TokenTransferDbObj>(r"SELECT * FROM token_transfer WHERE tx_id=$1")
-            .bind(tx_id)
-            .fetch_all(executor)
-            .await?;
+    let rows = query_as::<TokenTransferDbObj>(r"SELECT * FROM token_transfer WHERE tx_id=$1")
+        .bind(tx_id)
+        .fetch_all(executor)
+        .await?;
     Ok(rows)
 }
 
@@ -308,6 +656,47 @@ pub struct TransferStatsPart {
     ///None means native token
     pub erc20_token_transferred: BTreeMap<Address, U256>,
     pub native_token_transferred: U256,
+    /// Transactions moved to archive, not present in `transaction_ids`
+    pub archived_tx_count: u64,
+}
+
+impl TransferStatsPart {
+    pub fn tx_count(&self) -> usize {
+        self.transaction_ids.len() + self.archived_tx_count as usize
+    }
+
+    fn add_archived(&mut self, archived: &TransferArchiveStatsDbObj) -> Result<(), PaymentError> {
+        fn min_date(a: Option<DateTime<Utc>>, b: Option<DateTime<Utc>>) -> Option<DateTime<Utc>> {
+            a.into_iter().chain(b).min()
+        }
+        fn max_date(a: Option<DateTime<Utc>>, b: Option<DateTime<Utc>>) -> Option<DateTime<Utc>> {
+            a.into_iter().chain(b).max()
+        }
+        self.total_count += archived.transfer_count as u64;
+        self.done_count += archived.done_count as u64;
+        self.archived_tx_count += archived.tx_count as u64;
+        self.fee_paid += U256::from_dec_str(&archived.fee_paid).map_err(err_from!())?;
+        self.first_transfer_date = min_date(self.first_transfer_date, archived.first_transfer_date);
+        self.last_transfer_date = max_date(self.last_transfer_date, archived.last_transfer_date);
+        self.first_paid_date = min_date(self.first_paid_date, archived.first_paid_date);
+        self.last_paid_date = max_date(self.last_paid_date, archived.last_paid_date);
+        if let Some(delay) = archived.max_payment_delay.map(Duration::milliseconds) {
+            if self.max_payment_delay.is_none() || self.max_payment_delay.unwrap() < delay {
+                self.max_payment_delay = Some(delay);
+            }
+        }
+        let token_amount = U256::from_dec_str(&archived.token_amount).map_err(err_from!())?;
+        if let Some(token_addr) = &archived.token_addr {
+            let token_addr = Address::from_str(token_addr).map_err(err_from!())?;
+            self.erc20_token_transferred
+                .entry(token_addr)
+                .or_insert_with(U256::zero)
+                .add_assign(token_amount);
+        } else {
+            self.native_token_transferred.add_assign(token_amount);
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone, Default)]
@@ -321,11 +710,41 @@ pub struct TransferStats {
     pub per_sender: BTreeMap<Address, TransferStatsBase>,
 }
 
-pub async fn get_transfer_stats_from_blockchain(
-    conn: &SqlitePool,
+impl TransferStats {
+    /// Include totals of transfers moved to archive
+    pub fn add_archived(
+        &mut self,
+        archived: &[TransferArchiveStatsDbObj],
+    ) -> Result<(), PaymentError> {
+        for row in archived {
+            let from_addr = Address::from_str(&row.from_addr).map_err(err_from!())?;
+            let sender = self.per_sender.entry(from_addr).or_default();
+            match &row.receiver_addr {
+                Some(receiver_addr) => {
+                    let receiver_addr = Address::from_str(receiver_addr).map_err(err_from!())?;
+                    sender
+                        .per_receiver
+                        .entry(receiver_addr)
+                        .or_default()
+                        .add_archived(row)?;
+                }
+                None => sender.all.add_archived(row)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Archived transfers are included only when limit is not set
+pub async fn get_transfer_stats_from_blockchain<'c, E>(
+    conn: E,
     chain_id: i64,
     limit: Option<i64>,
-) -> Result<TransferStats, PaymentError> {
+) -> Result<TransferStats, PaymentError>
+where
+    E: Executor<'c> + Copy,
+    E::Database: PaymentDatabase,
+{
     let tt = get_chain_transfers_by_chain_id(conn, chain_id, limit)
         .await
         .map_err(err_from!())?;
@@ -382,14 +801,25 @@ pub async fn get_transfer_stats_from_blockchain(
             }
         }
     }
+    if limit.is_none() {
+        let archived = get_transfer_archive_stats(conn, chain_id, ARCHIVE_SOURCE_CHAIN_TRANSFER)
+            .await
+            .map_err(err_from!())?;
+        ts.add_archived(&archived)?;
+    }
     Ok(ts)
 }
 
-pub async fn get_transfer_stats(
-    conn: &SqlitePool,
+/// Archived transfers are included only when limit is not set
+pub async fn get_transfer_stats<'c, E>(
+    conn: E,
     chain_id: i64,
     limit: Option<i64>,
-) -> Result<TransferStats, PaymentError> {
+) -> Result<TransferStats, PaymentError>
+where
+    E: Executor<'c> + Copy,
+    E::Database: PaymentDatabase,
+{
     let tt = get_token_transfers_by_chain_id(conn, chain_id, limit)
         .await
         .map_err(err_from!())?;
@@ -464,19 +894,29 @@ pub async fn get_transfer_stats(
             }
         }
     }
+    if limit.is_none() {
+        let archived = get_transfer_archive_stats(conn, chain_id, ARCHIVE_SOURCE_TOKEN_TRANSFER)
+            .await
+            .map_err(err_from!())?;
+        ts.add_archived(&archived)?;
+    }
     Ok(ts)
 }
 
-pub async fn get_transfer_count(
-    conn: &SqlitePool,
+pub async fn get_transfer_count<'c, E>(
+    conn: E,
     transfer_filter: Option<&str>,
     sender: Option<&str>,
     receiver: Option<&str>,
-) -> Result<usize, sqlx::Error> {
+) -> Result<usize, sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
     let transfer_filter = transfer_filter.unwrap_or(TRANSFER_FILTER_ALL);
 
     let count = if let Some(sender) = sender {
-        sqlx::query_scalar::<_, i64>(
+        query_scalar::<i64>(
             format!(
                 r"SELECT COUNT(*) FROM token_transfer WHERE {transfer_filter} AND from_addr = $1"
             )
@@ -486,12 +926,12 @@ pub async fn get_transfer_count(
         .fetch_one(conn)
         .await?
     } else if let Some(receiver) = receiver {
-        sqlx::query_scalar::<_, i64>(format!(r"SELECT COUNT(*) FROM token_transfer WHERE {transfer_filter} AND receiver_addr = $1").as_str())
+        query_scalar::<i64>(format!(r"SELECT COUNT(*) FROM token_transfer WHERE {transfer_filter} AND receiver_addr = $1").as_str())
             .bind(receiver)
             .fetch_one(conn)
             .await?
     } else {
-        sqlx::query_scalar::<_, i64>(
+        query_scalar::<i64>(
             format!(r"SELECT COUNT(*) FROM token_transfer WHERE {transfer_filter}").as_str(),
         )
         .fetch_one(conn)
@@ -500,3 +940,123 @@ pub async fn get_transfer_count(
 
     Ok(count as usize)
 }
+
+#[tokio::test]
+async fn list_token_transfers_test() -> sqlx::Result<()> {
+    use crate::create_sqlite_connection;
+    use crate::db::model::TransferPriority;
+    use crate::db::store::TransferFilter;
+
+    let conn = create_sqlite_connection(None, None, false, true)
+        .await
+        .unwrap();
+
+    let start = Utc::now() - Duration::hours(10);
+    let receiver = |no: u64| format!("{:#x}", Address::from_low_u64_be(no));
+    let mut ids = Vec::new();
+    for i in 0..5 {
+        // archived insert keeps given create date
+        let tt = insert_archived_token_transfer(
+            &conn,
+            &TokenTransferDbObj {
+                id: 0,
+                payment_id: Some(format!("payment-{i}")),
+                from_addr: format!("{:#x}", Address::from_low_u64_be(1)),
+                receiver_addr: receiver(2 + i % 2),
+                chain_id: 987789,
+                token_addr: None,
+                token_amount: "1".to_string(),
+                deposit_id: (i == 4).then(|| "0x10-0x20".to_string()),
+                deposit_finish: 0,
+                create_date: start + Duration::hours(i as i64),
+                tx_id: None,
+                paid_date: None,
+                fee_paid: None,
+                error: None,
+                priority: TransferPriority::default().to_string(),
+                approved_date: None,
+                deadline: None,
+            },
+        )
+        .await?;
+        ids.push(tt.id);
+    }
+    let list = |filter: TransferListFilter| {
+        let conn = conn.clone();
+        async move {
+            list_token_transfers(&conn, &filter)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|tt| tt.id)
+                .collect::<Vec<_>>()
+        }
+    };
+
+    assert_eq!(
+        list(TransferListFilter::default()).await,
+        vec![ids[4], ids[3], ids[2], ids[1], ids[0]]
+    );
+    assert_eq!(
+        list(TransferListFilter {
+            receiver: Some(receiver(2)),
+            ..Default::default()
+        })
+        .await,
+        vec![ids[4], ids[2], ids[0]]
+    );
+    assert_eq!(
+        list(TransferListFilter {
+            payment_id: Some("payment-3".to_string()),
+            ..Default::default()
+        })
+        .await,
+        vec![ids[3]]
+    );
+    assert_eq!(
+        list(TransferListFilter {
+            deposit_id: Some("0x10-0x20".to_string()),
+            ..Default::default()
+        })
+        .await,
+        vec![ids[4]]
+    );
+    assert_eq!(
+        list(TransferListFilter {
+            created_from: Some(start + Duration::hours(2)),
+            created_to: Some(start + Duration::hours(4)),
+            ..Default::default()
+        })
+        .await,
+        vec![ids[3], ids[2]]
+    );
+    assert_eq!(
+        list(TransferListFilter {
+            sender: Some(receiver(2)),
+            ..Default::default()
+        })
+        .await,
+        Vec::<i64>::new()
+    );
+    assert_eq!(
+        list(TransferListFilter {
+            status: Some(TransferFilter::Done),
+            chain_id: Some(987789),
+            ..Default::default()
+        })
+        .await,
+        Vec::<i64>::new()
+    );
+
+    // keyset pagination, next page starts below last id of previous one
+    let page = |before_id: Option<i64>| TransferListFilter {
+        before_id,
+        limit: Some(2),
+        ..Default::default()
+    };
+    assert_eq!(list(page(None)).await, vec![ids[4], ids[3]]);
+    assert_eq!(list(page(Some(ids[3]))).await, vec![ids[2], ids[1]]);
+    assert_eq!(list(page(Some(ids[1]))).await, vec![ids[0]]);
+    assert_eq!(list(page(Some(ids[0]))).await, Vec::<i64>::new());
+    Ok(())
+}
@@ -0,0 +1,95 @@
+use super::model::TransferArchiveStatsDbObj;
+use crate::db::database::{query, query_as, PaymentDatabase};
+use sqlx::Executor;
+
+pub async fn insert_transfer_archive_stats<'c, E>(
+    conn: E,
+    stats: &TransferArchiveStatsDbObj,
+) -> Result<TransferArchiveStatsDbObj, sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
+    let res = query_as::<TransferArchiveStatsDbObj>(
+        r"INSERT INTO transfer_archive_stats
+(source, chain_id, from_addr, receiver_addr, token_addr, transfer_count, done_count, tx_count, token_amount, fee_paid, first_transfer_date, last_transfer_date, first_paid_date, last_paid_date, max_payment_delay)
+VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15) RETURNING *;
+",
+    )
+    .bind(&stats.source)
+    .bind(stats.chain_id)
+    .bind(&stats.from_addr)
+    .bind(&stats.receiver_addr)
+    .bind(&stats.token_addr)
+    .bind(stats.transfer_count)
+    .bind(stats.done_count)
+    .bind(stats.tx_count)
+    .bind(&stats.token_amount)
+    .bind(&stats.fee_paid)
+    .bind(stats.first_transfer_date)
+    .bind(stats.last_transfer_date)
+    .bind(stats.first_paid_date)
+    .bind(stats.last_paid_date)
+    .bind(stats.max_payment_delay)
+    .fetch_one(conn)
+    .await?;
+    Ok(res)
+}
+
+pub async fn update_transfer_archive_stats<'c, E>(
+    conn: E,
+    stats: &TransferArchiveStatsDbObj,
+) -> Result<(), sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
+    query(
+        r"UPDATE transfer_archive_stats SET
+transfer_count = $2,
+done_count = $3,
+tx_count = $4,
+token_amount = $5,
+fee_paid = $6,
+first_transfer_date = $7,
+last_transfer_date = $8,
+first_paid_date = $9,
+last_paid_date = $10,
+max_payment_delay = $11
+WHERE id = $1
+",
+    )
+    .bind(stats.id)
+    .bind(stats.transfer_count)
+    .bind(stats.done_count)
+    .bind(stats.tx_count)
+    .bind(&stats.token_amount)
+    .bind(&stats.fee_paid)
+    .bind(stats.first_transfer_date)
+    .bind(stats.last_transfer_date)
+    .bind(stats.first_paid_date)
+    .bind(stats.last_paid_date)
+    .bind(stats.max_payment_delay)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+pub async fn get_transfer_archive_stats<'c, E>(
+    conn: E,
+    chain_id: i64,
+    source: &str,
+) -> Result<Vec<TransferArchiveStatsDbObj>, sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
+    let rows = query_as::<TransferArchiveStatsDbObj>(
+        r"SELECT * FROM transfer_archive_stats WHERE chain_id = $1 AND source = $2 ORDER BY id ASC",
+    )
+    .bind(chain_id)
+    .bind(source)
+    .fetch_all(conn)
+    .await?;
+    Ok(rows)
+}
@@ -1,11 +1,17 @@
 use super::model::TransferInDbObj;
-use sqlx::SqlitePool;
+use crate::db::database::{query, query_as, PaymentDatabase};
+use crate::db::store::TransferInListFilter;
+use sqlx::Executor;
 
-pub async fn insert_transfer_in(
-    conn: &SqlitePool,
+pub async fn insert_transfer_in<'c, E>(
+    conn: E,
     token_transfer: &TransferInDbObj,
-) -> Result<TransferInDbObj, sqlx::Error> {
-    let res = sqlx::query_as::<_, TransferInDbObj>(
+) -> Result<TransferInDbObj, sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
+    let res = query_as::<TransferInDbObj>(
         r"INSERT INTO transfer_in
 (payment_id, from_addr, receiver_addr, chain_id, token_addr, token_amount, tx_hash, requested_date, received_date)
 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9) RETURNING *;
@@ -25,12 +31,16 @@ VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9) RETURNING *;
     Ok(res)
 }
 
-pub async fn update_transfer_in(
-    conn: &SqlitePool,
+pub async fn update_transfer_in<'c, E>(
+    conn: E,
     token_transfer: &TransferInDbObj,
-) -> Result<TransferInDbObj, sqlx::Error> {
-    let _res = sqlx::query(
-        r"UPDATE token_transfer SET
+) -> Result<TransferInDbObj, sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
+    let _res = query(
+        r"UPDATE transfer_in SET
 payment_id = $2,
 from_addr = $3,
 receiver_addr = $4,
@@ -39,7 +49,7 @@ token_addr = $6,
 token_amount = $7,
 tx_hash = $8,
 requested_date = $9,
-received_date = $10,
+received_date = $10
 WHERE id = $1
 ",
     )
@@ -58,13 +68,17 @@ WHERE id = $1
     Ok(token_transfer.clone())
 }
 
-pub async fn get_account_transfers_in(
-    conn: &SqlitePool,
+pub async fn get_account_transfers_in<'c, E>(
+    conn: E,
     account: &str,
     limit: Option<i64>,
-) -> Result<Vec<TransferInDbObj>, sqlx::Error> {
+) -> Result<Vec<TransferInDbObj>, sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
     let limit = limit.unwrap_or(i64::MAX);
-    let rows = sqlx::query_as::<_, TransferInDbObj>(
+    let rows = query_as::<TransferInDbObj>(
         r"SELECT * FROM transfer_in
 WHERE receiver_addr=$2
 ORDER by requested_date DESC
@@ -77,15 +91,50 @@ LIMIT $1",
     Ok(rows)
 }
 
-pub async fn get_all_transfers_in(
-    conn: &SqlitePool,
+pub async fn get_all_transfers_in<'c, E>(
+    conn: E,
     limit: Option<i64>,
-) -> Result<Vec<TransferInDbObj>, sqlx::Error> {
+) -> Result<Vec<TransferInDbObj>, sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
     let limit = limit.unwrap_or(i64::MAX);
-    let rows = sqlx::query_as::<_, TransferInDbObj>(
-        r"SELECT * FROM transfer_in ORDER by id DESC LIMIT $1",
+    let rows = query_as::<TransferInDbObj>(r"SELECT * FROM transfer_in ORDER by id DESC LIMIT $1")
+        .bind(limit)
+        .fetch_all(conn)
+        .await?;
+    Ok(rows)
+}
+
+pub async fn list_transfers_in<'c, E>(
+    conn: E,
+    filter: &TransferInListFilter,
+) -> Result<Vec<TransferInDbObj>, sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
+    let rows = query_as::<TransferInDbObj>(
+        r"SELECT * FROM transfer_in
+WHERE ($1 IS NULL OR id < $1)
+    AND ($2 IS NULL OR chain_id = $2)
+    AND ($3 IS NULL OR from_addr = $3)
+    AND ($4 IS NULL OR receiver_addr = $4)
+    AND ($5 IS NULL OR payment_id = $5)
+    AND ($6 IS NULL OR requested_date >= $6)
+    AND ($7 IS NULL OR requested_date < $7)
+ORDER BY id DESC
+LIMIT $8",
     )
-    .bind(limit)
+    .bind(filter.before_id)
+    .bind(filter.chain_id)
+    .bind(&filter.sender)
+    .bind(&filter.receiver)
+    .bind(&filter.payment_id)
+    .bind(filter.requested_from)
+    .bind(filter.requested_to)
+    .bind(filter.limit.unwrap_or(i64::MAX))
     .fetch_all(conn)
     .await?;
     Ok(rows)
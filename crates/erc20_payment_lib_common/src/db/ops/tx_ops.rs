@@ -1,6 +1,7 @@
 use super::model::TxDbObj;
-use sqlx::Sqlite;
-use sqlx::SqlitePool;
+use crate::db::database::{query, query_as, query_scalar, PaymentDatabase};
+use crate::db::store::TxListFilter;
+use chrono::{DateTime, Utc};
 use sqlx::{Executor, Transaction};
 use web3::types::Address;
 
@@ -9,9 +10,9 @@ pub const TRANSACTION_FILTER_PROCESSING: &str = "processing > 0 AND first_proces
 pub const TRANSACTION_FILTER_TO_PROCESS: &str = "processing > 0";
 pub const TRANSACTION_FILTER_ALL: &str = "id >= 0";
 pub const TRANSACTION_FILTER_DONE: &str = "processing = 0";
-pub const TRANSACTION_ORDER_BY_ID_AND_REPLACEMENT_ID: &str = "orig_tx_id DESC,id ASC";
+pub const TRANSACTION_ORDER_BY_ID_AND_REPLACEMENT_ID: &str = "orig_tx_id DESC NULLS LAST, id ASC";
 pub const TRANSACTION_ORDER_BY_CREATE_DATE: &str = "created_date ASC";
-pub const TRANSACTION_ORDER_BY_FIRST_PROCESSED_DATE_DESC: &str = "first_processed DESC";
+pub const TRANSACTION_ORDER_BY_FIRST_PROCESSED_DATE_DESC: &str = "first_processed DESC NULLS LAST";
 
 pub async fn get_next_transaction<'c, E>(
     executor: E,
@@ -19,9 +20,10 @@ pub async fn get_next_transaction<'c, E>(
     account: &str,
 ) -> Result<Option<TxDbObj>, sqlx::Error>
 where
-    E: Executor<'c, Database = Sqlite>,
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
 {
-    let res = sqlx::query_as::<_, TxDbObj>(
+    let res = query_as::<TxDbObj>(
         r"SELECT * FROM tx
         WHERE chain_id = $1 AND from_addr = $2 AND processing > 0 AND first_processed IS NULL
         ORDER BY id ASC LIMIT 1",
@@ -42,7 +44,8 @@ pub async fn get_transactions<'c, E>(
     chain_id: Option<i64>,
 ) -> Result<Vec<TxDbObj>, sqlx::Error>
 where
-    E: Executor<'c, Database = Sqlite>,
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
 {
     let limit = limit.unwrap_or(i64::MAX);
     let filter = filter.unwrap_or(TRANSACTION_FILTER_ALL);
@@ -55,7 +58,7 @@ where
         Some(chain_id) => format!("chain_id = {}", chain_id),
         None => "1 = 1".to_string(),
     };
-    let rows = sqlx::query_as::<_, TxDbObj>(
+    let rows = query_as::<TxDbObj>(
         format!(r"SELECT * FROM tx WHERE ({filter_chain}) AND ({filter_account}) AND ({filter}) ORDER BY {order} LIMIT {limit}").as_str(),
     )
     .fetch_all(executor)
@@ -63,40 +66,80 @@ where
     Ok(rows)
 }
 
+pub async fn list_transactions<'c, E>(
+    executor: E,
+    filter: &TxListFilter,
+) -> Result<Vec<TxDbObj>, sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
+    let status = filter
+        .status
+        .as_ref()
+        .map(|s| s.to_sql())
+        .unwrap_or(TRANSACTION_FILTER_ALL.to_string());
+    let rows = query_as::<TxDbObj>(
+        format!(
+            r"SELECT * FROM tx
+WHERE ({status})
+    AND ($1 IS NULL OR id < $1)
+    AND ($2 IS NULL OR chain_id = $2)
+    AND ($3 IS NULL OR from_addr = $3)
+    AND ($4 IS NULL OR created_date >= $4)
+    AND ($5 IS NULL OR created_date < $5)
+ORDER BY id DESC
+LIMIT $6"
+        )
+        .as_str(),
+    )
+    .bind(filter.before_id)
+    .bind(filter.chain_id)
+    .bind(&filter.account)
+    .bind(filter.created_from)
+    .bind(filter.created_to)
+    .bind(filter.limit.unwrap_or(i64::MAX))
+    .fetch_all(executor)
+    .await?;
+    Ok(rows)
+}
+
 pub async fn get_transaction<'c, E>(executor: E, tx_id: i64) -> Result<TxDbObj, sqlx::Error>
 where
-    E: Executor<'c, Database = Sqlite>,
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
 {
-    let row = sqlx::query_as::<_, TxDbObj>(r"SELECT * FROM tx WHERE id = $1")
+    let row = query_as::<TxDbObj>(r"SELECT * FROM tx WHERE id = $1")
         .bind(tx_id)
         .fetch_one(executor)
         .await?;
     Ok(row)
 }
 
-pub async fn get_last_unsent_tx<'c, E>(
-    executor: E,
-    tx_id: i64,
-) -> Result<Option<TxDbObj>, sqlx::Error>
+pub async fn get_last_unsent_tx<'c, E>(executor: E) -> Result<Option<TxDbObj>, sqlx::Error>
 where
-    E: Executor<'c, Database = Sqlite>,
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
 {
-    let row = sqlx::query_as::<_, TxDbObj>(r"SELECT * FROM tx WHERE broadcast_date is NULL AND signed_date is NULL ORDER BY id DESC LIMIT 1")
-        .bind(tx_id)
+    let row = query_as::<TxDbObj>(r"SELECT * FROM tx WHERE broadcast_date is NULL AND signed_date is NULL ORDER BY id DESC LIMIT 1")
         .fetch_optional(executor)
         .await?;
     Ok(row)
 }
 
 //call in transaction
-pub async fn get_transaction_chain(
-    executor: &mut Transaction<'_, Sqlite>,
+pub async fn get_transaction_chain<DB>(
+    executor: &mut Transaction<'_, DB>,
     tx_id: i64,
-) -> Result<Vec<TxDbObj>, sqlx::Error> {
+) -> Result<Vec<TxDbObj>, sqlx::Error>
+where
+    DB: PaymentDatabase,
+    for<'c> &'c mut DB::Connection: Executor<'c, Database = DB>,
+{
     let mut current_id = Some(tx_id);
     let mut res = vec![];
     while let Some(id) = current_id {
-        let row = sqlx::query_as::<_, TxDbObj>(r"SELECT * FROM tx WHERE id = $1")
+        let row = query_as::<TxDbObj>(r"SELECT * FROM tx WHERE id = $1")
             .bind(id)
             .fetch_one(&mut **executor)
             .await?;
@@ -108,21 +151,51 @@ pub async fn get_transaction_chain(
 
 pub async fn delete_tx<'c, E>(executor: E, tx_id: i64) -> Result<(), sqlx::Error>
 where
-    E: Executor<'c, Database = Sqlite>,
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
 {
-    sqlx::query(r"DELETE FROM tx WHERE id = $1")
+    query(r"DELETE FROM tx WHERE id = $1")
         .bind(tx_id)
         .execute(executor)
         .await?;
     Ok(())
 }
 
-pub async fn get_transaction_highest_block(
-    conn: &SqlitePool,
+/// Finished transactions created before given date, that can be moved to archive.
+/// Transactions referenced by allowances are kept, because allowances are never archived,
+/// as are transactions replaced by ones still processing.
+pub async fn get_archivable_transactions<'c, E>(
+    executor: E,
+    before: DateTime<Utc>,
+    limit: i64,
+) -> Result<Vec<TxDbObj>, sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
+    let rows = query_as::<TxDbObj>(
+        r"SELECT * FROM tx WHERE processing = 0 AND created_date < $1
+        AND id NOT IN (SELECT tx_id FROM allowance WHERE tx_id IS NOT NULL)
+        AND id NOT IN (SELECT orig_tx_id FROM tx WHERE orig_tx_id IS NOT NULL AND processing > 0)
+        ORDER BY id ASC LIMIT $2",
+    )
+    .bind(before)
+    .bind(limit)
+    .fetch_all(executor)
+    .await?;
+    Ok(rows)
+}
+
+pub async fn get_transaction_highest_block<'c, E>(
+    conn: E,
     chain_id: i64,
     from_addr: &str,
-) -> Result<Option<i64>, sqlx::Error> {
-    sqlx::query_scalar::<_, Option<i64>>(
+) -> Result<Option<i64>, sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
+    query_scalar::<Option<i64>>(
         r"SELECT MAX(block_number) FROM tx WHERE confirm_date
          IS NOT NULL
          AND chain_id = $1
@@ -135,12 +208,16 @@ pub async fn get_transaction_highest_block(
     .await
 }
 
-pub async fn get_transaction_highest_nonce(
-    conn: &SqlitePool,
+pub async fn get_transaction_highest_nonce<'c, E>(
+    conn: E,
     chain_id: i64,
     from_addr: &str,
-) -> Result<Option<i64>, sqlx::Error> {
-    sqlx::query_scalar::<_, Option<i64>>(
+) -> Result<Option<i64>, sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
+    query_scalar::<Option<i64>>(
         r"SELECT MAX(nonce) FROM tx WHERE confirm_date
          IS NOT NULL
          AND chain_id = $1
@@ -153,12 +230,16 @@ pub async fn get_transaction_highest_nonce(
     .await
 }
 
-pub async fn get_transaction_count(
-    conn: &SqlitePool,
+pub async fn get_transaction_count<'c, E>(
+    conn: E,
     transaction_filter: Option<&str>,
-) -> Result<usize, sqlx::Error> {
+) -> Result<usize, sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
     let transaction_filter = transaction_filter.unwrap_or(TRANSACTION_FILTER_ALL);
-    let count = sqlx::query_scalar::<_, i64>(
+    let count = query_scalar::<i64>(
         format!(r"SELECT COUNT(*) FROM tx WHERE {transaction_filter}").as_str(),
     )
     .fetch_one(conn)
@@ -166,12 +247,16 @@ pub async fn get_transaction_count(
     Ok(count as usize)
 }
 
-pub async fn get_next_transactions_to_process(
-    conn: &SqlitePool,
+pub async fn get_next_transactions_to_process<'c, E>(
+    conn: E,
     account: Option<Address>,
     limit: i64,
     chain_id: i64,
-) -> Result<Vec<TxDbObj>, sqlx::Error> {
+) -> Result<Vec<TxDbObj>, sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
     get_transactions(
         conn,
         account,
@@ -183,8 +268,12 @@ pub async fn get_next_transactions_to_process(
     .await
 }
 
-pub async fn force_tx_error(conn: &SqlitePool, tx: &TxDbObj) -> Result<(), sqlx::Error> {
-    sqlx::query(r"UPDATE tx SET error = 'forced error' WHERE id = $1")
+pub async fn force_tx_error<'c, E>(conn: E, tx: &TxDbObj) -> Result<(), sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
+    query(r"UPDATE tx SET error = 'forced error' WHERE id = $1")
         .bind(tx.id)
         .execute(conn)
         .await?;
@@ -193,9 +282,10 @@ pub async fn force_tx_error(conn: &SqlitePool, tx: &TxDbObj) -> Result<(), sqlx:
 
 pub async fn insert_tx<'c, E>(executor: E, tx: &TxDbObj) -> Result<TxDbObj, sqlx::Error>
 where
-    E: Executor<'c, Database = Sqlite>,
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
 {
-    let res = sqlx::query_as::<_, TxDbObj>(
+    let res = query_as::<TxDbObj>(
         r"INSERT INTO tx
 (method, from_addr, to_addr, chain_id, gas_limit, max_fee_per_gas, priority_fee, val, nonce, processing, call_data, created_date, first_processed, tx_hash, signed_raw_data, signed_date, broadcast_date, broadcast_count, first_stuck_date, confirm_date, blockchain_date, gas_used, block_number, chain_status, block_gas_price, effective_gas_price, fee_paid, error, orig_tx_id)
 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22, $23, $24, $25, $26, $27, $28, $29) RETURNING *;
@@ -240,9 +330,10 @@ pub async fn update_processing_and_first_processed_tx<'c, E>(
     tx: &TxDbObj,
 ) -> Result<TxDbObj, sqlx::Error>
 where
-    E: Executor<'c, Database = Sqlite>,
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
 {
-    let _res = sqlx::query(
+    let _res = query(
         r"UPDATE tx SET
 processing = $2,
 first_processed = $3
@@ -259,9 +350,10 @@ WHERE id = $1
 
 pub async fn update_tx<'c, E>(executor: E, tx: &TxDbObj) -> Result<TxDbObj, sqlx::Error>
 where
-    E: Executor<'c, Database = Sqlite>,
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
 {
-    let _res = sqlx::query(
+    let _res = query(
         r"UPDATE tx SET
 method = $2,
 from_addr = $3,
@@ -332,9 +424,10 @@ WHERE id = $1
 
 pub async fn update_tx_stuck_date<'c, E>(executor: E, tx: &TxDbObj) -> Result<TxDbObj, sqlx::Error>
 where
-    E: Executor<'c, Database = Sqlite>,
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
 {
-    let _res = sqlx::query(
+    let _res = query(
         r"UPDATE tx SET
 first_stuck_date = $2
 WHERE id = $1
@@ -411,3 +504,105 @@ async fn tx_test() -> sqlx::Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn list_transactions_test() -> sqlx::Result<()> {
+    use crate::create_sqlite_connection;
+    use crate::db::store::{TransactionFilter, TxListFilter};
+
+    let conn = create_sqlite_connection(None, None, false, true)
+        .await
+        .unwrap();
+
+    let start = chrono::Utc::now() - chrono::Duration::hours(10);
+    let account = |no: u64| format!("{:#x}", web3::types::Address::from_low_u64_be(no));
+    let mut ids = Vec::new();
+    for i in 0..4 {
+        let tx = insert_tx(
+            &conn,
+            &TxDbObj {
+                id: -1,
+                tx_hash: None,
+                signed_raw_data: None,
+                signed_date: None,
+                broadcast_date: None,
+                broadcast_count: 0,
+                first_stuck_date: None,
+                method: "ERC20.transfer".to_string(),
+                from_addr: account(1 + i % 2),
+                to_addr: account(10),
+                chain_id: 987789,
+                gas_limit: None,
+                max_fee_per_gas: None,
+                priority_fee: None,
+                val: "0".to_string(),
+                nonce: None,
+                processing: 1,
+                call_data: None,
+                created_date: start + chrono::Duration::hours(i as i64),
+                block_number: None,
+                chain_status: None,
+                block_gas_price: None,
+                effective_gas_price: None,
+                fee_paid: None,
+                error: None,
+                orig_tx_id: None,
+                engine_message: None,
+                engine_error: None,
+                first_processed: None,
+                confirm_date: None,
+                blockchain_date: None,
+                gas_used: None,
+            },
+        )
+        .await?;
+        ids.push(tx.id);
+    }
+    let list = |filter: TxListFilter| {
+        let conn = conn.clone();
+        async move {
+            list_transactions(&conn, &filter)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|tx| tx.id)
+                .collect::<Vec<_>>()
+        }
+    };
+
+    assert_eq!(
+        list(TxListFilter {
+            account: Some(account(1)),
+            ..Default::default()
+        })
+        .await,
+        vec![ids[2], ids[0]]
+    );
+    assert_eq!(
+        list(TxListFilter {
+            created_from: Some(start + chrono::Duration::hours(1)),
+            created_to: Some(start + chrono::Duration::hours(3)),
+            ..Default::default()
+        })
+        .await,
+        vec![ids[2], ids[1]]
+    );
+    assert_eq!(
+        list(TxListFilter {
+            status: Some(TransactionFilter::Done),
+            chain_id: Some(987789),
+            ..Default::default()
+        })
+        .await,
+        Vec::<i64>::new()
+    );
+
+    let page = |before_id: Option<i64>| TxListFilter {
+        before_id,
+        limit: Some(3),
+        ..Default::default()
+    };
+    assert_eq!(list(page(None)).await, vec![ids[3], ids[2], ids[1]]);
+    assert_eq!(list(page(Some(ids[1]))).await, vec![ids[0]]);
+    Ok(())
+}
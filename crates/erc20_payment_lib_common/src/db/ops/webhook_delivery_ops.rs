@@ -0,0 +1,142 @@
+use super::model::WebhookDeliveryDbObj;
+use crate::db::database::{query, query_as, PaymentDatabase};
+use crate::db::store::WebhookDeliveryListFilter;
+use chrono::{DateTime, Utc};
+use sqlx::Executor;
+
+pub async fn insert_webhook_delivery<'c, E>(
+    conn: E,
+    delivery: &WebhookDeliveryDbObj,
+) -> Result<WebhookDeliveryDbObj, sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
+    let res = query_as::<WebhookDeliveryDbObj>(
+        r"INSERT INTO webhook_delivery
+(webhook, event_type, payload, created_date, attempts, next_attempt_date, last_attempt_date, delivered_date, response_status, error)
+VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10) RETURNING *;
+",
+    )
+    .bind(&delivery.webhook)
+    .bind(&delivery.event_type)
+    .bind(&delivery.payload)
+    .bind(delivery.created_date)
+    .bind(delivery.attempts)
+    .bind(delivery.next_attempt_date)
+    .bind(delivery.last_attempt_date)
+    .bind(delivery.delivered_date)
+    .bind(delivery.response_status)
+    .bind(&delivery.error)
+    .fetch_one(conn)
+    .await?;
+    Ok(res)
+}
+
+pub async fn update_webhook_delivery<'c, E>(
+    conn: E,
+    delivery: &WebhookDeliveryDbObj,
+) -> Result<(), sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
+    query(
+        r"UPDATE webhook_delivery SET
+attempts = $2,
+next_attempt_date = $3,
+last_attempt_date = $4,
+delivered_date = $5,
+response_status = $6,
+error = $7
+WHERE id = $1
+",
+    )
+    .bind(delivery.id)
+    .bind(delivery.attempts)
+    .bind(delivery.next_attempt_date)
+    .bind(delivery.last_attempt_date)
+    .bind(delivery.delivered_date)
+    .bind(delivery.response_status)
+    .bind(&delivery.error)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+pub async fn get_webhook_delivery<'c, E>(
+    conn: E,
+    id: i64,
+) -> Result<Option<WebhookDeliveryDbObj>, sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
+    let row = query_as::<WebhookDeliveryDbObj>(r"SELECT * FROM webhook_delivery WHERE id = $1")
+        .bind(id)
+        .fetch_optional(conn)
+        .await?;
+    Ok(row)
+}
+
+/// Deliveries with next attempt scheduled not later than `now`, oldest first
+pub async fn get_webhook_deliveries_due<'c, E>(
+    conn: E,
+    now: DateTime<Utc>,
+    limit: i64,
+) -> Result<Vec<WebhookDeliveryDbObj>, sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
+    let rows = query_as::<WebhookDeliveryDbObj>(
+        r"SELECT * FROM webhook_delivery
+WHERE next_attempt_date IS NOT NULL AND next_attempt_date <= $1
+ORDER BY id ASC
+LIMIT $2",
+    )
+    .bind(now)
+    .bind(limit)
+    .fetch_all(conn)
+    .await?;
+    Ok(rows)
+}
+
+pub async fn list_webhook_deliveries<'c, E>(
+    conn: E,
+    filter: &WebhookDeliveryListFilter,
+) -> Result<Vec<WebhookDeliveryDbObj>, sqlx::Error>
+where
+    E: Executor<'c>,
+    E::Database: PaymentDatabase,
+{
+    let status = filter
+        .status
+        .map(|s| s.to_sql())
+        .unwrap_or(WEBHOOK_DELIVERY_FILTER_ALL);
+    let rows = query_as::<WebhookDeliveryDbObj>(
+        format!(
+            r"SELECT * FROM webhook_delivery
+WHERE {status}
+    AND ($1 IS NULL OR id < $1)
+    AND ($2 IS NULL OR webhook = $2)
+    AND ($3 IS NULL OR event_type = $3)
+ORDER BY id DESC
+LIMIT $4"
+        )
+        .as_str(),
+    )
+    .bind(filter.before_id)
+    .bind(&filter.webhook)
+    .bind(&filter.event_type)
+    .bind(filter.limit.unwrap_or(i64::MAX))
+    .fetch_all(conn)
+    .await?;
+    Ok(rows)
+}
+
+pub const WEBHOOK_DELIVERY_FILTER_ALL: &str = "(id >= 0)";
+pub const WEBHOOK_DELIVERY_FILTER_PENDING: &str = "(next_attempt_date IS NOT NULL)";
+pub const WEBHOOK_DELIVERY_FILTER_DELIVERED: &str = "(delivered_date IS NOT NULL)";
+pub const WEBHOOK_DELIVERY_FILTER_FAILED: &str =
+    "(next_attempt_date IS NULL AND delivered_date IS NULL)";
@@ -0,0 +1,812 @@
+mod any;
+mod memory;
+mod sql;
+
+use crate::db::model::*;
+use crate::db::ops::{
+    TRANSACTION_FILTER_ALL, TRANSACTION_FILTER_DONE, TRANSACTION_FILTER_PROCESSING,
+    TRANSACTION_FILTER_QUEUED, TRANSACTION_FILTER_TO_PROCESS, TRANSACTION_ORDER_BY_CREATE_DATE,
+    TRANSACTION_ORDER_BY_FIRST_PROCESSED_DATE_DESC, TRANSACTION_ORDER_BY_ID_AND_REPLACEMENT_ID,
+    TRANSFER_FILTER_ALL, TRANSFER_FILTER_DONE, TRANSFER_FILTER_PROCESSING, TRANSFER_FILTER_QUEUED,
+    WEBHOOK_DELIVERY_FILTER_DELIVERED, WEBHOOK_DELIVERY_FILTER_FAILED,
+    WEBHOOK_DELIVERY_FILTER_PENDING,
+};
+use crate::error::PaymentError;
+use crate::error::*;
+use crate::{err_create, err_custom_create, err_from};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::cmp::Ordering;
+use web3::types::Address;
+
+pub use any::{PaymentDbStore, PaymentDbTransaction};
+pub use memory::{MemoryStore, MemoryTransaction};
+pub use sql::{PostgresStore, SqlStore, SqlTransaction, SqliteStore};
+
+/// Filter of transactions, evaluated as SQL by database stores and directly by the memory store
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TransactionFilter {
+    All,
+    Queued,
+    Processing,
+    ToProcess,
+    Done,
+    /// Not yet finished transactions calling given contract method (i.e. FAUCET.create)
+    PendingMethod(String),
+}
+
+impl TransactionFilter {
+    pub fn to_sql(&self) -> String {
+        match self {
+            TransactionFilter::All => TRANSACTION_FILTER_ALL.to_string(),
+            TransactionFilter::Queued => TRANSACTION_FILTER_QUEUED.to_string(),
+            TransactionFilter::Processing => TRANSACTION_FILTER_PROCESSING.to_string(),
+            TransactionFilter::ToProcess => TRANSACTION_FILTER_TO_PROCESS.to_string(),
+            TransactionFilter::Done => TRANSACTION_FILTER_DONE.to_string(),
+            TransactionFilter::PendingMethod(method) => format!(
+                "method = '{}' AND fee_paid IS NULL",
+                method.replace('\'', "''")
+            ),
+        }
+    }
+
+    pub fn matches(&self, tx: &TxDbObj) -> bool {
+        match self {
+            TransactionFilter::All => true,
+            TransactionFilter::Queued => tx.processing > 0 && tx.first_processed.is_none(),
+            TransactionFilter::Processing => tx.processing > 0 && tx.first_processed.is_some(),
+            TransactionFilter::ToProcess => tx.processing > 0,
+            TransactionFilter::Done => tx.processing == 0,
+            TransactionFilter::PendingMethod(method) => {
+                &tx.method == method && tx.fee_paid.is_none()
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TransactionOrder {
+    #[default]
+    IdDesc,
+    /// Replacement transactions first, then oldest
+    IdAndReplacementId,
+    CreateDate,
+    FirstProcessedDateDesc,
+}
+
+impl TransactionOrder {
+    pub fn to_sql(&self) -> &'static str {
+        match self {
+            TransactionOrder::IdDesc => "id DESC",
+            TransactionOrder::IdAndReplacementId => TRANSACTION_ORDER_BY_ID_AND_REPLACEMENT_ID,
+            TransactionOrder::CreateDate => TRANSACTION_ORDER_BY_CREATE_DATE,
+            TransactionOrder::FirstProcessedDateDesc => {
+                TRANSACTION_ORDER_BY_FIRST_PROCESSED_DATE_DESC
+            }
+        }
+    }
+
+    /// Same ordering as SQL, NULL values are sorted last in descending order
+    pub fn compare(&self, a: &TxDbObj, b: &TxDbObj) -> Ordering {
+        match self {
+            TransactionOrder::IdDesc => b.id.cmp(&a.id),
+            TransactionOrder::IdAndReplacementId => {
+                b.orig_tx_id.cmp(&a.orig_tx_id).then(a.id.cmp(&b.id))
+            }
+            TransactionOrder::CreateDate => a.created_date.cmp(&b.created_date),
+            TransactionOrder::FirstProcessedDateDesc => b.first_processed.cmp(&a.first_processed),
+        }
+    }
+}
+
+/// Filter of token transfers, counterpart of TRANSFER_FILTER_* constants
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferFilter {
+    All,
+    Queued,
+    Processing,
+    Done,
+}
+
+impl TransferFilter {
+    pub fn to_sql(&self) -> &'static str {
+        match self {
+            TransferFilter::All => TRANSFER_FILTER_ALL,
+            TransferFilter::Queued => TRANSFER_FILTER_QUEUED,
+            TransferFilter::Processing => TRANSFER_FILTER_PROCESSING,
+            TransferFilter::Done => TRANSFER_FILTER_DONE,
+        }
+    }
+
+    pub fn matches(&self, transfer: &TokenTransferDbObj) -> bool {
+        match self {
+            TransferFilter::All => true,
+            TransferFilter::Queued => transfer.tx_id.is_none() && transfer.error.is_none(),
+            TransferFilter::Processing => transfer.tx_id.is_some() && transfer.fee_paid.is_none(),
+            TransferFilter::Done => transfer.fee_paid.is_some(),
+        }
+    }
+}
+
+/// Filters of transaction list, rows are returned newest first.
+/// `before_id` is the cursor of keyset pagination, only rows with lower id are returned.
+#[derive(Debug, Clone, Default)]
+pub struct TxListFilter {
+    pub status: Option<TransactionFilter>,
+    pub chain_id: Option<i64>,
+    pub account: Option<String>,
+    pub created_from: Option<DateTime<Utc>>,
+    pub created_to: Option<DateTime<Utc>>,
+    pub before_id: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+impl TxListFilter {
+    pub fn matches(&self, tx: &TxDbObj) -> bool {
+        self.status.as_ref().is_none_or(|s| s.matches(tx))
+            && self.chain_id.is_none_or(|c| tx.chain_id == c)
+            && self.account.as_ref().is_none_or(|a| &tx.from_addr == a)
+            && self.created_from.is_none_or(|d| tx.created_date >= d)
+            && self.created_to.is_none_or(|d| tx.created_date < d)
+            && self.before_id.is_none_or(|id| tx.id < id)
+    }
+}
+
+/// Filters of token transfer list, see [`TxListFilter`]
+#[derive(Debug, Clone, Default)]
+pub struct TransferListFilter {
+    pub status: Option<TransferFilter>,
+    pub chain_id: Option<i64>,
+    pub sender: Option<String>,
+    pub receiver: Option<String>,
+    pub payment_id: Option<String>,
+    pub deposit_id: Option<String>,
+    pub created_from: Option<DateTime<Utc>>,
+    pub created_to: Option<DateTime<Utc>>,
+    pub before_id: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+impl TransferListFilter {
+    pub fn matches(&self, tt: &TokenTransferDbObj) -> bool {
+        self.status.is_none_or(|s| s.matches(tt))
+            && self.chain_id.is_none_or(|c| tt.chain_id == c)
+            && self.sender.as_ref().is_none_or(|a| &tt.from_addr == a)
+            && self
+                .receiver
+                .as_ref()
+                .is_none_or(|a| &tt.receiver_addr == a)
+            && self
+                .payment_id
+                .as_ref()
+                .is_none_or(|p| tt.payment_id.as_ref() == Some(p))
+            && self
+                .deposit_id
+                .as_ref()
+                .is_none_or(|d| tt.deposit_id.as_ref() == Some(d))
+            && self.created_from.is_none_or(|d| tt.create_date >= d)
+            && self.created_to.is_none_or(|d| tt.create_date < d)
+            && self.before_id.is_none_or(|id| tt.id < id)
+    }
+}
+
+/// Filters of incoming transfer list, see [`TxListFilter`]
+#[derive(Debug, Clone, Default)]
+pub struct TransferInListFilter {
+    pub chain_id: Option<i64>,
+    pub sender: Option<String>,
+    pub receiver: Option<String>,
+    pub payment_id: Option<String>,
+    pub requested_from: Option<DateTime<Utc>>,
+    pub requested_to: Option<DateTime<Utc>>,
+    pub before_id: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+impl TransferInListFilter {
+    pub fn matches(&self, ti: &TransferInDbObj) -> bool {
+        self.chain_id.is_none_or(|c| ti.chain_id == c)
+            && self.sender.as_ref().is_none_or(|a| &ti.from_addr == a)
+            && self
+                .receiver
+                .as_ref()
+                .is_none_or(|a| &ti.receiver_addr == a)
+            && self.payment_id.as_ref().is_none_or(|p| &ti.payment_id == p)
+            && self.requested_from.is_none_or(|d| ti.requested_date >= d)
+            && self.requested_to.is_none_or(|d| ti.requested_date < d)
+            && self.before_id.is_none_or(|id| ti.id < id)
+    }
+}
+
+/// Filters of faucet request log, see [`TxListFilter`]
+#[derive(Debug, Clone, Default)]
+pub struct FaucetRequestListFilter {
+    pub chain_id: Option<i64>,
+    pub receiver: Option<String>,
+    pub client_ip: Option<String>,
+    pub created_from: Option<DateTime<Utc>>,
+    pub before_id: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+impl FaucetRequestListFilter {
+    pub fn matches(&self, request: &FaucetRequestDbObj) -> bool {
+        self.chain_id.is_none_or(|c| request.chain_id == c)
+            && self
+                .receiver
+                .as_ref()
+                .is_none_or(|a| &request.receiver_addr == a)
+            && self
+                .client_ip
+                .as_ref()
+                .is_none_or(|ip| request.client_ip.as_ref() == Some(ip))
+            && self.created_from.is_none_or(|d| request.create_date >= d)
+            && self.before_id.is_none_or(|id| request.id < id)
+    }
+}
+
+/// State of webhook delivery, counterpart of WEBHOOK_DELIVERY_FILTER_* constants
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebhookDeliveryStatus {
+    /// Waiting for first attempt or retry
+    Pending,
+    Delivered,
+    /// All attempts failed
+    Failed,
+}
+
+impl WebhookDeliveryStatus {
+    pub fn to_sql(&self) -> &'static str {
+        match self {
+            WebhookDeliveryStatus::Pending => WEBHOOK_DELIVERY_FILTER_PENDING,
+            WebhookDeliveryStatus::Delivered => WEBHOOK_DELIVERY_FILTER_DELIVERED,
+            WebhookDeliveryStatus::Failed => WEBHOOK_DELIVERY_FILTER_FAILED,
+        }
+    }
+
+    pub fn of(delivery: &WebhookDeliveryDbObj) -> Self {
+        if delivery.delivered_date.is_some() {
+            WebhookDeliveryStatus::Delivered
+        } else if delivery.next_attempt_date.is_some() {
+            WebhookDeliveryStatus::Pending
+        } else {
+            WebhookDeliveryStatus::Failed
+        }
+    }
+}
+
+/// Filters of webhook delivery log, see [`TxListFilter`]
+#[derive(Debug, Clone, Default)]
+pub struct WebhookDeliveryListFilter {
+    pub status: Option<WebhookDeliveryStatus>,
+    pub webhook: Option<String>,
+    pub event_type: Option<String>,
+    pub before_id: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+impl WebhookDeliveryListFilter {
+    pub fn matches(&self, delivery: &WebhookDeliveryDbObj) -> bool {
+        self.status
+            .is_none_or(|s| s == WebhookDeliveryStatus::of(delivery))
+            && self.webhook.as_ref().is_none_or(|w| &delivery.webhook == w)
+            && self
+                .event_type
+                .as_ref()
+                .is_none_or(|e| &delivery.event_type == e)
+            && self.before_id.is_none_or(|id| delivery.id < id)
+    }
+}
+
+/// Operations on payment tables, available both on the store and inside of store transaction.
+/// Semantics follow functions from `ops` module.
+#[async_trait]
+pub trait PaymentStoreOps: Send + Sync {
+    async fn insert_tx(&self, tx: &TxDbObj) -> Result<TxDbObj, sqlx::Error>;
+    async fn update_tx(&self, tx: &TxDbObj) -> Result<TxDbObj, sqlx::Error>;
+    async fn update_processing_and_first_processed_tx(
+        &self,
+        tx: &TxDbObj,
+    ) -> Result<TxDbObj, sqlx::Error>;
+    async fn update_tx_stuck_date(&self, tx: &TxDbObj) -> Result<TxDbObj, sqlx::Error>;
+    async fn delete_tx(&self, tx_id: i64) -> Result<(), sqlx::Error>;
+    async fn get_transaction(&self, tx_id: i64) -> Result<TxDbObj, sqlx::Error>;
+    async fn get_transactions(
+        &self,
+        account: Option<Address>,
+        filter: TransactionFilter,
+        limit: Option<i64>,
+        order: TransactionOrder,
+        chain_id: Option<i64>,
+    ) -> Result<Vec<TxDbObj>, sqlx::Error>;
+    async fn list_transactions(&self, filter: &TxListFilter) -> Result<Vec<TxDbObj>, sqlx::Error>;
+    async fn get_transaction_count(&self, filter: TransactionFilter) -> Result<usize, sqlx::Error>;
+    async fn get_transaction_highest_nonce(
+        &self,
+        chain_id: i64,
+        from_addr: &str,
+    ) -> Result<Option<i64>, sqlx::Error>;
+    async fn get_last_unsent_tx(&self) -> Result<Option<TxDbObj>, sqlx::Error>;
+    async fn get_archivable_transactions(
+        &self,
+        before: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<TxDbObj>, sqlx::Error>;
+
+    async fn insert_token_transfer(
+        &self,
+        token_transfer: &TokenTransferDbObj,
+    ) -> Result<TokenTransferDbObj, sqlx::Error>;
+    async fn update_token_transfer(
+        &self,
+        token_transfer: &TokenTransferDbObj,
+    ) -> Result<TokenTransferDbObj, sqlx::Error>;
+    async fn remap_token_transfer_tx(
+        &self,
+        old_tx_id: i64,
+        new_tx_id: i64,
+    ) -> Result<(), sqlx::Error>;
+    async fn cleanup_token_transfer_tx(&self, tx_id: i64) -> Result<(), sqlx::Error>;
+    async fn delete_token_transfers_by_tx(&self, tx_id: i64) -> Result<(), sqlx::Error>;
+    async fn get_token_transfer(&self, id: i64) -> Result<Option<TokenTransferDbObj>, sqlx::Error>;
+    async fn delete_pending_token_transfer(&self, id: i64) -> Result<bool, sqlx::Error>;
+    async fn approve_token_transfer(&self, id: i64) -> Result<bool, sqlx::Error>;
+    async fn attach_token_transfer_to_tx(
+        &self,
+        token_transfer: &TokenTransferDbObj,
+        tx_id: i64,
+    ) -> Result<bool, sqlx::Error>;
+    async fn update_pending_token_transfer(
+        &self,
+        id: i64,
+        receiver_addr: &str,
+        token_amount: &str,
+        priority: &str,
+        deadline: Option<DateTime<Utc>>,
+    ) -> Result<bool, sqlx::Error>;
+    async fn get_token_transfer_by_payment_id(
+        &self,
+        chain_id: i64,
+        from_addr: &str,
+        payment_id: &str,
+    ) -> Result<Option<TokenTransferDbObj>, sqlx::Error>;
+    async fn check_if_deposit_closed(
+        &self,
+        chain_id: i64,
+        deposit_id: &str,
+    ) -> Result<bool, sqlx::Error>;
+    async fn get_all_token_transfers(
+        &self,
+        limit: Option<i64>,
+    ) -> Result<Vec<TokenTransferDbObj>, sqlx::Error>;
+    async fn list_token_transfers(
+        &self,
+        filter: &TransferListFilter,
+    ) -> Result<Vec<TokenTransferDbObj>, sqlx::Error>;
+    async fn get_token_transfers_by_chain_id(
+        &self,
+        chain_id: i64,
+        limit: Option<i64>,
+    ) -> Result<Vec<TokenTransferDbObj>, sqlx::Error>;
+    async fn get_token_transfers_by_deposit_id(
+        &self,
+        chain_id: i64,
+        deposit_id: &str,
+    ) -> Result<Vec<TokenTransferDbObj>, sqlx::Error>;
+    async fn get_pending_token_transfers(
+        &self,
+        account: Address,
+        chain_id: i64,
+    ) -> Result<Vec<TokenTransferDbObj>, sqlx::Error>;
+    async fn get_next_token_transfer_deadline(
+        &self,
+        account: Address,
+        chain_id: i64,
+        after: DateTime<Utc>,
+    ) -> Result<Option<DateTime<Utc>>, sqlx::Error>;
+    async fn get_pending_token_transfers_by_receiver(
+        &self,
+        receiver: Address,
+    ) -> Result<Vec<TokenTransferDbObj>, sqlx::Error>;
+    async fn get_token_transfers_spent_since(
+        &self,
+        account: Address,
+        chain_id: i64,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<TokenTransferDbObj>, sqlx::Error>;
+    async fn get_first_token_transfer_to_receiver(
+        &self,
+        account: Address,
+        receiver: Address,
+    ) -> Result<Option<TokenTransferDbObj>, sqlx::Error>;
+    async fn get_unpaid_token_transfers(
+        &self,
+        chain_id: i64,
+        sender: Address,
+    ) -> Result<Vec<TokenTransferDbObj>, sqlx::Error>;
+    async fn get_token_transfers_by_tx(
+        &self,
+        tx_id: i64,
+    ) -> Result<Vec<TokenTransferDbObj>, sqlx::Error>;
+    async fn get_transfer_count(
+        &self,
+        filter: TransferFilter,
+        sender: Option<&str>,
+        receiver: Option<&str>,
+    ) -> Result<usize, sqlx::Error>;
+
+    async fn insert_allowance(
+        &self,
+        allowance: &AllowanceDbObj,
+    ) -> Result<AllowanceDbObj, sqlx::Error>;
+    async fn update_allowance(&self, allowance: &AllowanceDbObj) -> Result<(), sqlx::Error>;
+    async fn get_all_allowances(&self) -> Result<Vec<AllowanceDbObj>, sqlx::Error>;
+    async fn get_allowance_by_tx(&self, tx_id: i64) -> Result<AllowanceDbObj, sqlx::Error>;
+    async fn find_allowance(
+        &self,
+        owner: &str,
+        token_addr: &str,
+        spender: &str,
+        chain_id: i64,
+    ) -> Result<Option<AllowanceDbObj>, sqlx::Error>;
+    async fn get_allowances_by_owner(
+        &self,
+        owner: &str,
+    ) -> Result<Vec<AllowanceDbObj>, sqlx::Error>;
+    async fn remap_allowance_tx(&self, old_tx_id: i64, new_tx_id: i64) -> Result<(), sqlx::Error>;
+    async fn cleanup_allowance_tx(&self, tx_id: i64) -> Result<(), sqlx::Error>;
+
+    async fn insert_chain_tx(&self, tx: &ChainTxDbObj) -> Result<ChainTxDbObj, sqlx::Error>;
+    async fn get_chain_tx_hash(&self, tx_hash: String)
+        -> Result<Option<ChainTxDbObj>, sqlx::Error>;
+    async fn get_chain_txs_by_chain_id(
+        &self,
+        chain_id: i64,
+        limit: Option<i64>,
+    ) -> Result<Vec<ChainTxDbObj>, sqlx::Error>;
+    async fn get_chain_txs_before(
+        &self,
+        before: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<ChainTxDbObj>, sqlx::Error>;
+    async fn delete_chain_tx(&self, id: i64) -> Result<(), sqlx::Error>;
+
+    async fn insert_chain_transfer(
+        &self,
+        chain_transfer: &ChainTransferDbObj,
+    ) -> Result<ChainTransferDbObj, sqlx::Error>;
+    async fn get_chain_transfers_by_chain_id(
+        &self,
+        chain_id: i64,
+        limit: Option<i64>,
+    ) -> Result<Vec<ChainTransferDbObj>, sqlx::Error>;
+    async fn get_chain_transfers_by_chain_tx(
+        &self,
+        chain_tx_id: i64,
+    ) -> Result<Vec<ChainTransferDbObj>, sqlx::Error>;
+    async fn delete_chain_transfers_by_chain_tx(&self, chain_tx_id: i64)
+        -> Result<(), sqlx::Error>;
+    async fn get_all_chain_transfers_ext(
+        &self,
+        chain_id: i64,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        limit: Option<i64>,
+    ) -> Result<Vec<ChainTransferDbObjExt>, sqlx::Error>;
+    async fn get_all_chain_transfers_by_receiver_ext(
+        &self,
+        chain_id: i64,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        receiver: &str,
+        limit: Option<i64>,
+    ) -> Result<Vec<ChainTransferDbObjExt>, sqlx::Error>;
+
+    async fn insert_transfer_in(
+        &self,
+        transfer_in: &TransferInDbObj,
+    ) -> Result<TransferInDbObj, sqlx::Error>;
+    async fn update_transfer_in(
+        &self,
+        transfer_in: &TransferInDbObj,
+    ) -> Result<TransferInDbObj, sqlx::Error>;
+    async fn get_account_transfers_in(
+        &self,
+        account: &str,
+        limit: Option<i64>,
+    ) -> Result<Vec<TransferInDbObj>, sqlx::Error>;
+    async fn get_all_transfers_in(
+        &self,
+        limit: Option<i64>,
+    ) -> Result<Vec<TransferInDbObj>, sqlx::Error>;
+    async fn list_transfers_in(
+        &self,
+        filter: &TransferInListFilter,
+    ) -> Result<Vec<TransferInDbObj>, sqlx::Error>;
+
+    async fn get_scan_info(
+        &self,
+        chain_id: i64,
+        filter: &str,
+    ) -> Result<Option<ScanDaoDbObj>, sqlx::Error>;
+    async fn upsert_scan_info(&self, scan_info: &ScanDaoDbObj)
+        -> Result<ScanDaoDbObj, sqlx::Error>;
+    async fn delete_scan_info(&self, chain_id: i64, filter: &str) -> Result<(), sqlx::Error>;
+
+    async fn insert_transfer_archive_stats(
+        &self,
+        stats: &TransferArchiveStatsDbObj,
+    ) -> Result<TransferArchiveStatsDbObj, sqlx::Error>;
+    async fn update_transfer_archive_stats(
+        &self,
+        stats: &TransferArchiveStatsDbObj,
+    ) -> Result<(), sqlx::Error>;
+    async fn get_transfer_archive_stats(
+        &self,
+        chain_id: i64,
+        source: &str,
+    ) -> Result<Vec<TransferArchiveStatsDbObj>, sqlx::Error>;
+
+    async fn insert_webhook_delivery(
+        &self,
+        delivery: &WebhookDeliveryDbObj,
+    ) -> Result<WebhookDeliveryDbObj, sqlx::Error>;
+    async fn update_webhook_delivery(
+        &self,
+        delivery: &WebhookDeliveryDbObj,
+    ) -> Result<(), sqlx::Error>;
+    async fn get_webhook_delivery(
+        &self,
+        id: i64,
+    ) -> Result<Option<WebhookDeliveryDbObj>, sqlx::Error>;
+    async fn get_webhook_deliveries_due(
+        &self,
+        now: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<WebhookDeliveryDbObj>, sqlx::Error>;
+    async fn list_webhook_deliveries(
+        &self,
+        filter: &WebhookDeliveryListFilter,
+    ) -> Result<Vec<WebhookDeliveryDbObj>, sqlx::Error>;
+
+    async fn insert_driver_event(
+        &self,
+        event: &DriverEventDbObj,
+    ) -> Result<DriverEventDbObj, sqlx::Error>;
+    async fn get_driver_events_after(
+        &self,
+        after_id: i64,
+        limit: i64,
+    ) -> Result<Vec<DriverEventDbObj>, sqlx::Error>;
+    async fn delete_driver_events_before(&self, before: DateTime<Utc>) -> Result<(), sqlx::Error>;
+    async fn get_last_driver_event_id(&self) -> Result<Option<i64>, sqlx::Error>;
+    async fn get_event_offset(&self, consumer: &str) -> Result<Option<i64>, sqlx::Error>;
+    async fn set_event_offset(&self, consumer: &str, last_event_id: i64)
+        -> Result<(), sqlx::Error>;
+
+    async fn insert_faucet_request(
+        &self,
+        request: &FaucetRequestDbObj,
+    ) -> Result<FaucetRequestDbObj, sqlx::Error>;
+    async fn list_faucet_requests(
+        &self,
+        filter: &FaucetRequestListFilter,
+    ) -> Result<Vec<FaucetRequestDbObj>, sqlx::Error>;
+
+    async fn get_next_transactions_to_process(
+        &self,
+        account: Option<Address>,
+        limit: i64,
+        chain_id: i64,
+    ) -> Result<Vec<TxDbObj>, sqlx::Error> {
+        self.get_transactions(
+            account,
+            TransactionFilter::ToProcess,
+            Some(limit),
+            TransactionOrder::IdAndReplacementId,
+            Some(chain_id),
+        )
+        .await
+    }
+
+    /// Transaction followed by transactions it replaced (call in transaction)
+    async fn get_transaction_chain(&self, tx_id: i64) -> Result<Vec<TxDbObj>, sqlx::Error> {
+        let mut current_id = Some(tx_id);
+        let mut res = vec![];
+        while let Some(id) = current_id {
+            let row = self.get_transaction(id).await?;
+            current_id = row.orig_tx_id;
+            res.push(row);
+        }
+        Ok(res)
+    }
+}
+
+#[async_trait]
+pub trait PaymentStoreTransaction: PaymentStoreOps {
+    async fn commit(self) -> Result<(), sqlx::Error>;
+}
+
+/// Result of [`PaymentStore::insert_token_transfer_idempotent`]
+#[derive(Debug, Clone)]
+pub enum TransferInsertResult {
+    Created(TokenTransferDbObj),
+    /// Identical transfer with the same payment id was submitted before
+    Existing(TokenTransferDbObj),
+}
+
+impl TransferInsertResult {
+    pub fn transfer(&self) -> &TokenTransferDbObj {
+        match self {
+            TransferInsertResult::Created(tt) | TransferInsertResult::Existing(tt) => tt,
+        }
+    }
+}
+
+/// Error of [`PaymentStore::insert_token_transfers_idempotent`]
+#[derive(Debug)]
+pub struct BulkInsertError {
+    /// Index of the transfer that could not be inserted, None if the transaction failed
+    pub index: Option<usize>,
+    pub error: PaymentError,
+}
+
+/// Transfers are identical if they have the same payment parameters, state is not compared
+fn is_same_transfer(a: &TokenTransferDbObj, b: &TokenTransferDbObj) -> bool {
+    a.receiver_addr == b.receiver_addr
+        && a.token_addr == b.token_addr
+        && a.token_amount == b.token_amount
+        && a.deposit_id == b.deposit_id
+}
+
+/// Transfer with the same payment id as `token_transfer`, [`PaymentIdConflict`] error
+/// if it has different parameters
+async fn find_same_transfer<T: PaymentStoreOps + ?Sized>(
+    ops: &T,
+    token_transfer: &TokenTransferDbObj,
+    payment_id: &str,
+) -> Result<Option<TokenTransferDbObj>, PaymentError> {
+    let existing = ops
+        .get_token_transfer_by_payment_id(
+            token_transfer.chain_id,
+            &token_transfer.from_addr,
+            payment_id,
+        )
+        .await
+        .map_err(err_from!())?;
+    match existing {
+        Some(existing) if is_same_transfer(&existing, token_transfer) => Ok(Some(existing)),
+        Some(existing) => Err(err_create!(PaymentIdConflict {
+            chain_id: token_transfer.chain_id,
+            from_addr: token_transfer.from_addr.clone(),
+            payment_id: payment_id.to_string(),
+            existing_id: existing.id,
+        })),
+        None => Ok(None),
+    }
+}
+
+/// Persistence used by the payment runtime, sender and web server.
+/// Implemented for SQLite and PostgreSQL ([`SqlStore`]) and in memory ([`MemoryStore`]),
+/// embedders can provide their own implementation.
+#[async_trait]
+pub trait PaymentStore: PaymentStoreOps + Clone + 'static {
+    type Transaction: PaymentStoreTransaction;
+
+    /// Changes made in transaction are discarded if it is dropped without commit
+    async fn begin(&self) -> Result<Self::Transaction, sqlx::Error>;
+
+    async fn insert_token_transfer_with_deposit_check(
+        &self,
+        token_transfer: &TokenTransferDbObj,
+    ) -> Result<TokenTransferDbObj, PaymentError> {
+        if let Some(deposit_id) = token_transfer.deposit_id.as_ref() {
+            let transaction = self.begin().await.map_err(err_from!())?;
+            let is_finished = transaction
+                .check_if_deposit_closed(token_transfer.chain_id, deposit_id)
+                .await
+                .map_err(err_from!())?;
+            if is_finished {
+                return Err(err_custom_create!(
+                    "Cannot add token_transfer to already finished deposit"
+                ));
+            }
+            let res = transaction
+                .insert_token_transfer(token_transfer)
+                .await
+                .map_err(err_from!())?;
+            transaction.commit().await.map_err(err_from!())?;
+            Ok(res)
+        } else {
+            self.insert_token_transfer(token_transfer)
+                .await
+                .map_err(err_from!())
+        }
+    }
+
+    /// Payment id is unique per chain and sender, so retried request does not create
+    /// another transfer. Returns existing transfer if it is the same as the new one,
+    /// [`PaymentIdConflict`] error if it differs.
+    async fn insert_token_transfer_idempotent(
+        &self,
+        token_transfer: &TokenTransferDbObj,
+    ) -> Result<TransferInsertResult, PaymentError> {
+        let Some(payment_id) = token_transfer.payment_id.as_deref() else {
+            return self
+                .insert_token_transfer_with_deposit_check(token_transfer)
+                .await
+                .map(TransferInsertResult::Created);
+        };
+        let find_existing = || async {
+            Ok::<_, PaymentError>(
+                find_same_transfer(self, token_transfer, payment_id)
+                    .await?
+                    .map(TransferInsertResult::Existing),
+            )
+        };
+        if let Some(existing) = find_existing().await? {
+            return Ok(existing);
+        }
+        match self
+            .insert_token_transfer_with_deposit_check(token_transfer)
+            .await
+        {
+            Ok(tt) => Ok(TransferInsertResult::Created(tt)),
+            Err(err) => {
+                // concurrent request with the same payment id was inserted first
+                if let Some(existing) = find_existing().await? {
+                    return Ok(existing);
+                }
+                Err(err)
+            }
+        }
+    }
+
+    /// Insert transfers in one transaction with the same rules as
+    /// [`Self::insert_token_transfer_idempotent`]. Nothing is inserted when any of them
+    /// fails.
+    async fn insert_token_transfers_idempotent(
+        &self,
+        token_transfers: &[TokenTransferDbObj],
+    ) -> Result<Vec<TransferInsertResult>, BulkInsertError> {
+        let transaction_error = |err| BulkInsertError {
+            index: None,
+            error: err_from!()(err),
+        };
+        let transaction = self.begin().await.map_err(transaction_error)?;
+        let mut results = Vec::with_capacity(token_transfers.len());
+        for (idx, token_transfer) in token_transfers.iter().enumerate() {
+            let insert = async {
+                if let Some(payment_id) = token_transfer.payment_id.as_deref() {
+                    if let Some(existing) =
+                        find_same_transfer(&transaction, token_transfer, payment_id).await?
+                    {
+                        return Ok(TransferInsertResult::Existing(existing));
+                    }
+                }
+                if let Some(deposit_id) = token_transfer.deposit_id.as_ref() {
+                    if transaction
+                        .check_if_deposit_closed(token_transfer.chain_id, deposit_id)
+                        .await
+                        .map_err(err_from!())?
+                    {
+                        return Err(err_custom_create!(
+                            "Cannot add token_transfer to already finished deposit"
+                        ));
+                    }
+                }
+                transaction
+                    .insert_token_transfer(token_transfer)
+                    .await
+                    .map(TransferInsertResult::Created)
+                    .map_err(err_from!())
+            };
+            results.push(insert.await.map_err(|error| BulkInsertError {
+                index: Some(idx),
+                error,
+            })?);
+        }
+        transaction.commit().await.map_err(transaction_error)?;
+        Ok(results)
+    }
+}
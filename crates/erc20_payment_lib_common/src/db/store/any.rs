@@ -0,0 +1,735 @@
+use super::{
+    FaucetRequestListFilter, PaymentStore, PaymentStoreOps, PaymentStoreTransaction, PostgresStore,
+    SqlTransaction, SqliteStore, TransactionFilter, TransactionOrder, TransferFilter,
+    TransferInListFilter, TransferListFilter, TxListFilter, WebhookDeliveryListFilter,
+};
+use crate::db::connection::PaymentDbPool;
+use crate::db::database::DbBackend;
+use crate::db::model::*;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{Postgres, Sqlite};
+use web3::types::Address;
+
+/// Store over database selected at runtime by connection url, see
+/// [`crate::create_db_connection`]. Operations are delegated to [`SqliteStore`] or [`PostgresStore`].
+#[derive(Debug, Clone)]
+pub enum PaymentDbStore {
+    Sqlite(SqliteStore),
+    Postgres(PostgresStore),
+}
+
+pub enum PaymentDbTransaction {
+    Sqlite(SqlTransaction<Sqlite>),
+    Postgres(Box<SqlTransaction<Postgres>>),
+}
+
+impl From<PaymentDbPool> for PaymentDbStore {
+    fn from(pool: PaymentDbPool) -> Self {
+        match pool {
+            PaymentDbPool::Sqlite(pool) => PaymentDbStore::Sqlite(SqliteStore::new(pool)),
+            PaymentDbPool::Postgres(pool) => PaymentDbStore::Postgres(PostgresStore::new(pool)),
+        }
+    }
+}
+
+impl From<sqlx::SqlitePool> for PaymentDbStore {
+    fn from(pool: sqlx::SqlitePool) -> Self {
+        PaymentDbStore::Sqlite(SqliteStore::new(pool))
+    }
+}
+
+impl PaymentDbStore {
+    pub fn backend(&self) -> DbBackend {
+        match self {
+            PaymentDbStore::Sqlite(_) => DbBackend::Sqlite,
+            PaymentDbStore::Postgres(_) => DbBackend::Postgres,
+        }
+    }
+
+    /// SQLite pool, for features working only on SQLite (backups, archive, direct ops calls)
+    pub fn sqlite_pool(&self) -> Option<&sqlx::SqlitePool> {
+        match self {
+            PaymentDbStore::Sqlite(store) => Some(store.pool()),
+            PaymentDbStore::Postgres(_) => None,
+        }
+    }
+
+    pub async fn close(&self) {
+        match self {
+            PaymentDbStore::Sqlite(store) => store.pool().close().await,
+            PaymentDbStore::Postgres(store) => store.pool().close().await,
+        }
+    }
+}
+
+macro_rules! db_call {
+    ($self:ident, $fun:ident($($arg:expr),*)) => {
+        match $self {
+            Self::Sqlite(store) => store.$fun($($arg),*).await,
+            Self::Postgres(store) => store.$fun($($arg),*).await,
+        }
+    };
+}
+
+macro_rules! impl_db_store_ops {
+    ($store:ident) => {
+        #[async_trait]
+        impl PaymentStoreOps for $store {
+            async fn insert_tx(&self, tx: &TxDbObj) -> Result<TxDbObj, sqlx::Error> {
+                db_call!(self, insert_tx(tx))
+            }
+            async fn update_tx(&self, tx: &TxDbObj) -> Result<TxDbObj, sqlx::Error> {
+                db_call!(self, update_tx(tx))
+            }
+            async fn update_processing_and_first_processed_tx(
+                &self,
+                tx: &TxDbObj,
+            ) -> Result<TxDbObj, sqlx::Error> {
+                db_call!(self, update_processing_and_first_processed_tx(tx))
+            }
+            async fn update_tx_stuck_date(&self, tx: &TxDbObj) -> Result<TxDbObj, sqlx::Error> {
+                db_call!(self, update_tx_stuck_date(tx))
+            }
+            async fn delete_tx(&self, tx_id: i64) -> Result<(), sqlx::Error> {
+                db_call!(self, delete_tx(tx_id))
+            }
+            async fn get_transaction(&self, tx_id: i64) -> Result<TxDbObj, sqlx::Error> {
+                db_call!(self, get_transaction(tx_id))
+            }
+            async fn get_transactions(
+                &self,
+                account: Option<Address>,
+                filter: TransactionFilter,
+                limit: Option<i64>,
+                order: TransactionOrder,
+                chain_id: Option<i64>,
+            ) -> Result<Vec<TxDbObj>, sqlx::Error> {
+                db_call!(
+                    self,
+                    get_transactions(account, filter, limit, order, chain_id)
+                )
+            }
+            async fn list_transactions(
+                &self,
+                filter: &TxListFilter,
+            ) -> Result<Vec<TxDbObj>, sqlx::Error> {
+                db_call!(self, list_transactions(filter))
+            }
+            async fn get_transaction_count(
+                &self,
+                filter: TransactionFilter,
+            ) -> Result<usize, sqlx::Error> {
+                db_call!(self, get_transaction_count(filter))
+            }
+            async fn get_transaction_highest_nonce(
+                &self,
+                chain_id: i64,
+                from_addr: &str,
+            ) -> Result<Option<i64>, sqlx::Error> {
+                db_call!(self, get_transaction_highest_nonce(chain_id, from_addr))
+            }
+            async fn get_last_unsent_tx(&self) -> Result<Option<TxDbObj>, sqlx::Error> {
+                db_call!(self, get_last_unsent_tx())
+            }
+            async fn get_archivable_transactions(
+                &self,
+                before: DateTime<Utc>,
+                limit: i64,
+            ) -> Result<Vec<TxDbObj>, sqlx::Error> {
+                db_call!(self, get_archivable_transactions(before, limit))
+            }
+            async fn insert_token_transfer(
+                &self,
+                token_transfer: &TokenTransferDbObj,
+            ) -> Result<TokenTransferDbObj, sqlx::Error> {
+                db_call!(self, insert_token_transfer(token_transfer))
+            }
+            async fn update_token_transfer(
+                &self,
+                token_transfer: &TokenTransferDbObj,
+            ) -> Result<TokenTransferDbObj, sqlx::Error> {
+                db_call!(self, update_token_transfer(token_transfer))
+            }
+            async fn remap_token_transfer_tx(
+                &self,
+                old_tx_id: i64,
+                new_tx_id: i64,
+            ) -> Result<(), sqlx::Error> {
+                db_call!(self, remap_token_transfer_tx(old_tx_id, new_tx_id))
+            }
+            async fn cleanup_token_transfer_tx(&self, tx_id: i64) -> Result<(), sqlx::Error> {
+                db_call!(self, cleanup_token_transfer_tx(tx_id))
+            }
+            async fn delete_token_transfers_by_tx(&self, tx_id: i64) -> Result<(), sqlx::Error> {
+                db_call!(self, delete_token_transfers_by_tx(tx_id))
+            }
+            async fn get_token_transfer(
+                &self,
+                id: i64,
+            ) -> Result<Option<TokenTransferDbObj>, sqlx::Error> {
+                db_call!(self, get_token_transfer(id))
+            }
+            async fn delete_pending_token_transfer(&self, id: i64) -> Result<bool, sqlx::Error> {
+                db_call!(self, delete_pending_token_transfer(id))
+            }
+            async fn approve_token_transfer(&self, id: i64) -> Result<bool, sqlx::Error> {
+                db_call!(self, approve_token_transfer(id))
+            }
+            async fn attach_token_transfer_to_tx(
+                &self,
+                token_transfer: &TokenTransferDbObj,
+                tx_id: i64,
+            ) -> Result<bool, sqlx::Error> {
+                db_call!(self, attach_token_transfer_to_tx(token_transfer, tx_id))
+            }
+            async fn update_pending_token_transfer(
+                &self,
+                id: i64,
+                receiver_addr: &str,
+                token_amount: &str,
+                priority: &str,
+                deadline: Option<DateTime<Utc>>,
+            ) -> Result<bool, sqlx::Error> {
+                db_call!(
+                    self,
+                    update_pending_token_transfer(
+                        id,
+                        receiver_addr,
+                        token_amount,
+                        priority,
+                        deadline
+                    )
+                )
+            }
+            async fn get_token_transfer_by_payment_id(
+                &self,
+                chain_id: i64,
+                from_addr: &str,
+                payment_id: &str,
+            ) -> Result<Option<TokenTransferDbObj>, sqlx::Error> {
+                db_call!(
+                    self,
+                    get_token_transfer_by_payment_id(chain_id, from_addr, payment_id)
+                )
+            }
+            async fn check_if_deposit_closed(
+                &self,
+                chain_id: i64,
+                deposit_id: &str,
+            ) -> Result<bool, sqlx::Error> {
+                db_call!(self, check_if_deposit_closed(chain_id, deposit_id))
+            }
+            async fn get_all_token_transfers(
+                &self,
+                limit: Option<i64>,
+            ) -> Result<Vec<TokenTransferDbObj>, sqlx::Error> {
+                db_call!(self, get_all_token_transfers(limit))
+            }
+            async fn list_token_transfers(
+                &self,
+                filter: &TransferListFilter,
+            ) -> Result<Vec<TokenTransferDbObj>, sqlx::Error> {
+                db_call!(self, list_token_transfers(filter))
+            }
+            async fn get_token_transfers_by_chain_id(
+                &self,
+                chain_id: i64,
+                limit: Option<i64>,
+            ) -> Result<Vec<TokenTransferDbObj>, sqlx::Error> {
+                db_call!(self, get_token_transfers_by_chain_id(chain_id, limit))
+            }
+            async fn get_token_transfers_by_deposit_id(
+                &self,
+                chain_id: i64,
+                deposit_id: &str,
+            ) -> Result<Vec<TokenTransferDbObj>, sqlx::Error> {
+                db_call!(
+                    self,
+                    get_token_transfers_by_deposit_id(chain_id, deposit_id)
+                )
+            }
+            async fn get_pending_token_transfers(
+                &self,
+                account: Address,
+                chain_id: i64,
+            ) -> Result<Vec<TokenTransferDbObj>, sqlx::Error> {
+                db_call!(self, get_pending_token_transfers(account, chain_id))
+            }
+            async fn get_next_token_transfer_deadline(
+                &self,
+                account: Address,
+                chain_id: i64,
+                after: DateTime<Utc>,
+            ) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+                db_call!(
+                    self,
+                    get_next_token_transfer_deadline(account, chain_id, after)
+                )
+            }
+            async fn get_pending_token_transfers_by_receiver(
+                &self,
+                receiver: Address,
+            ) -> Result<Vec<TokenTransferDbObj>, sqlx::Error> {
+                db_call!(self, get_pending_token_transfers_by_receiver(receiver))
+            }
+            async fn get_token_transfers_spent_since(
+                &self,
+                account: Address,
+                chain_id: i64,
+                since: DateTime<Utc>,
+            ) -> Result<Vec<TokenTransferDbObj>, sqlx::Error> {
+                db_call!(
+                    self,
+                    get_token_transfers_spent_since(account, chain_id, since)
+                )
+            }
+            async fn get_first_token_transfer_to_receiver(
+                &self,
+                account: Address,
+                receiver: Address,
+            ) -> Result<Option<TokenTransferDbObj>, sqlx::Error> {
+                db_call!(
+                    self,
+                    get_first_token_transfer_to_receiver(account, receiver)
+                )
+            }
+            async fn get_unpaid_token_transfers(
+                &self,
+                chain_id: i64,
+                sender: Address,
+            ) -> Result<Vec<TokenTransferDbObj>, sqlx::Error> {
+                db_call!(self, get_unpaid_token_transfers(chain_id, sender))
+            }
+            async fn get_token_transfers_by_tx(
+                &self,
+                tx_id: i64,
+            ) -> Result<Vec<TokenTransferDbObj>, sqlx::Error> {
+                db_call!(self, get_token_transfers_by_tx(tx_id))
+            }
+            async fn get_transfer_count(
+                &self,
+                filter: TransferFilter,
+                sender: Option<&str>,
+                receiver: Option<&str>,
+            ) -> Result<usize, sqlx::Error> {
+                db_call!(self, get_transfer_count(filter, sender, receiver))
+            }
+            async fn insert_allowance(
+                &self,
+                allowance: &AllowanceDbObj,
+            ) -> Result<AllowanceDbObj, sqlx::Error> {
+                db_call!(self, insert_allowance(allowance))
+            }
+            async fn update_allowance(
+                &self,
+                allowance: &AllowanceDbObj,
+            ) -> Result<(), sqlx::Error> {
+                db_call!(self, update_allowance(allowance))
+            }
+            async fn get_all_allowances(&self) -> Result<Vec<AllowanceDbObj>, sqlx::Error> {
+                db_call!(self, get_all_allowances())
+            }
+            async fn get_allowance_by_tx(&self, tx_id: i64) -> Result<AllowanceDbObj, sqlx::Error> {
+                db_call!(self, get_allowance_by_tx(tx_id))
+            }
+            async fn find_allowance(
+                &self,
+                owner: &str,
+                token_addr: &str,
+                spender: &str,
+                chain_id: i64,
+            ) -> Result<Option<AllowanceDbObj>, sqlx::Error> {
+                db_call!(self, find_allowance(owner, token_addr, spender, chain_id))
+            }
+            async fn get_allowances_by_owner(
+                &self,
+                owner: &str,
+            ) -> Result<Vec<AllowanceDbObj>, sqlx::Error> {
+                db_call!(self, get_allowances_by_owner(owner))
+            }
+            async fn remap_allowance_tx(
+                &self,
+                old_tx_id: i64,
+                new_tx_id: i64,
+            ) -> Result<(), sqlx::Error> {
+                db_call!(self, remap_allowance_tx(old_tx_id, new_tx_id))
+            }
+            async fn cleanup_allowance_tx(&self, tx_id: i64) -> Result<(), sqlx::Error> {
+                db_call!(self, cleanup_allowance_tx(tx_id))
+            }
+            async fn insert_chain_tx(
+                &self,
+                tx: &ChainTxDbObj,
+            ) -> Result<ChainTxDbObj, sqlx::Error> {
+                db_call!(self, insert_chain_tx(tx))
+            }
+            async fn get_chain_tx_hash(
+                &self,
+                tx_hash: String,
+            ) -> Result<Option<ChainTxDbObj>, sqlx::Error> {
+                db_call!(self, get_chain_tx_hash(tx_hash))
+            }
+            async fn get_chain_txs_by_chain_id(
+                &self,
+                chain_id: i64,
+                limit: Option<i64>,
+            ) -> Result<Vec<ChainTxDbObj>, sqlx::Error> {
+                db_call!(self, get_chain_txs_by_chain_id(chain_id, limit))
+            }
+            async fn get_chain_txs_before(
+                &self,
+                before: DateTime<Utc>,
+                limit: i64,
+            ) -> Result<Vec<ChainTxDbObj>, sqlx::Error> {
+                db_call!(self, get_chain_txs_before(before, limit))
+            }
+            async fn delete_chain_tx(&self, id: i64) -> Result<(), sqlx::Error> {
+                db_call!(self, delete_chain_tx(id))
+            }
+            async fn insert_chain_transfer(
+                &self,
+                chain_transfer: &ChainTransferDbObj,
+            ) -> Result<ChainTransferDbObj, sqlx::Error> {
+                db_call!(self, insert_chain_transfer(chain_transfer))
+            }
+            async fn get_chain_transfers_by_chain_id(
+                &self,
+                chain_id: i64,
+                limit: Option<i64>,
+            ) -> Result<Vec<ChainTransferDbObj>, sqlx::Error> {
+                db_call!(self, get_chain_transfers_by_chain_id(chain_id, limit))
+            }
+            async fn get_chain_transfers_by_chain_tx(
+                &self,
+                chain_tx_id: i64,
+            ) -> Result<Vec<ChainTransferDbObj>, sqlx::Error> {
+                db_call!(self, get_chain_transfers_by_chain_tx(chain_tx_id))
+            }
+            async fn delete_chain_transfers_by_chain_tx(
+                &self,
+                chain_tx_id: i64,
+            ) -> Result<(), sqlx::Error> {
+                db_call!(self, delete_chain_transfers_by_chain_tx(chain_tx_id))
+            }
+            async fn get_all_chain_transfers_ext(
+                &self,
+                chain_id: i64,
+                from: DateTime<Utc>,
+                to: DateTime<Utc>,
+                limit: Option<i64>,
+            ) -> Result<Vec<ChainTransferDbObjExt>, sqlx::Error> {
+                db_call!(self, get_all_chain_transfers_ext(chain_id, from, to, limit))
+            }
+            async fn get_all_chain_transfers_by_receiver_ext(
+                &self,
+                chain_id: i64,
+                from: DateTime<Utc>,
+                to: DateTime<Utc>,
+                receiver: &str,
+                limit: Option<i64>,
+            ) -> Result<Vec<ChainTransferDbObjExt>, sqlx::Error> {
+                db_call!(
+                    self,
+                    get_all_chain_transfers_by_receiver_ext(chain_id, from, to, receiver, limit)
+                )
+            }
+            async fn insert_transfer_in(
+                &self,
+                transfer_in: &TransferInDbObj,
+            ) -> Result<TransferInDbObj, sqlx::Error> {
+                db_call!(self, insert_transfer_in(transfer_in))
+            }
+            async fn update_transfer_in(
+                &self,
+                transfer_in: &TransferInDbObj,
+            ) -> Result<TransferInDbObj, sqlx::Error> {
+                db_call!(self, update_transfer_in(transfer_in))
+            }
+            async fn get_account_transfers_in(
+                &self,
+                account: &str,
+                limit: Option<i64>,
+            ) -> Result<Vec<TransferInDbObj>, sqlx::Error> {
+                db_call!(self, get_account_transfers_in(account, limit))
+            }
+            async fn get_all_transfers_in(
+                &self,
+                limit: Option<i64>,
+            ) -> Result<Vec<TransferInDbObj>, sqlx::Error> {
+                db_call!(self, get_all_transfers_in(limit))
+            }
+            async fn list_transfers_in(
+                &self,
+                filter: &TransferInListFilter,
+            ) -> Result<Vec<TransferInDbObj>, sqlx::Error> {
+                db_call!(self, list_transfers_in(filter))
+            }
+            async fn get_scan_info(
+                &self,
+                chain_id: i64,
+                filter: &str,
+            ) -> Result<Option<ScanDaoDbObj>, sqlx::Error> {
+                db_call!(self, get_scan_info(chain_id, filter))
+            }
+            async fn upsert_scan_info(
+                &self,
+                scan_info: &ScanDaoDbObj,
+            ) -> Result<ScanDaoDbObj, sqlx::Error> {
+                db_call!(self, upsert_scan_info(scan_info))
+            }
+            async fn delete_scan_info(
+                &self,
+                chain_id: i64,
+                filter: &str,
+            ) -> Result<(), sqlx::Error> {
+                db_call!(self, delete_scan_info(chain_id, filter))
+            }
+            async fn insert_transfer_archive_stats(
+                &self,
+                stats: &TransferArchiveStatsDbObj,
+            ) -> Result<TransferArchiveStatsDbObj, sqlx::Error> {
+                db_call!(self, insert_transfer_archive_stats(stats))
+            }
+            async fn update_transfer_archive_stats(
+                &self,
+                stats: &TransferArchiveStatsDbObj,
+            ) -> Result<(), sqlx::Error> {
+                db_call!(self, update_transfer_archive_stats(stats))
+            }
+            async fn get_transfer_archive_stats(
+                &self,
+                chain_id: i64,
+                source: &str,
+            ) -> Result<Vec<TransferArchiveStatsDbObj>, sqlx::Error> {
+                db_call!(self, get_transfer_archive_stats(chain_id, source))
+            }
+            async fn insert_webhook_delivery(
+                &self,
+                delivery: &WebhookDeliveryDbObj,
+            ) -> Result<WebhookDeliveryDbObj, sqlx::Error> {
+                db_call!(self, insert_webhook_delivery(delivery))
+            }
+            async fn update_webhook_delivery(
+                &self,
+                delivery: &WebhookDeliveryDbObj,
+            ) -> Result<(), sqlx::Error> {
+                db_call!(self, update_webhook_delivery(delivery))
+            }
+            async fn get_webhook_delivery(
+                &self,
+                id: i64,
+            ) -> Result<Option<WebhookDeliveryDbObj>, sqlx::Error> {
+                db_call!(self, get_webhook_delivery(id))
+            }
+            async fn get_webhook_deliveries_due(
+                &self,
+                now: DateTime<Utc>,
+                limit: i64,
+            ) -> Result<Vec<WebhookDeliveryDbObj>, sqlx::Error> {
+                db_call!(self, get_webhook_deliveries_due(now, limit))
+            }
+            async fn list_webhook_deliveries(
+                &self,
+                filter: &WebhookDeliveryListFilter,
+            ) -> Result<Vec<WebhookDeliveryDbObj>, sqlx::Error> {
+                db_call!(self, list_webhook_deliveries(filter))
+            }
+            async fn insert_driver_event(
+                &self,
+                event: &DriverEventDbObj,
+            ) -> Result<DriverEventDbObj, sqlx::Error> {
+                db_call!(self, insert_driver_event(event))
+            }
+            async fn get_driver_events_after(
+                &self,
+                after_id: i64,
+                limit: i64,
+            ) -> Result<Vec<DriverEventDbObj>, sqlx::Error> {
+                db_call!(self, get_driver_events_after(after_id, limit))
+            }
+            async fn delete_driver_events_before(
+                &self,
+                before: DateTime<Utc>,
+            ) -> Result<(), sqlx::Error> {
+                db_call!(self, delete_driver_events_before(before))
+            }
+            async fn get_last_driver_event_id(&self) -> Result<Option<i64>, sqlx::Error> {
+                db_call!(self, get_last_driver_event_id())
+            }
+            async fn get_event_offset(&self, consumer: &str) -> Result<Option<i64>, sqlx::Error> {
+                db_call!(self, get_event_offset(consumer))
+            }
+            async fn set_event_offset(
+                &self,
+                consumer: &str,
+                last_event_id: i64,
+            ) -> Result<(), sqlx::Error> {
+                db_call!(self, set_event_offset(consumer, last_event_id))
+            }
+            async fn insert_faucet_request(
+                &self,
+                request: &FaucetRequestDbObj,
+            ) -> Result<FaucetRequestDbObj, sqlx::Error> {
+                db_call!(self, insert_faucet_request(request))
+            }
+            async fn list_faucet_requests(
+                &self,
+                filter: &FaucetRequestListFilter,
+            ) -> Result<Vec<FaucetRequestDbObj>, sqlx::Error> {
+                db_call!(self, list_faucet_requests(filter))
+            }
+        }
+    };
+}
+
+impl_db_store_ops!(PaymentDbStore);
+impl_db_store_ops!(PaymentDbTransaction);
+
+#[async_trait]
+impl PaymentStoreTransaction for PaymentDbTransaction {
+    async fn commit(self) -> Result<(), sqlx::Error> {
+        match self {
+            PaymentDbTransaction::Sqlite(transaction) => transaction.commit().await,
+            PaymentDbTransaction::Postgres(transaction) => transaction.commit().await,
+        }
+    }
+}
+
+#[async_trait]
+impl PaymentStore for PaymentDbStore {
+    type Transaction = PaymentDbTransaction;
+
+    async fn begin(&self) -> Result<Self::Transaction, sqlx::Error> {
+        Ok(match self {
+            PaymentDbStore::Sqlite(store) => PaymentDbTransaction::Sqlite(store.begin().await?),
+            PaymentDbStore::Postgres(store) => {
+                PaymentDbTransaction::Postgres(Box::new(store.begin().await?))
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::store::TransferInsertResult;
+    use crate::{create_db_connection, is_postgres_url};
+    use std::str::FromStr;
+
+    async fn check_store_ops(store: PaymentDbStore) {
+        // random sender, so the test can run repeatedly against the same database
+        let from_addr = format!("{:#x}", Address::from_low_u64_be(fastrand::u64(1..)));
+        let tt = TokenTransferDbObj {
+            id: 0,
+            payment_id: Some("store-ops-1".to_string()),
+            from_addr: from_addr.clone(),
+            receiver_addr: format!("{:#x}", Address::from_low_u64_be(2)),
+            chain_id: 987789,
+            token_addr: None,
+            token_amount: "1".to_string(),
+            deposit_id: None,
+            deposit_finish: 0,
+            create_date: Utc::now(),
+            tx_id: None,
+            paid_date: None,
+            fee_paid: None,
+            error: None,
+            priority: TransferPriority::default().to_string(),
+            approved_date: None,
+            deadline: None,
+        };
+
+        let created = store.insert_token_transfer_idempotent(&tt).await.unwrap();
+        assert!(matches!(created, TransferInsertResult::Created(_)));
+        let existing = store.insert_token_transfer_idempotent(&tt).await.unwrap();
+        assert!(matches!(existing, TransferInsertResult::Existing(_)));
+        assert_eq!(existing.transfer().id, created.transfer().id);
+
+        let later = DateTime::from_timestamp(1_900_000_000, 0).unwrap();
+        let earlier = DateTime::from_timestamp(1_800_000_000, 0).unwrap();
+        let t = store.begin().await.unwrap();
+        let mut second = tt.clone();
+        second.payment_id = Some("store-ops-2".to_string());
+        second.deadline = Some(later);
+        let second = t.insert_token_transfer(&second).await.unwrap();
+        t.commit().await.unwrap();
+
+        let found = store
+            .get_token_transfer_by_payment_id(987789, &from_addr, "store-ops-2")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.id, second.id);
+        assert_eq!(found.deadline, Some(later));
+        assert_eq!(
+            store
+                .get_token_transfer(second.id)
+                .await
+                .unwrap()
+                .unwrap()
+                .token_amount,
+            "1"
+        );
+
+        // deadline is stored, approval is kept when only deadline changes
+        let sender = Address::from_str(&from_addr).unwrap();
+        let next_deadline = |after| store.get_next_token_transfer_deadline(sender, 987789, after);
+        assert_eq!(next_deadline(earlier).await.unwrap(), Some(later));
+        assert_eq!(next_deadline(later).await.unwrap(), None);
+        assert!(store.approve_token_transfer(second.id).await.unwrap());
+        let receiver_addr = second.receiver_addr.as_str();
+        let priority = second.priority.as_str();
+        assert!(store
+            .update_pending_token_transfer(second.id, receiver_addr, "1", priority, Some(earlier))
+            .await
+            .unwrap());
+        let edited = store.get_token_transfer(second.id).await.unwrap().unwrap();
+        assert_eq!(edited.deadline, Some(earlier));
+        assert!(edited.approved_date.is_some());
+        assert_eq!(
+            next_deadline(earlier - chrono::Duration::seconds(1))
+                .await
+                .unwrap(),
+            Some(earlier)
+        );
+        assert!(store
+            .update_pending_token_transfer(second.id, receiver_addr, "2", priority, Some(earlier))
+            .await
+            .unwrap());
+        let edited = store.get_token_transfer(second.id).await.unwrap().unwrap();
+        assert!(edited.approved_date.is_none());
+
+        let consumer = format!("store-ops-{}", from_addr);
+        assert_eq!(store.get_event_offset(&consumer).await.unwrap(), None);
+        store.set_event_offset(&consumer, 5).await.unwrap();
+        store.set_event_offset(&consumer, 7).await.unwrap();
+        assert_eq!(store.get_event_offset(&consumer).await.unwrap(), Some(7));
+    }
+
+    #[tokio::test]
+    async fn test_db_store_ops_sqlite() {
+        let pool = crate::create_sqlite_connection(None, None, false, true)
+            .await
+            .unwrap();
+        let store = PaymentDbStore::from(pool);
+        assert_eq!(store.backend(), DbBackend::Sqlite);
+        check_store_ops(store).await;
+    }
+
+    /// Runs only when DATABASE_URL points to PostgreSQL database, i.e. in `storage_tests` CI job.
+    /// Locally: `DATABASE_URL=postgres://postgres@localhost/payments cargo test -p erc20_payment_lib_common`
+    #[tokio::test]
+    async fn test_db_store_ops_postgres() {
+        let Some(url) = std::env::var("DATABASE_URL")
+            .ok()
+            .filter(|url| is_postgres_url(url))
+        else {
+            println!("Skipping test_db_store_ops_postgres, DATABASE_URL is not a postgres url");
+            return;
+        };
+        let store = PaymentDbStore::from(create_db_connection(&url, false, true).await.unwrap());
+        assert_eq!(store.backend(), DbBackend::Postgres);
+        check_store_ops(store).await;
+    }
+}
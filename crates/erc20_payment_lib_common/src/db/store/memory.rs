@@ -0,0 +1,1455 @@
+use super::{
+    FaucetRequestListFilter, PaymentStore, PaymentStoreOps, PaymentStoreTransaction,
+    TransactionFilter, TransactionOrder, TransferFilter, TransferInListFilter, TransferListFilter,
+    TxListFilter, WebhookDeliveryListFilter,
+};
+use crate::db::model::*;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tokio::sync::{Mutex, OwnedMutexGuard};
+use web3::types::Address;
+
+#[derive(Debug, Clone)]
+struct Table<T> {
+    rows: BTreeMap<i64, T>,
+    /// Ids are never reused, same as AUTOINCREMENT in SQLite
+    last_id: i64,
+}
+
+impl<T> Default for Table<T> {
+    fn default() -> Self {
+        Self {
+            rows: BTreeMap::new(),
+            last_id: 0,
+        }
+    }
+}
+
+impl<T: Clone> Table<T> {
+    fn insert(&mut self, create_row: impl FnOnce(i64) -> T) -> T {
+        self.last_id += 1;
+        let row = create_row(self.last_id);
+        self.rows.insert(self.last_id, row.clone());
+        row
+    }
+
+    fn update(&mut self, id: i64, row: &T) {
+        if let Some(existing) = self.rows.get_mut(&id) {
+            *existing = row.clone();
+        }
+    }
+
+    fn get(&self, id: i64) -> Result<T, sqlx::Error> {
+        self.rows.get(&id).cloned().ok_or(sqlx::Error::RowNotFound)
+    }
+
+    fn find(&self, predicate: impl Fn(&T) -> bool) -> Vec<T> {
+        self.rows
+            .values()
+            .filter(|r| predicate(r))
+            .cloned()
+            .collect()
+    }
+
+    /// Newest rows first, as ORDER BY id DESC LIMIT $1
+    fn find_newest(&self, limit: Option<i64>, predicate: impl Fn(&T) -> bool) -> Vec<T> {
+        self.rows
+            .values()
+            .rev()
+            .filter(|r| predicate(r))
+            .take(limit_to_usize(limit))
+            .cloned()
+            .collect()
+    }
+}
+
+fn limit_to_usize(limit: Option<i64>) -> usize {
+    limit.map(|l| l.max(0) as usize).unwrap_or(usize::MAX)
+}
+
+#[derive(Debug, Clone, Default)]
+struct MemoryTables {
+    tx: Table<TxDbObj>,
+    token_transfer: Table<TokenTransferDbObj>,
+    allowance: Table<AllowanceDbObj>,
+    chain_tx: Table<ChainTxDbObj>,
+    chain_transfer: Table<ChainTransferDbObj>,
+    transfer_in: Table<TransferInDbObj>,
+    scan_info: Table<ScanDaoDbObj>,
+    transfer_archive_stats: Table<TransferArchiveStatsDbObj>,
+    webhook_delivery: Table<WebhookDeliveryDbObj>,
+    driver_event: Table<DriverEventDbObj>,
+    event_offset: BTreeMap<String, i64>,
+    faucet_request: Table<FaucetRequestDbObj>,
+}
+
+impl MemoryTables {
+    fn chain_transfers_ext(
+        &self,
+        chain_id: i64,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        receiver: Option<&str>,
+        limit: Option<i64>,
+    ) -> Result<Vec<ChainTransferDbObjExt>, sqlx::Error> {
+        self.chain_transfer
+            .find_newest(limit, |ct| {
+                ct.chain_id == chain_id
+                    && ct.blockchain_date.is_some_and(|d| d >= from && d <= to)
+                    && receiver.is_none_or(|r| ct.receiver_addr == r)
+            })
+            .into_iter()
+            .map(|ct| {
+                let cx = self.chain_tx.get(ct.chain_tx_id)?;
+                Ok(ChainTransferDbObjExt {
+                    id: ct.id,
+                    from_addr: ct.from_addr,
+                    receiver_addr: ct.receiver_addr,
+                    chain_id: ct.chain_id,
+                    token_addr: ct.token_addr,
+                    token_amount: ct.token_amount,
+                    chain_tx_id: ct.chain_tx_id,
+                    fee_paid: ct.fee_paid,
+                    blockchain_date: ct.blockchain_date,
+                    tx_hash: cx.tx_hash,
+                    block_number: cx.block_number,
+                    to_addr: cx.to_addr,
+                    caller_addr: cx.from_addr,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Store keeping everything in memory, intended for unit tests and embedders that do not
+/// need persistence. Transactions are serialized: while a transaction is open other
+/// operations wait for it to finish.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStore {
+    tables: Arc<Mutex<MemoryTables>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    async fn with_tables<R>(&self, f: impl FnOnce(&mut MemoryTables) -> R + Send) -> R {
+        let mut tables = self.tables.lock().await;
+        f(&mut tables)
+    }
+}
+
+/// Works on copy of the tables, which replaces the store content on commit
+pub struct MemoryTransaction {
+    guard: OwnedMutexGuard<MemoryTables>,
+    working: std::sync::Mutex<MemoryTables>,
+}
+
+impl MemoryTransaction {
+    async fn with_tables<R>(&self, f: impl FnOnce(&mut MemoryTables) -> R + Send) -> R {
+        f(&mut self.working.lock().unwrap())
+    }
+}
+
+macro_rules! impl_memory_store_ops {
+    ($store:ty) => {
+        #[async_trait]
+        impl PaymentStoreOps for $store {
+            async fn insert_tx(&self, tx: &TxDbObj) -> Result<TxDbObj, sqlx::Error> {
+                self.with_tables(|t| Ok(t.tx.insert(|id| TxDbObj { id, ..tx.clone() })))
+                    .await
+            }
+            async fn update_tx(&self, tx: &TxDbObj) -> Result<TxDbObj, sqlx::Error> {
+                self.with_tables(|t| t.tx.update(tx.id, tx)).await;
+                Ok(tx.clone())
+            }
+            async fn update_processing_and_first_processed_tx(
+                &self,
+                tx: &TxDbObj,
+            ) -> Result<TxDbObj, sqlx::Error> {
+                self.with_tables(|t| {
+                    if let Some(row) = t.tx.rows.get_mut(&tx.id) {
+                        row.processing = tx.processing;
+                        row.first_processed = tx.first_processed;
+                    }
+                })
+                .await;
+                Ok(tx.clone())
+            }
+            async fn update_tx_stuck_date(&self, tx: &TxDbObj) -> Result<TxDbObj, sqlx::Error> {
+                self.with_tables(|t| {
+                    if let Some(row) = t.tx.rows.get_mut(&tx.id) {
+                        row.first_stuck_date = tx.first_stuck_date;
+                    }
+                })
+                .await;
+                Ok(tx.clone())
+            }
+            async fn delete_tx(&self, tx_id: i64) -> Result<(), sqlx::Error> {
+                self.with_tables(|t| t.tx.rows.remove(&tx_id)).await;
+                Ok(())
+            }
+            async fn get_transaction(&self, tx_id: i64) -> Result<TxDbObj, sqlx::Error> {
+                self.with_tables(|t| t.tx.get(tx_id)).await
+            }
+            async fn get_transactions(
+                &self,
+                account: Option<Address>,
+                filter: TransactionFilter,
+                limit: Option<i64>,
+                order: TransactionOrder,
+                chain_id: Option<i64>,
+            ) -> Result<Vec<TxDbObj>, sqlx::Error> {
+                let account = account.map(|a| format!("{:#x}", a));
+                let mut rows = self
+                    .with_tables(|t| {
+                        t.tx.find(|tx| {
+                            filter.matches(tx)
+                                && account.as_ref().is_none_or(|a| &tx.from_addr == a)
+                                && chain_id.is_none_or(|c| tx.chain_id == c)
+                        })
+                    })
+                    .await;
+                rows.sort_by(|a, b| order.compare(a, b));
+                rows.truncate(limit_to_usize(limit));
+                Ok(rows)
+            }
+            async fn list_transactions(
+                &self,
+                filter: &TxListFilter,
+            ) -> Result<Vec<TxDbObj>, sqlx::Error> {
+                Ok(self
+                    .with_tables(|t| t.tx.find_newest(filter.limit, |tx| filter.matches(tx)))
+                    .await)
+            }
+            async fn get_transaction_count(
+                &self,
+                filter: TransactionFilter,
+            ) -> Result<usize, sqlx::Error> {
+                Ok(self
+                    .with_tables(|t| t.tx.rows.values().filter(|tx| filter.matches(tx)).count())
+                    .await)
+            }
+            async fn get_transaction_highest_nonce(
+                &self,
+                chain_id: i64,
+                from_addr: &str,
+            ) -> Result<Option<i64>, sqlx::Error> {
+                Ok(self
+                    .with_tables(|t| {
+                        t.tx.rows
+                            .values()
+                            .filter(|tx| {
+                                tx.confirm_date.is_some()
+                                    && tx.chain_id == chain_id
+                                    && tx.from_addr == from_addr
+                            })
+                            .filter_map(|tx| tx.nonce)
+                            .max()
+                    })
+                    .await)
+            }
+            async fn get_last_unsent_tx(&self) -> Result<Option<TxDbObj>, sqlx::Error> {
+                Ok(self
+                    .with_tables(|t| {
+                        t.tx.find_newest(Some(1), |tx| {
+                            tx.broadcast_date.is_none() && tx.signed_date.is_none()
+                        })
+                        .pop()
+                    })
+                    .await)
+            }
+            async fn get_archivable_transactions(
+                &self,
+                before: DateTime<Utc>,
+                limit: i64,
+            ) -> Result<Vec<TxDbObj>, sqlx::Error> {
+                Ok(self
+                    .with_tables(|t| {
+                        let mut rows = t.tx.find(|tx| {
+                            tx.processing == 0
+                                && tx.created_date < before
+                                && !t.allowance.rows.values().any(|a| a.tx_id == Some(tx.id))
+                                && !t
+                                    .tx
+                                    .rows
+                                    .values()
+                                    .any(|r| r.orig_tx_id == Some(tx.id) && r.processing > 0)
+                        });
+                        rows.truncate(limit_to_usize(Some(limit)));
+                        rows
+                    })
+                    .await)
+            }
+
+            async fn insert_token_transfer(
+                &self,
+                token_transfer: &TokenTransferDbObj,
+            ) -> Result<TokenTransferDbObj, sqlx::Error> {
+                self.with_tables(|t| {
+                    if token_transfer.payment_id.is_some()
+                        && t.token_transfer.rows.values().any(|tt| {
+                            tt.chain_id == token_transfer.chain_id
+                                && tt.from_addr == token_transfer.from_addr
+                                && tt.payment_id == token_transfer.payment_id
+                        })
+                    {
+                        return Err(sqlx::Error::Protocol(format!(
+                            "UNIQUE constraint failed: token_transfer.payment_id {}",
+                            token_transfer.payment_id.as_deref().unwrap_or_default()
+                        )));
+                    }
+                    Ok(t.token_transfer.insert(|id| TokenTransferDbObj {
+                        id,
+                        create_date: Utc::now(),
+                        ..token_transfer.clone()
+                    }))
+                })
+                .await
+            }
+            async fn update_token_transfer(
+                &self,
+                token_transfer: &TokenTransferDbObj,
+            ) -> Result<TokenTransferDbObj, sqlx::Error> {
+                self.with_tables(|t| {
+                    if let Some(row) = t.token_transfer.rows.get_mut(&token_transfer.id) {
+                        // create date is not updated, same as in SQL stores
+                        *row = TokenTransferDbObj {
+                            create_date: row.create_date,
+                            ..token_transfer.clone()
+                        };
+                    }
+                })
+                .await;
+                Ok(token_transfer.clone())
+            }
+            async fn remap_token_transfer_tx(
+                &self,
+                old_tx_id: i64,
+                new_tx_id: i64,
+            ) -> Result<(), sqlx::Error> {
+                self.with_tables(|t| {
+                    for row in t.token_transfer.rows.values_mut() {
+                        if row.tx_id == Some(old_tx_id) {
+                            row.tx_id = Some(new_tx_id);
+                        }
+                    }
+                })
+                .await;
+                Ok(())
+            }
+            async fn cleanup_token_transfer_tx(&self, tx_id: i64) -> Result<(), sqlx::Error> {
+                self.with_tables(|t| {
+                    for row in t.token_transfer.rows.values_mut() {
+                        if row.tx_id == Some(tx_id) {
+                            row.tx_id = None;
+                            row.fee_paid = None;
+                            row.error = None;
+                            row.paid_date = None;
+                        }
+                    }
+                })
+                .await;
+                Ok(())
+            }
+            async fn delete_token_transfers_by_tx(&self, tx_id: i64) -> Result<(), sqlx::Error> {
+                self.with_tables(|t| {
+                    t.token_transfer
+                        .rows
+                        .retain(|_, tt| tt.tx_id != Some(tx_id))
+                })
+                .await;
+                Ok(())
+            }
+            async fn get_token_transfer(
+                &self,
+                id: i64,
+            ) -> Result<Option<TokenTransferDbObj>, sqlx::Error> {
+                Ok(self.with_tables(|t| t.token_transfer.get(id).ok()).await)
+            }
+            async fn delete_pending_token_transfer(&self, id: i64) -> Result<bool, sqlx::Error> {
+                Ok(self
+                    .with_tables(|t| {
+                        let pending = t
+                            .token_transfer
+                            .rows
+                            .get(&id)
+                            .is_some_and(|tt| tt.tx_id.is_none());
+                        if pending {
+                            t.token_transfer.rows.remove(&id);
+                        }
+                        pending
+                    })
+                    .await)
+            }
+            async fn approve_token_transfer(&self, id: i64) -> Result<bool, sqlx::Error> {
+                Ok(self
+                    .with_tables(|t| match t.token_transfer.rows.get_mut(&id) {
+                        Some(tt) if tt.tx_id.is_none() => {
+                            tt.approved_date = Some(Utc::now());
+                            tt.error = None;
+                            true
+                        }
+                        _ => false,
+                    })
+                    .await)
+            }
+            async fn attach_token_transfer_to_tx(
+                &self,
+                token_transfer: &TokenTransferDbObj,
+                tx_id: i64,
+            ) -> Result<bool, sqlx::Error> {
+                Ok(self
+                    .with_tables(
+                        |t| match t.token_transfer.rows.get_mut(&token_transfer.id) {
+                            Some(tt)
+                                if tt.tx_id.is_none()
+                                    && tt.receiver_addr == token_transfer.receiver_addr
+                                    && tt.token_amount == token_transfer.token_amount =>
+                            {
+                                tt.tx_id = Some(tx_id);
+                                true
+                            }
+                            _ => false,
+                        },
+                    )
+                    .await)
+            }
+            async fn update_pending_token_transfer(
+                &self,
+                id: i64,
+                receiver_addr: &str,
+                token_amount: &str,
+                priority: &str,
+                deadline: Option<DateTime<Utc>>,
+            ) -> Result<bool, sqlx::Error> {
+                Ok(self
+                    .with_tables(|t| match t.token_transfer.rows.get_mut(&id) {
+                        Some(tt) if tt.tx_id.is_none() => {
+                            if tt.receiver_addr != receiver_addr
+                                || tt.token_amount != token_amount
+                                || tt.priority != priority
+                            {
+                                tt.approved_date = None;
+                            }
+                            tt.receiver_addr = receiver_addr.to_string();
+                            tt.token_amount = token_amount.to_string();
+                            tt.priority = priority.to_string();
+                            tt.deadline = deadline;
+                            tt.error = None;
+                            true
+                        }
+                        _ => false,
+                    })
+                    .await)
+            }
+            async fn get_token_transfer_by_payment_id(
+                &self,
+                chain_id: i64,
+                from_addr: &str,
+                payment_id: &str,
+            ) -> Result<Option<TokenTransferDbObj>, sqlx::Error> {
+                Ok(self
+                    .with_tables(|t| {
+                        t.token_transfer
+                            .find(|tt| {
+                                tt.chain_id == chain_id
+                                    && tt.from_addr == from_addr
+                                    && tt.payment_id.as_deref() == Some(payment_id)
+                            })
+                            .pop()
+                    })
+                    .await)
+            }
+            async fn check_if_deposit_closed(
+                &self,
+                chain_id: i64,
+                deposit_id: &str,
+            ) -> Result<bool, sqlx::Error> {
+                Ok(self
+                    .with_tables(|t| {
+                        t.token_transfer.rows.values().any(|tt| {
+                            tt.chain_id == chain_id
+                                && tt.deposit_id.as_deref() == Some(deposit_id)
+                                && tt.deposit_finish == 1
+                        })
+                    })
+                    .await)
+            }
+            async fn get_all_token_transfers(
+                &self,
+                limit: Option<i64>,
+            ) -> Result<Vec<TokenTransferDbObj>, sqlx::Error> {
+                Ok(self
+                    .with_tables(|t| t.token_transfer.find_newest(limit, |_| true))
+                    .await)
+            }
+            async fn list_token_transfers(
+                &self,
+                filter: &TransferListFilter,
+            ) -> Result<Vec<TokenTransferDbObj>, sqlx::Error> {
+                Ok(self
+                    .with_tables(|t| {
+                        t.token_transfer
+                            .find_newest(filter.limit, |tt| filter.matches(tt))
+                    })
+                    .await)
+            }
+            async fn get_token_transfers_by_chain_id(
+                &self,
+                chain_id: i64,
+                limit: Option<i64>,
+            ) -> Result<Vec<TokenTransferDbObj>, sqlx::Error> {
+                Ok(self
+                    .with_tables(|t| {
+                        t.token_transfer
+                            .find_newest(limit, |tt| tt.chain_id == chain_id)
+                    })
+                    .await)
+            }
+            async fn get_token_transfers_by_deposit_id(
+                &self,
+                chain_id: i64,
+                deposit_id: &str,
+            ) -> Result<Vec<TokenTransferDbObj>, sqlx::Error> {
+                Ok(self
+                    .with_tables(|t| {
+                        t.token_transfer.find_newest(None, |tt| {
+                            tt.chain_id == chain_id && tt.deposit_id.as_deref() == Some(deposit_id)
+                        })
+                    })
+                    .await)
+            }
+            async fn get_pending_token_transfers(
+                &self,
+                account: Address,
+                chain_id: i64,
+            ) -> Result<Vec<TokenTransferDbObj>, sqlx::Error> {
+                let account = format!("{:#x}", account);
+                Ok(self
+                    .with_tables(|t| {
+                        t.token_transfer.find(|tt| {
+                            tt.tx_id.is_none()
+                                && tt.error.is_none()
+                                && tt.from_addr == account
+                                && tt.chain_id == chain_id
+                        })
+                    })
+                    .await)
+            }
+            async fn get_next_token_transfer_deadline(
+                &self,
+                account: Address,
+                chain_id: i64,
+                after: DateTime<Utc>,
+            ) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+                let account = format!("{:#x}", account);
+                Ok(self
+                    .with_tables(|t| {
+                        t.token_transfer
+                            .rows
+                            .values()
+                            .filter(|tt| {
+                                tt.tx_id.is_none()
+                                    && tt.error.is_none()
+                                    && tt.from_addr == account
+                                    && tt.chain_id == chain_id
+                            })
+                            .filter_map(|tt| tt.deadline)
+                            .filter(|deadline| *deadline > after)
+                            .min()
+                    })
+                    .await)
+            }
+            async fn get_pending_token_transfers_by_receiver(
+                &self,
+                receiver: Address,
+            ) -> Result<Vec<TokenTransferDbObj>, sqlx::Error> {
+                let receiver = format!("{:#x}", receiver);
+                Ok(self
+                    .with_tables(|t| {
+                        t.token_transfer.find(|tt| {
+                            tt.tx_id.is_none() && tt.error.is_none() && tt.receiver_addr == receiver
+                        })
+                    })
+                    .await)
+            }
+            async fn get_token_transfers_spent_since(
+                &self,
+                account: Address,
+                chain_id: i64,
+                since: DateTime<Utc>,
+            ) -> Result<Vec<TokenTransferDbObj>, sqlx::Error> {
+                let account = format!("{:#x}", account);
+                Ok(self
+                    .with_tables(|t| {
+                        t.token_transfer.find(|tt| {
+                            tt.tx_id.is_some()
+                                && tt.error.is_none()
+                                && tt.from_addr == account
+                                && tt.chain_id == chain_id
+                                && tt.paid_date.map(|d| d >= since).unwrap_or(true)
+                        })
+                    })
+                    .await)
+            }
+            async fn get_first_token_transfer_to_receiver(
+                &self,
+                account: Address,
+                receiver: Address,
+            ) -> Result<Option<TokenTransferDbObj>, sqlx::Error> {
+                let account = format!("{:#x}", account);
+                let receiver = format!("{:#x}", receiver);
+                Ok(self
+                    .with_tables(|t| {
+                        t.token_transfer
+                            .find(|tt| tt.from_addr == account && tt.receiver_addr == receiver)
+                            .into_iter()
+                            .min_by_key(|tt| (tt.create_date, tt.id))
+                    })
+                    .await)
+            }
+            async fn get_unpaid_token_transfers(
+                &self,
+                chain_id: i64,
+                sender: Address,
+            ) -> Result<Vec<TokenTransferDbObj>, sqlx::Error> {
+                let sender = format!("{:#x}", sender);
+                Ok(self
+                    .with_tables(|t| {
+                        t.token_transfer.find(|tt| {
+                            tt.fee_paid.is_none()
+                                && tt.chain_id == chain_id
+                                && tt.from_addr == sender
+                        })
+                    })
+                    .await)
+            }
+            async fn get_token_transfers_by_tx(
+                &self,
+                tx_id: i64,
+            ) -> Result<Vec<TokenTransferDbObj>, sqlx::Error> {
+                Ok(self
+                    .with_tables(|t| t.token_transfer.find(|tt| tt.tx_id == Some(tx_id)))
+                    .await)
+            }
+            async fn get_transfer_count(
+                &self,
+                filter: TransferFilter,
+                sender: Option<&str>,
+                receiver: Option<&str>,
+            ) -> Result<usize, sqlx::Error> {
+                Ok(self
+                    .with_tables(|t| {
+                        t.token_transfer
+                            .rows
+                            .values()
+                            .filter(|tt| {
+                                // same as in SQL, receiver is ignored when sender is given
+                                filter.matches(tt)
+                                    && match (sender, receiver) {
+                                        (Some(sender), _) => tt.from_addr == sender,
+                                        (None, Some(receiver)) => tt.receiver_addr == receiver,
+                                        (None, None) => true,
+                                    }
+                            })
+                            .count()
+                    })
+                    .await)
+            }
+
+            async fn insert_allowance(
+                &self,
+                allowance: &AllowanceDbObj,
+            ) -> Result<AllowanceDbObj, sqlx::Error> {
+                self.with_tables(|t| {
+                    Ok(t.allowance.insert(|id| AllowanceDbObj {
+                        id,
+                        ..allowance.clone()
+                    }))
+                })
+                .await
+            }
+            async fn update_allowance(
+                &self,
+                allowance: &AllowanceDbObj,
+            ) -> Result<(), sqlx::Error> {
+                self.with_tables(|t| t.allowance.update(allowance.id, allowance))
+                    .await;
+                Ok(())
+            }
+            async fn get_all_allowances(&self) -> Result<Vec<AllowanceDbObj>, sqlx::Error> {
+                Ok(self.with_tables(|t| t.allowance.find(|_| true)).await)
+            }
+            async fn get_allowance_by_tx(&self, tx_id: i64) -> Result<AllowanceDbObj, sqlx::Error> {
+                self.with_tables(|t| {
+                    t.allowance
+                        .find(|a| a.tx_id == Some(tx_id))
+                        .into_iter()
+                        .next()
+                        .ok_or(sqlx::Error::RowNotFound)
+                })
+                .await
+            }
+            async fn find_allowance(
+                &self,
+                owner: &str,
+                token_addr: &str,
+                spender: &str,
+                chain_id: i64,
+            ) -> Result<Option<AllowanceDbObj>, sqlx::Error> {
+                Ok(self
+                    .with_tables(|t| {
+                        t.allowance
+                            .find(|a| {
+                                a.owner == owner
+                                    && a.token_addr == token_addr
+                                    && a.spender == spender
+                                    && a.chain_id == chain_id
+                            })
+                            .into_iter()
+                            .next()
+                    })
+                    .await)
+            }
+            async fn get_allowances_by_owner(
+                &self,
+                owner: &str,
+            ) -> Result<Vec<AllowanceDbObj>, sqlx::Error> {
+                Ok(self
+                    .with_tables(|t| t.allowance.find(|a| a.owner == owner))
+                    .await)
+            }
+            async fn remap_allowance_tx(
+                &self,
+                old_tx_id: i64,
+                new_tx_id: i64,
+            ) -> Result<(), sqlx::Error> {
+                self.with_tables(|t| {
+                    for row in t.allowance.rows.values_mut() {
+                        if row.tx_id == Some(old_tx_id) {
+                            row.tx_id = Some(new_tx_id);
+                        }
+                    }
+                })
+                .await;
+                Ok(())
+            }
+            async fn cleanup_allowance_tx(&self, tx_id: i64) -> Result<(), sqlx::Error> {
+                self.with_tables(|t| {
+                    for row in t.allowance.rows.values_mut() {
+                        if row.tx_id == Some(tx_id) {
+                            row.tx_id = None;
+                            row.fee_paid = None;
+                            row.error = None;
+                            row.confirm_date = None;
+                        }
+                    }
+                })
+                .await;
+                Ok(())
+            }
+
+            async fn insert_chain_tx(
+                &self,
+                tx: &ChainTxDbObj,
+            ) -> Result<ChainTxDbObj, sqlx::Error> {
+                self.with_tables(|t| {
+                    if t.chain_tx.rows.values().any(|cx| cx.tx_hash == tx.tx_hash) {
+                        return Err(sqlx::Error::Protocol(format!(
+                            "UNIQUE constraint failed: chain_tx.tx_hash {}",
+                            tx.tx_hash
+                        )));
+                    }
+                    Ok(t.chain_tx.insert(|id| ChainTxDbObj { id, ..tx.clone() }))
+                })
+                .await
+            }
+            async fn get_chain_tx_hash(
+                &self,
+                tx_hash: String,
+            ) -> Result<Option<ChainTxDbObj>, sqlx::Error> {
+                Ok(self
+                    .with_tables(|t| t.chain_tx.find(|cx| cx.tx_hash == tx_hash).pop())
+                    .await)
+            }
+            async fn get_chain_txs_by_chain_id(
+                &self,
+                chain_id: i64,
+                limit: Option<i64>,
+            ) -> Result<Vec<ChainTxDbObj>, sqlx::Error> {
+                Ok(self
+                    .with_tables(|t| t.chain_tx.find_newest(limit, |cx| cx.chain_id == chain_id))
+                    .await)
+            }
+            async fn get_chain_txs_before(
+                &self,
+                before: DateTime<Utc>,
+                limit: i64,
+            ) -> Result<Vec<ChainTxDbObj>, sqlx::Error> {
+                Ok(self
+                    .with_tables(|t| {
+                        let mut rows = t.chain_tx.find(|cx| cx.blockchain_date < before);
+                        rows.truncate(limit_to_usize(Some(limit)));
+                        rows
+                    })
+                    .await)
+            }
+            async fn delete_chain_tx(&self, id: i64) -> Result<(), sqlx::Error> {
+                self.with_tables(|t| t.chain_tx.rows.remove(&id)).await;
+                Ok(())
+            }
+
+            async fn insert_chain_transfer(
+                &self,
+                chain_transfer: &ChainTransferDbObj,
+            ) -> Result<ChainTransferDbObj, sqlx::Error> {
+                self.with_tables(|t| {
+                    Ok(t.chain_transfer.insert(|id| ChainTransferDbObj {
+                        id,
+                        ..chain_transfer.clone()
+                    }))
+                })
+                .await
+            }
+            async fn get_chain_transfers_by_chain_id(
+                &self,
+                chain_id: i64,
+                limit: Option<i64>,
+            ) -> Result<Vec<ChainTransferDbObj>, sqlx::Error> {
+                Ok(self
+                    .with_tables(|t| {
+                        t.chain_transfer
+                            .find_newest(limit, |ct| ct.chain_id == chain_id)
+                    })
+                    .await)
+            }
+            async fn get_chain_transfers_by_chain_tx(
+                &self,
+                chain_tx_id: i64,
+            ) -> Result<Vec<ChainTransferDbObj>, sqlx::Error> {
+                Ok(self
+                    .with_tables(|t| t.chain_transfer.find(|ct| ct.chain_tx_id == chain_tx_id))
+                    .await)
+            }
+            async fn delete_chain_transfers_by_chain_tx(
+                &self,
+                chain_tx_id: i64,
+            ) -> Result<(), sqlx::Error> {
+                self.with_tables(|t| {
+                    t.chain_transfer
+                        .rows
+                        .retain(|_, ct| ct.chain_tx_id != chain_tx_id)
+                })
+                .await;
+                Ok(())
+            }
+            async fn get_all_chain_transfers_ext(
+                &self,
+                chain_id: i64,
+                from: DateTime<Utc>,
+                to: DateTime<Utc>,
+                limit: Option<i64>,
+            ) -> Result<Vec<ChainTransferDbObjExt>, sqlx::Error> {
+                self.with_tables(|t| t.chain_transfers_ext(chain_id, from, to, None, limit))
+                    .await
+            }
+            async fn get_all_chain_transfers_by_receiver_ext(
+                &self,
+                chain_id: i64,
+                from: DateTime<Utc>,
+                to: DateTime<Utc>,
+                receiver: &str,
+                limit: Option<i64>,
+            ) -> Result<Vec<ChainTransferDbObjExt>, sqlx::Error> {
+                self.with_tables(|t| {
+                    t.chain_transfers_ext(chain_id, from, to, Some(receiver), limit)
+                })
+                .await
+            }
+
+            async fn insert_transfer_in(
+                &self,
+                transfer_in: &TransferInDbObj,
+            ) -> Result<TransferInDbObj, sqlx::Error> {
+                self.with_tables(|t| {
+                    Ok(t.transfer_in.insert(|id| TransferInDbObj {
+                        id,
+                        ..transfer_in.clone()
+                    }))
+                })
+                .await
+            }
+            async fn update_transfer_in(
+                &self,
+                transfer_in: &TransferInDbObj,
+            ) -> Result<TransferInDbObj, sqlx::Error> {
+                self.with_tables(|t| t.transfer_in.update(transfer_in.id, transfer_in))
+                    .await;
+                Ok(transfer_in.clone())
+            }
+            async fn get_account_transfers_in(
+                &self,
+                account: &str,
+                limit: Option<i64>,
+            ) -> Result<Vec<TransferInDbObj>, sqlx::Error> {
+                let mut rows = self
+                    .with_tables(|t| t.transfer_in.find(|ti| ti.receiver_addr == account))
+                    .await;
+                rows.sort_by(|a, b| b.requested_date.cmp(&a.requested_date));
+                rows.truncate(limit_to_usize(limit));
+                Ok(rows)
+            }
+            async fn get_all_transfers_in(
+                &self,
+                limit: Option<i64>,
+            ) -> Result<Vec<TransferInDbObj>, sqlx::Error> {
+                Ok(self
+                    .with_tables(|t| t.transfer_in.find_newest(limit, |_| true))
+                    .await)
+            }
+            async fn list_transfers_in(
+                &self,
+                filter: &TransferInListFilter,
+            ) -> Result<Vec<TransferInDbObj>, sqlx::Error> {
+                Ok(self
+                    .with_tables(|t| {
+                        t.transfer_in
+                            .find_newest(filter.limit, |ti| filter.matches(ti))
+                    })
+                    .await)
+            }
+
+            async fn get_scan_info(
+                &self,
+                chain_id: i64,
+                filter: &str,
+            ) -> Result<Option<ScanDaoDbObj>, sqlx::Error> {
+                Ok(self
+                    .with_tables(|t| {
+                        t.scan_info
+                            .find(|si| si.chain_id == chain_id && si.filter == filter)
+                            .pop()
+                    })
+                    .await)
+            }
+            async fn upsert_scan_info(
+                &self,
+                scan_info: &ScanDaoDbObj,
+            ) -> Result<ScanDaoDbObj, sqlx::Error> {
+                self.with_tables(|t| {
+                    t.scan_info.rows.retain(|_, si| {
+                        si.chain_id != scan_info.chain_id || si.filter != scan_info.filter
+                    });
+                    Ok(t.scan_info.insert(|id| ScanDaoDbObj {
+                        id,
+                        ..scan_info.clone()
+                    }))
+                })
+                .await
+            }
+            async fn delete_scan_info(
+                &self,
+                chain_id: i64,
+                filter: &str,
+            ) -> Result<(), sqlx::Error> {
+                self.with_tables(|t| {
+                    t.scan_info
+                        .rows
+                        .retain(|_, si| si.chain_id != chain_id || si.filter != filter)
+                })
+                .await;
+                Ok(())
+            }
+
+            async fn insert_transfer_archive_stats(
+                &self,
+                stats: &TransferArchiveStatsDbObj,
+            ) -> Result<TransferArchiveStatsDbObj, sqlx::Error> {
+                self.with_tables(|t| {
+                    Ok(t.transfer_archive_stats
+                        .insert(|id| TransferArchiveStatsDbObj {
+                            id,
+                            ..stats.clone()
+                        }))
+                })
+                .await
+            }
+            async fn update_transfer_archive_stats(
+                &self,
+                stats: &TransferArchiveStatsDbObj,
+            ) -> Result<(), sqlx::Error> {
+                self.with_tables(|t| t.transfer_archive_stats.update(stats.id, stats))
+                    .await;
+                Ok(())
+            }
+            async fn get_transfer_archive_stats(
+                &self,
+                chain_id: i64,
+                source: &str,
+            ) -> Result<Vec<TransferArchiveStatsDbObj>, sqlx::Error> {
+                Ok(self
+                    .with_tables(|t| {
+                        t.transfer_archive_stats
+                            .find(|s| s.chain_id == chain_id && s.source == source)
+                    })
+                    .await)
+            }
+
+            async fn insert_webhook_delivery(
+                &self,
+                delivery: &WebhookDeliveryDbObj,
+            ) -> Result<WebhookDeliveryDbObj, sqlx::Error> {
+                self.with_tables(|t| {
+                    Ok(t.webhook_delivery.insert(|id| WebhookDeliveryDbObj {
+                        id,
+                        ..delivery.clone()
+                    }))
+                })
+                .await
+            }
+            async fn update_webhook_delivery(
+                &self,
+                delivery: &WebhookDeliveryDbObj,
+            ) -> Result<(), sqlx::Error> {
+                self.with_tables(|t| t.webhook_delivery.update(delivery.id, delivery))
+                    .await;
+                Ok(())
+            }
+            async fn get_webhook_delivery(
+                &self,
+                id: i64,
+            ) -> Result<Option<WebhookDeliveryDbObj>, sqlx::Error> {
+                Ok(self
+                    .with_tables(|t| t.webhook_delivery.rows.get(&id).cloned())
+                    .await)
+            }
+            async fn get_webhook_deliveries_due(
+                &self,
+                now: DateTime<Utc>,
+                limit: i64,
+            ) -> Result<Vec<WebhookDeliveryDbObj>, sqlx::Error> {
+                Ok(self
+                    .with_tables(|t| {
+                        t.webhook_delivery
+                            .find(|d| d.next_attempt_date.is_some_and(|n| n <= now))
+                            .into_iter()
+                            .take(limit_to_usize(Some(limit)))
+                            .collect()
+                    })
+                    .await)
+            }
+            async fn list_webhook_deliveries(
+                &self,
+                filter: &WebhookDeliveryListFilter,
+            ) -> Result<Vec<WebhookDeliveryDbObj>, sqlx::Error> {
+                Ok(self
+                    .with_tables(|t| {
+                        t.webhook_delivery
+                            .find_newest(filter.limit, |d| filter.matches(d))
+                    })
+                    .await)
+            }
+
+            async fn insert_driver_event(
+                &self,
+                event: &DriverEventDbObj,
+            ) -> Result<DriverEventDbObj, sqlx::Error> {
+                self.with_tables(|t| {
+                    Ok(t.driver_event.insert(|id| DriverEventDbObj {
+                        id,
+                        ..event.clone()
+                    }))
+                })
+                .await
+            }
+            async fn get_driver_events_after(
+                &self,
+                after_id: i64,
+                limit: i64,
+            ) -> Result<Vec<DriverEventDbObj>, sqlx::Error> {
+                Ok(self
+                    .with_tables(|t| {
+                        t.driver_event
+                            .rows
+                            .range(after_id.saturating_add(1)..)
+                            .map(|(_, e)| e.clone())
+                            .take(limit_to_usize(Some(limit)))
+                            .collect()
+                    })
+                    .await)
+            }
+            async fn delete_driver_events_before(
+                &self,
+                before: DateTime<Utc>,
+            ) -> Result<(), sqlx::Error> {
+                self.with_tables(|t| t.driver_event.rows.retain(|_, e| e.create_date >= before))
+                    .await;
+                Ok(())
+            }
+            async fn get_last_driver_event_id(&self) -> Result<Option<i64>, sqlx::Error> {
+                Ok(self
+                    .with_tables(|t| t.driver_event.rows.keys().next_back().copied())
+                    .await)
+            }
+            async fn get_event_offset(&self, consumer: &str) -> Result<Option<i64>, sqlx::Error> {
+                Ok(self
+                    .with_tables(|t| t.event_offset.get(consumer).copied())
+                    .await)
+            }
+            async fn set_event_offset(
+                &self,
+                consumer: &str,
+                last_event_id: i64,
+            ) -> Result<(), sqlx::Error> {
+                self.with_tables(|t| t.event_offset.insert(consumer.to_string(), last_event_id))
+                    .await;
+                Ok(())
+            }
+
+            async fn insert_faucet_request(
+                &self,
+                request: &FaucetRequestDbObj,
+            ) -> Result<FaucetRequestDbObj, sqlx::Error> {
+                self.with_tables(|t| {
+                    Ok(t.faucet_request.insert(|id| FaucetRequestDbObj {
+                        id,
+                        ..request.clone()
+                    }))
+                })
+                .await
+            }
+            async fn list_faucet_requests(
+                &self,
+                filter: &FaucetRequestListFilter,
+            ) -> Result<Vec<FaucetRequestDbObj>, sqlx::Error> {
+                Ok(self
+                    .with_tables(|t| {
+                        t.faucet_request
+                            .find_newest(filter.limit, |r| filter.matches(r))
+                    })
+                    .await)
+            }
+        }
+    };
+}
+
+impl_memory_store_ops!(MemoryStore);
+impl_memory_store_ops!(MemoryTransaction);
+
+#[async_trait]
+impl PaymentStoreTransaction for MemoryTransaction {
+    async fn commit(self) -> Result<(), sqlx::Error> {
+        let mut guard = self.guard;
+        *guard = self.working.into_inner().unwrap();
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl PaymentStore for MemoryStore {
+    type Transaction = MemoryTransaction;
+
+    async fn begin(&self) -> Result<Self::Transaction, sqlx::Error> {
+        let guard = self.tables.clone().lock_owned().await;
+        let working = std::sync::Mutex::new(guard.clone());
+        Ok(MemoryTransaction { guard, working })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::store::TransferInsertResult;
+    use crate::error::ErrorBag;
+
+    fn token_transfer(from: &str, receiver: &str, deposit_id: Option<&str>) -> TokenTransferDbObj {
+        TokenTransferDbObj {
+            id: 0,
+            payment_id: None,
+            from_addr: from.to_string(),
+            receiver_addr: receiver.to_string(),
+            chain_id: 987789,
+            token_addr: None,
+            token_amount: "1".to_string(),
+            deposit_id: deposit_id.map(|d| d.to_string()),
+            deposit_finish: 0,
+            create_date: Utc::now(),
+            tx_id: None,
+            paid_date: None,
+            fee_paid: None,
+            error: None,
+            priority: TransferPriority::default().to_string(),
+            approved_date: None,
+            deadline: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_memory_store_transactions() {
+        let store = MemoryStore::new();
+        let sender = Address::from_low_u64_be(1);
+        let sender_str = format!("{:#x}", sender);
+
+        let tt = store
+            .insert_token_transfer(&token_transfer(&sender_str, "0x02", None))
+            .await
+            .unwrap();
+        assert_eq!(tt.id, 1);
+
+        // dropped transaction is rolled back
+        {
+            let transaction = store.begin().await.unwrap();
+            let tx = transaction
+                .insert_tx(&TxDbObj {
+                    from_addr: sender_str.clone(),
+                    chain_id: 987789,
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+            transaction
+                .update_token_transfer(&TokenTransferDbObj {
+                    tx_id: Some(tx.id),
+                    ..tt.clone()
+                })
+                .await
+                .unwrap();
+        }
+        assert_eq!(
+            store
+                .get_transaction_count(TransactionFilter::All)
+                .await
+                .unwrap(),
+            0
+        );
+        assert_eq!(
+            store
+                .get_pending_token_transfers(sender, 987789)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+
+        // committed transaction is visible
+        let transaction = store.begin().await.unwrap();
+        let tx = transaction
+            .insert_tx(&TxDbObj {
+                from_addr: sender_str.clone(),
+                chain_id: 987789,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        transaction
+            .update_token_transfer(&TokenTransferDbObj {
+                tx_id: Some(tx.id),
+                ..tt.clone()
+            })
+            .await
+            .unwrap();
+        transaction.commit().await.unwrap();
+
+        let to_process = store
+            .get_next_transactions_to_process(Some(sender), 10, 987789)
+            .await
+            .unwrap();
+        assert_eq!(to_process.len(), 1);
+        assert_eq!(
+            store.get_token_transfers_by_tx(tx.id).await.unwrap().len(),
+            1
+        );
+        assert_eq!(
+            store
+                .get_transfer_count(TransferFilter::Processing, Some(&sender_str), None)
+                .await
+                .unwrap(),
+            1
+        );
+        assert!(store
+            .get_pending_token_transfers(sender, 987789)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_memory_store_idempotent_insert() {
+        let store = MemoryStore::new();
+        let mut tt = token_transfer("0x01", "0x02", None);
+        tt.payment_id = Some("payment-1".to_string());
+
+        let created = store.insert_token_transfer_idempotent(&tt).await.unwrap();
+        assert!(matches!(created, TransferInsertResult::Created(_)));
+        let existing = store.insert_token_transfer_idempotent(&tt).await.unwrap();
+        assert!(matches!(existing, TransferInsertResult::Existing(_)));
+        assert_eq!(existing.transfer().id, created.transfer().id);
+
+        tt.token_amount = "2".to_string();
+        let err = store
+            .insert_token_transfer_idempotent(&tt)
+            .await
+            .unwrap_err();
+        assert!(matches!(*err.inner, ErrorBag::PaymentIdConflict(_)));
+
+        // payment id is unique only per sender
+        tt.from_addr = "0x03".to_string();
+        assert!(matches!(
+            store.insert_token_transfer_idempotent(&tt).await.unwrap(),
+            TransferInsertResult::Created(_)
+        ));
+        assert_eq!(store.get_all_token_transfers(None).await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_memory_store_bulk_insert() {
+        let store = MemoryStore::new();
+        let with_payment_id = |payment_id: &str, amount: &str| TokenTransferDbObj {
+            payment_id: Some(payment_id.to_string()),
+            token_amount: amount.to_string(),
+            ..token_transfer("0x01", "0x02", None)
+        };
+        store
+            .insert_token_transfer_idempotent(&with_payment_id("payment-1", "1"))
+            .await
+            .unwrap();
+
+        // conflicting row rolls back the whole batch
+        let err = store
+            .insert_token_transfers_idempotent(&[
+                with_payment_id("payment-2", "1"),
+                with_payment_id("payment-1", "2"),
+            ])
+            .await
+            .unwrap_err();
+        assert_eq!(err.index, Some(1));
+        assert!(matches!(*err.error.inner, ErrorBag::PaymentIdConflict(_)));
+        assert_eq!(store.get_all_token_transfers(None).await.unwrap().len(), 1);
+
+        let results = store
+            .insert_token_transfers_idempotent(&[
+                with_payment_id("payment-2", "1"),
+                with_payment_id("payment-1", "1"),
+            ])
+            .await
+            .unwrap();
+        assert!(matches!(results[0], TransferInsertResult::Created(_)));
+        assert!(matches!(results[1], TransferInsertResult::Existing(_)));
+        assert_eq!(store.get_all_token_transfers(None).await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_memory_store_pending_transfer_changes() {
+        let store = MemoryStore::new();
+        let gathered = store
+            .insert_token_transfer(&token_transfer("0x01", "0x02", None))
+            .await
+            .unwrap();
+        let cancelled = store
+            .insert_token_transfer(&token_transfer("0x01", "0x02", None))
+            .await
+            .unwrap();
+
+        // transfer edited after it was gathered is not attached with stale amount
+        assert!(store
+            .update_pending_token_transfer(gathered.id, "0x03", "5", "normal", None)
+            .await
+            .unwrap());
+        assert!(!store
+            .attach_token_transfer_to_tx(&gathered, 1)
+            .await
+            .unwrap());
+        let edited = store
+            .get_token_transfer(gathered.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(store.attach_token_transfer_to_tx(&edited, 1).await.unwrap());
+
+        // attached transfer can no longer be changed
+        assert!(!store
+            .update_pending_token_transfer(gathered.id, "0x02", "1", "normal", None)
+            .await
+            .unwrap());
+        assert!(!store
+            .delete_pending_token_transfer(gathered.id)
+            .await
+            .unwrap());
+
+        assert!(store
+            .delete_pending_token_transfer(cancelled.id)
+            .await
+            .unwrap());
+        assert!(!store
+            .attach_token_transfer_to_tx(&cancelled, 1)
+            .await
+            .unwrap());
+        assert_eq!(store.get_all_token_transfers(None).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_memory_store_deposit_check() {
+        let store = MemoryStore::new();
+        let mut finish = token_transfer("0x01", "0x02", Some("0x10-0x20"));
+        finish.deposit_finish = 1;
+        store
+            .insert_token_transfer_with_deposit_check(&finish)
+            .await
+            .unwrap();
+        assert!(store
+            .insert_token_transfer_with_deposit_check(&token_transfer(
+                "0x01",
+                "0x02",
+                Some("0x10-0x20")
+            ))
+            .await
+            .is_err());
+        assert!(store
+            .insert_token_transfer_with_deposit_check(&token_transfer("0x01", "0x02", None))
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_list_token_transfers_same_as_sql() {
+        let sql_store = crate::db::store::SqliteStore::from(
+            crate::create_sqlite_connection(None, None, false, true)
+                .await
+                .unwrap(),
+        );
+        let memory_store = MemoryStore::new();
+        for (from, receiver) in [("0x01", "0x02"), ("0x01", "0x03"), ("0x04", "0x02")] {
+            for _ in 0..3 {
+                let tt = token_transfer(from, receiver, None);
+                sql_store.insert_token_transfer(&tt).await.unwrap();
+                memory_store.insert_token_transfer(&tt).await.unwrap();
+            }
+        }
+
+        let mut filter = TransferListFilter {
+            receiver: Some("0x02".to_string()),
+            status: Some(TransferFilter::Queued),
+            limit: Some(4),
+            ..Default::default()
+        };
+        let ids = |rows: Vec<TokenTransferDbObj>| rows.iter().map(|tt| tt.id).collect::<Vec<_>>();
+        let first_page = ids(sql_store.list_token_transfers(&filter).await.unwrap());
+        assert_eq!(first_page, vec![9, 8, 7, 3]);
+        assert_eq!(
+            ids(memory_store.list_token_transfers(&filter).await.unwrap()),
+            first_page
+        );
+
+        filter.before_id = first_page.last().copied();
+        let second_page = ids(sql_store.list_token_transfers(&filter).await.unwrap());
+        assert_eq!(second_page, vec![2, 1]);
+        assert_eq!(
+            ids(memory_store.list_token_transfers(&filter).await.unwrap()),
+            second_page
+        );
+    }
+}
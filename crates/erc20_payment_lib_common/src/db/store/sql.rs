@@ -0,0 +1,680 @@
+use super::{
+    FaucetRequestListFilter, PaymentStore, PaymentStoreOps, PaymentStoreTransaction,
+    TransactionFilter, TransactionOrder, TransferFilter, TransferInListFilter, TransferListFilter,
+    TxListFilter, WebhookDeliveryListFilter,
+};
+use crate::db::connection::PaymentDbPool;
+use crate::db::model::*;
+use crate::db::ops;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{Database, Pool, Postgres, Sqlite};
+use tokio::sync::Mutex;
+use web3::types::Address;
+
+/// Store keeping payments in SQL database, all operations are delegated to `ops` module
+#[derive(Debug)]
+pub struct SqlStore<DB: Database> {
+    pool: Pool<DB>,
+}
+
+// derive would require DB: Clone
+impl<DB: Database> Clone for SqlStore<DB> {
+    fn clone(&self) -> Self {
+        Self {
+            pool: self.pool.clone(),
+        }
+    }
+}
+
+pub type SqliteStore = SqlStore<Sqlite>;
+pub type PostgresStore = SqlStore<Postgres>;
+
+impl<DB: Database> SqlStore<DB> {
+    pub fn new(pool: Pool<DB>) -> Self {
+        Self { pool }
+    }
+
+    pub fn pool(&self) -> &Pool<DB> {
+        &self.pool
+    }
+}
+
+impl From<sqlx::SqlitePool> for SqliteStore {
+    fn from(pool: sqlx::SqlitePool) -> Self {
+        Self::new(pool)
+    }
+}
+
+impl From<sqlx::PgPool> for PostgresStore {
+    fn from(pool: sqlx::PgPool) -> Self {
+        Self::new(pool)
+    }
+}
+
+impl PaymentDbPool {
+    pub fn sqlite_store(&self) -> Option<SqliteStore> {
+        match self {
+            PaymentDbPool::Sqlite(pool) => Some(SqlStore::new(pool.clone())),
+            PaymentDbPool::Postgres(_) => None,
+        }
+    }
+
+    pub fn postgres_store(&self) -> Option<PostgresStore> {
+        match self {
+            PaymentDbPool::Sqlite(_) => None,
+            PaymentDbPool::Postgres(pool) => Some(SqlStore::new(pool.clone())),
+        }
+    }
+}
+
+pub struct SqlTransaction<DB: Database> {
+    transaction: Mutex<sqlx::Transaction<'static, DB>>,
+}
+
+macro_rules! sql_call {
+    (pool, $self:ident, $fun:ident($($arg:expr),*)) => {
+        ops::$fun(&$self.pool, $($arg),*).await
+    };
+    (transaction, $self:ident, $fun:ident($($arg:expr),*)) => {{
+        let mut transaction = $self.transaction.lock().await;
+        ops::$fun(&mut **transaction, $($arg),*).await
+    }};
+}
+
+macro_rules! impl_sql_store_ops {
+    ($store:ident, $db:ty, $mode:ident) => {
+        #[async_trait]
+        impl PaymentStoreOps for $store<$db> {
+            async fn insert_tx(&self, tx: &TxDbObj) -> Result<TxDbObj, sqlx::Error> {
+                sql_call!($mode, self, insert_tx(tx))
+            }
+            async fn update_tx(&self, tx: &TxDbObj) -> Result<TxDbObj, sqlx::Error> {
+                sql_call!($mode, self, update_tx(tx))
+            }
+            async fn update_processing_and_first_processed_tx(
+                &self,
+                tx: &TxDbObj,
+            ) -> Result<TxDbObj, sqlx::Error> {
+                sql_call!($mode, self, update_processing_and_first_processed_tx(tx))
+            }
+            async fn update_tx_stuck_date(&self, tx: &TxDbObj) -> Result<TxDbObj, sqlx::Error> {
+                sql_call!($mode, self, update_tx_stuck_date(tx))
+            }
+            async fn delete_tx(&self, tx_id: i64) -> Result<(), sqlx::Error> {
+                sql_call!($mode, self, delete_tx(tx_id))
+            }
+            async fn get_transaction(&self, tx_id: i64) -> Result<TxDbObj, sqlx::Error> {
+                sql_call!($mode, self, get_transaction(tx_id))
+            }
+            async fn get_transactions(
+                &self,
+                account: Option<Address>,
+                filter: TransactionFilter,
+                limit: Option<i64>,
+                order: TransactionOrder,
+                chain_id: Option<i64>,
+            ) -> Result<Vec<TxDbObj>, sqlx::Error> {
+                let filter = filter.to_sql();
+                sql_call!(
+                    $mode,
+                    self,
+                    get_transactions(
+                        account,
+                        Some(&filter),
+                        limit,
+                        Some(order.to_sql()),
+                        chain_id
+                    )
+                )
+            }
+            async fn list_transactions(
+                &self,
+                filter: &TxListFilter,
+            ) -> Result<Vec<TxDbObj>, sqlx::Error> {
+                sql_call!($mode, self, list_transactions(filter))
+            }
+            async fn get_transaction_count(
+                &self,
+                filter: TransactionFilter,
+            ) -> Result<usize, sqlx::Error> {
+                let filter = filter.to_sql();
+                sql_call!($mode, self, get_transaction_count(Some(&filter)))
+            }
+            async fn get_transaction_highest_nonce(
+                &self,
+                chain_id: i64,
+                from_addr: &str,
+            ) -> Result<Option<i64>, sqlx::Error> {
+                sql_call!(
+                    $mode,
+                    self,
+                    get_transaction_highest_nonce(chain_id, from_addr)
+                )
+            }
+            async fn get_last_unsent_tx(&self) -> Result<Option<TxDbObj>, sqlx::Error> {
+                sql_call!($mode, self, get_last_unsent_tx())
+            }
+            async fn get_archivable_transactions(
+                &self,
+                before: DateTime<Utc>,
+                limit: i64,
+            ) -> Result<Vec<TxDbObj>, sqlx::Error> {
+                sql_call!($mode, self, get_archivable_transactions(before, limit))
+            }
+
+            async fn insert_token_transfer(
+                &self,
+                token_transfer: &TokenTransferDbObj,
+            ) -> Result<TokenTransferDbObj, sqlx::Error> {
+                sql_call!($mode, self, insert_token_transfer(token_transfer))
+            }
+            async fn update_token_transfer(
+                &self,
+                token_transfer: &TokenTransferDbObj,
+            ) -> Result<TokenTransferDbObj, sqlx::Error> {
+                sql_call!($mode, self, update_token_transfer(token_transfer))
+            }
+            async fn remap_token_transfer_tx(
+                &self,
+                old_tx_id: i64,
+                new_tx_id: i64,
+            ) -> Result<(), sqlx::Error> {
+                sql_call!($mode, self, remap_token_transfer_tx(old_tx_id, new_tx_id))
+            }
+            async fn cleanup_token_transfer_tx(&self, tx_id: i64) -> Result<(), sqlx::Error> {
+                sql_call!($mode, self, cleanup_token_transfer_tx(tx_id))
+            }
+            async fn delete_token_transfers_by_tx(&self, tx_id: i64) -> Result<(), sqlx::Error> {
+                sql_call!($mode, self, delete_token_transfers_by_tx(tx_id))
+            }
+            async fn get_token_transfer(
+                &self,
+                id: i64,
+            ) -> Result<Option<TokenTransferDbObj>, sqlx::Error> {
+                sql_call!($mode, self, get_token_transfer(id))
+            }
+            async fn delete_pending_token_transfer(&self, id: i64) -> Result<bool, sqlx::Error> {
+                sql_call!($mode, self, delete_pending_token_transfer(id))
+            }
+            async fn approve_token_transfer(&self, id: i64) -> Result<bool, sqlx::Error> {
+                sql_call!($mode, self, approve_token_transfer(id))
+            }
+            async fn attach_token_transfer_to_tx(
+                &self,
+                token_transfer: &TokenTransferDbObj,
+                tx_id: i64,
+            ) -> Result<bool, sqlx::Error> {
+                sql_call!(
+                    $mode,
+                    self,
+                    attach_token_transfer_to_tx(token_transfer, tx_id)
+                )
+            }
+            async fn update_pending_token_transfer(
+                &self,
+                id: i64,
+                receiver_addr: &str,
+                token_amount: &str,
+                priority: &str,
+                deadline: Option<DateTime<Utc>>,
+            ) -> Result<bool, sqlx::Error> {
+                sql_call!(
+                    $mode,
+                    self,
+                    update_pending_token_transfer(
+                        id,
+                        receiver_addr,
+                        token_amount,
+                        priority,
+                        deadline
+                    )
+                )
+            }
+            async fn get_token_transfer_by_payment_id(
+                &self,
+                chain_id: i64,
+                from_addr: &str,
+                payment_id: &str,
+            ) -> Result<Option<TokenTransferDbObj>, sqlx::Error> {
+                sql_call!(
+                    $mode,
+                    self,
+                    get_token_transfer_by_payment_id(chain_id, from_addr, payment_id)
+                )
+            }
+            async fn check_if_deposit_closed(
+                &self,
+                chain_id: i64,
+                deposit_id: &str,
+            ) -> Result<bool, sqlx::Error> {
+                sql_call!($mode, self, check_if_deposit_closed(chain_id, deposit_id))
+            }
+            async fn get_all_token_transfers(
+                &self,
+                limit: Option<i64>,
+            ) -> Result<Vec<TokenTransferDbObj>, sqlx::Error> {
+                sql_call!($mode, self, get_all_token_transfers(limit))
+            }
+            async fn list_token_transfers(
+                &self,
+                filter: &TransferListFilter,
+            ) -> Result<Vec<TokenTransferDbObj>, sqlx::Error> {
+                sql_call!($mode, self, list_token_transfers(filter))
+            }
+            async fn get_token_transfers_by_chain_id(
+                &self,
+                chain_id: i64,
+                limit: Option<i64>,
+            ) -> Result<Vec<TokenTransferDbObj>, sqlx::Error> {
+                sql_call!(
+                    $mode,
+                    self,
+                    get_token_transfers_by_chain_id(chain_id, limit)
+                )
+            }
+            async fn get_token_transfers_by_deposit_id(
+                &self,
+                chain_id: i64,
+                deposit_id: &str,
+            ) -> Result<Vec<TokenTransferDbObj>, sqlx::Error> {
+                sql_call!(
+                    $mode,
+                    self,
+                    get_token_transfers_by_deposit_id(chain_id, deposit_id)
+                )
+            }
+            async fn get_pending_token_transfers(
+                &self,
+                account: Address,
+                chain_id: i64,
+            ) -> Result<Vec<TokenTransferDbObj>, sqlx::Error> {
+                sql_call!($mode, self, get_pending_token_transfers(account, chain_id))
+            }
+            async fn get_next_token_transfer_deadline(
+                &self,
+                account: Address,
+                chain_id: i64,
+                after: DateTime<Utc>,
+            ) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+                sql_call!(
+                    $mode,
+                    self,
+                    get_next_token_transfer_deadline(account, chain_id, after)
+                )
+            }
+            async fn get_pending_token_transfers_by_receiver(
+                &self,
+                receiver: Address,
+            ) -> Result<Vec<TokenTransferDbObj>, sqlx::Error> {
+                sql_call!(
+                    $mode,
+                    self,
+                    get_pending_token_transfers_by_receiver(receiver)
+                )
+            }
+            async fn get_token_transfers_spent_since(
+                &self,
+                account: Address,
+                chain_id: i64,
+                since: DateTime<Utc>,
+            ) -> Result<Vec<TokenTransferDbObj>, sqlx::Error> {
+                sql_call!(
+                    $mode,
+                    self,
+                    get_token_transfers_spent_since(account, chain_id, since)
+                )
+            }
+            async fn get_first_token_transfer_to_receiver(
+                &self,
+                account: Address,
+                receiver: Address,
+            ) -> Result<Option<TokenTransferDbObj>, sqlx::Error> {
+                sql_call!(
+                    $mode,
+                    self,
+                    get_first_token_transfer_to_receiver(account, receiver)
+                )
+            }
+            async fn get_unpaid_token_transfers(
+                &self,
+                chain_id: i64,
+                sender: Address,
+            ) -> Result<Vec<TokenTransferDbObj>, sqlx::Error> {
+                sql_call!($mode, self, get_unpaid_token_transfers(chain_id, sender))
+            }
+            async fn get_token_transfers_by_tx(
+                &self,
+                tx_id: i64,
+            ) -> Result<Vec<TokenTransferDbObj>, sqlx::Error> {
+                sql_call!($mode, self, get_token_transfers_by_tx(tx_id))
+            }
+            async fn get_transfer_count(
+                &self,
+                filter: TransferFilter,
+                sender: Option<&str>,
+                receiver: Option<&str>,
+            ) -> Result<usize, sqlx::Error> {
+                sql_call!(
+                    $mode,
+                    self,
+                    get_transfer_count(Some(filter.to_sql()), sender, receiver)
+                )
+            }
+
+            async fn insert_allowance(
+                &self,
+                allowance: &AllowanceDbObj,
+            ) -> Result<AllowanceDbObj, sqlx::Error> {
+                sql_call!($mode, self, insert_allowance(allowance))
+            }
+            async fn update_allowance(
+                &self,
+                allowance: &AllowanceDbObj,
+            ) -> Result<(), sqlx::Error> {
+                sql_call!($mode, self, update_allowance(allowance))
+            }
+            async fn get_all_allowances(&self) -> Result<Vec<AllowanceDbObj>, sqlx::Error> {
+                sql_call!($mode, self, get_all_allowances())
+            }
+            async fn get_allowance_by_tx(&self, tx_id: i64) -> Result<AllowanceDbObj, sqlx::Error> {
+                sql_call!($mode, self, get_allowance_by_tx(tx_id))
+            }
+            async fn find_allowance(
+                &self,
+                owner: &str,
+                token_addr: &str,
+                spender: &str,
+                chain_id: i64,
+            ) -> Result<Option<AllowanceDbObj>, sqlx::Error> {
+                sql_call!(
+                    $mode,
+                    self,
+                    find_allowance(owner, token_addr, spender, chain_id)
+                )
+            }
+            async fn get_allowances_by_owner(
+                &self,
+                owner: &str,
+            ) -> Result<Vec<AllowanceDbObj>, sqlx::Error> {
+                sql_call!($mode, self, get_allowances_by_owner(owner))
+            }
+            async fn remap_allowance_tx(
+                &self,
+                old_tx_id: i64,
+                new_tx_id: i64,
+            ) -> Result<(), sqlx::Error> {
+                sql_call!($mode, self, remap_allowance_tx(old_tx_id, new_tx_id))
+            }
+            async fn cleanup_allowance_tx(&self, tx_id: i64) -> Result<(), sqlx::Error> {
+                sql_call!($mode, self, cleanup_allowance_tx(tx_id))
+            }
+
+            async fn insert_chain_tx(
+                &self,
+                tx: &ChainTxDbObj,
+            ) -> Result<ChainTxDbObj, sqlx::Error> {
+                sql_call!($mode, self, insert_chain_tx(tx))
+            }
+            async fn get_chain_tx_hash(
+                &self,
+                tx_hash: String,
+            ) -> Result<Option<ChainTxDbObj>, sqlx::Error> {
+                sql_call!($mode, self, get_chain_tx_hash(tx_hash))
+            }
+            async fn get_chain_txs_by_chain_id(
+                &self,
+                chain_id: i64,
+                limit: Option<i64>,
+            ) -> Result<Vec<ChainTxDbObj>, sqlx::Error> {
+                sql_call!($mode, self, get_chain_txs_by_chain_id(chain_id, limit))
+            }
+            async fn get_chain_txs_before(
+                &self,
+                before: DateTime<Utc>,
+                limit: i64,
+            ) -> Result<Vec<ChainTxDbObj>, sqlx::Error> {
+                sql_call!($mode, self, get_chain_txs_before(before, limit))
+            }
+            async fn delete_chain_tx(&self, id: i64) -> Result<(), sqlx::Error> {
+                sql_call!($mode, self, delete_chain_tx(id))
+            }
+
+            async fn insert_chain_transfer(
+                &self,
+                chain_transfer: &ChainTransferDbObj,
+            ) -> Result<ChainTransferDbObj, sqlx::Error> {
+                sql_call!($mode, self, insert_chain_transfer(chain_transfer))
+            }
+            async fn get_chain_transfers_by_chain_id(
+                &self,
+                chain_id: i64,
+                limit: Option<i64>,
+            ) -> Result<Vec<ChainTransferDbObj>, sqlx::Error> {
+                sql_call!(
+                    $mode,
+                    self,
+                    get_chain_transfers_by_chain_id(chain_id, limit)
+                )
+            }
+            async fn get_chain_transfers_by_chain_tx(
+                &self,
+                chain_tx_id: i64,
+            ) -> Result<Vec<ChainTransferDbObj>, sqlx::Error> {
+                sql_call!($mode, self, get_chain_transfers_by_chain_tx(chain_tx_id))
+            }
+            async fn delete_chain_transfers_by_chain_tx(
+                &self,
+                chain_tx_id: i64,
+            ) -> Result<(), sqlx::Error> {
+                sql_call!($mode, self, delete_chain_transfers_by_chain_tx(chain_tx_id))
+            }
+            async fn get_all_chain_transfers_ext(
+                &self,
+                chain_id: i64,
+                from: DateTime<Utc>,
+                to: DateTime<Utc>,
+                limit: Option<i64>,
+            ) -> Result<Vec<ChainTransferDbObjExt>, sqlx::Error> {
+                sql_call!(
+                    $mode,
+                    self,
+                    get_all_chain_transfers_ext(chain_id, from, to, limit)
+                )
+            }
+            async fn get_all_chain_transfers_by_receiver_ext(
+                &self,
+                chain_id: i64,
+                from: DateTime<Utc>,
+                to: DateTime<Utc>,
+                receiver: &str,
+                limit: Option<i64>,
+            ) -> Result<Vec<ChainTransferDbObjExt>, sqlx::Error> {
+                sql_call!(
+                    $mode,
+                    self,
+                    get_all_chain_transfers_by_receiver_ext(chain_id, from, to, receiver, limit)
+                )
+            }
+
+            async fn insert_transfer_in(
+                &self,
+                transfer_in: &TransferInDbObj,
+            ) -> Result<TransferInDbObj, sqlx::Error> {
+                sql_call!($mode, self, insert_transfer_in(transfer_in))
+            }
+            async fn update_transfer_in(
+                &self,
+                transfer_in: &TransferInDbObj,
+            ) -> Result<TransferInDbObj, sqlx::Error> {
+                sql_call!($mode, self, update_transfer_in(transfer_in))
+            }
+            async fn get_account_transfers_in(
+                &self,
+                account: &str,
+                limit: Option<i64>,
+            ) -> Result<Vec<TransferInDbObj>, sqlx::Error> {
+                sql_call!($mode, self, get_account_transfers_in(account, limit))
+            }
+            async fn get_all_transfers_in(
+                &self,
+                limit: Option<i64>,
+            ) -> Result<Vec<TransferInDbObj>, sqlx::Error> {
+                sql_call!($mode, self, get_all_transfers_in(limit))
+            }
+            async fn list_transfers_in(
+                &self,
+                filter: &TransferInListFilter,
+            ) -> Result<Vec<TransferInDbObj>, sqlx::Error> {
+                sql_call!($mode, self, list_transfers_in(filter))
+            }
+
+            async fn get_scan_info(
+                &self,
+                chain_id: i64,
+                filter: &str,
+            ) -> Result<Option<ScanDaoDbObj>, sqlx::Error> {
+                sql_call!($mode, self, get_scan_info(chain_id, filter))
+            }
+            async fn upsert_scan_info(
+                &self,
+                scan_info: &ScanDaoDbObj,
+            ) -> Result<ScanDaoDbObj, sqlx::Error> {
+                sql_call!($mode, self, upsert_scan_info(scan_info))
+            }
+            async fn delete_scan_info(
+                &self,
+                chain_id: i64,
+                filter: &str,
+            ) -> Result<(), sqlx::Error> {
+                sql_call!($mode, self, delete_scan_info(chain_id, filter))
+            }
+
+            async fn insert_transfer_archive_stats(
+                &self,
+                stats: &TransferArchiveStatsDbObj,
+            ) -> Result<TransferArchiveStatsDbObj, sqlx::Error> {
+                sql_call!($mode, self, insert_transfer_archive_stats(stats))
+            }
+            async fn update_transfer_archive_stats(
+                &self,
+                stats: &TransferArchiveStatsDbObj,
+            ) -> Result<(), sqlx::Error> {
+                sql_call!($mode, self, update_transfer_archive_stats(stats))
+            }
+            async fn get_transfer_archive_stats(
+                &self,
+                chain_id: i64,
+                source: &str,
+            ) -> Result<Vec<TransferArchiveStatsDbObj>, sqlx::Error> {
+                sql_call!($mode, self, get_transfer_archive_stats(chain_id, source))
+            }
+
+            async fn insert_webhook_delivery(
+                &self,
+                delivery: &WebhookDeliveryDbObj,
+            ) -> Result<WebhookDeliveryDbObj, sqlx::Error> {
+                sql_call!($mode, self, insert_webhook_delivery(delivery))
+            }
+            async fn update_webhook_delivery(
+                &self,
+                delivery: &WebhookDeliveryDbObj,
+            ) -> Result<(), sqlx::Error> {
+                sql_call!($mode, self, update_webhook_delivery(delivery))
+            }
+            async fn get_webhook_delivery(
+                &self,
+                id: i64,
+            ) -> Result<Option<WebhookDeliveryDbObj>, sqlx::Error> {
+                sql_call!($mode, self, get_webhook_delivery(id))
+            }
+            async fn get_webhook_deliveries_due(
+                &self,
+                now: DateTime<Utc>,
+                limit: i64,
+            ) -> Result<Vec<WebhookDeliveryDbObj>, sqlx::Error> {
+                sql_call!($mode, self, get_webhook_deliveries_due(now, limit))
+            }
+            async fn list_webhook_deliveries(
+                &self,
+                filter: &WebhookDeliveryListFilter,
+            ) -> Result<Vec<WebhookDeliveryDbObj>, sqlx::Error> {
+                sql_call!($mode, self, list_webhook_deliveries(filter))
+            }
+
+            async fn insert_driver_event(
+                &self,
+                event: &DriverEventDbObj,
+            ) -> Result<DriverEventDbObj, sqlx::Error> {
+                sql_call!($mode, self, insert_driver_event(event))
+            }
+            async fn get_driver_events_after(
+                &self,
+                after_id: i64,
+                limit: i64,
+            ) -> Result<Vec<DriverEventDbObj>, sqlx::Error> {
+                sql_call!($mode, self, get_driver_events_after(after_id, limit))
+            }
+            async fn delete_driver_events_before(
+                &self,
+                before: DateTime<Utc>,
+            ) -> Result<(), sqlx::Error> {
+                sql_call!($mode, self, delete_driver_events_before(before))
+            }
+            async fn get_last_driver_event_id(&self) -> Result<Option<i64>, sqlx::Error> {
+                sql_call!($mode, self, get_last_driver_event_id())
+            }
+            async fn get_event_offset(&self, consumer: &str) -> Result<Option<i64>, sqlx::Error> {
+                sql_call!($mode, self, get_event_offset(consumer))
+            }
+            async fn set_event_offset(
+                &self,
+                consumer: &str,
+                last_event_id: i64,
+            ) -> Result<(), sqlx::Error> {
+                sql_call!($mode, self, set_event_offset(consumer, last_event_id))
+            }
+
+            async fn insert_faucet_request(
+                &self,
+                request: &FaucetRequestDbObj,
+            ) -> Result<FaucetRequestDbObj, sqlx::Error> {
+                sql_call!($mode, self, insert_faucet_request(request))
+            }
+            async fn list_faucet_requests(
+                &self,
+                filter: &FaucetRequestListFilter,
+            ) -> Result<Vec<FaucetRequestDbObj>, sqlx::Error> {
+                sql_call!($mode, self, list_faucet_requests(filter))
+            }
+        }
+    };
+}
+
+macro_rules! impl_sql_store {
+    ($db:ty) => {
+        impl_sql_store_ops!(SqlStore, $db, pool);
+        impl_sql_store_ops!(SqlTransaction, $db, transaction);
+
+        #[async_trait]
+        impl PaymentStoreTransaction for SqlTransaction<$db> {
+            async fn commit(self) -> Result<(), sqlx::Error> {
+                self.transaction.into_inner().commit().await
+            }
+        }
+
+        #[async_trait]
+        impl PaymentStore for SqlStore<$db> {
+            type Transaction = SqlTransaction<$db>;
+
+            async fn begin(&self) -> Result<Self::Transaction, sqlx::Error> {
+                Ok(SqlTransaction {
+                    transaction: Mutex::new(self.pool.begin().await?),
+                })
+            }
+        }
+    };
+}
+
+impl_sql_store!(Sqlite);
+impl_sql_store!(Postgres);
@@ -0,0 +1,192 @@
+use crate::err_custom_create;
+use crate::error::PaymentError;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::sync::RwLock;
+use trust_dns_resolver::config::{
+    NameServerConfig, NameServerConfigGroup, Protocol, ResolverConfig, ResolverOpts,
+};
+use trust_dns_resolver::TokioAsyncResolver;
+
+lazy_static! {
+    static ref DNS_RESOLVER_CONFIG: RwLock<Option<(ResolverConfig, ResolverOpts)>> =
+        RwLock::new(None);
+    static ref DNS_RESOLVER: RwLock<Option<TokioAsyncResolver>> = RwLock::new(None);
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum DnsResolverKind {
+    /// Google public DNS (8.8.8.8), used when nothing is configured
+    #[default]
+    Google,
+    Cloudflare,
+    /// Resolver configured in the operating system (/etc/resolv.conf or registry on Windows)
+    System,
+    /// Plain DNS (udp/tcp) to given nameservers
+    Custom,
+    /// DNS-over-HTTPS, Cloudflare if no nameservers are given
+    Https,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct DnsResolverSettings {
+    #[serde(default)]
+    pub resolver: DnsResolverKind,
+    #[serde(default)]
+    pub nameservers: Vec<SocketAddr>,
+    /// Name used to verify certificate of DNS-over-HTTPS nameservers
+    pub tls_dns_name: Option<String>,
+}
+
+impl DnsResolverSettings {
+    pub fn resolver_config(&self) -> Result<(ResolverConfig, ResolverOpts), PaymentError> {
+        let config = match self.resolver {
+            DnsResolverKind::Google => ResolverConfig::google(),
+            DnsResolverKind::Cloudflare => ResolverConfig::cloudflare(),
+            DnsResolverKind::System => {
+                return trust_dns_resolver::system_conf::read_system_conf().map_err(|e| {
+                    err_custom_create!("Failed to read system DNS configuration: {}", e)
+                })
+            }
+            DnsResolverKind::Custom => {
+                if self.nameservers.is_empty() {
+                    return Err(err_custom_create!(
+                        "Custom DNS resolver requires at least one nameserver"
+                    ));
+                }
+                let mut group = NameServerConfigGroup::with_capacity(self.nameservers.len() * 2);
+                for addr in &self.nameservers {
+                    group.push(NameServerConfig::new(*addr, Protocol::Udp));
+                    group.push(NameServerConfig::new(*addr, Protocol::Tcp));
+                }
+                ResolverConfig::from_parts(None, vec![], group)
+            }
+            DnsResolverKind::Https if self.nameservers.is_empty() => {
+                ResolverConfig::cloudflare_https()
+            }
+            DnsResolverKind::Https => {
+                let tls_dns_name = self.tls_dns_name.clone().ok_or(err_custom_create!(
+                    "DNS-over-HTTPS resolver with custom nameservers requires tls-dns-name"
+                ))?;
+                let mut group = NameServerConfigGroup::with_capacity(self.nameservers.len());
+                for addr in &self.nameservers {
+                    let mut ns = NameServerConfig::new(*addr, Protocol::Https);
+                    ns.tls_dns_name = Some(tls_dns_name.clone());
+                    group.push(ns);
+                }
+                ResolverConfig::from_parts(None, vec![], group)
+            }
+        };
+        Ok((config, ResolverOpts::default()))
+    }
+}
+
+/// Set resolver used for all DNS lookups done by the library (rpc dns sources, faucet discovery)
+pub fn set_dns_resolver_settings(settings: &DnsResolverSettings) -> Result<(), PaymentError> {
+    let config = settings.resolver_config()?;
+    log::debug!("Using DNS resolver: {:?}", settings.resolver);
+    *DNS_RESOLVER_CONFIG.write().unwrap() = Some(config);
+    // rebuilt with new settings on next use
+    *DNS_RESOLVER.write().unwrap() = None;
+    Ok(())
+}
+
+/// Resolver shared by the whole library, returned clones use the same connections and cache
+pub fn create_dns_resolver() -> TokioAsyncResolver {
+    if let Some(resolver) = DNS_RESOLVER.read().unwrap().as_ref() {
+        return resolver.clone();
+    }
+    let mut resolver = DNS_RESOLVER.write().unwrap();
+    resolver
+        .get_or_insert_with(|| match DNS_RESOLVER_CONFIG.read().unwrap().clone() {
+            Some((config, opts)) => TokioAsyncResolver::tokio(config, opts),
+            None => TokioAsyncResolver::tokio(ResolverConfig::google(), ResolverOpts::default()),
+        })
+        .clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use tokio::net::UdpSocket;
+    use trust_dns_resolver::proto::op::{Message, MessageType};
+    use trust_dns_resolver::proto::rr::rdata::TXT;
+    use trust_dns_resolver::proto::rr::{RData, Record};
+
+    /// Answers every query with single TXT record, counts received queries
+    async fn run_stub_nameserver(socket: UdpSocket, txt: String, queries: Arc<AtomicUsize>) {
+        let mut buf = [0u8; 512];
+        loop {
+            let (len, peer) = socket.recv_from(&mut buf).await.unwrap();
+            queries.fetch_add(1, Ordering::SeqCst);
+            let request = Message::from_vec(&buf[..len]).unwrap();
+            let mut response = Message::new();
+            response
+                .set_id(request.id())
+                .set_message_type(MessageType::Response)
+                .set_op_code(request.op_code())
+                .set_recursion_desired(request.recursion_desired())
+                .set_recursion_available(true);
+            for query in request.queries() {
+                response.add_query(query.clone());
+                response.add_answer(Record::from_rdata(
+                    query.name().clone(),
+                    60,
+                    RData::TXT(TXT::new(vec![txt.clone()])),
+                ));
+            }
+            socket
+                .send_to(&response.to_vec().unwrap(), peer)
+                .await
+                .unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_custom_nameserver() {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let settings = DnsResolverSettings {
+            resolver: DnsResolverKind::Custom,
+            nameservers: vec![socket.local_addr().unwrap()],
+            tls_dns_name: None,
+        };
+        let queries = Arc::new(AtomicUsize::new(0));
+        tokio::spawn(run_stub_nameserver(
+            socket,
+            "https://rpc.example".to_string(),
+            queries.clone(),
+        ));
+
+        set_dns_resolver_settings(&settings).unwrap();
+        for _ in 0..2 {
+            let records = create_dns_resolver()
+                .txt_lookup("rpc.corp.example.")
+                .await
+                .unwrap()
+                .iter()
+                .map(|txt| txt.to_string())
+                .collect::<Vec<_>>();
+            assert_eq!(records, vec!["https://rpc.example".to_string()]);
+        }
+        // second lookup is answered from the cache of the shared resolver
+        assert_eq!(queries.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_resolver_settings_validation() {
+        let settings: DnsResolverSettings =
+            toml::from_str("resolver = \"custom\"\nnameservers = []").unwrap();
+        assert!(settings.resolver_config().is_err());
+        let settings: DnsResolverSettings = toml::from_str(
+            "resolver = \"https\"\nnameservers = [\"10.0.0.1:443\"]\ntls-dns-name = \"dns.corp\"",
+        )
+        .unwrap();
+        assert!(settings.resolver_config().is_ok());
+        assert!(DnsResolverSettings::default().resolver_config().is_ok());
+    }
+}
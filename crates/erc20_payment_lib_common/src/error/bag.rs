@@ -1,5 +1,7 @@
 use super::{CustomError, TransactionFailedError};
 use crate::error::allowance::AllowanceRequest;
+use crate::error::payment_id::PaymentIdConflict;
+use crate::error::transfer::TransferNotPending;
 use crate::utils::ConversionError;
 use rustc_hex::FromHexError;
 use std::fmt::Display;
@@ -24,6 +26,8 @@ pub enum ErrorBag {
     NoAllowanceFound(AllowanceRequest),
     FromDecStrErr(FromDecStrErr),
     TimeLimitReached(std::time::Duration),
+    PaymentIdConflict(PaymentIdConflict),
+    TransferNotPending(TransferNotPending),
 }
 
 impl Display for ErrorBag {
@@ -44,6 +48,23 @@ impl Display for ErrorBag {
             ErrorBag::NoAllowanceFound(allowance_request) => write!(f, "{allowance_request:?}"),
             ErrorBag::FromDecStrErr(from_dec_str_err) => write!(f, "{from_dec_str_err:?}"),
             ErrorBag::TimeLimitReached(duration) => write!(f, "Time limit reached: {duration:?}"),
+            ErrorBag::PaymentIdConflict(conflict) => write!(
+                f,
+                "Transfer with payment id {} already exists with different parameters (id: {})",
+                conflict.payment_id, conflict.existing_id
+            ),
+            ErrorBag::TransferNotPending(not_pending) => match not_pending.tx_id {
+                Some(tx_id) => write!(
+                    f,
+                    "Token transfer {} is already attached to transaction {}",
+                    not_pending.id, tx_id
+                ),
+                None => write!(
+                    f,
+                    "Token transfer {} was changed or cancelled",
+                    not_pending.id
+                ),
+            },
         }
     }
 }
@@ -127,3 +148,15 @@ impl From<Duration> for ErrorBag {
         ErrorBag::TimeLimitReached(err)
     }
 }
+
+impl From<PaymentIdConflict> for ErrorBag {
+    fn from(err: PaymentIdConflict) -> Self {
+        ErrorBag::PaymentIdConflict(err)
+    }
+}
+
+impl From<TransferNotPending> for ErrorBag {
+    fn from(err: TransferNotPending) -> Self {
+        ErrorBag::TransferNotPending(err)
+    }
+}
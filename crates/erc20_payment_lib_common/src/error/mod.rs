@@ -5,8 +5,12 @@ mod wrapped;
 pub use allowance::AllowanceRequest;
 pub use bag::ErrorBag;
 pub use custom::{CustomError, TransactionFailedError};
+pub use payment_id::PaymentIdConflict;
+pub use transfer::TransferNotPending;
 pub use wrapped::PaymentError;
 
 mod allowance;
 /// Export macros for creating errors
 mod macros;
+mod payment_id;
+mod transfer;
@@ -0,0 +1,8 @@
+/// Transfer with the same payment id already exists, but with different parameters
+#[derive(Debug)]
+pub struct PaymentIdConflict {
+    pub chain_id: i64,
+    pub from_addr: String,
+    pub payment_id: String,
+    pub existing_id: i64,
+}
@@ -0,0 +1,7 @@
+/// Token transfer can no longer be changed, because it was picked up for sending
+/// or it was modified concurrently
+#[derive(Debug)]
+pub struct TransferNotPending {
+    pub id: i64,
+    pub tx_id: Option<i64>,
+}
@@ -5,7 +5,7 @@ use std::error::Error;
 /// Note that only creating via macro is possible to catch line and file
 #[derive(Debug)]
 pub struct PaymentError {
-    pub inner: ErrorBag,
+    pub inner: Box<ErrorBag>,
     pub msg: Option<String>,
     pub file: &'static str,
     pub line: u32,
@@ -14,7 +14,7 @@ pub struct PaymentError {
 
 impl Error for PaymentError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
-        Some(&self.inner)
+        Some(&*self.inner)
     }
 }
 
@@ -1,8 +1,7 @@
 use crate::model::{AllowanceDbObj, TokenTransferDbObj, TxDbObj};
 use chrono::{DateTime, Utc};
 use rust_decimal::Decimal;
-use serde::Serialize;
-use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
 use web3::types::Address;
 
 #[derive(Clone, Debug, PartialEq, Serialize)]
@@ -31,19 +30,13 @@ pub enum StatusProperty {
     },
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SharedInfoTx {
     pub message: String,
     pub error: Option<String>,
     pub skip: bool,
 }
 
-#[derive(Debug, Clone, Serialize)]
-pub struct FaucetData {
-    pub faucet_events: BTreeMap<String, DateTime<Utc>>,
-    pub last_cleanup: DateTime<Utc>,
-}
-
 #[derive(Debug, Clone, Serialize)]
 pub struct GasLowInfo {
     pub tx: TxDbObj,
@@ -94,6 +87,13 @@ pub struct TransactionFinishedInfo {
     pub tx_dao: TxDbObj,
 }
 
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PolicyViolationInfo {
+    pub token_transfer_dao: TokenTransferDbObj,
+    pub reason: String,
+}
+
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub enum Web3RpcPoolContent {
@@ -144,13 +144,49 @@ pub enum DriverEventContent {
     TransactionStuck(TransactionStuckReason),
     TransactionFailed(TransactionFailedReason),
     CantSign(CantSignContent),
+    PolicyViolation(PolicyViolationInfo),
     StatusChanged(Vec<StatusProperty>),
     Web3RpcMessage(Web3RpcPoolInfo),
 }
 
+/// All values returned by [`DriverEventContent::event_type`]
+pub const DRIVER_EVENT_TYPES: &[&str] = &[
+    "alive",
+    "transactionConfirmed",
+    "transferFinished",
+    "approveFinished",
+    "transactionStuck",
+    "transactionFailed",
+    "cantSign",
+    "policyViolation",
+    "statusChanged",
+    "web3RpcMessage",
+];
+
+impl DriverEventContent {
+    /// Name of the variant as serialized, i.e. `transferFinished`
+    pub fn event_type(&self) -> &'static str {
+        match self {
+            DriverEventContent::Alive => "alive",
+            DriverEventContent::TransactionConfirmed(_) => "transactionConfirmed",
+            DriverEventContent::TransferFinished(_) => "transferFinished",
+            DriverEventContent::ApproveFinished(_) => "approveFinished",
+            DriverEventContent::TransactionStuck(_) => "transactionStuck",
+            DriverEventContent::TransactionFailed(_) => "transactionFailed",
+            DriverEventContent::CantSign(_) => "cantSign",
+            DriverEventContent::PolicyViolation(_) => "policyViolation",
+            DriverEventContent::StatusChanged(_) => "statusChanged",
+            DriverEventContent::Web3RpcMessage(_) => "web3RpcMessage",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DriverEvent {
+    /// Id in the event log, None for events that are not stored (i.e. alive)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<i64>,
     pub create_date: DateTime<Utc>,
     pub content: DriverEventContent,
 }
@@ -158,8 +194,112 @@ pub struct DriverEvent {
 impl DriverEvent {
     pub fn now(content: DriverEventContent) -> Self {
         DriverEvent {
+            id: None,
             create_date: Utc::now(),
             content,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn all_event_contents() -> Vec<DriverEventContent> {
+        let tx = TxDbObj::default();
+        let transfer = TokenTransferDbObj {
+            id: 1,
+            payment_id: None,
+            from_addr: format!("{:#x}", Address::zero()),
+            receiver_addr: format!("{:#x}", Address::zero()),
+            chain_id: 1,
+            token_addr: None,
+            token_amount: "1".to_string(),
+            deposit_id: None,
+            deposit_finish: 0,
+            create_date: Utc::now(),
+            tx_id: None,
+            paid_date: None,
+            fee_paid: None,
+            error: None,
+            priority: "normal".to_string(),
+            approved_date: None,
+            deadline: None,
+        };
+        let allowance = AllowanceDbObj {
+            id: 1,
+            owner: format!("{:#x}", Address::zero()),
+            token_addr: format!("{:#x}", Address::zero()),
+            spender: format!("{:#x}", Address::zero()),
+            allowance: "1".to_string(),
+            chain_id: 1,
+            tx_id: None,
+            fee_paid: None,
+            confirm_date: None,
+            error: None,
+        };
+        // the match below stops compiling when a variant is added, so it is not missed here
+        let contents = vec![
+            DriverEventContent::Alive,
+            DriverEventContent::TransactionConfirmed(tx.clone()),
+            DriverEventContent::TransferFinished(TransactionFinishedInfo {
+                token_transfer_dao: transfer.clone(),
+                tx_dao: tx.clone(),
+            }),
+            DriverEventContent::ApproveFinished(allowance),
+            DriverEventContent::TransactionStuck(TransactionStuckReason::RPCEndpointProblems(
+                "test".to_string(),
+            )),
+            DriverEventContent::TransactionFailed(TransactionFailedReason::Unknown),
+            DriverEventContent::CantSign(CantSignContent::Tx(tx)),
+            DriverEventContent::PolicyViolation(PolicyViolationInfo {
+                token_transfer_dao: transfer,
+                reason: "test".to_string(),
+            }),
+            DriverEventContent::StatusChanged(vec![]),
+            DriverEventContent::Web3RpcMessage(Web3RpcPoolInfo {
+                chain_id: 1,
+                content: Web3RpcPoolContent::Success,
+            }),
+        ];
+        for content in &contents {
+            match content {
+                DriverEventContent::Alive
+                | DriverEventContent::TransactionConfirmed(_)
+                | DriverEventContent::TransferFinished(_)
+                | DriverEventContent::ApproveFinished(_)
+                | DriverEventContent::TransactionStuck(_)
+                | DriverEventContent::TransactionFailed(_)
+                | DriverEventContent::CantSign(_)
+                | DriverEventContent::PolicyViolation(_)
+                | DriverEventContent::StatusChanged(_)
+                | DriverEventContent::Web3RpcMessage(_) => {}
+            }
+        }
+        contents
+    }
+
+    #[test]
+    fn test_driver_event_types() {
+        let contents = all_event_contents();
+        let mut types = HashSet::new();
+        for content in &contents {
+            let event_type = content.event_type();
+            assert!(
+                DRIVER_EVENT_TYPES.contains(&event_type),
+                "{event_type} is missing in DRIVER_EVENT_TYPES"
+            );
+            // event type is the same as the serialized variant name
+            let json = serde_json::to_value(content).unwrap();
+            let tag = match &json {
+                serde_json::Value::String(tag) => tag.clone(),
+                serde_json::Value::Object(obj) => obj.keys().next().unwrap().clone(),
+                _ => panic!("Unexpected serialization of {event_type}"),
+            };
+            assert_eq!(tag, event_type);
+            assert!(types.insert(event_type), "{event_type} used twice");
+        }
+        assert_eq!(types.len(), DRIVER_EVENT_TYPES.len());
+    }
+}
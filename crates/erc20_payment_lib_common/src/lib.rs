@@ -1,10 +1,15 @@
 mod db;
+mod dns;
 pub mod error;
 mod events;
 mod metrics;
 pub mod utils;
 
 pub use crate::metrics::*;
-pub use db::connection::create_sqlite_connection;
+pub use db::connection::{
+    check_sqlite_migrations, create_db_connection, create_postgres_connection,
+    create_sqlite_connection, is_postgres_url, redact_db_url, PaymentDbPool,
+};
 pub use db::*;
+pub use dns::*;
 pub use events::*;
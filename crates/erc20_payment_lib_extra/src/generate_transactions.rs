@@ -3,13 +3,14 @@ use erc20_payment_lib::misc::{
     create_test_amount_pool, generate_transaction_batch, ordered_address_pool, random_address_pool,
 };
 use erc20_payment_lib_common::error::*;
-use erc20_payment_lib_common::ops::insert_token_transfer;
+use erc20_payment_lib_common::store::{
+    PaymentDbStore, PaymentStore, PaymentStoreOps, PaymentStoreTransaction,
+};
 
 use erc20_payment_lib::config;
 use erc20_payment_lib_common::*;
 use futures_util::StreamExt;
 use futures_util::TryStreamExt;
-use sqlx::SqlitePool;
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Instant;
@@ -63,7 +64,7 @@ pub async fn generate_test_payments(
     generate_options: GenerateOptions,
     config: &config::Config,
     from_addrs: Vec<Address>,
-    sqlite_pool: Option<SqlitePool>,
+    store: Option<PaymentDbStore>,
 ) -> Result<(), PaymentError> {
     let chain_cfg = config
         .chain
@@ -94,10 +95,10 @@ pub async fn generate_test_payments(
     let writer = Arc::new(Mutex::new(writer));
 
     let conn = if generate_options.append_to_db {
-        if let Some(sqlite_pool) = sqlite_pool {
-            Some(sqlite_pool)
+        if let Some(store) = store {
+            Some(store)
         } else {
-            return Err(err_custom_create!("Sqlite pool is not provided"));
+            return Err(err_custom_create!("Payment store is not provided"));
         }
     } else {
         None
@@ -164,9 +165,9 @@ pub async fn generate_test_payments(
                 Ok(())
             };
             if let Some(conn) = conn {
-                let mut t = conn.begin().await.unwrap();
+                let t = conn.begin().await.unwrap();
 
-                insert_token_transfer(&mut *t, &token_transfer)
+                t.insert_token_transfer(&token_transfer)
                     .await
                     .map_err(|err| {
                         err_custom_create!(
@@ -183,7 +184,7 @@ pub async fn generate_test_payments(
         Ok(_) => {
             log::info!("All transactions generated successfully");
         }
-        Err(err) => match err.inner {
+        Err(err) => match *err.inner {
             ErrorBag::TimeLimitReached(d) => {
                 log::info!("Time limit reached: {} seconds, exiting", d.as_secs_f64());
             }
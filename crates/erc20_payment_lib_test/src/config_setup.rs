@@ -27,6 +27,13 @@ pub async fn create_default_config_setup(proxy_url_base: &str, proxy_key: &str)
             max_timeout_ms: None,
             allowed_head_behind_secs: Some(200000000000),
             max_consecutive_errors: None,
+            rate_limit_burst: None,
+            rate_limit_per_sec: None,
+            daily_request_budget: None,
+            monthly_request_budget: None,
+            headers: None,
+            source_signer: None,
+            from_env: false,
         }],
         currency_symbol: "tETH".to_string(),
         priority_fee: Decimal::from_f64(1.1).unwrap(),
@@ -51,9 +58,11 @@ pub async fn create_default_config_setup(proxy_url_base: &str, proxy_key: &str)
         confirmation_blocks: 1,
         faucet_eth_amount: Some(Decimal::from_f64(10.0).unwrap()),
         faucet_glm_amount: Some(Decimal::from_f64(20.0).unwrap()),
+        faucet_daily_limit: None,
         block_explorer_url: Some("http://127.0.0.1:4000".to_string()),
         replacement_timeout: Some(1.0),
         external_source_check_interval: None,
+        batching: None,
     };
     let mut chain_map = BTreeMap::new();
     chain_map.insert("dev".to_string(), chain);
@@ -73,6 +82,16 @@ pub async fn create_default_config_setup(proxy_url_base: &str, proxy_key: &str)
             gather_at_start: false,
             ignore_deadlines: false,
         },
+        dns: None,
+        archive: None,
+        backup: None,
+        scheduling: None,
+        payout: None,
+        policy: None,
+        api_auth: None,
+        webhook: None,
+        event_log: None,
+        faucet: None,
     }
 }
 
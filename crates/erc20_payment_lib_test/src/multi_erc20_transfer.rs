@@ -115,7 +115,7 @@ pub async fn test_durability(accounts_count: usize, generate_count: u64, gen_int
         let generate_test_payments_handle = tokio::spawn (
             async move {
                 log::info!("Generating test payments");
-                generate_test_payments(gtp, &config_, public_keys, Some(conn_)).await?;
+                generate_test_payments(gtp, &config_, public_keys, Some(conn_.into())).await?;
                 log::info!("Finished generating test payments");
                 Ok::<(), PaymentError>(())
             }
@@ -131,7 +131,7 @@ pub async fn test_durability(accounts_count: usize, generate_count: u64, gen_int
                         secret_keys: private_keys,
                         db_filename: Default::default(),
                         config: config.clone(),
-                        conn: Some(conn_.clone()),
+                        conn: Some(conn_.clone().into()),
                         options: Some(AdditionalOptions {
                             keep_running: false,
                             use_transfer_for_single_payment: false,
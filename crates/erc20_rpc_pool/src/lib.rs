@@ -1,6 +1,12 @@
 mod rpc_pool;
 
+pub use rpc_pool::create_web3_for_endpoint;
+pub use rpc_pool::endpoint_list_message;
+pub use rpc_pool::parse_source_signer;
+pub use rpc_pool::redact_url;
 pub use rpc_pool::resolve_txt_record_to_string_array;
+pub use rpc_pool::split_dns_signature;
+pub use rpc_pool::verify_endpoint_list_signature;
 pub use rpc_pool::VerifyEndpointResult;
 pub use rpc_pool::VerifyEndpointStatus;
 pub use rpc_pool::Web3EndpointParams;
@@ -11,7 +17,12 @@ pub use rpc_pool::Web3ExternalSources;
 pub use rpc_pool::Web3FullNodeData;
 pub use rpc_pool::Web3PoolType;
 pub use rpc_pool::Web3RpcEndpoint;
+pub use rpc_pool::Web3RpcEndpointChange;
+pub use rpc_pool::Web3RpcEndpointOrigin;
+pub use rpc_pool::Web3RpcHeader;
 pub use rpc_pool::Web3RpcInfo;
 pub use rpc_pool::Web3RpcParams;
 pub use rpc_pool::Web3RpcPool;
+pub use rpc_pool::Web3RpcRateLimitInfo;
+pub use rpc_pool::Web3RpcSecret;
 pub use rpc_pool::Web3RpcSingleParams;
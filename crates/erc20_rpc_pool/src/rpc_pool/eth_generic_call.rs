@@ -9,6 +9,9 @@ use std::sync::Arc;
 use std::time::Duration;
 use web3::{api::Eth, helpers::CallFuture};
 
+/// Do not wait longer than this for the rate limited endpoints, fail instead
+const MAX_RATE_LIMIT_WAIT: Duration = Duration::from_secs(5);
+
 pub trait EthMethod<T: web3::Transport> {
     const METHOD: &'static str;
     type Args: Clone;
@@ -27,6 +30,18 @@ impl Web3RpcPool {
         loop {
             let resp = self.clone().choose_best_endpoints().await;
             if resp.allowed_endpoints.is_empty() && !resp.is_resolving {
+                if let Some(wait_time) = self.rate_limit_wait_time() {
+                    if loop_no < LOOP_COUNT && wait_time <= MAX_RATE_LIMIT_WAIT {
+                        log::debug!(
+                            "All endpoints for chain id {} are rate limited, waiting {:?}",
+                            self.chain_id,
+                            wait_time
+                        );
+                        tokio::time::sleep(wait_time).await;
+                        loop_no += 1;
+                        continue;
+                    }
+                }
                 log::warn!("No valid endpoints found for chain id {}, wait until next check. Call yagna payment driver rpc --verify for details", self.chain_id);
                 return Err(web3::Error::Unreachable);
             }
@@ -42,6 +57,7 @@ impl Web3RpcPool {
                     {
                         let _ = event_sender
                             .send(DriverEvent {
+                                id: None,
                                 create_date: chrono::Utc::now(),
                                 content: DriverEventContent::Web3RpcMessage(Web3RpcPoolInfo {
                                     chain_id: self.chain_id,
@@ -64,6 +80,13 @@ impl Web3RpcPool {
             }
 
             for idx in idx_vec {
+                if !self.try_acquire_request(idx) {
+                    log::debug!(
+                        "Endpoint {} is rate limited, trying next one",
+                        self.get_name(idx)
+                    );
+                    continue;
+                }
                 let res = match self.get_web3(idx) {
                     Some(web3) => tokio::time::timeout(
                         self.get_max_timeout(idx),
@@ -84,6 +107,7 @@ impl Web3RpcPool {
                         {
                             let _ = event_sender
                                 .send(DriverEvent {
+                                    id: None,
                                     create_date: chrono::Utc::now(),
                                     content: DriverEventContent::Web3RpcMessage(Web3RpcPoolInfo {
                                         chain_id: self.chain_id,
@@ -104,6 +128,7 @@ impl Web3RpcPool {
                                 {
                                     let _ = event_sender
                                         .send(DriverEvent {
+                                            id: None,
                                             create_date: chrono::Utc::now(),
                                             content: DriverEventContent::Web3RpcMessage(
                                                 Web3RpcPoolInfo {
@@ -165,6 +190,7 @@ impl Web3RpcPool {
                     {
                         let _ = event_sender
                             .send(DriverEvent {
+                                id: None,
                                 create_date: chrono::Utc::now(),
                                 content: DriverEventContent::Web3RpcMessage(Web3RpcPoolInfo {
                                     chain_id: self.chain_id,
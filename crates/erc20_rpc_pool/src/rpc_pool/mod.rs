@@ -10,9 +10,16 @@ mod eth_transaction;
 mod eth_transaction_count;
 mod eth_transaction_receipt;
 mod pool;
+mod rate_limit;
+mod signature;
 mod utils;
 mod verify;
 mod web3_error_list;
 
 pub use pool::*;
+pub use rate_limit::Web3RpcRateLimitInfo;
+pub use signature::{
+    endpoint_list_message, parse_source_signer, split_dns_signature, verify_endpoint_list_signature,
+};
+pub use utils::redact_url;
 pub use verify::*;
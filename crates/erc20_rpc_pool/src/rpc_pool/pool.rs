@@ -1,23 +1,31 @@
+mod management;
 mod resolver;
 mod verifier;
 
+pub use management::Web3RpcEndpointChange;
+
 use crate::rpc_pool::pool::resolver::ExternalSourceResolver;
 use crate::rpc_pool::pool::verifier::EndpointsVerifier;
-use crate::rpc_pool::verify::{ReqStats, Web3EndpointParams, Web3RpcSingleParams};
+use crate::rpc_pool::utils::redact_url;
+use crate::rpc_pool::verify::{
+    serialize_redacted_url, ReqStats, Web3EndpointParams, Web3RpcEndpointOrigin,
+    Web3RpcSingleParams,
+};
 use crate::rpc_pool::VerifyEndpointResult;
 use crate::Web3RpcInfo;
 use chrono::Utc;
-use erc20_payment_lib_common::DriverEvent;
+use erc20_payment_lib_common::{create_dns_resolver, DriverEvent};
 use parking_lot::{Mutex, RwLock};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, USER_AGENT};
 use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
 use std::sync::Arc;
 use std::time::Duration;
 use thunderdome::{Arena, Index};
-use trust_dns_resolver::config::{ResolverConfig, ResolverOpts};
-use trust_dns_resolver::TokioAsyncResolver;
 use uuid::Uuid;
+use web3::error::TransportError;
 use web3::transports::Http;
+use web3::types::Address;
 use web3::Web3;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -26,6 +34,12 @@ pub struct Web3ExternalEndpointList {
     pub chain_id: u64,
     pub names: Vec<String>,
     pub urls: Vec<String>,
+    /// Signature of the list, see [`crate::endpoint_list_message`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+    /// Unix timestamp after which signed list is no longer accepted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub valid_until: Option<i64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -33,8 +47,12 @@ pub struct Web3ExternalEndpointList {
 pub struct Web3ExternalJsonSource {
     pub chain_id: u64,
     pub unique_source_id: Uuid,
+    #[serde(serialize_with = "serialize_redacted_url")]
     pub url: String,
     pub endpoint_params: Web3EndpointParams,
+    /// When set, only lists signed by this address are accepted
+    #[serde(default)]
+    pub signer: Option<Address>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -44,6 +62,9 @@ pub struct Web3ExternalDnsSource {
     pub unique_source_id: Uuid,
     pub dns_url: String,
     pub endpoint_params: Web3EndpointParams,
+    /// When set, only lists signed by this address are accepted
+    #[serde(default)]
+    pub signer: Option<Address>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -91,9 +112,20 @@ impl Web3RpcEndpoint {
     }
 
     pub fn is_allowed(&self) -> bool {
-        if self.web3_rpc_info.removed_date.is_some() {
+        if self.web3_rpc_info.removed_date.is_some() || self.web3_rpc_info.disabled {
+            return false;
+        }
+        if !self
+            .web3_rpc_info
+            .rate_limit
+            .has_capacity(&self.web3_rpc_params.web3_endpoint_params, Utc::now())
+        {
             return false;
         }
+        self.is_validated()
+    }
+
+    fn is_validated(&self) -> bool {
         self.web3_rpc_info.is_allowed || self.web3_rpc_params.web3_endpoint_params.skip_validation
     }
 
@@ -122,9 +154,46 @@ pub struct Web3RpcPool {
     pub endpoint_verifier: Arc<EndpointsVerifier>,
 }
 
+/// Create web3 http transport for the endpoint, attaching configured headers to every request
+pub fn create_web3_for_endpoint(params: &Web3RpcSingleParams) -> Result<Web3<Http>, web3::Error> {
+    let mut headers = HeaderMap::new();
+    headers.insert(USER_AGENT, HeaderValue::from_static("web3.rs"));
+    for header in &params.web3_endpoint_params.headers {
+        let name = HeaderName::from_bytes(header.name.as_bytes()).map_err(|e| {
+            web3::Error::Transport(TransportError::Message(format!(
+                "Invalid header name {} for endpoint {}: {}",
+                header.name, params.name, e
+            )))
+        })?;
+        let mut value = HeaderValue::from_str(header.value.expose()).map_err(|_| {
+            web3::Error::Transport(TransportError::Message(format!(
+                "Invalid value of header {} for endpoint {}",
+                header.name, params.name
+            )))
+        })?;
+        value.set_sensitive(true);
+        headers.insert(name, value);
+    }
+    let client = reqwest::Client::builder()
+        .default_headers(headers)
+        .build()
+        .map_err(|e| {
+            web3::Error::Transport(TransportError::Message(format!(
+                "Failed to build client: {}",
+                e
+            )))
+        })?;
+    let url = params.endpoint.parse().map_err(|_| {
+        web3::Error::Transport(TransportError::Message(format!(
+            "Invalid url for endpoint {}",
+            params.name
+        )))
+    })?;
+    Ok(Web3::new(Http::with_client(client, url)))
+}
+
 pub async fn resolve_txt_record_to_string_array(record: &str) -> std::io::Result<Vec<String>> {
-    let resolver: TokioAsyncResolver =
-        TokioAsyncResolver::tokio(ResolverConfig::google(), ResolverOpts::default());
+    let resolver = create_dns_resolver();
 
     Ok(resolver
         .txt_lookup(record)
@@ -161,14 +230,19 @@ impl Web3RpcPool {
                 );
                 continue;
             }
-            let http = Http::new(&endpoint_params.endpoint).unwrap();
-            let web3 = Web3::new(http);
+            let web3 = match create_web3_for_endpoint(&endpoint_params) {
+                Ok(web3) => web3,
+                Err(e) => {
+                    log::error!("Failed to create endpoint {}: {}", endpoint_params.name, e);
+                    continue;
+                }
+            };
             let endpoint = Web3RpcEndpoint {
                 web3: Some(web3),
                 web3_rpc_params: endpoint_params,
                 web3_rpc_info: Default::default(),
             };
-            log::debug!("Added endpoint {:?}", endpoint);
+            log::debug!("Added endpoint {:?}", endpoint.web3_rpc_params.redacted());
             web3_endpoints.insert(Arc::new(RwLock::new(endpoint)));
         }
 
@@ -216,7 +290,7 @@ impl Web3RpcPool {
             .iter()
             .map(|endpoint| Web3RpcSingleParams {
                 chain_id,
-                name: endpoint.clone(),
+                name: redact_url(endpoint),
                 endpoint: endpoint.clone(),
                 web3_endpoint_params: Web3EndpointParams {
                     backup_level: 0,
@@ -226,8 +300,14 @@ impl Web3RpcPool {
                     min_interval_requests_ms: None,
                     max_head_behind_secs: Some(120),
                     max_response_time_ms: 5000,
+                    rate_limit_burst: None,
+                    rate_limit_per_sec: None,
+                    daily_request_budget: None,
+                    monthly_request_budget: None,
+                    headers: Vec::new(),
                 },
                 source_id: None,
+                origin: Web3RpcEndpointOrigin::Config,
             })
             .collect();
         Self::new(
@@ -254,18 +334,23 @@ impl Web3RpcPool {
         for (_idx, el) in endpoints_locked.iter() {
             let el = el.try_read_for(Duration::from_secs(5)).unwrap();
             if !el.is_removed() && el.web3_rpc_params.endpoint == endpoint.endpoint {
-                log::debug!("Endpoint {} already exists", endpoint.endpoint);
+                log::debug!("Endpoint {} already exists", endpoint.name);
                 return;
             }
         }
-        let http = Http::new(&endpoint.endpoint).unwrap();
-        let web3 = Web3::new(http);
+        let web3 = match create_web3_for_endpoint(&endpoint) {
+            Ok(web3) => web3,
+            Err(e) => {
+                log::error!("Failed to create endpoint {}: {}", endpoint.name, e);
+                return;
+            }
+        };
         let endpoint = Web3RpcEndpoint {
             web3: Some(web3),
             web3_rpc_params: endpoint,
             web3_rpc_info: Default::default(),
         };
-        log::debug!("Added endpoint {:?}", endpoint);
+        log::debug!("Added endpoint {:?}", endpoint.web3_rpc_params.redacted());
         endpoints_locked.insert(Arc::new(RwLock::new(endpoint)));
     }
 
@@ -475,6 +560,44 @@ impl Web3RpcPool {
         })
     }
 
+    /// Take request from endpoint token bucket and budgets, false if endpoint is rate limited
+    pub fn try_acquire_request(&self, idx: Index) -> bool {
+        let endpoints = self.endpoints.try_lock_for(Duration::from_secs(5)).unwrap();
+        let Some(endpoint) = endpoints.get(idx) else {
+            return false;
+        };
+        let mut endpoint = endpoint.try_write_for(Duration::from_secs(5)).unwrap();
+        let params = endpoint.web3_rpc_params.web3_endpoint_params.clone();
+        let acquired = endpoint
+            .web3_rpc_info
+            .rate_limit
+            .try_acquire(&params, Utc::now());
+        if !acquired {
+            metrics::counter!("web3_rpc_rate_limited", 1, "chain_id" => self.chain_id.to_string(), "endpoint" => endpoint.web3_rpc_params.name.clone());
+        }
+        acquired
+    }
+
+    /// Shortest time after which any of the validated endpoints will be out of rate limit.
+    /// None if there are no such endpoints (or all of them have exhausted budgets)
+    pub fn rate_limit_wait_time(&self) -> Option<Duration> {
+        let now = Utc::now();
+        self.endpoints
+            .try_lock_for(Duration::from_secs(5))
+            .unwrap()
+            .iter()
+            .filter_map(|(_idx, el)| {
+                let el = el.try_read_for(Duration::from_secs(5)).unwrap();
+                if el.is_removed() || !el.is_validated() {
+                    return None;
+                }
+                el.web3_rpc_info
+                    .rate_limit
+                    .wait_time(&el.web3_rpc_params.web3_endpoint_params, now)
+            })
+            .min()
+    }
+
     pub fn mark_rpc_chosen(&self, idx: Index) {
         let endpoints = self.endpoints.try_lock_for(Duration::from_secs(5)).unwrap();
         endpoints
@@ -597,6 +720,7 @@ impl Web3RpcPool {
         } // stats lock is released here
     }
 
+    /// Endpoints with statistics, endpoint urls are redacted so result is safe to display
     pub fn get_endpoints_info(&self) -> Vec<(Index, Web3RpcSingleParams, Web3RpcInfo)> {
         self.endpoints
             .try_lock_for(Duration::from_secs(5))
@@ -608,7 +732,7 @@ impl Web3RpcPool {
                     w.try_read_for(Duration::from_secs(5))
                         .unwrap()
                         .web3_rpc_params
-                        .clone(),
+                        .redacted(),
                     w.try_read_for(Duration::from_secs(5))
                         .unwrap()
                         .web3_rpc_info
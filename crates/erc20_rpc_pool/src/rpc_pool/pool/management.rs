@@ -0,0 +1,288 @@
+use crate::rpc_pool::pool::create_web3_for_endpoint;
+use crate::{
+    Web3EndpointParams, Web3RpcEndpoint, Web3RpcEndpointOrigin, Web3RpcPool, Web3RpcSingleParams,
+};
+use chrono::Utc;
+use erc20_payment_lib_common::err_custom_create;
+use erc20_payment_lib_common::error::PaymentError;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Change of the endpoint list applied at runtime (management api)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "camelCase")]
+pub enum Web3RpcEndpointChange {
+    #[serde(rename_all = "camelCase")]
+    Add {
+        name: String,
+        endpoint: String,
+        endpoint_params: Option<Web3EndpointParams>,
+    },
+    Remove {
+        name: String,
+    },
+    Disable {
+        name: String,
+    },
+    Enable {
+        name: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    SetBackupLevel {
+        name: String,
+        backup_level: i64,
+    },
+}
+
+impl Web3RpcPool {
+    fn modify_endpoints_by_name<F: Fn(&mut Web3RpcEndpoint)>(
+        &self,
+        name: &str,
+        modify: F,
+    ) -> Result<(), PaymentError> {
+        let endpoints = self.endpoints.try_lock_for(Duration::from_secs(5)).unwrap();
+        let mut found = false;
+        for (_idx, el) in endpoints.iter() {
+            let mut el = el.try_write_for(Duration::from_secs(5)).unwrap();
+            if !el.is_removed() && el.web3_rpc_params.name == name {
+                modify(&mut el);
+                // endpoint is managed by operator from now on, config reload will not change it
+                if el.web3_rpc_params.origin == Web3RpcEndpointOrigin::Config {
+                    el.web3_rpc_params.origin = Web3RpcEndpointOrigin::Api;
+                }
+                found = true;
+            }
+        }
+        if !found {
+            return Err(err_custom_create!(
+                "Endpoint {} not found for chain id {}",
+                name,
+                self.chain_id
+            ));
+        }
+        Ok(())
+    }
+
+    /// Apply change requested by operator. Calls in progress are not affected,
+    /// they keep using web3 instance obtained before the change.
+    pub fn apply_endpoint_change(&self, change: Web3RpcEndpointChange) -> Result<(), PaymentError> {
+        log::info!(
+            "Applying endpoint change for chain id {}: {:?}",
+            self.chain_id,
+            change
+        );
+        match change {
+            Web3RpcEndpointChange::Add {
+                name,
+                endpoint,
+                endpoint_params,
+            } => {
+                let params = Web3RpcSingleParams {
+                    chain_id: self.chain_id,
+                    name,
+                    endpoint,
+                    web3_endpoint_params: endpoint_params.unwrap_or_default(),
+                    source_id: None,
+                    origin: Web3RpcEndpointOrigin::Api,
+                };
+                create_web3_for_endpoint(&params)
+                    .map_err(|e| err_custom_create!("Cannot add endpoint: {}", e))?;
+                self.add_endpoint(params);
+                Ok(())
+            }
+            Web3RpcEndpointChange::Remove { name } => self.modify_endpoints_by_name(&name, |el| {
+                el.web3_rpc_info.removed_date = Some(Utc::now());
+            }),
+            Web3RpcEndpointChange::Disable { name } => self.modify_endpoints_by_name(&name, |el| {
+                el.web3_rpc_info.disabled = true;
+            }),
+            Web3RpcEndpointChange::Enable { name } => self.modify_endpoints_by_name(&name, |el| {
+                el.web3_rpc_info.disabled = false;
+            }),
+            Web3RpcEndpointChange::SetBackupLevel { name, backup_level } => self
+                .modify_endpoints_by_name(&name, |el| {
+                    el.web3_rpc_params.web3_endpoint_params.backup_level = backup_level;
+                }),
+        }
+    }
+
+    /// Synchronize endpoints defined directly in config (not coming from dns/json sources)
+    /// with the new list. Missing endpoints are removed, new ones added and existing updated.
+    /// Endpoints added or changed through the api are not touched (also when removed through the api)
+    /// and chains with endpoints set by env variables are skipped entirely.
+    pub fn sync_static_endpoints(&self, new_endpoints: Vec<Web3RpcSingleParams>) {
+        let endpoints_copy = self
+            .endpoints
+            .try_lock_for(Duration::from_secs(5))
+            .unwrap()
+            .clone();
+        let origin_of = |url: &str| {
+            endpoints_copy
+                .iter()
+                .map(|(_idx, el)| el.try_read_for(Duration::from_secs(5)).unwrap())
+                .filter(|el| el.web3_rpc_params.endpoint == url)
+                .map(|el| el.web3_rpc_params.origin)
+                .collect::<Vec<_>>()
+        };
+        if endpoints_copy.iter().any(|(_idx, el)| {
+            el.try_read_for(Duration::from_secs(5))
+                .unwrap()
+                .web3_rpc_params
+                .origin
+                == Web3RpcEndpointOrigin::Env
+        }) {
+            log::warn!(
+                "Endpoints for chain id {} are set by env variable, ignoring config change",
+                self.chain_id
+            );
+            return;
+        }
+        let new_endpoints = new_endpoints
+            .into_iter()
+            .filter(|new_params| {
+                new_params.origin == Web3RpcEndpointOrigin::Config
+                    && !origin_of(&new_params.endpoint).contains(&Web3RpcEndpointOrigin::Api)
+            })
+            .collect::<Vec<_>>();
+        for (_idx, el) in endpoints_copy.iter() {
+            let mut el = el.try_write_for(Duration::from_secs(5)).unwrap();
+            if el.is_removed() || el.web3_rpc_params.origin != Web3RpcEndpointOrigin::Config {
+                continue;
+            }
+            let Some(new_params) = new_endpoints
+                .iter()
+                .find(|p| p.endpoint == el.web3_rpc_params.endpoint)
+            else {
+                log::info!(
+                    "Endpoint {} removed from config, chain id {}",
+                    el.web3_rpc_params.name,
+                    self.chain_id
+                );
+                el.web3_rpc_info.removed_date = Some(Utc::now());
+                continue;
+            };
+            if el.web3_rpc_params.web3_endpoint_params.headers
+                != new_params.web3_endpoint_params.headers
+            {
+                match create_web3_for_endpoint(new_params) {
+                    Ok(web3) => el.web3 = Some(web3),
+                    Err(e) => {
+                        log::error!(
+                            "Failed to update endpoint {}, keeping old settings: {}",
+                            new_params.name,
+                            e
+                        );
+                        continue;
+                    }
+                }
+            }
+            el.web3_rpc_params.name.clone_from(&new_params.name);
+            el.web3_rpc_params
+                .web3_endpoint_params
+                .clone_from(&new_params.web3_endpoint_params);
+        }
+        for new_params in new_endpoints {
+            // add_endpoint skips endpoints already present
+            self.add_endpoint(new_params);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn endpoint(name: &str, origin: Web3RpcEndpointOrigin) -> Web3RpcSingleParams {
+        Web3RpcSingleParams {
+            chain_id: 17000,
+            name: name.to_string(),
+            endpoint: format!("http://{}.example", name),
+            web3_endpoint_params: Web3EndpointParams::default(),
+            source_id: None,
+            origin,
+        }
+    }
+
+    fn pool(endpoints: Vec<Web3RpcSingleParams>) -> Arc<Web3RpcPool> {
+        Web3RpcPool::new(
+            17000,
+            endpoints,
+            Vec::new(),
+            Vec::new(),
+            None,
+            Duration::from_secs(10),
+            Duration::from_secs(300),
+        )
+    }
+
+    /// Names and backup levels of endpoints that were not removed
+    fn active_endpoints(pool: &Web3RpcPool) -> Vec<(String, i64)> {
+        let mut active = pool
+            .endpoints
+            .try_lock_for(Duration::from_secs(5))
+            .unwrap()
+            .iter()
+            .map(|(_idx, el)| el.try_read_for(Duration::from_secs(5)).unwrap().clone())
+            .filter(|el| !el.is_removed())
+            .map(|el| {
+                (
+                    el.web3_rpc_params.name,
+                    el.web3_rpc_params.web3_endpoint_params.backup_level,
+                )
+            })
+            .collect::<Vec<_>>();
+        active.sort();
+        active
+    }
+
+    #[test]
+    fn test_endpoint_changes_survive_reload() {
+        let config = |names: &[&str]| {
+            names
+                .iter()
+                .map(|name| endpoint(name, Web3RpcEndpointOrigin::Config))
+                .collect::<Vec<_>>()
+        };
+        let pool = pool(config(&["a", "b", "c"]));
+
+        pool.apply_endpoint_change(Web3RpcEndpointChange::Add {
+            name: "manual".to_string(),
+            endpoint: "http://manual.example".to_string(),
+            endpoint_params: None,
+        })
+        .unwrap();
+        pool.apply_endpoint_change(Web3RpcEndpointChange::Remove {
+            name: "b".to_string(),
+        })
+        .unwrap();
+        pool.apply_endpoint_change(Web3RpcEndpointChange::SetBackupLevel {
+            name: "c".to_string(),
+            backup_level: 2,
+        })
+        .unwrap();
+        assert!(pool
+            .apply_endpoint_change(Web3RpcEndpointChange::Remove {
+                name: "b".to_string(),
+            })
+            .is_err());
+
+        // a is dropped from config, d is new, b and c are managed through the api now
+        pool.sync_static_endpoints(config(&["b", "c", "d"]));
+        assert_eq!(
+            active_endpoints(&pool),
+            vec![
+                ("c".to_string(), 2),
+                ("d".to_string(), 0),
+                ("manual".to_string(), 0)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reload_keeps_env_endpoints() {
+        let pool = pool(vec![endpoint("env", Web3RpcEndpointOrigin::Env)]);
+        pool.sync_static_endpoints(vec![endpoint("a", Web3RpcEndpointOrigin::Config)]);
+        assert_eq!(active_endpoints(&pool), vec![("env".to_string(), 0)]);
+    }
+}
@@ -1,7 +1,12 @@
+use crate::rpc_pool::utils::redact_url;
 use crate::{
-    resolve_txt_record_to_string_array, Web3ExternalEndpointList, Web3RpcPool, Web3RpcSingleParams,
+    resolve_txt_record_to_string_array, split_dns_signature, verify_endpoint_list_signature,
+    Web3ExternalEndpointList, Web3RpcEndpointOrigin, Web3RpcPool, Web3RpcSingleParams,
 };
 use chrono::Utc;
+use erc20_payment_lib_common::{
+    DriverEvent, DriverEventContent, Web3RpcPoolContent, Web3RpcPoolInfo,
+};
 use parking_lot::Mutex;
 use reqwest::Client;
 use std::error::Error;
@@ -19,14 +24,47 @@ async fn get_awc_response(url: &str) -> Result<Web3ExternalEndpointList, Box<dyn
         .get(url)
         .send()
         .await
-        .map_err(|e| format!("Error getting response from {} {}", url, e))?
+        .map_err(|e| {
+            format!(
+                "Error getting response from {} {}",
+                redact_url(url),
+                e.without_url()
+            )
+        })?
         .text()
         .await
-        .map_err(|e| format!("Error getting response from {} {}", url, e))?;
+        .map_err(|e| {
+            format!(
+                "Error getting response from {} {}",
+                redact_url(url),
+                e.without_url()
+            )
+        })?;
     Ok(serde_json::from_str::<Web3ExternalEndpointList>(&response)
         .map_err(|e| format!("Error parsing json: {} {}", e, &response))?)
 }
 
+async fn report_rejected_source(pool: &Web3RpcPool, source: &str, reason: String) {
+    let message = format!(
+        "Endpoint list from {} rejected, chain id {}: {}",
+        source, pool.chain_id, reason
+    );
+    log::error!("{}", message);
+    metrics::counter!("resolver_rejected_list", 1, "chain_id" => pool.chain_id.to_string());
+    if let Some(event_sender) = pool.event_sender.clone().and_then(|es| es.upgrade()) {
+        let _ = event_sender
+            .send(DriverEvent {
+                id: None,
+                create_date: Utc::now(),
+                content: DriverEventContent::Web3RpcMessage(Web3RpcPoolInfo {
+                    chain_id: pool.chain_id,
+                    content: Web3RpcPoolContent::Error(message),
+                }),
+            })
+            .await;
+    }
+}
+
 impl Default for ExternalSourceResolver {
     fn default() -> Self {
         Self::new()
@@ -80,14 +118,29 @@ impl ExternalSourceResolver {
                 pool.chain_id,
                 dns_source.dns_url
             );
-            let urls = match resolve_txt_record_to_string_array(&dns_source.dns_url).await {
+            let records = match resolve_txt_record_to_string_array(&dns_source.dns_url).await {
                 Ok(record) => record,
                 Err(e) => {
                     log::warn!("Error resolving dns entry {}: {}", &dns_source.dns_url, e);
                     continue;
                 }
             };
-            let names = urls.clone();
+            let (urls, signature, valid_until) = split_dns_signature(records);
+            if let Some(signer) = dns_source.signer {
+                if let Err(e) = verify_endpoint_list_signature(
+                    pool.chain_id,
+                    &[],
+                    &urls,
+                    valid_until,
+                    signature.as_deref(),
+                    signer,
+                ) {
+                    // keep endpoints resolved previously, do not trust any part of this list
+                    report_rejected_source(&pool, &dns_source.dns_url, e).await;
+                    continue;
+                }
+            }
+            let names = urls.iter().map(|url| redact_url(url)).collect::<Vec<_>>();
 
             for (url, name) in urls.iter().zip(names) {
                 pool.add_endpoint(Web3RpcSingleParams {
@@ -96,6 +149,7 @@ impl ExternalSourceResolver {
                     name: name.clone(),
                     web3_endpoint_params: dns_source.endpoint_params.clone(),
                     source_id: Some(dns_source.unique_source_id),
+                    origin: Web3RpcEndpointOrigin::ExternalSource,
                 });
             }
 
@@ -117,7 +171,7 @@ impl ExternalSourceResolver {
             log::debug!(
                 "Chain id: {} Checking external json source: {}",
                 pool.chain_id,
-                json_source.url
+                redact_url(&json_source.url)
             );
             let res = match get_awc_response(&json_source.url).await {
                 Ok(res) => res,
@@ -126,6 +180,19 @@ impl ExternalSourceResolver {
                     continue;
                 }
             };
+            if let Some(signer) = json_source.signer {
+                if let Err(e) = verify_endpoint_list_signature(
+                    pool.chain_id,
+                    &res.names,
+                    &res.urls,
+                    res.valid_until,
+                    res.signature.as_deref(),
+                    signer,
+                ) {
+                    report_rejected_source(&pool, &redact_url(&json_source.url), e).await;
+                    continue;
+                }
+            }
 
             if res.names.len() != res.urls.len() {
                 log::error!(
@@ -142,6 +209,7 @@ impl ExternalSourceResolver {
                     name: name.clone(),
                     web3_endpoint_params: json_source.endpoint_params.clone(),
                     source_id: Some(json_source.unique_source_id),
+                    origin: Web3RpcEndpointOrigin::ExternalSource,
                 });
             }
 
@@ -0,0 +1,242 @@
+use crate::rpc_pool::verify::Web3EndpointParams;
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Runtime state of endpoint rate limiting (token bucket and request budgets)
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Web3RpcRateLimitInfo {
+    /// Tokens left in the bucket at the time of last refill
+    pub tokens: f64,
+    pub last_refill: Option<DateTime<Utc>>,
+    pub last_request: Option<DateTime<Utc>>,
+    /// Day (UTC) in which requests_this_day were sent
+    pub current_day: Option<NaiveDate>,
+    pub requests_this_day: u64,
+    /// First day of month (UTC) in which requests_this_month were sent
+    pub current_month: Option<NaiveDate>,
+    pub requests_this_month: u64,
+    /// Number of times endpoint was skipped because of rate limit or exhausted budget
+    pub rate_limited_count: u64,
+}
+
+fn first_day_of_month(now: DateTime<Utc>) -> NaiveDate {
+    now.date_naive()
+        .with_day(1)
+        .expect("first day of month always exists")
+}
+
+fn bucket_capacity(params: &Web3EndpointParams) -> Option<(f64, f64)> {
+    let rate = params.rate_limit_per_sec.filter(|rate| *rate > 0.0)?;
+    let burst = params
+        .rate_limit_burst
+        .map(|burst| burst as f64)
+        .unwrap_or(rate.ceil())
+        .max(1.0);
+    Some((burst, rate))
+}
+
+impl Web3RpcRateLimitInfo {
+    fn available_tokens(&self, params: &Web3EndpointParams, now: DateTime<Utc>) -> Option<f64> {
+        let (burst, rate) = bucket_capacity(params)?;
+        Some(match self.last_refill {
+            Some(last_refill) => {
+                let elapsed = (now - last_refill).num_milliseconds().max(0) as f64 / 1000.0;
+                (self.tokens + elapsed * rate).min(burst)
+            }
+            None => burst,
+        })
+    }
+
+    pub fn requests_today(&self, now: DateTime<Utc>) -> u64 {
+        if self.current_day == Some(now.date_naive()) {
+            self.requests_this_day
+        } else {
+            0
+        }
+    }
+
+    pub fn requests_this_month(&self, now: DateTime<Utc>) -> u64 {
+        if self.current_month == Some(first_day_of_month(now)) {
+            self.requests_this_month
+        } else {
+            0
+        }
+    }
+
+    pub fn remaining_daily_budget(
+        &self,
+        params: &Web3EndpointParams,
+        now: DateTime<Utc>,
+    ) -> Option<u64> {
+        params
+            .daily_request_budget
+            .map(|budget| budget.saturating_sub(self.requests_today(now)))
+    }
+
+    pub fn remaining_monthly_budget(
+        &self,
+        params: &Web3EndpointParams,
+        now: DateTime<Utc>,
+    ) -> Option<u64> {
+        params
+            .monthly_request_budget
+            .map(|budget| budget.saturating_sub(self.requests_this_month(now)))
+    }
+
+    pub fn is_budget_exhausted(&self, params: &Web3EndpointParams, now: DateTime<Utc>) -> bool {
+        self.remaining_daily_budget(params, now) == Some(0)
+            || self.remaining_monthly_budget(params, now) == Some(0)
+    }
+
+    /// Time to wait until next request can be sent.
+    /// None if budget is exhausted (endpoint is unavailable until the end of the day/month)
+    pub fn wait_time(&self, params: &Web3EndpointParams, now: DateTime<Utc>) -> Option<Duration> {
+        if self.is_budget_exhausted(params, now) {
+            return None;
+        }
+        let mut wait_ms = 0;
+        if let (Some(min_interval), Some(last_request)) =
+            (params.min_interval_requests_ms, self.last_request)
+        {
+            let since_last_ms = (now - last_request).num_milliseconds().max(0) as u64;
+            wait_ms = wait_ms.max(min_interval.saturating_sub(since_last_ms));
+        }
+        if let (Some(tokens), Some((_burst, rate))) =
+            (self.available_tokens(params, now), bucket_capacity(params))
+        {
+            if tokens < 1.0 {
+                wait_ms = wait_ms.max(((1.0 - tokens) / rate * 1000.0).ceil() as u64);
+            }
+        }
+        Some(Duration::from_millis(wait_ms))
+    }
+
+    pub fn has_capacity(&self, params: &Web3EndpointParams, now: DateTime<Utc>) -> bool {
+        self.wait_time(params, now) == Some(Duration::ZERO)
+    }
+
+    /// Count requests against bucket and budgets without checking limits (i.e. verification requests)
+    pub fn record_requests(&mut self, params: &Web3EndpointParams, now: DateTime<Utc>, count: u64) {
+        if let Some(tokens) = self.available_tokens(params, now) {
+            self.tokens = (tokens - count as f64).max(0.0);
+            self.last_refill = Some(now);
+        }
+        self.last_request = Some(now);
+
+        let today = now.date_naive();
+        if self.current_day != Some(today) {
+            self.current_day = Some(today);
+            self.requests_this_day = 0;
+        }
+        self.requests_this_day += count;
+
+        let month = first_day_of_month(now);
+        if self.current_month != Some(month) {
+            self.current_month = Some(month);
+            self.requests_this_month = 0;
+        }
+        self.requests_this_month += count;
+    }
+
+    /// Take one request from the bucket if limits allow it
+    pub fn try_acquire(&mut self, params: &Web3EndpointParams, now: DateTime<Utc>) -> bool {
+        if !self.has_capacity(params, now) {
+            self.rate_limited_count += 1;
+            return false;
+        }
+        self.record_requests(params, now, 1);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn params() -> Web3EndpointParams {
+        Web3EndpointParams {
+            skip_validation: false,
+            backup_level: 0,
+            max_number_of_consecutive_errors: 5,
+            verify_interval_secs: 120,
+            min_interval_requests_ms: None,
+            max_head_behind_secs: None,
+            max_response_time_ms: 5000,
+            rate_limit_burst: None,
+            rate_limit_per_sec: None,
+            daily_request_budget: None,
+            monthly_request_budget: None,
+            headers: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_token_bucket() {
+        let params = Web3EndpointParams {
+            rate_limit_burst: Some(3),
+            rate_limit_per_sec: Some(2.0),
+            ..params()
+        };
+        let now = Utc.with_ymd_and_hms(2024, 1, 31, 23, 59, 0).unwrap();
+        let mut info = Web3RpcRateLimitInfo::default();
+        assert!(info.try_acquire(&params, now));
+        assert!(info.try_acquire(&params, now));
+        assert!(info.try_acquire(&params, now));
+        assert!(!info.try_acquire(&params, now));
+        assert_eq!(info.rate_limited_count, 1);
+        assert_eq!(
+            info.wait_time(&params, now),
+            Some(Duration::from_millis(500))
+        );
+        let later = now + chrono::Duration::try_milliseconds(500).unwrap();
+        assert!(info.try_acquire(&params, later));
+        assert!(!info.try_acquire(&params, later));
+        let much_later = now + chrono::Duration::try_seconds(60).unwrap();
+        assert_eq!(info.available_tokens(&params, much_later), Some(3.0));
+    }
+
+    #[test]
+    fn test_request_budgets() {
+        let params = Web3EndpointParams {
+            daily_request_budget: Some(2),
+            monthly_request_budget: Some(3),
+            ..params()
+        };
+        let now = Utc.with_ymd_and_hms(2024, 1, 31, 23, 59, 0).unwrap();
+        let mut info = Web3RpcRateLimitInfo::default();
+        assert!(info.try_acquire(&params, now));
+        assert!(info.try_acquire(&params, now));
+        assert!(!info.try_acquire(&params, now));
+        assert_eq!(info.wait_time(&params, now), None);
+        assert_eq!(info.remaining_daily_budget(&params, now), Some(0));
+        assert_eq!(info.remaining_monthly_budget(&params, now), Some(1));
+
+        // new day and new month - both budgets are renewed
+        let next_day = Utc.with_ymd_and_hms(2024, 2, 1, 0, 0, 1).unwrap();
+        assert_eq!(info.remaining_daily_budget(&params, next_day), Some(2));
+        assert_eq!(info.remaining_monthly_budget(&params, next_day), Some(3));
+        assert!(info.try_acquire(&params, next_day));
+        assert_eq!(info.requests_this_month(next_day), 1);
+    }
+
+    #[test]
+    fn test_min_interval() {
+        let params = Web3EndpointParams {
+            min_interval_requests_ms: Some(300),
+            ..params()
+        };
+        let now = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+        let mut info = Web3RpcRateLimitInfo::default();
+        assert!(info.try_acquire(&params, now));
+        let later = now + chrono::Duration::try_milliseconds(100).unwrap();
+        assert_eq!(
+            info.wait_time(&params, later),
+            Some(Duration::from_millis(200))
+        );
+        let later = now + chrono::Duration::try_milliseconds(300).unwrap();
+        assert!(info.try_acquire(&params, later));
+    }
+}
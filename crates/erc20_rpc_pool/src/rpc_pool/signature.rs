@@ -0,0 +1,228 @@
+use web3::signing::{hash_message, keccak256, recover};
+use web3::types::Address;
+
+/// Prefix of the TXT record carrying signature of the endpoint list
+pub const DNS_SIGNATURE_PREFIX: &str = "sig=";
+/// Prefix of the TXT record carrying expiry (unix timestamp) of the endpoint list
+pub const DNS_VALID_UNTIL_PREFIX: &str = "valid-until=";
+
+/// Message signed by the owner of the endpoint list.
+/// Endpoints are sorted by url, because order of TXT records returned by DNS is not guaranteed.
+/// Names are given only by json sources, dns sources sign urls alone.
+pub fn endpoint_list_message(
+    chain_id: u64,
+    names: &[String],
+    urls: &[String],
+    valid_until: i64,
+) -> String {
+    let mut endpoints = urls
+        .iter()
+        .enumerate()
+        .map(|(idx, url)| match names.get(idx) {
+            Some(name) => format!("{} {}", url, name),
+            None => url.clone(),
+        })
+        .collect::<Vec<_>>();
+    endpoints.sort();
+    endpoints.dedup();
+    let mut message = format!(
+        "erc20 rpc endpoint list\nchain id: {}\nvalid until: {}\n",
+        chain_id, valid_until
+    );
+    for endpoint in endpoints {
+        message.push_str(&endpoint);
+        message.push('\n');
+    }
+    message
+}
+
+/// Split TXT records into endpoint urls, signature record and expiry record (if present)
+pub fn split_dns_signature(records: Vec<String>) -> (Vec<String>, Option<String>, Option<i64>) {
+    let mut signature = None;
+    let mut valid_until = None;
+    let urls = records
+        .into_iter()
+        .filter(|record| {
+            if let Some(sig) = record.strip_prefix(DNS_SIGNATURE_PREFIX) {
+                signature = Some(sig.trim().to_string());
+                false
+            } else if let Some(until) = record.strip_prefix(DNS_VALID_UNTIL_PREFIX) {
+                valid_until = until.trim().parse::<i64>().ok();
+                false
+            } else {
+                true
+            }
+        })
+        .collect();
+    (urls, signature, valid_until)
+}
+
+/// Parse signer given in config, accepts address or uncompressed public key (hex)
+pub fn parse_source_signer(signer: &str) -> Result<Address, String> {
+    let bytes = hex::decode(signer.trim().trim_start_matches("0x"))
+        .map_err(|e| format!("Invalid signer {}: {}", signer, e))?;
+    match bytes.len() {
+        20 => Ok(Address::from_slice(&bytes)),
+        64 => Ok(Address::from_slice(&keccak256(&bytes)[12..])),
+        65 if bytes[0] == 0x04 => Ok(Address::from_slice(&keccak256(&bytes[1..])[12..])),
+        _ => Err(format!(
+            "Invalid signer {}: expected address or uncompressed public key",
+            signer
+        )),
+    }
+}
+
+/// Check that the list was signed (EIP-191 personal message) by the expected signer
+/// and is not expired, so old lists (i.e. with compromised endpoints) cannot be replayed.
+/// Lists are signed with personal_sign, so any ethereum wallet can be used to produce signature.
+pub fn verify_endpoint_list_signature(
+    chain_id: u64,
+    names: &[String],
+    urls: &[String],
+    valid_until: Option<i64>,
+    signature: Option<&str>,
+    signer: Address,
+) -> Result<(), String> {
+    let signature = signature.ok_or("Endpoint list is not signed".to_string())?;
+    let valid_until = valid_until.ok_or("Signed endpoint list has no valid-until".to_string())?;
+    if !names.is_empty() && names.len() != urls.len() {
+        return Err(format!(
+            "Endpoint names and endpoints have to have same length {} != {}",
+            names.len(),
+            urls.len()
+        ));
+    }
+    let signature = hex::decode(signature.trim().trim_start_matches("0x"))
+        .map_err(|e| format!("Invalid signature format: {}", e))?;
+    if signature.len() != 65 {
+        return Err(format!(
+            "Invalid signature length {}, expected 65",
+            signature.len()
+        ));
+    }
+    let recovery_id = match signature[64] {
+        v @ 0..=1 => v as i32,
+        v @ 27..=28 => (v - 27) as i32,
+        v => return Err(format!("Invalid signature recovery id {}", v)),
+    };
+    let message_hash = hash_message(endpoint_list_message(chain_id, names, urls, valid_until));
+    let recovered = recover(message_hash.as_bytes(), &signature[..64], recovery_id)
+        .map_err(|e| format!("Cannot recover signer: {}", e))?;
+    if recovered != signer {
+        return Err(format!(
+            "Endpoint list signed by {:#x}, expected {:#x}",
+            recovered, signer
+        ));
+    }
+    let now = chrono::Utc::now().timestamp();
+    if valid_until < now {
+        return Err(format!(
+            "Endpoint list expired {} seconds ago",
+            now - valid_until
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use web3::signing::{Key, SecretKey, SecretKeyRef};
+
+    fn sign(
+        chain_id: u64,
+        names: &[String],
+        urls: &[String],
+        valid_until: i64,
+        key: &SecretKey,
+    ) -> String {
+        let message_hash = hash_message(endpoint_list_message(chain_id, names, urls, valid_until));
+        let signature = SecretKeyRef::new(key)
+            .sign_message(message_hash.as_bytes())
+            .unwrap();
+        let mut bytes = signature.r.as_bytes().to_vec();
+        bytes.extend_from_slice(signature.s.as_bytes());
+        bytes.push(signature.v as u8 + 27);
+        format!("0x{}", hex::encode(bytes))
+    }
+
+    #[test]
+    fn test_verify_endpoint_list_signature() {
+        let key = SecretKey::from_slice(&[7u8; 32]).unwrap();
+        let signer = SecretKeyRef::new(&key).address();
+        let urls = vec![
+            "https://b.example".to_string(),
+            "https://a.example".to_string(),
+        ];
+        let valid_until = chrono::Utc::now().timestamp() + 3600;
+        let signature = sign(137, &[], &urls, valid_until, &key);
+
+        // order of records does not matter
+        let (dns_urls, dns_signature, dns_valid_until) = split_dns_signature(vec![
+            "https://a.example".to_string(),
+            format!("sig={}", signature),
+            format!("valid-until={}", valid_until),
+            "https://b.example".to_string(),
+        ]);
+        assert_eq!(dns_urls.len(), 2);
+        assert_eq!(dns_valid_until, Some(valid_until));
+        let verify = |names: &[String], urls: &[String], valid_until, signature, signer| {
+            verify_endpoint_list_signature(137, names, urls, valid_until, signature, signer)
+        };
+        verify(
+            &[],
+            &dns_urls,
+            dns_valid_until,
+            dns_signature.as_deref(),
+            signer,
+        )
+        .unwrap();
+
+        // tampered list, other chain, other signer, changed expiry or missing signature are rejected
+        let tampered = vec![
+            "https://a.example".to_string(),
+            "https://evil.example".to_string(),
+        ];
+        let sig = Some(signature.as_str());
+        assert!(verify(&[], &tampered, Some(valid_until), sig, signer).is_err());
+        assert!(
+            verify_endpoint_list_signature(1, &[], &urls, Some(valid_until), sig, signer).is_err()
+        );
+        assert!(verify(&[], &urls, Some(valid_until), sig, Address::zero()).is_err());
+        assert!(verify(&[], &urls, Some(valid_until + 1), sig, signer).is_err());
+        assert!(verify(&[], &urls, None, sig, signer).is_err());
+        assert!(verify(&[], &urls, Some(valid_until), None, signer).is_err());
+
+        // names of json lists are signed too
+        let names = vec!["b".to_string(), "a".to_string()];
+        let signature = sign(137, &names, &urls, valid_until, &key);
+        let sig = Some(signature.as_str());
+        verify(&names, &urls, Some(valid_until), sig, signer).unwrap();
+        let renamed = vec!["b".to_string(), "evil".to_string()];
+        assert!(verify(&renamed, &urls, Some(valid_until), sig, signer).is_err());
+
+        // expired list is rejected even with valid signature
+        let expired = chrono::Utc::now().timestamp() - 10;
+        let signature = sign(137, &names, &urls, expired, &key);
+        let err = verify(&names, &urls, Some(expired), Some(&signature), signer).unwrap_err();
+        assert!(err.contains("expired"));
+    }
+
+    #[test]
+    fn test_parse_source_signer() {
+        let key = SecretKey::from_slice(&[7u8; 32]).unwrap();
+        let address = SecretKeyRef::new(&key).address();
+        let public_key = secp256k1::PublicKey::from_secret_key(&secp256k1::Secp256k1::new(), &key)
+            .serialize_uncompressed();
+
+        assert_eq!(
+            parse_source_signer(&format!("{:#x}", address)).unwrap(),
+            address
+        );
+        assert_eq!(
+            parse_source_signer(&hex::encode(public_key)).unwrap(),
+            address
+        );
+        assert!(parse_source_signer("0x1234").is_err());
+    }
+}
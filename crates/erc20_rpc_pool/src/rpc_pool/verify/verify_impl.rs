@@ -107,6 +107,17 @@ pub async fn verify_endpoint(chain_id: u64, m: Arc<RwLock<Web3RpcEndpoint>>, for
         }
     }
 
+    if web3_rpc_info
+        .rate_limit
+        .is_budget_exhausted(&web3_rpc_params.web3_endpoint_params, Utc::now())
+    {
+        log::debug!(
+            "Verification skipped, request budget exhausted {}",
+            web3_rpc_params.name
+        );
+        return;
+    }
+
     let verify_result = verify_endpoint_int(
         &web3,
         &web3_rpc_params.name,
@@ -130,6 +141,10 @@ pub async fn verify_endpoint(chain_id: u64, m: Arc<RwLock<Web3RpcEndpoint>>, for
     } else {
         web3_rpc_info.penalty_from_last_critical_error /= 2;
     }
+    // verification uses two requests (chain id and latest block)
+    web3_rpc_info
+        .rate_limit
+        .record_requests(&web3_rpc_params.web3_endpoint_params, Utc::now(), 2);
     web3_rpc_info.last_verified = Some(Utc::now());
     web3_rpc_info.verify_result = Some(verify_result.clone());
     web3_rpc_info.penalty_from_errors = 0;
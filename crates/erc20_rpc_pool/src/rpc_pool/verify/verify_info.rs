@@ -1,6 +1,9 @@
+use crate::rpc_pool::rate_limit::Web3RpcRateLimitInfo;
+use crate::rpc_pool::utils::redact_url;
 use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::collections::BTreeMap;
+use std::fmt;
 use uuid::Uuid;
 
 pub struct VerifyEndpointParams {
@@ -28,6 +31,68 @@ pub enum VerifyEndpointResult {
     Unreachable,
 }
 
+/// String holding secret value (api key, token), never printed in plain text.
+/// Serialized as is, so configuration can be written back - use
+/// [`serialize_redacted_secret`] for values exposed through the api
+#[derive(Clone, PartialEq, Eq)]
+pub struct Web3RpcSecret(String);
+
+impl Web3RpcSecret {
+    pub fn new(secret: String) -> Self {
+        Self(secret)
+    }
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Web3RpcSecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "***")
+    }
+}
+
+impl fmt::Display for Web3RpcSecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "***")
+    }
+}
+
+impl Serialize for Web3RpcSecret {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+pub fn serialize_redacted_secret<S: Serializer>(
+    _secret: &Web3RpcSecret,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str("***")
+}
+
+impl<'de> Deserialize<'de> for Web3RpcSecret {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Self(String::deserialize(deserializer)?))
+    }
+}
+
+/// Additional http header sent with every request to the endpoint (for example Authorization)
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Web3RpcHeader {
+    pub name: String,
+    #[serde(serialize_with = "serialize_redacted_secret")]
+    pub value: Web3RpcSecret,
+}
+
+pub(crate) fn serialize_redacted_url<S: Serializer>(
+    url: &str,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&redact_url(url))
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Web3EndpointParams {
@@ -40,12 +105,55 @@ pub struct Web3EndpointParams {
     pub max_number_of_consecutive_errors: u64,
     /// After this time revalidate endpoint
     pub verify_interval_secs: u64,
-    /// rate limit endpoint
+    /// rate limit endpoint (minimum gap between requests)
     pub min_interval_requests_ms: Option<u64>,
+    /// token bucket capacity - number of requests that can be sent in a burst
+    pub rate_limit_burst: Option<u64>,
+    /// token bucket refill rate - requests per second
+    pub rate_limit_per_sec: Option<f64>,
+    /// endpoint is unavailable after this number of requests in a day (UTC)
+    pub daily_request_budget: Option<u64>,
+    /// endpoint is unavailable after this number of requests in a month (UTC)
+    pub monthly_request_budget: Option<u64>,
     /// if head is behind this time mark endpoint as not available
     pub max_head_behind_secs: Option<u64>,
     /// limit response timeout
     pub max_response_time_ms: u64,
+    /// http headers added to every request (values are secret)
+    #[serde(default)]
+    pub headers: Vec<Web3RpcHeader>,
+}
+
+impl Default for Web3EndpointParams {
+    fn default() -> Self {
+        Self {
+            skip_validation: false,
+            backup_level: 0,
+            max_number_of_consecutive_errors: 5,
+            verify_interval_secs: 120,
+            min_interval_requests_ms: None,
+            max_head_behind_secs: Some(120),
+            max_response_time_ms: 10000,
+            rate_limit_burst: None,
+            rate_limit_per_sec: None,
+            daily_request_budget: None,
+            monthly_request_budget: None,
+            headers: Vec::new(),
+        }
+    }
+}
+
+/// Where the endpoint definition comes from. Config reload changes only `Config` endpoints,
+/// endpoints set by env variables or changed through the management api are left alone.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum Web3RpcEndpointOrigin {
+    #[default]
+    Config,
+    Env,
+    Api,
+    /// Resolved from dns or json source
+    ExternalSource,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -54,10 +162,14 @@ pub struct Web3RpcSingleParams {
     /// If chain id is different than expected endpoint will be marked as critical
     pub chain_id: u64,
     pub name: String,
+    /// Url of the endpoint, secrets (user info, query values, api keys in path) are redacted when serialized
+    #[serde(serialize_with = "serialize_redacted_url")]
     pub endpoint: String,
 
     pub web3_endpoint_params: Web3EndpointParams,
     pub source_id: Option<Uuid>,
+    #[serde(default)]
+    pub origin: Web3RpcEndpointOrigin,
 }
 
 impl Web3RpcSingleParams {
@@ -68,6 +180,15 @@ impl Web3RpcSingleParams {
             endpoint,
             web3_endpoint_params: params.web3_endpoint_params,
             source_id: None,
+            origin: Web3RpcEndpointOrigin::Config,
+        }
+    }
+
+    /// Copy of params safe for displaying - endpoint url is redacted
+    pub fn redacted(&self) -> Self {
+        Self {
+            endpoint: redact_url(&self.endpoint),
+            ..self.clone()
         }
     }
 }
@@ -123,6 +244,8 @@ pub struct Web3RpcInfo {
 
     /// If endpoint is critical it won't be chosen at all
     pub is_allowed: bool,
+    /// Endpoint disabled by operator, it won't be chosen until enabled again
+    pub disabled: bool,
     /// If endpoint was critical in previous validation phase give it penalty (halve it for every validation phase)
     pub penalty_from_last_critical_error: i64,
     /// Increase this penalty for every error endpoint creates
@@ -137,6 +260,9 @@ pub struct Web3RpcInfo {
 
     pub endpoint_consecutive_errors: u64,
 
+    /// Token bucket and request budget usage
+    pub rate_limit: Web3RpcRateLimitInfo,
+
     pub removed_date: Option<DateTime<Utc>>,
 }
 
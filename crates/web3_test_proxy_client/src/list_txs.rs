@@ -12,6 +12,7 @@ pub async fn list_transactions_human(proxy_url_base: &str, proxy_key: &str) -> V
         .expect("Expected calls");
     let mut results: Vec<String> = Vec::new();
 
+    #[allow(clippy::unnecessary_sort_by)]
     calls.calls.sort_by(|a, b| a.date.cmp(&b.date));
     let first_time = calls.calls.first().unwrap().date;
     for (no, call) in calls.calls.into_iter().enumerate() {
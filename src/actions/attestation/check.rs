@@ -1,4 +1,3 @@
-use sqlx::SqlitePool;
 use structopt::StructOpt;
 use web3::ethabi;
 
@@ -7,6 +6,7 @@ use erc20_payment_lib::eth::{get_attestation_details, get_schema_details};
 use erc20_payment_lib::setup::PaymentSetup;
 use erc20_payment_lib_common::err_custom_create;
 use erc20_payment_lib_common::error::PaymentError;
+use erc20_payment_lib_common::store::PaymentDbStore;
 use web3::types::H256;
 
 #[derive(StructOpt)]
@@ -20,7 +20,7 @@ pub struct AttestationCheckOptions {
 }
 
 pub async fn check_attestation_local(
-    _conn: SqlitePool,
+    _store: PaymentDbStore,
     options: AttestationCheckOptions,
     config: Config,
 ) -> Result<(), PaymentError> {
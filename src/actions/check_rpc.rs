@@ -3,7 +3,8 @@ use erc20_payment_lib::config::Config;
 use erc20_payment_lib_common::err_custom_create;
 use erc20_payment_lib_common::error::PaymentError;
 use erc20_rpc_pool::{
-    resolve_txt_record_to_string_array, Web3EndpointParams, Web3ExternalEndpointList, Web3RpcPool,
+    redact_url, resolve_txt_record_to_string_array, split_dns_signature,
+    verify_endpoint_list_signature, Web3ExternalEndpointList, Web3RpcEndpointOrigin, Web3RpcPool,
     Web3RpcSingleParams,
 };
 use std::collections::HashSet;
@@ -32,31 +33,20 @@ pub async fn check_rpc_local(
             ))?;
     let mut single_endpoints = Vec::with_capacity(100);
     for rpc_settings in &chain_cfg.rpc_endpoints {
-        let max_head_behind_secs = rpc_settings.allowed_head_behind_secs.unwrap_or(120);
-        let max_head_behind_secs = if max_head_behind_secs < 0 {
-            None
-        } else {
-            Some(max_head_behind_secs as u64)
-        };
+        let endpoint_params = rpc_settings.endpoint_params()?;
         let endpoint_names = split_string_by_coma(&rpc_settings.names).unwrap_or_default();
         if let Some(endpoints) = split_string_by_coma(&rpc_settings.endpoints) {
             for (idx, endpoint) in endpoints.iter().enumerate() {
                 let endpoint = Web3RpcSingleParams {
                     chain_id: chain_cfg.chain_id as u64,
                     endpoint: endpoint.clone(),
-                    name: endpoint_names.get(idx).unwrap_or(&endpoint.clone()).clone(),
-                    web3_endpoint_params: Web3EndpointParams {
-                        backup_level: rpc_settings.backup_level.unwrap_or(0),
-                        skip_validation: rpc_settings.skip_validation.unwrap_or(false),
-                        verify_interval_secs: rpc_settings.verify_interval_secs.unwrap_or(120),
-                        max_response_time_ms: rpc_settings.max_timeout_ms.unwrap_or(10000),
-                        max_head_behind_secs,
-                        max_number_of_consecutive_errors: rpc_settings
-                            .max_consecutive_errors
-                            .unwrap_or(5),
-                        min_interval_requests_ms: rpc_settings.min_interval_ms,
-                    },
+                    name: endpoint_names
+                        .get(idx)
+                        .cloned()
+                        .unwrap_or_else(|| redact_url(endpoint)),
+                    web3_endpoint_params: endpoint_params.clone(),
                     source_id: None,
+                    origin: Web3RpcEndpointOrigin::Config,
                 };
                 single_endpoints.push(endpoint);
             }
@@ -79,22 +69,30 @@ pub async fn check_rpc_local(
         Duration::from_secs(300),
     );
     for rpc_settings in &chain_cfg.rpc_endpoints {
-        let max_head_behind_secs = rpc_settings.allowed_head_behind_secs.unwrap_or(120);
-        let max_head_behind_secs = if max_head_behind_secs < 0 {
-            None
-        } else {
-            Some(max_head_behind_secs as u64)
-        };
+        let endpoint_params = rpc_settings.endpoint_params()?;
         if split_string_by_coma(&rpc_settings.endpoints).is_some() {
             //already processed above
         } else if let Some(dns_source) = &rpc_settings.dns_source {
-            let urls = resolve_txt_record_to_string_array(dns_source)
+            let records = resolve_txt_record_to_string_array(dns_source)
                 .await
                 .map_err(|e| {
                     err_custom_create!("Error resolving dns entry {}: {}", dns_source, e)
                 })?;
+            let (urls, signature, valid_until) = split_dns_signature(records);
+            if let Some(signer) = rpc_settings.source_signer()? {
+                verify_endpoint_list_signature(
+                    chain_cfg.chain_id as u64,
+                    &[],
+                    &urls,
+                    valid_until,
+                    signature.as_deref(),
+                    signer,
+                )
+                .map_err(|e| err_custom_create!("Dns source {} rejected: {}", dns_source, e))?;
+                log::info!("Dns source {} signature verified", dns_source);
+            }
 
-            let names = urls.clone();
+            let names = urls.iter().map(|url| redact_url(url)).collect::<Vec<_>>();
 
             for (url, name) in urls.iter().zip(names) {
                 log::info!("Imported from dns source: {}", name);
@@ -102,18 +100,9 @@ pub async fn check_rpc_local(
                     chain_id: chain_cfg.chain_id as u64,
                     endpoint: url.clone(),
                     name: name.clone(),
-                    web3_endpoint_params: Web3EndpointParams {
-                        backup_level: rpc_settings.backup_level.unwrap_or(0),
-                        skip_validation: rpc_settings.skip_validation.unwrap_or(false),
-                        verify_interval_secs: rpc_settings.verify_interval_secs.unwrap_or(120),
-                        max_response_time_ms: rpc_settings.max_timeout_ms.unwrap_or(10000),
-                        max_head_behind_secs,
-                        max_number_of_consecutive_errors: rpc_settings
-                            .max_consecutive_errors
-                            .unwrap_or(5),
-                        min_interval_requests_ms: rpc_settings.min_interval_ms,
-                    },
+                    web3_endpoint_params: endpoint_params.clone(),
                     source_id: None,
+                    origin: Web3RpcEndpointOrigin::Config,
                 });
             }
         } else if let Some(json_source) = &rpc_settings.json_source {
@@ -138,6 +127,20 @@ pub async fn check_rpc_local(
                         String::from_utf8_lossy(&response)
                     )
                 })?;
+            if let Some(signer) = rpc_settings.source_signer()? {
+                verify_endpoint_list_signature(
+                    chain_cfg.chain_id as u64,
+                    &res.names,
+                    &res.urls,
+                    res.valid_until,
+                    res.signature.as_deref(),
+                    signer,
+                )
+                .map_err(|e| {
+                    err_custom_create!("Json source {} rejected: {}", redact_url(json_source), e)
+                })?;
+                log::info!("Json source {} signature verified", redact_url(json_source));
+            }
             if res.names.len() != res.urls.len() {
                 return Err(err_custom_create!(
                     "Endpoint names and endpoints have to have same length {} != {}",
@@ -153,18 +156,9 @@ pub async fn check_rpc_local(
                     chain_id: chain_cfg.chain_id as u64,
                     endpoint: url.clone(),
                     name: name.clone(),
-                    web3_endpoint_params: Web3EndpointParams {
-                        backup_level: rpc_settings.backup_level.unwrap_or(0),
-                        skip_validation: rpc_settings.skip_validation.unwrap_or(false),
-                        verify_interval_secs: rpc_settings.verify_interval_secs.unwrap_or(120),
-                        max_response_time_ms: rpc_settings.max_timeout_ms.unwrap_or(10000),
-                        max_head_behind_secs,
-                        max_number_of_consecutive_errors: rpc_settings
-                            .max_consecutive_errors
-                            .unwrap_or(5),
-                        min_interval_requests_ms: rpc_settings.min_interval_ms,
-                    },
+                    web3_endpoint_params: endpoint_params.clone(),
                     source_id: None,
+                    origin: Web3RpcEndpointOrigin::Config,
                 });
             }
         } else {
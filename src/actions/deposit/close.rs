@@ -4,7 +4,7 @@ use erc20_payment_lib::setup::PaymentSetup;
 use erc20_payment_lib_common::err_custom_create;
 use erc20_payment_lib_common::error::PaymentError;
 use erc20_payment_lib_common::model::DepositId;
-use sqlx::SqlitePool;
+use erc20_payment_lib_common::store::PaymentDbStore;
 use std::str::FromStr;
 use structopt::StructOpt;
 use web3::types::{Address, U256};
@@ -35,7 +35,7 @@ pub struct CloseDepositOptions {
 }
 
 pub async fn close_deposit_local(
-    conn: SqlitePool,
+    store: PaymentDbStore,
     close_deposit_options: CloseDepositOptions,
     config: Config,
     public_addrs: &[Address],
@@ -77,7 +77,7 @@ pub async fn close_deposit_local(
 
     close_deposit(
         web3,
-        &conn,
+        &store,
         chain_cfg.chain_id as u64,
         public_addr,
         CloseDepositOptionsInt {
@@ -9,9 +9,9 @@ use erc20_payment_lib::signer::PrivateKeySigner;
 use erc20_payment_lib::utils::DecimalConvExt;
 use erc20_payment_lib_common::error::ErrorBag;
 use erc20_payment_lib_common::error::{AllowanceRequest, PaymentError};
+use erc20_payment_lib_common::store::PaymentDbStore;
 use erc20_payment_lib_common::{err_custom_create, err_from};
 use rand::Rng;
-use sqlx::SqlitePool;
 use std::sync::Arc;
 use structopt::StructOpt;
 use web3::types::{Address, U256};
@@ -76,7 +76,7 @@ pub struct CreateDepositOptions {
 }
 
 pub async fn make_deposit_local(
-    conn: SqlitePool,
+    store: PaymentDbStore,
     make_deposit_options: CreateDepositOptions,
     config: Config,
     public_addrs: &[Address],
@@ -156,7 +156,7 @@ pub async fn make_deposit_local(
             };
 
             let _ = process_allowance(
-                &conn.clone(),
+                &store,
                 &payment_setup,
                 &allowance_request,
                 Arc::new(Box::new(signer)),
@@ -186,7 +186,7 @@ pub async fn make_deposit_local(
 
     make_deposit(
         web3,
-        &conn,
+        &store,
         chain_cfg.chain_id as u64,
         public_addr,
         chain_cfg.token.address,
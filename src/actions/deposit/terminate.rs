@@ -5,7 +5,7 @@ use erc20_payment_lib::setup::PaymentSetup;
 use erc20_payment_lib_common::err_custom_create;
 use erc20_payment_lib_common::error::PaymentError;
 use erc20_payment_lib_common::model::DepositId;
-use sqlx::SqlitePool;
+use erc20_payment_lib_common::store::PaymentDbStore;
 use std::str::FromStr;
 use structopt::StructOpt;
 use web3::types::{Address, U256};
@@ -39,7 +39,7 @@ pub struct TerminateDepositOptions {
 }
 
 pub async fn terminate_deposit_local(
-    conn: SqlitePool,
+    store: PaymentDbStore,
     terminate_deposit_options: TerminateDepositOptions,
     config: Config,
     public_addrs: &[Address],
@@ -98,7 +98,7 @@ pub async fn terminate_deposit_local(
 
     terminate_deposit(
         web3,
-        &conn,
+        &store,
         chain_cfg.chain_id as u64,
         public_addr,
         TerminateDepositOptionsInt {
@@ -6,16 +6,15 @@ use erc20_payment_lib::transaction::{import_erc20_txs, ImportErc20TxsArgs};
 use erc20_payment_lib_common::error::ErrorBag;
 use erc20_payment_lib_common::error::PaymentError;
 use erc20_payment_lib_common::model::ScanDaoDbObj;
-use erc20_payment_lib_common::ops::{delete_scan_info, get_scan_info, upsert_scan_info};
+use erc20_payment_lib_common::store::{PaymentDbStore, PaymentStoreOps};
 use erc20_payment_lib_common::{err_custom_create, err_from};
 use erc20_rpc_pool::Web3RpcPool;
-use sqlx::SqlitePool;
 use std::str::FromStr;
 use std::sync::Arc;
 use web3::types::Address;
 
 async fn scan_int(
-    conn: SqlitePool,
+    store: PaymentDbStore,
     scan_blockchain_options: &ScanBlockchainOptions,
     chain_cfg: Chain,
     web3: Arc<Web3RpcPool>,
@@ -43,7 +42,7 @@ async fn scan_int(
     for tx in &txs {
         match transaction_from_chain_and_into_db(
             web3.clone(),
-            &conn.clone(),
+            &store,
             chain_cfg.chain_id,
             &format!("{tx:#x}"),
             chain_cfg.token.address,
@@ -69,7 +68,7 @@ async fn scan_int(
 
 #[allow(clippy::too_many_arguments)]
 async fn scan_auto_step(
-    conn: SqlitePool,
+    store: PaymentDbStore,
     scan_blockchain_options: &ScanBlockchainOptions,
     chain_cfg: Chain,
     web3: Arc<Web3RpcPool>,
@@ -143,7 +142,7 @@ async fn scan_auto_step(
     );
 
     scan_int(
-        conn.clone(),
+        store.clone(),
         scan_blockchain_options,
         chain_cfg.clone(),
         web3.clone(),
@@ -177,7 +176,8 @@ async fn scan_auto_step(
         );
     }
 
-    upsert_scan_info(&conn.clone(), scan_info)
+    store
+        .upsert_scan_info(scan_info)
         .await
         .map_err(err_from!())?;
 
@@ -185,7 +185,7 @@ async fn scan_auto_step(
 }
 
 pub async fn scan_blockchain_local(
-    conn: SqlitePool,
+    store: PaymentDbStore,
     scan_blockchain_options: ScanBlockchainOptions,
     config: Config,
 ) -> Result<(), PaymentError> {
@@ -215,13 +215,15 @@ pub async fn scan_blockchain_local(
         start_block: -1,
         last_block: -1,
     };
-    let scan_info_from_db = get_scan_info(&conn.clone(), chain_cfg.chain_id, &scan_info.filter)
+    let scan_info_from_db = store
+        .get_scan_info(chain_cfg.chain_id, &scan_info.filter)
         .await
         .map_err(err_from!())?;
 
     let scan_info = if scan_blockchain_options.start_new_scan {
         log::warn!("Starting new scan - removing old scan info from db");
-        delete_scan_info(&conn.clone(), scan_info.chain_id, &scan_info.filter)
+        store
+            .delete_scan_info(scan_info.chain_id, &scan_info.filter)
             .await
             .map_err(err_from!())?;
         scan_info
@@ -262,7 +264,7 @@ pub async fn scan_blockchain_local(
         let mut scan_info = scan_info.clone();
         loop {
             match scan_auto_step(
-                conn.clone(),
+                store.clone(),
                 &scan_blockchain_options,
                 chain_cfg.clone(),
                 web3.clone(),
@@ -335,7 +337,7 @@ pub async fn scan_blockchain_local(
         }
 
         scan_int(
-            conn.clone(),
+            store.clone(),
             &scan_blockchain_options,
             chain_cfg.clone(),
             web3.clone(),
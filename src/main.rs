@@ -6,15 +6,16 @@ use crate::options::{AttestationCommands, DepositCommands, PaymentCommands, Paym
 use actix_web::Scope;
 use actix_web::{web, App, HttpServer};
 use csv::ReaderBuilder;
+use erc20_payment_lib::archive::{archive_before as archive_before_date, open_archive};
+use erc20_payment_lib::backup::{backup_sqlite, restore_sqlite, rotate_backups, InstanceLock};
 use erc20_payment_lib::config::{AdditionalOptions, RpcSettings};
 use erc20_payment_lib::signer::PrivateKeySigner;
-use erc20_payment_lib_common::create_sqlite_connection;
 use erc20_payment_lib_common::error::*;
-use erc20_payment_lib_common::ops::{
-    get_next_transactions_to_process, insert_token_transfer,
-    insert_token_transfer_with_deposit_check, update_token_transfer,
+use erc20_payment_lib_common::store::{
+    PaymentDbStore, PaymentStore, PaymentStoreOps, TransferInsertResult,
 };
 use erc20_payment_lib_common::*;
+use erc20_payment_lib_common::{create_db_connection, is_postgres_url, redact_db_url};
 
 use crate::actions::scan_chain::scan_blockchain_local;
 use erc20_payment_lib::{
@@ -23,6 +24,7 @@ use erc20_payment_lib::{
     runtime::PaymentRuntime,
 };
 use std::env;
+use std::path::PathBuf;
 use std::str::FromStr;
 
 use crate::actions::attestation::check::check_attestation_local;
@@ -34,12 +36,15 @@ use crate::actions::deposit::details::deposit_details_local;
 use crate::actions::deposit::terminate::terminate_deposit_local;
 use crate::stats::{export_stats, run_stats};
 use erc20_payment_lib::eth::GetBalanceArgs;
+use erc20_payment_lib::faucet::FaucetLimits;
 use erc20_payment_lib::faucet_client::faucet_donate;
 use erc20_payment_lib::misc::gen_private_keys;
 use erc20_payment_lib::runtime::{
-    distribute_gas, get_token_balance, mint_golem_token, remove_last_unsent_transactions,
-    remove_transaction_force, PaymentRuntimeArgs,
+    cancel_token_transfer, distribute_gas, edit_token_transfer, get_token_balance,
+    mint_golem_token, remove_last_unsent_transactions, remove_transaction_force,
+    PaymentRuntimeArgs,
 };
+use erc20_payment_lib::server::auth::ApiAuth;
 use erc20_payment_lib::server::web::{runtime_web_scope, ServerData};
 use erc20_payment_lib::setup::PaymentSetup;
 use erc20_payment_lib_common::init_metrics;
@@ -86,7 +91,17 @@ async fn main_internal() -> Result<(), PaymentError> {
         PaymentCommands::ExportHistory { .. } => {}
         PaymentCommands::DecryptKeyStore { .. } => {}
         PaymentCommands::Cleanup { .. } => {}
-        PaymentCommands::ShowConfig { .. } => {}
+        PaymentCommands::CancelTransfer { .. } | PaymentCommands::EditTransfer { .. } => {
+            private_key_load_needed = false;
+        }
+        PaymentCommands::Backup { .. } => {
+            private_key_load_needed = false;
+        }
+        PaymentCommands::Restore { .. } => {
+            private_key_load_needed = false;
+            db_connection_needed = false;
+        }
+        PaymentCommands::ShowConfig => {}
         PaymentCommands::Attestation { .. } => {
             private_key_load_needed = false;
         }
@@ -102,9 +117,10 @@ async fn main_internal() -> Result<(), PaymentError> {
     };
     let signer = PrivateKeySigner::new(private_keys.clone());
 
-    let mut config = match config::Config::load("config-payments.toml").await {
+    let config_path = PathBuf::from("config-payments.toml");
+    let mut config = match config::Config::load(&config_path).await {
         Ok(c) => c,
-        Err(err) => match err.inner {
+        Err(err) => match *err.inner {
             ErrorBag::IoError(_) => {
                 log::info!("No local config found, using default config");
                 config::Config::default_config()
@@ -113,6 +129,11 @@ async fn main_internal() -> Result<(), PaymentError> {
         },
     };
 
+    // actions like check-rpc or get-dev-eth resolve dns before payment setup is created
+    if let Some(dns) = &config.dns {
+        set_dns_resolver_settings(dns)?;
+    }
+
     let rpc_endpoints_from_env = [
         ("POLYGON_GETH_ADDR", "polygon"),
         ("GOERLI_GETH_ADDR", "goerli"),
@@ -142,6 +163,13 @@ async fn main_internal() -> Result<(), PaymentError> {
                     max_consecutive_errors: None,
                     dns_source: None,
                     json_source: None,
+                    rate_limit_burst: None,
+                    rate_limit_per_sec: None,
+                    daily_request_budget: None,
+                    monthly_request_budget: None,
+                    headers: None,
+                    source_signer: None,
+                    from_env: true,
                 })
                 .collect();
             config.change_rpc_endpoints(f.1, rpcs).await?;
@@ -169,32 +197,32 @@ async fn main_internal() -> Result<(), PaymentError> {
     }
 
     let db_filename = cli.sqlite_db_file;
-    let conn = if db_connection_needed {
+    let db_url = cli
+        .db_url
+        .clone()
+        .unwrap_or_else(|| db_filename.display().to_string());
+    let pool = if db_connection_needed {
         if cli.sqlite_read_only {
             log::info!(
                 "Connecting read only to db: {} (journal mode: {})",
-                db_filename.display(),
+                redact_db_url(&db_url),
                 cli.sqlite_journal
             );
         } else {
             log::info!(
                 "Connecting read/write connection to db: {} (journal mode: {})",
-                db_filename.display(),
+                redact_db_url(&db_url),
                 cli.sqlite_journal
             );
         }
         env::set_var("ERC20_LIB_SQLITE_JOURNAL_MODE", cli.sqlite_journal);
-        let conn = create_sqlite_connection(
-            Some(&db_filename),
-            None,
-            cli.sqlite_read_only,
-            !cli.skip_migrations,
-        )
-        .await?;
-        Some(conn)
+        let pool =
+            create_db_connection(&db_url, cli.sqlite_read_only, !cli.skip_migrations).await?;
+        Some(pool)
     } else {
         None
     };
+    let store = pool.clone().map(PaymentDbStore::from);
 
     match cli.commands {
         PaymentCommands::Run { run_options } => {
@@ -220,13 +248,19 @@ async fn main_internal() -> Result<(), PaymentError> {
                 }
             });
 
-            let (broadcast_sender, broadcast_receiver) = broadcast::channel(10);
+            let archive_settings = config.archive.clone();
+            let backup_settings = config.backup.clone();
+            let webhook_settings = config.webhook.clone();
+            let api_auth_settings = config.api_auth.clone();
+            let faucet_settings = config.faucet.clone();
+            let _instance_lock = InstanceLock::new(&db_filename).await?;
+            let (broadcast_sender, broadcast_receiver) = broadcast::channel(100);
             let sp = PaymentRuntime::new(
                 PaymentRuntimeArgs {
                     secret_keys: private_keys,
                     db_filename,
                     config,
-                    conn: Some(conn.clone().unwrap()),
+                    conn: pool.clone(),
                     options: Some(add_opt),
                     mspc_sender: None,
                     broadcast_sender: Some(broadcast_sender),
@@ -236,44 +270,82 @@ async fn main_internal() -> Result<(), PaymentError> {
             )
             .await?;
 
-            if run_options.http {
+            if let Some(archive_settings) = archive_settings {
+                sp.start_archive_job(archive_settings)?;
+            }
+            if let Some(backup_settings) = backup_settings {
+                sp.start_backup_job(backup_settings)?;
+            }
+            if let Some(webhook_settings) = webhook_settings {
+                sp.start_webhook_job(webhook_settings)?;
+            }
+
+            if run_options.watch_config {
+                sp.start_config_watcher(config_path, std::time::Duration::from_secs(5));
+            }
+
+            #[cfg(feature = "grpc")]
+            let grpc_addr = run_options.grpc_addr;
+            #[cfg(not(feature = "grpc"))]
+            let grpc_addr: Option<std::net::SocketAddr> = None;
+
+            if run_options.http || grpc_addr.is_some() {
                 let server_data = web::Data::new(Box::new(ServerData {
                     shared_state: sp.shared_state.clone(),
-                    db_connection: Arc::new(Mutex::new(conn.clone().unwrap())),
+                    db_connection: Arc::new(Mutex::new(store.clone().unwrap())),
                     payment_setup: sp.setup.clone(),
                     payment_runtime: sp,
+                    auth: Arc::new(ApiAuth::new(
+                        api_auth_settings.as_ref(),
+                        env::var("ERC20_ADMIN_TOKEN").ok().filter(|t| !t.is_empty()),
+                    )),
+                    faucet: FaucetLimits::new(faucet_settings.as_ref())?,
                 }));
 
-                let server = HttpServer::new(move || {
-                    let cors = actix_cors::Cors::default()
-                        .allow_any_origin()
-                        .allow_any_method()
-                        .allow_any_header()
-                        .max_age(3600);
-
-                    let scope = runtime_web_scope(
-                        Scope::new("erc20"),
+                #[cfg(feature = "grpc")]
+                if let Some(grpc_addr) = grpc_addr {
+                    erc20_payment_lib::server::grpc::start_grpc_server(
                         server_data.clone(),
-                        run_options.faucet,
+                        grpc_addr,
                         run_options.transfers,
-                        run_options.debug,
-                        run_options.frontend,
-                    );
+                    )
+                    .await?;
+                }
 
-                    App::new().wrap(cors).service(scope)
-                })
-                .workers(run_options.http_threads as usize)
-                .bind((run_options.http_addr.as_str(), run_options.http_port))
-                .expect("Cannot run server")
-                .run();
-
-                log::info!(
-                    "http server starting on {}:{}",
-                    run_options.http_addr,
-                    run_options.http_port
-                );
+                if run_options.http {
+                    let server = HttpServer::new(move || {
+                        let cors = actix_cors::Cors::default()
+                            .allow_any_origin()
+                            .allow_any_method()
+                            .allow_any_header()
+                            .max_age(3600);
+
+                        let scope = runtime_web_scope(
+                            Scope::new("erc20"),
+                            server_data.clone(),
+                            run_options.faucet,
+                            run_options.transfers,
+                            run_options.debug,
+                            run_options.frontend,
+                        );
+
+                        App::new().wrap(cors).service(scope)
+                    })
+                    .workers(run_options.http_threads as usize)
+                    .bind((run_options.http_addr.as_str(), run_options.http_port))
+                    .expect("Cannot run server")
+                    .run();
 
-                server.await.unwrap();
+                    log::info!(
+                        "http server starting on {}:{}",
+                        run_options.http_addr,
+                        run_options.http_port
+                    );
+
+                    server.await.unwrap();
+                } else {
+                    server_data.payment_runtime.join_tasks().await.unwrap();
+                }
             } else {
                 sp.join_tasks().await.unwrap();
             }
@@ -333,7 +405,7 @@ async fn main_internal() -> Result<(), PaymentError> {
 
             distribute_gas(
                 web3,
-                &conn.clone().unwrap(),
+                store.as_ref().unwrap(),
                 chain_cfg.chain_id as u64,
                 public_addr,
                 chain_cfg.distributor_contract.clone().map(|c| c.address),
@@ -408,7 +480,7 @@ async fn main_internal() -> Result<(), PaymentError> {
 
             mint_golem_token(
                 web3,
-                &conn.clone().unwrap(),
+                store.as_ref().unwrap(),
                 chain_cfg.chain_id as u64,
                 public_addr,
                 chain_cfg.token.address,
@@ -420,7 +492,7 @@ async fn main_internal() -> Result<(), PaymentError> {
         }
         PaymentCommands::Attestation { attest } => match attest {
             AttestationCommands::Check { options } => {
-                check_attestation_local(conn.clone().unwrap(), options, config).await?;
+                check_attestation_local(store.clone().unwrap(), options, config).await?;
             }
         },
         PaymentCommands::Deposit { deposit } => match deposit {
@@ -428,7 +500,7 @@ async fn main_internal() -> Result<(), PaymentError> {
                 make_deposit_options,
             } => {
                 make_deposit_local(
-                    conn.clone().unwrap(),
+                    store.clone().unwrap(),
                     make_deposit_options,
                     config,
                     &public_addrs,
@@ -440,7 +512,7 @@ async fn main_internal() -> Result<(), PaymentError> {
                 close_deposit_options,
             } => {
                 close_deposit_local(
-                    conn.clone().unwrap(),
+                    store.clone().unwrap(),
                     close_deposit_options,
                     config,
                     &public_addrs,
@@ -451,7 +523,7 @@ async fn main_internal() -> Result<(), PaymentError> {
                 terminate_deposit_options,
             } => {
                 terminate_deposit_local(
-                    conn.clone().unwrap(),
+                    store.clone().unwrap(),
                     terminate_deposit_options,
                     config,
                     &public_addrs,
@@ -600,9 +672,10 @@ async fn main_internal() -> Result<(), PaymentError> {
             } else {
                 None
             };
-            let mut tt = insert_token_transfer_with_deposit_check(
-                &conn.clone().unwrap(),
-                &TokenTransferDbObj {
+            let mut tt = store
+                .as_ref()
+                .unwrap()
+                .insert_token_transfer_with_deposit_check(&TokenTransferDbObj {
                     id: 0,
                     payment_id: None,
                     from_addr: format!("{:#x}", public_addr),
@@ -617,14 +690,19 @@ async fn main_internal() -> Result<(), PaymentError> {
                     paid_date: None,
                     fee_paid: None,
                     error: None,
-                },
-            )
-            .await
-            .unwrap();
+                    priority: single_transfer_options.priority.to_string(),
+                    approved_date: None,
+                    deadline: None,
+                })
+                .await
+                .unwrap();
 
             let payment_id = format!("{}_transfer_{}", single_transfer_options.token, tt.id);
             tt.payment_id = Some(payment_id.clone());
-            update_token_transfer(&conn.clone().unwrap(), &tt)
+            store
+                .as_ref()
+                .unwrap()
+                .update_token_transfer(&tt)
                 .await
                 .unwrap();
 
@@ -660,26 +738,27 @@ async fn main_internal() -> Result<(), PaymentError> {
             if generate_options.append_to_db && cli.sqlite_read_only {
                 return Err(err_custom_create!("Cannot append to db in read-only mode"));
             }
-            generate_test_payments(
-                generate_options,
-                &config,
-                public_addrs,
-                Some(conn.clone().unwrap()),
-            )
-            .await?;
+            generate_test_payments(generate_options, &config, public_addrs, store.clone()).await?;
         }
         PaymentCommands::ExportHistory {
             export_history_stats_options,
-        } => export_stats(conn.clone().unwrap(), export_history_stats_options, &config).await?,
+        } => {
+            export_stats(
+                store.clone().unwrap(),
+                export_history_stats_options,
+                &config,
+            )
+            .await?
+        }
         PaymentCommands::PaymentStats {
             payment_stats_options,
-        } => run_stats(conn.clone().unwrap(), payment_stats_options, &config).await?,
+        } => run_stats(store.clone().unwrap(), payment_stats_options, &config).await?,
         PaymentCommands::ScanBlockchain {
             scan_blockchain_options,
-        } => scan_blockchain_local(conn.clone().unwrap(), scan_blockchain_options, config).await?,
+        } => scan_blockchain_local(store.clone().unwrap(), scan_blockchain_options, config).await?,
         PaymentCommands::ImportPayments { import_options } => {
             log::info!("importing payments from file: {}", import_options.file);
-            if !cli.sqlite_read_only {
+            if cli.sqlite_read_only {
                 return Err(err_custom_create!(
                     "Cannot import payments in read-only mode"
                 ));
@@ -729,10 +808,19 @@ async fn main_internal() -> Result<(), PaymentError> {
                 token_transfer_list.len(),
                 import_options.file
             );
+            // importing the same file again does not duplicate transfers with payment id
+            let store = store.clone().unwrap();
+            let mut existing_count = 0;
             for token_transfer in token_transfer_list {
-                insert_token_transfer(&conn.clone().unwrap(), &token_transfer)
-                    .await
-                    .map_err(err_from!())?;
+                if let TransferInsertResult::Existing(_) = store
+                    .insert_token_transfer_idempotent(&token_transfer)
+                    .await?
+                {
+                    existing_count += 1;
+                }
+            }
+            if existing_count > 0 {
+                log::info!("Skipped {} transfers already present in db", existing_count);
             }
         }
         PaymentCommands::DecryptKeyStore { decrypt_options } => {
@@ -747,7 +835,7 @@ async fn main_internal() -> Result<(), PaymentError> {
             if cleanup_options.remove_unsent_tx {
                 let mut number_of_unsent_removed = 0;
                 loop {
-                    match remove_last_unsent_transactions(conn.clone().unwrap()).await {
+                    match remove_last_unsent_transactions(store.as_ref().unwrap()).await {
                         Ok(Some(id)) => {
                             println!("Removed unsent transaction with id {}", id);
                             number_of_unsent_removed += 1;
@@ -770,23 +858,25 @@ async fn main_internal() -> Result<(), PaymentError> {
                 }
             }
             if cleanup_options.remove_tx_stuck {
-                let mut transactions = get_next_transactions_to_process(
-                    &conn.clone().unwrap(),
-                    None,
-                    1,
-                    cleanup_options.chain_id.ok_or(err_custom_create!(
-                        "Chain id not specified for stuck tx removal"
-                    ))?,
-                )
-                .await
-                .map_err(err_from!())?;
+                let mut transactions = store
+                    .as_ref()
+                    .unwrap()
+                    .get_next_transactions_to_process(
+                        None,
+                        1,
+                        cleanup_options.chain_id.ok_or(err_custom_create!(
+                            "Chain id not specified for stuck tx removal"
+                        ))?,
+                    )
+                    .await
+                    .map_err(err_from!())?;
 
                 let Some(tx) = transactions.get_mut(0) else {
                     println!("No transactions found to remove");
                     return Ok(());
                 };
                 if tx.first_stuck_date.is_some() {
-                    match remove_transaction_force(&conn.clone().unwrap(), tx.id).await {
+                    match remove_transaction_force(store.as_ref().unwrap(), tx.id).await {
                         Ok(_) => {
                             println!(
                                 "Removed stuck transaction with id {} (nonce: {})",
@@ -807,22 +897,24 @@ async fn main_internal() -> Result<(), PaymentError> {
                 }
             }
             if cleanup_options.remove_tx_unsafe {
-                let mut transactions = get_next_transactions_to_process(
-                    &conn.clone().unwrap(),
-                    None,
-                    1,
-                    cleanup_options.chain_id.ok_or(err_custom_create!(
-                        "Chain id not specified for unsafe tx removal"
-                    ))?,
-                )
-                .await
-                .map_err(err_from!())?;
+                let mut transactions = store
+                    .as_ref()
+                    .unwrap()
+                    .get_next_transactions_to_process(
+                        None,
+                        1,
+                        cleanup_options.chain_id.ok_or(err_custom_create!(
+                            "Chain id not specified for unsafe tx removal"
+                        ))?,
+                    )
+                    .await
+                    .map_err(err_from!())?;
 
                 let Some(tx) = transactions.get_mut(0) else {
                     println!("No transactions found to remove");
                     return Ok(());
                 };
-                match remove_transaction_force(&conn.clone().unwrap(), tx.id).await {
+                match remove_transaction_force(store.as_ref().unwrap(), tx.id).await {
                     Ok(_) => {
                         println!("Removed transaction with id {}", tx.id);
                     }
@@ -835,6 +927,90 @@ async fn main_internal() -> Result<(), PaymentError> {
                     }
                 }
             }
+            if let Some(archive_before) = cleanup_options.archive_before {
+                let archive_conn = open_archive(&cleanup_options.archive_file).await?;
+                let res =
+                    archive_before_date(store.as_ref().unwrap(), &archive_conn, archive_before)
+                        .await?;
+                archive_conn.close().await;
+                println!(
+                    "Archived {} transactions ({} transfers) and {} chain transactions ({} transfers) to {}",
+                    res.tx_count,
+                    res.token_transfer_count,
+                    res.chain_tx_count,
+                    res.chain_transfer_count,
+                    cleanup_options.archive_file.display()
+                );
+            }
+        }
+        PaymentCommands::CancelTransfer {
+            cancel_transfer_options,
+        } => {
+            let transfer =
+                cancel_token_transfer(store.as_ref().unwrap(), cancel_transfer_options.id).await?;
+            println!(
+                "Cancelled transfer {} of {} to {}",
+                transfer.id, transfer.token_amount, transfer.receiver_addr
+            );
+        }
+        PaymentCommands::EditTransfer {
+            edit_transfer_options,
+        } => {
+            let amount = edit_transfer_options
+                .amount
+                .map(|amount| amount.to_u256_from_eth())
+                .transpose()
+                .map_err(err_from!())?;
+            let transfer = edit_token_transfer(
+                store.as_ref().unwrap(),
+                edit_transfer_options.id,
+                edit_transfer_options.recipient,
+                amount,
+                edit_transfer_options.priority,
+                edit_transfer_options.deadline,
+            )
+            .await?;
+            println!(
+                "Transfer {} changed to {} for {}",
+                transfer.id, transfer.token_amount, transfer.receiver_addr
+            );
+        }
+        PaymentCommands::Backup { backup_options } => {
+            // backup and restore work on the SQLite file, use pg_dump for PostgreSQL
+            let sqlite_pool =
+                store
+                    .as_ref()
+                    .and_then(|s| s.sqlite_pool())
+                    .ok_or(err_custom_create!(
+                        "Backup is supported only for SQLite database"
+                    ))?;
+            let path = backup_sqlite(
+                sqlite_pool,
+                &backup_options.backup_dir,
+                &backup_options.prefix,
+            )
+            .await?;
+            println!("Backup written to {}", path.display());
+            if let Some(keep) = backup_options.keep {
+                for removed in
+                    rotate_backups(&backup_options.backup_dir, &backup_options.prefix, keep)?
+                {
+                    println!("Removed old backup {}", removed.display());
+                }
+            }
+        }
+        PaymentCommands::Restore { restore_options } => {
+            if is_postgres_url(&db_url) {
+                return Err(err_custom_create!(
+                    "Restore is supported only for SQLite database"
+                ));
+            }
+            restore_sqlite(&restore_options.backup_file, &db_filename).await?;
+            println!(
+                "Database {} restored from {}",
+                db_filename.display(),
+                restore_options.backup_file.display()
+            );
         }
         PaymentCommands::ShowConfig => {
             println!(
@@ -846,8 +1022,8 @@ async fn main_internal() -> Result<(), PaymentError> {
         }
     }
 
-    if let Some(conn) = conn.clone() {
-        conn.close().await;
+    if let Some(store) = store {
+        store.close().await;
     }
     Ok(())
 }
@@ -5,6 +5,8 @@ use crate::actions::deposit::close::CloseDepositOptions;
 use crate::actions::deposit::create::CreateDepositOptions;
 use crate::actions::deposit::details::CheckDepositOptions;
 use crate::actions::deposit::terminate::TerminateDepositOptions;
+use chrono::{DateTime, Utc};
+use erc20_payment_lib_common::model::TransferPriority;
 use erc20_payment_lib_extra::{BalanceOptions, GenerateOptions};
 use structopt::StructOpt;
 use web3::types::Address;
@@ -60,6 +62,13 @@ pub struct RunOptions {
     )]
     pub http_addr: String,
 
+    #[cfg(feature = "grpc")]
+    #[structopt(
+        long = "grpc-addr",
+        help = "Enable gRPC server on given address, i.e. 127.0.0.1:50051"
+    )]
+    pub grpc_addr: Option<std::net::SocketAddr>,
+
     #[structopt(long = "faucet", help = "Enabled faucet for the server")]
     pub faucet: bool,
 
@@ -72,6 +81,12 @@ pub struct RunOptions {
     #[structopt(long = "frontend", help = "Enabled frontend serving for the server")]
     pub frontend: bool,
 
+    #[structopt(
+        long = "watch-config",
+        help = "Reload rpc endpoints when config-payments.toml changes (endpoints set by env variables or changed through the api are kept)"
+    )]
+    pub watch_config: bool,
+
     #[structopt(
         long = "balance-check-loop",
         help = "Run forever in loop (for RPC testing) or active balance monitoring. Set number of desired iterations. 0 means forever."
@@ -171,6 +186,9 @@ pub struct TransferOptions {
     #[structopt(long = "deposit-id")]
     pub deposit_id: Option<String>,
 
+    #[structopt(long = "priority", help = "Priority class", default_value = "normal", possible_values = &["urgent", "normal", "bulk"])]
+    pub priority: TransferPriority,
+
     #[structopt(
         long = "lock-contract",
         help = "Lock contract address (if not specified, it will be taken from config)"
@@ -263,6 +281,76 @@ pub struct ExportHistoryStatsOptions {
     pub export_sqlite_file: PathBuf,
 }
 
+#[derive(StructOpt)]
+#[structopt(about = "Cancel transfer options")]
+pub struct CancelTransferOptions {
+    #[structopt(
+        long = "id",
+        help = "Id of the transfer, it cannot be attached to transaction"
+    )]
+    pub id: i64,
+}
+
+#[derive(StructOpt)]
+#[structopt(about = "Edit transfer options")]
+pub struct EditTransferOptions {
+    #[structopt(
+        long = "id",
+        help = "Id of the transfer, it cannot be attached to transaction"
+    )]
+    pub id: i64,
+
+    #[structopt(short = "r", long = "recipient", help = "New recipient")]
+    pub recipient: Option<Address>,
+
+    #[structopt(
+        short = "a",
+        long = "amount",
+        help = "New amount (decimal, full precision, i.e. 0.01)"
+    )]
+    pub amount: Option<rust_decimal::Decimal>,
+
+    #[structopt(long = "priority", help = "New priority class", possible_values = &["urgent", "normal", "bulk"])]
+    pub priority: Option<TransferPriority>,
+
+    #[structopt(
+        long = "deadline",
+        help = "Send transfer not later than given date (i.e. 2024-01-01T00:00:00Z)"
+    )]
+    pub deadline: Option<DateTime<Utc>>,
+}
+
+#[derive(StructOpt)]
+#[structopt(about = "Backup database options")]
+pub struct BackupOptions {
+    #[structopt(
+        long = "backup-dir",
+        help = "Directory for backups",
+        default_value = "backups"
+    )]
+    pub backup_dir: PathBuf,
+
+    #[structopt(
+        long = "prefix",
+        help = "Backup file name prefix",
+        default_value = "erc20lib"
+    )]
+    pub prefix: String,
+
+    #[structopt(
+        long = "keep",
+        help = "Remove older backups, keeping given number of newest"
+    )]
+    pub keep: Option<usize>,
+}
+
+#[derive(StructOpt)]
+#[structopt(about = "Restore database options")]
+pub struct RestoreOptions {
+    #[structopt(long = "backup-file", help = "Backup file to restore")]
+    pub backup_file: PathBuf,
+}
+
 #[derive(StructOpt)]
 #[structopt(about = "Payment statistics options")]
 pub struct PaymentStatsOptions {
@@ -337,6 +425,20 @@ pub struct CleanupOptions {
 
     #[structopt(long = "chain-id", help = "Chain id to use")]
     pub chain_id: Option<i64>,
+
+    #[structopt(
+        long = "archive-before",
+        help = "Move finished transactions and chain data older than given date \
+    (i.e. 2024-01-01T00:00:00Z) to archive file"
+    )]
+    pub archive_before: Option<DateTime<Utc>>,
+
+    #[structopt(
+        long = "archive-file",
+        help = "Archive file used with --archive-before",
+        default_value = "archive.sqlite"
+    )]
+    pub archive_file: PathBuf,
 }
 
 #[derive(StructOpt)]
@@ -441,6 +543,26 @@ pub enum PaymentCommands {
         #[structopt(flatten)]
         cleanup_options: CleanupOptions,
     },
+    #[structopt(about = "Remove transfer that is not yet sent")]
+    CancelTransfer {
+        #[structopt(flatten)]
+        cancel_transfer_options: CancelTransferOptions,
+    },
+    #[structopt(about = "Change recipient or amount of transfer that is not yet sent")]
+    EditTransfer {
+        #[structopt(flatten)]
+        edit_transfer_options: EditTransferOptions,
+    },
+    #[structopt(about = "Write consistent snapshot of the SQLite database")]
+    Backup {
+        #[structopt(flatten)]
+        backup_options: BackupOptions,
+    },
+    #[structopt(about = "Replace SQLite database with backup, instance must not be running")]
+    Restore {
+        #[structopt(flatten)]
+        restore_options: RestoreOptions,
+    },
     ShowConfig,
 }
 
@@ -454,6 +576,13 @@ pub struct PaymentOptions {
     )]
     pub sqlite_db_file: PathBuf,
 
+    #[structopt(
+        long = "db-url",
+        env = "ERC20_LIB_DB_URL",
+        help = "Database url, postgres://... selects PostgreSQL (overrides --sqlite-db-file)"
+    )]
+    pub db_url: Option<String>,
+
     #[structopt(long = "sqlite-read-only", help = "Create read only connection")]
     pub sqlite_read_only: bool,
 
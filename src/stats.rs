@@ -5,20 +5,22 @@ use erc20_payment_lib_common::error::ErrorBag;
 use erc20_payment_lib_common::error::PaymentError;
 use erc20_payment_lib_common::model::ChainTxDbObj;
 use erc20_payment_lib_common::ops::{
-    get_chain_transfers_by_chain_id, get_chain_txs_by_chain_id, get_transfer_stats,
-    get_transfer_stats_from_blockchain, TransferStatsPart,
+    get_transfer_stats, get_transfer_stats_from_blockchain, TransferStats, TransferStatsPart,
 };
+use erc20_payment_lib_common::store::{PaymentDbStore, PaymentStoreOps};
 use erc20_payment_lib_common::utils::{u256_eth_from_str, U256ConvExt};
 use erc20_payment_lib_common::{err_custom_create, err_from};
 use itertools::Itertools;
 use rust_decimal::Decimal;
-use sqlx::{Executor, SqlitePool};
+use sqlx::Executor;
 use std::collections::HashMap;
 use std::{env, fs};
 use web3::types::{H160, U256};
 
+/// Reads history from the payment store (SQLite or PostgreSQL),
+/// the exported stats file is always SQLite
 pub async fn export_stats(
-    conn: SqlitePool,
+    store: PaymentDbStore,
     payment_stats_options: ExportHistoryStatsOptions,
     config: &Config,
 ) -> Result<(), PaymentError> {
@@ -60,11 +62,13 @@ pub async fn export_stats(
         .await
         .map_err(err_from!())?;
 
-    let tchains = get_chain_transfers_by_chain_id(&conn, chain_cfg.chain_id, None)
+    let tchains = store
+        .get_chain_transfers_by_chain_id(chain_cfg.chain_id, None)
         .await
         .unwrap();
 
-    let txs = get_chain_txs_by_chain_id(&conn, chain_cfg.chain_id, None)
+    let txs = store
+        .get_chain_txs_by_chain_id(chain_cfg.chain_id, None)
         .await
         .unwrap();
 
@@ -113,8 +117,25 @@ pub async fn export_stats(
     Ok(())
 }
 
+async fn transfer_stats(
+    store: &PaymentDbStore,
+    chain_id: i64,
+    from_blockchain: bool,
+) -> Result<TransferStats, PaymentError> {
+    match (store, from_blockchain) {
+        (PaymentDbStore::Sqlite(s), true) => {
+            get_transfer_stats_from_blockchain(s.pool(), chain_id, None).await
+        }
+        (PaymentDbStore::Sqlite(s), false) => get_transfer_stats(s.pool(), chain_id, None).await,
+        (PaymentDbStore::Postgres(s), true) => {
+            get_transfer_stats_from_blockchain(s.pool(), chain_id, None).await
+        }
+        (PaymentDbStore::Postgres(s), false) => get_transfer_stats(s.pool(), chain_id, None).await,
+    }
+}
+
 pub async fn run_stats(
-    conn: SqlitePool,
+    store: PaymentDbStore,
     payment_stats_options: PaymentStatsOptions,
     config: &Config,
 ) -> Result<(), PaymentError> {
@@ -134,15 +155,13 @@ pub async fn run_stats(
         payment_stats_options.chain_name
     );
 
-    let transfer_stats = if payment_stats_options.from_blockchain {
-        get_transfer_stats_from_blockchain(&conn, chain_cfg.chain_id, None)
-            .await
-            .unwrap()
-    } else {
-        get_transfer_stats(&conn, chain_cfg.chain_id, None)
-            .await
-            .unwrap()
-    };
+    let transfer_stats = transfer_stats(
+        &store,
+        chain_cfg.chain_id,
+        payment_stats_options.from_blockchain,
+    )
+    .await
+    .unwrap();
     if transfer_stats.per_sender.is_empty() {
         println!("No transfers found");
         return Ok(());
@@ -199,7 +218,7 @@ pub async fn run_stats(
             "# TYPE transaction_count counter",
             chain_cfg.chain_id,
             sender,
-            stats.all.transaction_ids.len(),
+            stats.all.tx_count(),
         );
 
         metrics += &format!(
@@ -214,7 +233,7 @@ pub async fn run_stats(
 
     println!(
         "Number of web3 transactions: {}",
-        main_sender.1.all.transaction_ids.len()
+        main_sender.1.all.tx_count()
     );
 
     println!(
@@ -329,7 +348,7 @@ pub async fn run_stats(
             "Receiver: {:#x}\n  count (payment/web3): {}/{}, gas: {}, native token sent: {}, token sent: {}",
             receiver.0,
             receiver.1.done_count,
-            receiver.1.transaction_ids.len(),
+            receiver.1.tx_count(),
             receiver.1.fee_paid.to_eth().unwrap(),
             receiver.1.native_token_transferred.to_eth().unwrap(),
             ts.to_eth().unwrap(),
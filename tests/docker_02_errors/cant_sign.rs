@@ -78,7 +78,7 @@ async fn test_erc20_transfer() -> Result<(), anyhow::Error> {
                 secret_keys: private_keys.0,
                 db_filename: Default::default(),
                 config: config.clone(),
-                conn: Some(conn.clone()),
+                conn: Some(conn.clone().into()),
                 options: Some(AdditionalOptions {
                     keep_running: false,
                     ..Default::default()
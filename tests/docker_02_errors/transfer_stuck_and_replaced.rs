@@ -119,7 +119,7 @@ async fn test_transfer_stuck_and_replaced(scenario: Scenarios) -> Result<(), any
                 secret_keys: private_keys.0.clone(),
                 db_filename: Default::default(),
                 config: config.clone(),
-                conn: Some(conn.clone()),
+                conn: Some(conn.clone().into()),
                 options: Some(AdditionalOptions {
                     keep_running: false,
                     ..Default::default()
@@ -148,7 +148,7 @@ async fn test_transfer_stuck_and_replaced(scenario: Scenarios) -> Result<(), any
                 secret_keys: private_keys.0.clone(),
                 db_filename: Default::default(),
                 config: config.clone(),
-                conn: Some(conn.clone()),
+                conn: Some(conn.clone().into()),
                 options: Some(AdditionalOptions {
                     keep_running: false,
                     ..Default::default()
@@ -186,7 +186,7 @@ async fn test_transfer_stuck_and_replaced(scenario: Scenarios) -> Result<(), any
                 secret_keys: private_keys.0.clone(),
                 db_filename: Default::default(),
                 config: config.clone(),
-                conn: Some(conn.clone()),
+                conn: Some(conn.clone().into()),
                 options: Some(AdditionalOptions {
                     keep_running: false,
                     ..Default::default()
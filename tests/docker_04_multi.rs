@@ -134,7 +134,7 @@ async fn test_multi_erc20_transfer(payment_count: usize, use_direct_method: bool
                 secret_keys: private_keys.0,
                 db_filename: Default::default(),
                 config: config.clone(),
-                conn: Some(conn.clone()),
+                conn: Some(conn.clone().into()),
                 options: Some(AdditionalOptions {
                     keep_running: false,
                     generate_tx_only: false,
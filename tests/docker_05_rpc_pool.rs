@@ -79,6 +79,13 @@ async fn test_rpc_pool() -> Result<(), anyhow::Error> {
             max_consecutive_errors: None,
             dns_source: None,
             json_source: None,
+            rate_limit_burst: None,
+            rate_limit_per_sec: None,
+            daily_request_budget: None,
+            monthly_request_budget: None,
+            headers: None,
+            source_signer: None,
+            from_env: false,
         },
     ];
 
@@ -113,7 +120,7 @@ async fn test_rpc_pool() -> Result<(), anyhow::Error> {
                 secret_keys: private_keys.0,
                 db_filename: Default::default(),
                 config: config.clone(),
-                conn: Some(conn.clone()),
+                conn: Some(conn.clone().into()),
                 options: Some(AdditionalOptions {
                     keep_running: false,
                     ..Default::default()